@@ -0,0 +1,265 @@
+# English messages, also used for anything missing from other catalogs.
+#
+# To add a language, copy this file to `<language>.ftl` in the locales directory
+# and translate the values. Keys that are left out fall back to English.
+
+## Framework
+
+command-error = Error occured in `{ $command }`: { $error }
+command-disabled = `{ $command }` is disabled in this server.
+unknown-command = There is no command named `{ $command }`.
+command-usage = Usage: `{ $usage }`
+restarting = I'm restarting right now, try again in a moment.
+rate-limited = You're using commands too quickly, slow down.
+rate-limit-blacklisted = You've been blacklisted for { $minutes } minutes for spamming commands.
+cooldown = Slow down! You can use `{ $command }` again in { $seconds }s.
+nsfw-only = Sorry, this can only be used in age-restricted channels.
+missing-permissions = I'm missing the following permissions in { $channel }: { $permissions }
+permission-send-messages = Send Messages
+permission-embed-links = Embed Links
+permission-attach-files = Attach Files
+
+## Arguments
+
+flag-takes-no-value = `--{ $flag }` doesn't take a value.
+flag-missing-value = Missing value for `--{ $flag }`.
+flag-invalid-value = `{ $value }` is not a valid value for `--{ $flag }`.
+flag-out-of-range = `--{ $flag }` must be between { $min } and { $max }, got { $value }.
+toggle-invalid = Expected `on` or `off`, got `{ $value }`.
+toggle-on = On
+toggle-off = Off
+invalid-id = `{ $id }` is not a valid id.
+invalid-url = `{ $url }` is not a valid URL.
+filter-unknown = Unknown filter `{ $filter }`.
+filter-unknown-param = Unknown option `--{ $flag }`.
+unknown-format = Unknown output format `{ $format }`. (Expected one of `png`, `jpeg` or `gif`.)
+unknown-source = Unknown source `{ $source }`. (Expected one of `query`, `attachment`, `reply` or `avatar`.)
+size-mib = { $size } MiB
+latency-ms = { $ms } ms
+
+## Image resolution
+
+url-bad-status = URL returned status code { $status }
+resolve-not-found = Could not retrieve an image from the message.
+resolve-not-found-query = Could not retrieve an image from the given query.
+resolve-not-found-attachment = Could not retrieve an image from the message attachments.
+resolve-not-found-reply = Could not retrieve an image from the replied message.
+resolve-not-found-avatar = Could not retrieve an image from the author's avatar.
+resolve-needs-two = This takes two images. Give them as attachments, links or links to messages, or give one to combine with your avatar.
+resolve-extension-not-allowed = File extension `{ $extension }` is not allowed
+resolve-content-type-not-allowed = Content-Type `{ $content_type }` is not allowed
+resolve-invalid-content-type = Invalid Content-Type.
+resolve-attachment-too-big = Attachment is too big. (`{ $size }` > `{ $max }`)
+resolve-file-too-big = File is too big. (`{ $size }` > `{ $max }`)
+resolve-no-dimensions = Invalid attachment. (Could not get a width or height from it.)
+resolve-too-wide = Attachment width of { $width } surpasses the maximum of { $max }.
+resolve-too-tall = Attachment height of { $height } surpasses the maximum of { $max }.
+resolve-invalid-page = `--page` must be at least 1.
+resolve-blocked = This image has been blocked by the moderators of this server.
+gif-no-frames = Could not decode any frames from this GIF.
+gif-not-animated = This only works on animated images, and this one is a still image.
+pdf-unreadable = Could not read this PDF document. ({ $error })
+pdf-page-missing = Page { $page } does not exist, this document only has { $count } pages.
+pdf-page-missing-single = Page { $page } does not exist, this document only has 1 page.
+pdf-render-failed = Could not render this PDF page.
+
+## Output
+
+output-too-big = The result is too big to upload. (`{ $size } MiB` > `{ $limit } MiB`)
+output-sent-to-dms = Sent the result to your DMs.
+degraded-note = Note: I'm under heavy load right now, so this was made at a reduced quality.
+job-timed-out = This took longer than { $seconds } seconds to make, so I gave up on it. Try a smaller image or one with fewer frames.
+
+## Miscellaneous
+
+ping-pong = Pong! Shard #{ $shard } heartbeat: { $latency }
+ping-shard = Shard #{ $shard }: { $latency }
+ping-average = Average across { $shards } shards: { $ms } ms
+ping-no-heartbeat = no heartbeat yet
+stats-title = Command stats
+stats-command-title = Stats for `{ $command }`
+stats-uses = Uses
+stats-unique-users = Unique users
+stats-processed = Processed
+stats-average-latency = Average latency
+stats-top-commands = Top commands
+stats-top-entry = { $rank }. `{ $command }` - { $uses } uses, { $ms } ms avg
+stats-nothing-yet = Nothing yet.
+
+## API
+
+api-unauthorized = Missing or invalid API token.
+api-bad-multipart = The request body is not valid multipart form data.
+api-bad-field = The `{ $field }` field is too long or not valid UTF-8.
+api-too-many-fields = Requests may have at most { $max } fields.
+api-missing-image = The request has no `image` field.
+api-image-too-big = The image is bigger than the maximum of { $max }.
+
+## Help
+
+help-title = Commands
+help-overview = Use `{ $prefix }help <command>` for details on a command.
+help-no-description = No description.
+help-usage = Usage
+help-examples = Examples
+help-flags = Flags
+help-aliases = Aliases
+help-flag-default = { $value } by default.
+help-flag-source = Only look for the image in this place.
+help-flag-page = The page to use when the image is a PDF document.
+help-flag-zip = Send the results as a single ZIP archive.
+help-flag-makegif-delay = How long each image is shown in milliseconds, 500 by default.
+help-flag-crossfade-frames = How many frames the fade takes each way, 12 by default.
+help-flag-blend-mode = How the second image is blended in, see above for the modes.
+help-flag-blend-opacity = How much of the second image shows, from 0 to 1.
+help-flag-overlay-anchor = What the image on top is lined up with: `center`, an edge like `top` or a corner like `bottom-left`.
+help-flag-overlay-x = How many pixels to move the image on top to the right, negative to move it left.
+help-flag-overlay-y = How many pixels to move the image on top down, negative to move it up.
+help-flag-overlay-scale = How large the image on top is compared to the one below, 1 fitting it just inside.
+help-flag-compare-orientation = `h` to put the images next to each other, `v` to stack them.
+help-flag-compare-labels = Two labels separated by a comma, written above the images.
+help-flag-grid-gap = How many pixels to leave around and between the images, 8 by default.
+help-flag-grid-background = The color showing in the gaps, white by default.
+help-flag-watermark-corner = Where the watermark goes this time, instead of where the server put it.
+help-flag-watermark-opacity = How much the watermark shows this time, from 0.05 to 1.
+help-flag-frames-zip = Always send the frames in a ZIP, even when they would fit in one message.
+help-flag-removebg-backend = How to find the background: from the edges in, by its color everywhere, or with the segmentation model if there is one.
+help-flag-removebg-color = The color to key out, which picks the chroma backend. The background's by default.
+help-flag-removebg-tolerance = How different from the background colors can be and still be removed, 40 by default.
+
+## Imaging
+
+resize-invalid-size = `{ $size }` is not a size. Give a width and height like `512x256`, just one of them like `512` or `x256`, or a percentage like `50%`.
+sizes-invalid-size = `{ $size }` is not a valid size. (Sizes must be between 1 and { $max }.)
+sizes-invalid-count = Give between 1 and { $max } comma-separated sizes.
+makegif-not-enough = Attach or link at least two images to make a GIF out of.
+makegif-too-many = A GIF can be made out of at most { $max } images.
+blend-unknown-mode = There is no blend mode called `{ $mode }`. Try `normal`, `multiply`, `screen`, `overlay`, `add` or `difference`.
+overlay-unknown-anchor = `{ $anchor }` is not a position. Use `center`, `top`, `bottom`, `left`, `right`, `top-left`, `top-right`, `bottom-left` or `bottom-right`.
+compare-invalid-labels = `{ $labels }` is not a pair of labels. Separate the two with a comma, like `before,after`.
+ship-unknown-user = Could not find the user `{ $user }`.
+grid-not-enough = Give at least two images to put in a grid, as attachments, mentions or links.
+grid-too-many = A grid can have at most { $max } images.
+watermark-not-set = This server has no watermark yet. Admins can set one with `settings watermark`.
+removebg-model-unavailable = No segmentation model is set up, use another backend such as `flood` or `chroma`.
+removebg-model-failed = Could not run the segmentation model. ({ $error })
+frames-out-of-range = This GIF only has { $count } frames.
+frames-invalid-range = `{ $range }` isn't a valid frame range. Frames are numbered from 1 and ranges go forwards, like `2-8`, `5-` or `-3`.
+frames-too-many = That's { $count } frames, only up to { $max } can be extracted at once. Give a smaller range like `1-{ $max }`.
+gifinfo-title = GIF info
+gifinfo-frames = Frames
+gifinfo-duration = Duration
+gifinfo-duration-value = { $seconds } s
+gifinfo-loops = Loops
+gifinfo-loops-once = Plays once
+gifinfo-loops-forever = Forever
+gifinfo-loops-count = Plays { $count } times
+gifinfo-delays-title = Frame delays
+gifinfo-delay = { $delay } ms each
+gifinfo-delays = { $min } to { $max } ms, { $average } ms on average
+gifinfo-dimensions = Dimensions
+gifinfo-size = Size
+gifinfo-palettes = Palettes
+gifinfo-palette-global = A global palette of { $colors } colors
+gifinfo-palette-local = { $frames } frames with their own palette, of up to { $colors } colors
+gifinfo-palette-none = None, so viewers pick the colors
+loop-invalid-count = `{ $count }` is not a loop count. Give how many times to play it, `once` or `forever`.
+
+lut-unknown = There is no LUT named `{ $name }`. See `lut list` for the available ones.
+lut-invalid = This is not a valid `.cube` LUT, line { $line }: { $reason }
+lut-list-title = LUTs
+lut-list-bundled = Bundled
+lut-list-uploaded = This server
+lut-list-none = None yet, add one with `lut upload <name>`.
+lut-invalid-name = `{ $name }` can't be used as a LUT name. Names are up to 32 lowercase letters, digits, `-` or `_`, and can't be taken by a bundled LUT or a subcommand.
+lut-missing-file = Attach the `.cube` file to upload.
+lut-file-too-big = LUT files may be at most { $max } MiB.
+lut-too-many = This server already has the maximum of { $max } LUTs, remove one first.
+lut-not-text = This file is not a text `.cube` LUT.
+lut-uploaded = Added the { $size }³ LUT `{ $name }`.
+lut-removed = Removed the LUT `{ $name }`.
+
+## Fonts
+
+font-unknown = There is no font named `{ $name }`. See `fonts list` for the available ones.
+font-invalid = This is not a TrueType or OpenType font.
+font-list-title = Fonts
+font-list-bundled = Bundled
+font-list-uploaded = This server
+font-list-none = None yet, add one with `fonts upload <name>`.
+font-invalid-name = `{ $name }` can't be used as a font name. Names are up to 32 lowercase letters, digits, `-` or `_`, and can't be taken by a bundled font.
+font-missing-file = Attach the `.ttf` or `.otf` file to upload.
+font-file-too-big = Font files may be at most { $max } MiB.
+font-too-many = This server already has the maximum of { $max } fonts, remove one first.
+font-uploaded = Added the font `{ $name }`.
+font-removed = Removed the font `{ $name }`.
+
+## Memes
+
+meme-missing-text = Missing the { $slot }. Usage: `{ $usage }`
+
+## Moderation
+
+block-image-no-reply = Reply to the offending image (or to my output of it) to use this command.
+block-image-done = Blocked that image. It and near-identical copies will no longer be processed in this server.
+unblock-image-no-reply = Reply to the blocked image (or to my output of it) to use this command.
+unblock-image-not-blocked = That image is not blocked.
+unblock-image-done = Unblocked that image.
+replied-image-missing = Could not find an image on the replied message.
+replied-image-unreadable = Could not read the replied image.
+
+## Settings
+
+settings-title = Server settings
+settings-prefix = Prefix
+settings-max-size = Max image size
+settings-default-format = Default format
+settings-language = Language
+settings-disabled-commands = Disabled commands
+settings-watermark = Watermark
+settings-none = None
+settings-prefix-reset = Reset this server's prefix.
+settings-prefix-set = Set this server's prefix to `{ $prefix }`.
+settings-prefix-invalid = Prefixes must be at most 16 characters long and contain no whitespace.
+settings-command-disabled = Disabled `{ $command }`.
+settings-command-enabled = Enabled `{ $command }`.
+settings-max-size-reset = Reset this server's maximum image size.
+settings-max-size-set = Set this server's maximum image size to { $size } MiB.
+settings-max-size-invalid = `{ $size }` is not a valid size.
+settings-max-size-too-big = The maximum image size cannot exceed { $limit } MiB.
+settings-format-set = Set this server's default output format to `{ $format }`.
+settings-language-list = Available languages: { $languages }
+settings-language-set = I'll now reply in `{ $language }` in this server.
+settings-language-unknown = Unknown language `{ $language }`. (Available: { $languages })
+settings-watermark-image = An image
+settings-watermark-summary = { $mark } at `{ $anchor }`, { $opacity }% opaque
+settings-watermark-set = Set this server's watermark, it goes at `{ $anchor }` by default.
+settings-watermark-reset = Removed this server's watermark.
+settings-watermark-not-set = This server has no watermark to remove.
+settings-watermark-missing = Attach an image or give some text to use as the watermark.
+settings-watermark-too-big = Watermark images can be at most { $max } KiB.
+settings-watermark-text-too-long = Watermark text can be at most { $max } characters long.
+
+## Preferences
+
+prefs-title = Preferences for { $user }
+prefs-format = Output format
+prefs-server-default = Server default
+prefs-static-avatars = Static avatars
+prefs-spoiler = Spoiler outputs
+prefs-dm-results = DM results
+prefs-format-set = Your outputs will now be sent as `{ $format }`.
+prefs-format-reset = Your outputs will now follow the server's default format.
+prefs-toggle-on = { $name } is now on.
+prefs-toggle-off = { $name } is now off.
+
+## Blacklist
+
+blacklist-unknown-kind = Expected `user` or `guild`, got `{ $kind }`.
+blacklist-added = Blacklisted { $kind } `{ $id }`.
+blacklist-removed = Removed { $kind } `{ $id }` from the blacklist.
+blacklist-not-listed = { $kind } `{ $id }` is not blacklisted.
+blacklist-empty = The blacklist is empty.
+blacklist-title = Blacklist ({ $count } entries)
+blacklist-expires = { " " }(expires <t:{ $timestamp }:R>)
+blacklist-more = ...and { $count } more
@@ -0,0 +1,164 @@
+//! Runs filters on local files, for trying out and benchmarking them without a bot.
+//!
+//! ```text
+//! photon apply invert input.gif -o out.gif
+//! photon apply resize input.png --width 256 --repeat 20
+//! photon filters
+//! ```
+
+use photon_core::{filters, Filter, Media, OutputFormat, ParamKind, Params, Quality};
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "\
+usage:
+    photon apply <filter> <input> [-o <output>] [--format <png | jpeg | gif>] [--repeat <n>] [--<param> <value>...]
+    photon filters";
+
+type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+struct Apply {
+    filter: &'static Filter,
+    input: PathBuf,
+    output: Option<PathBuf>,
+    format: Option<OutputFormat>,
+    repeat: u32,
+    params: Params,
+}
+
+fn parse_apply(mut args: impl Iterator<Item = String>) -> Result<Apply> {
+    let name = args.next().ok_or("missing filter name")?;
+    let filter = filters::find(&name).ok_or_else(|| photon_core::Error::UnknownFilter(name))?;
+
+    let mut input = None;
+    let mut output = None;
+    let mut format = None;
+    let mut repeat = 1;
+    let mut params = Vec::<(String, Option<String>)>::new();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => output = Some(PathBuf::from(args.next().ok_or("missing value for --output")?)),
+            "--format" => format = Some(args.next().ok_or("missing value for --format")?.parse()?),
+            "--repeat" => repeat = args.next().ok_or("missing value for --repeat")?.parse::<u32>()?.max(1),
+            _ => match arg.strip_prefix("--") {
+                Some(param) => {
+                    if let Some((name, value)) = param.split_once('=') {
+                        params.push((name.to_string(), Some(value.to_string())));
+                        continue;
+                    }
+
+                    // Booleans are switches, everything else takes the next argument.
+                    let is_bool = filter.params
+                        .iter()
+                        .any(|spec| spec.name.eq_ignore_ascii_case(param) && spec.kind == ParamKind::Bool);
+                    let value = if is_bool { None } else { args.next() };
+
+                    params.push((param.to_string(), value));
+                },
+                None if input.is_none() => input = Some(PathBuf::from(arg)),
+                None => return Err(format!("unexpected argument `{}`", arg).into()),
+            },
+        }
+    }
+
+    let params = Params::parse(filter.params, params.iter().map(|(name, value)| (name.as_str(), value.as_deref())))?;
+
+    Ok(Apply {
+        filter,
+        input: input.ok_or("missing input file")?,
+        output,
+        format,
+        repeat,
+        params,
+    })
+}
+
+/// `<input stem>-<filter>.<extension>` next to the input.
+fn default_output(input: &Path, filter: &str, format: OutputFormat) -> PathBuf {
+    let stem = input.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+    input.with_file_name(format!("{}-{}.{}", stem, filter, format.extension()))
+}
+
+fn apply(command: Apply) -> Result<()> {
+    let data = std::fs::read(&command.input)?;
+
+    // Static outputs follow the output's extension unless a format was given.
+    let format = command.format
+        .or_else(|| command.output.as_ref()?.extension()?.to_str()?.parse().ok())
+        .unwrap_or_default();
+
+    let started = Instant::now();
+    let media = Media::decode(&data, &Quality::full())?;
+    let decoded = started.elapsed();
+
+    let mut filtering = Duration::ZERO;
+    let mut result = None;
+    for _ in 0..command.repeat {
+        let started = Instant::now();
+        result = Some(command.filter.apply(media.clone(), &command.params)?);
+        filtering += started.elapsed();
+    }
+
+    let started = Instant::now();
+    let (encoded, format) = result.expect("repeat is at least 1").encode(format)?;
+    let encoded_in = started.elapsed();
+
+    let output = command.output.unwrap_or_else(|| default_output(&command.input, command.filter.name, format));
+    std::fs::write(&output, encoded)?;
+
+    eprintln!(
+        "wrote {} (decode {:?}, {} {:?}{}, encode {:?})",
+        output.display(),
+        decoded,
+        command.filter.name,
+        filtering / command.repeat,
+        if command.repeat > 1 { format!(" avg over {} runs", command.repeat) } else { String::new() },
+        encoded_in,
+    );
+    Ok(())
+}
+
+fn list_filters() {
+    for filter in filters::FILTERS {
+        println!("{} - {}", filter.name, filter.description);
+
+        for param in filter.params {
+            let kind = match param.kind {
+                ParamKind::Int { min, max, default } => format!("{}..={}, default {}", min, max, default),
+                ParamKind::Float { min, max, default } => format!("{}..={}, default {}", min, max, default),
+                ParamKind::Bool => "switch".to_string(),
+                ParamKind::Choice { options, default } => format!("{}, default {}", options.join(" | "), default),
+                ParamKind::Color { default: [r, g, b, a] } => format!("color, default #{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+                ParamKind::Colors { min, max, default } => {
+                    let default = default.iter().map(|[r, g, b, a]| format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)).collect::<Vec<_>>();
+                    format!("{} to {} colors, default {}", min, max, default.join(","))
+                },
+            };
+
+            println!("    --{} ({}) {}", param.name, kind, param.description);
+        }
+    }
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+
+    let result = match args.next().as_deref() {
+        Some("apply") => parse_apply(args).and_then(apply),
+        Some("filters") => {
+            list_filters();
+            Ok(())
+        },
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        },
+    };
+
+    if let Err(why) = result {
+        eprintln!("error: {}", why);
+        std::process::exit(1);
+    }
+}
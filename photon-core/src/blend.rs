@@ -0,0 +1,91 @@
+//! Blend modes for laying one color over another, shared by everything that composites colors or
+//! images.
+
+use image::Rgba;
+
+use crate::geometry::blend_over;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// The top color as it is.
+    Normal,
+    /// Darkens, white leaving the image as it is.
+    Multiply,
+    /// Lightens, black leaving the image as it is.
+    Screen,
+    /// Multiplies the darks and screens the lights of the image below, adding contrast.
+    Overlay,
+    /// Adds up the colors, clipping at white.
+    Add,
+    /// How far apart the colors are, black where they match.
+    Difference,
+}
+
+impl BlendMode {
+    /// The names modes are given by, in the order of the variants.
+    pub const NAMES: &'static [&'static str] = &["normal", "multiply", "screen", "overlay", "add", "difference"];
+    const ALL: [Self; 6] = [Self::Normal, Self::Multiply, Self::Screen, Self::Overlay, Self::Add, Self::Difference];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().position(|n| n.eq_ignore_ascii_case(name)).map(|index| Self::ALL[index])
+    }
+
+    /// Blends a single channel of the top color into the one below, both from 0 to 255.
+    pub fn channel(self, below: f32, top: f32) -> f32 {
+        match self {
+            Self::Normal => top,
+            Self::Multiply => below * top / 255.0,
+            Self::Screen => 255.0 - (255.0 - below) * (255.0 - top) / 255.0,
+            Self::Overlay if below < 128.0 => 2.0 * below * top / 255.0,
+            Self::Overlay => 255.0 - 2.0 * (255.0 - below) * (255.0 - top) / 255.0,
+            Self::Add => (below + top).min(255.0),
+            Self::Difference => (below - top).abs(),
+        }
+    }
+
+    /// Lays `top` over `below` at `opacity` (further scaled by its own transparency). Where `below` is
+    /// transparent the top color shows as it is, like the blend modes of image editors.
+    pub fn blend(self, below: Rgba<u8>, top: Rgba<u8>, opacity: f32) -> Rgba<u8> {
+        let below_alpha = below[3] as f32 / 255.0;
+        let mut mixed = top;
+
+        for c in 0..3 {
+            let blended = self.channel(below[c] as f32, top[c] as f32);
+            mixed[c] = (top[c] as f32 + (blended - top[c] as f32) * below_alpha).round().clamp(0.0, 255.0) as u8;
+        }
+        mixed[3] = (top[3] as f32 * opacity.clamp(0.0, 1.0)).round() as u8;
+
+        blend_over(below, mixed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_modes_by_name() {
+        assert_eq!(BlendMode::from_name("Screen"), Some(BlendMode::Screen));
+        assert_eq!(BlendMode::from_name("dodge"), None);
+        assert!(BlendMode::NAMES.iter().all(|name| BlendMode::from_name(name).is_some()));
+    }
+
+    #[test]
+    fn blends_channels() {
+        assert_eq!(BlendMode::Multiply.channel(255.0, 100.0), 100.0);
+        assert_eq!(BlendMode::Screen.channel(0.0, 100.0), 100.0);
+        assert_eq!(BlendMode::Overlay.channel(0.0, 200.0), 0.0);
+        assert_eq!(BlendMode::Overlay.channel(255.0, 20.0), 255.0);
+        assert_eq!(BlendMode::Add.channel(200.0, 100.0), 255.0);
+        assert_eq!(BlendMode::Difference.channel(50.0, 200.0), 150.0);
+    }
+
+    #[test]
+    fn blends_pixels_with_opacity() {
+        let below = Rgba([200, 100, 0, 255]);
+
+        assert_eq!(BlendMode::Difference.blend(below, Rgba([200, 100, 0, 255]), 1.0), Rgba([0, 0, 0, 255]));
+        assert_eq!(BlendMode::Normal.blend(below, Rgba([0, 0, 0, 255]), 0.5), Rgba([100, 50, 0, 255]));
+        assert_eq!(BlendMode::Multiply.blend(Rgba([0, 0, 0, 0]), Rgba([10, 20, 30, 255]), 1.0), Rgba([10, 20, 30, 255]));
+    }
+}
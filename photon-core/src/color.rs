@@ -0,0 +1,171 @@
+//! Parsing colors given as hex codes or common names, and converting between color spaces.
+
+use image::Rgba;
+
+/// Named colors accepted on top of hex codes.
+pub static NAMED: &[(&str, [u8; 4])] = &[
+    ("black", [0, 0, 0, 255]),
+    ("white", [255, 255, 255, 255]),
+    ("gray", [128, 128, 128, 255]),
+    ("grey", [128, 128, 128, 255]),
+    ("red", [255, 0, 0, 255]),
+    ("green", [0, 128, 0, 255]),
+    ("lime", [0, 255, 0, 255]),
+    ("blue", [0, 0, 255, 255]),
+    ("yellow", [255, 255, 0, 255]),
+    ("cyan", [0, 255, 255, 255]),
+    ("magenta", [255, 0, 255, 255]),
+    ("orange", [255, 165, 0, 255]),
+    ("purple", [128, 0, 128, 255]),
+    ("pink", [255, 192, 203, 255]),
+    ("brown", [139, 69, 19, 255]),
+    ("blurple", [88, 101, 242, 255]),
+    ("transparent", [0, 0, 0, 0]),
+];
+
+/// Parses `#rgb`, `#rgba`, `#rrggbb`, `#rrggbbaa` (the `#` being optional) or one of the [`NAMED`] colors.
+pub fn parse_color(s: &str) -> Option<Rgba<u8>> {
+    let s = s.trim();
+
+    if let Some((_, rgba)) = NAMED.iter().find(|(name, _)| name.eq_ignore_ascii_case(s)) {
+        return Some(Rgba(*rgba));
+    }
+
+    let hex = s.strip_prefix('#').unwrap_or(s);
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let digit = |i: usize| u8::from_str_radix(&hex[i..=i], 16).ok();
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+
+    let channels = match hex.len() {
+        3 | 4 => (0..hex.len()).map(|i| digit(i).map(|d| d * 17)).collect::<Option<Vec<_>>>()?,
+        6 | 8 => (0..hex.len()).step_by(2).map(byte).collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+
+    Some(Rgba([channels[0], channels[1], channels[2], channels.get(3).copied().unwrap_or(255)]))
+}
+
+/// Converts 0 to 1 RGB to hue (in degrees), saturation and lightness (0 to 1).
+pub fn rgb_to_hsl([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    let lightness = (max + min) / 2.0;
+    let delta = max - min;
+
+    if delta <= f32::EPSILON {
+        return [0.0, 0.0, lightness];
+    }
+
+    let saturation = delta / (1.0 - (2.0 * lightness - 1.0).abs());
+    let hue = match max {
+        max if max == r => ((g - b) / delta).rem_euclid(6.0),
+        max if max == g => (b - r) / delta + 2.0,
+        _ => (r - g) / delta + 4.0,
+    };
+
+    [hue * 60.0, saturation.min(1.0), lightness]
+}
+
+/// The inverse of [`rgb_to_hsl`]. Hues outside of 0 to 360 wrap around.
+pub fn hsl_to_rgb([hue, saturation, lightness]: [f32; 3]) -> [f32; 3] {
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let hue = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (hue % 2.0 - 1.0).abs());
+
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+
+    [r + m, g + m, b + m]
+}
+
+/// Converts 0 to 1 sRGB to CIELAB under the D65 white point, lightness going from 0 to 100.
+pub fn rgb_to_lab(rgb: [f32; 3]) -> [f32; 3] {
+    let [r, g, b] = rgb.map(|c| match c <= 0.04045 {
+        true => c / 12.92,
+        false => ((c + 0.055) / 1.055).powf(2.4),
+    });
+
+    // Linear RGB to XYZ, relative to the white point.
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f32| match t > 216.0 / 24389.0 {
+        true => t.cbrt(),
+        false => (24389.0 / 27.0 * t + 16.0) / 116.0,
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    [116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz)]
+}
+
+/// How different two colors look (CIE76 ΔE), ignoring transparency. Around 2 is barely noticeable, and
+/// black and white are 100 apart.
+pub fn lab_distance(a: Rgba<u8>, b: Rgba<u8>) -> f32 {
+    let lab = |pixel: Rgba<u8>| rgb_to_lab([pixel[0], pixel[1], pixel[2]].map(|c| c as f32 / 255.0));
+    let (a, b) = (lab(a), lab(b));
+
+    a.iter().zip(&b).map(|(a, b)| (a - b).powi(2)).sum::<f32>().sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_hex_codes() {
+        assert_eq!(parse_color("#ff8000"), Some(Rgba([255, 128, 0, 255])));
+        assert_eq!(parse_color("FF800080"), Some(Rgba([255, 128, 0, 128])));
+        assert_eq!(parse_color("#f80"), Some(Rgba([255, 136, 0, 255])));
+        assert_eq!(parse_color("#f808"), Some(Rgba([255, 136, 0, 136])));
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(parse_color("White"), Some(Rgba([255, 255, 255, 255])));
+        assert_eq!(parse_color("transparent"), Some(Rgba([0, 0, 0, 0])));
+    }
+
+    #[test]
+    fn converts_to_hsl_and_back() {
+        let close = |a: [f32; 3], b: [f32; 3]| a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 1e-4);
+
+        assert!(close(rgb_to_hsl([1.0, 0.0, 0.0]), [0.0, 1.0, 0.5]));
+        assert!(close(rgb_to_hsl([0.0, 0.0, 1.0]), [240.0, 1.0, 0.5]));
+        assert!(close(rgb_to_hsl([0.5, 0.5, 0.5]), [0.0, 0.0, 0.5]));
+
+        for rgb in [[0.2, 0.7, 0.4], [0.9, 0.1, 0.6], [1.0, 1.0, 0.0], [0.05, 0.0, 0.02]] {
+            assert!(close(hsl_to_rgb(rgb_to_hsl(rgb)), rgb), "{:?} did not survive the round trip", rgb);
+        }
+    }
+
+    #[test]
+    fn converts_to_lab() {
+        let close = |a: [f32; 3], b: [f32; 3]| a.iter().zip(&b).all(|(a, b)| (a - b).abs() < 0.05);
+
+        assert!(close(rgb_to_lab([1.0, 1.0, 1.0]), [100.0, 0.0, 0.0]));
+        assert!(close(rgb_to_lab([0.0, 0.0, 0.0]), [0.0, 0.0, 0.0]));
+        assert!(close(rgb_to_lab([1.0, 0.0, 0.0]), [53.24, 80.09, 67.20]), "{:?}", rgb_to_lab([1.0, 0.0, 0.0]));
+
+        assert!((lab_distance(Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255])) - 100.0).abs() < 0.05);
+        assert_eq!(lab_distance(Rgba([12, 34, 56, 255]), Rgba([12, 34, 56, 0])), 0.0);
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(parse_color(""), None);
+        assert_eq!(parse_color("#12345"), None);
+        assert_eq!(parse_color("#ggg"), None);
+        assert_eq!(parse_color("+12"), None);
+        assert_eq!(parse_color("blurple-ish"), None);
+    }
+}
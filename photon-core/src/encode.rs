@@ -0,0 +1,125 @@
+use image::codecs::{gif::GifEncoder, jpeg::JpegEncoder, png::PngEncoder};
+use image::RgbaImage;
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{Error, Result};
+
+pub const JPEG_QUALITY: u8 = 90;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    Jpeg,
+    Gif,
+}
+
+impl OutputFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+            Self::Gif => "gif",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Gif => "gif",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Png => "image/png",
+            Self::Jpeg => "image/jpeg",
+            Self::Gif => "image/gif",
+        }
+    }
+
+    /// The format to use instead when the output must not lose anything, PNG in place of JPEG.
+    pub fn lossless(self) -> Self {
+        match self {
+            Self::Jpeg => Self::Png,
+            format => format,
+        }
+    }
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        Self::Png
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().trim_start_matches('.') {
+            "png" => Ok(Self::Png),
+            "jpeg" | "jpg" => Ok(Self::Jpeg),
+            "gif" => Ok(Self::Gif),
+            _ => Err(Error::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+/// Encodes a static image into the given format, returning the encoded bytes.
+pub fn encode_image(img: &RgbaImage, format: OutputFormat) -> Result<Vec<u8>> {
+    let mut buffer = std::io::Cursor::new(vec![]);
+    let (width, height) = img.dimensions();
+
+    match format {
+        OutputFormat::Png => {
+            PngEncoder::new(&mut buffer).encode(img.as_raw(), width, height, image::ColorType::Rgba8)?;
+        },
+        OutputFormat::Jpeg => {
+            // JPEG has no alpha channel, so it has to be dropped first.
+            let rgb = image::DynamicImage::ImageRgba8(img.clone()).into_rgb8();
+            JpegEncoder::new_with_quality(&mut buffer, JPEG_QUALITY).encode(rgb.as_raw(), width, height, image::ColorType::Rgb8)?;
+        },
+        OutputFormat::Gif => {
+            GifEncoder::new(&mut buffer).encode(img.as_raw(), width, height, image::ColorType::Rgba8)?;
+        },
+    }
+
+    Ok(buffer.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_format_names() {
+        assert_eq!("PNG".parse::<OutputFormat>().unwrap(), OutputFormat::Png);
+        assert_eq!(".jpg".parse::<OutputFormat>().unwrap(), OutputFormat::Jpeg);
+        assert!(matches!("webp".parse::<OutputFormat>(), Err(Error::UnknownFormat(_))));
+    }
+
+    #[test]
+    fn lossless_formats() {
+        assert_eq!(OutputFormat::Jpeg.lossless(), OutputFormat::Png);
+        assert_eq!(OutputFormat::Gif.lossless(), OutputFormat::Gif);
+    }
+
+    #[test]
+    fn encodes_decodable_images() {
+        let img = RgbaImage::from_pixel(4, 3, image::Rgba([255, 0, 0, 255]));
+
+        for format in [OutputFormat::Png, OutputFormat::Jpeg, OutputFormat::Gif] {
+            let decoded = image::load_from_memory(&encode_image(&img, format).unwrap()).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (4, 3));
+        }
+    }
+}
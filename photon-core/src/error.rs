@@ -0,0 +1,63 @@
+use std::fmt;
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+#[derive(Debug)]
+pub enum Error {
+    Image(image::ImageError),
+    Io(std::io::Error),
+    /// An animated input had no frames that could be decoded.
+    NoFrames,
+    /// A filter that only works on animations was given a still image.
+    NotAnimated,
+    UnknownFormat(String),
+    UnknownFilter(String),
+    UnknownParam(String),
+    /// A non-boolean parameter was given without a value.
+    MissingValue(&'static str),
+    InvalidParam { name: &'static str, value: String },
+    OutOfRange { name: &'static str, min: f64, max: f64, value: f64 },
+    InvalidLut { line: usize, reason: &'static str },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Image(why) => write!(f, "{}", why),
+            Self::Io(why) => write!(f, "{}", why),
+            Self::NoFrames => f.write_str("the GIF has no frames"),
+            Self::NotAnimated => f.write_str("the image is not animated"),
+            Self::UnknownFormat(format) => write!(f, "unknown image format `{}`", format),
+            Self::UnknownFilter(name) => write!(f, "unknown filter `{}`", name),
+            Self::UnknownParam(name) => write!(f, "unknown parameter `{}`", name),
+            Self::MissingValue(name) => write!(f, "parameter `{}` needs a value", name),
+            Self::InvalidParam { name, value } => write!(f, "`{}` is not a valid value for `{}`", value, name),
+            Self::OutOfRange { name, min, max, value } => {
+                write!(f, "`{}` must be between {} and {}, got {}", name, min, max, value)
+            },
+            Self::InvalidLut { line, reason } => write!(f, "invalid LUT on line {}: {}", line, reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Image(why) => Some(why),
+            Self::Io(why) => Some(why),
+            _ => None,
+        }
+    }
+}
+
+impl From<image::ImageError> for Error {
+    fn from(why: image::ImageError) -> Self {
+        Self::Image(why)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(why: std::io::Error) -> Self {
+        Self::Io(why)
+    }
+}
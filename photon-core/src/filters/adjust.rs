@@ -0,0 +1,460 @@
+//! Filters that change each pixel's color on its own.
+
+use image::{Rgba, RgbaImage};
+
+use crate::color::{hsl_to_rgb, lab_distance, rgb_to_hsl};
+use crate::{BlendMode, Lut, Media};
+
+/// Relative luminance (Rec. 709) of a pixel, from 0 to 255.
+pub fn luma(pixel: Rgba<u8>) -> f32 {
+    0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32
+}
+
+/// Rounds a channel value to the nearest of `levels` evenly spaced values.
+pub fn quantize(value: f32, levels: u32) -> u8 {
+    let steps = (levels.max(2) - 1) as f32;
+    ((value / 255.0 * steps).round() / steps * 255.0).round().clamp(0.0, 255.0) as u8
+}
+
+pub fn invert(media: Media) -> Media {
+    media.map_frames(image::imageops::invert)
+}
+
+/// Converts to grayscale, keeping transparency. Fewer than 256 `levels` posterizes the grays.
+pub fn grayscale(media: Media, levels: u32) -> Media {
+    media.map_pixels(|pixel| {
+        let gray = if levels >= 256 { luma(pixel).round() as u8 } else { quantize(luma(pixel), levels) };
+
+        Rgba([gray, gray, gray, pixel[3]])
+    })
+}
+
+/// Reduces each color channel to `levels` values, keeping transparency.
+pub fn posterize(media: Media, levels: u32) -> Media {
+    media.map_pixels(|pixel| {
+        let channel = |c: usize| quantize(pixel[c] as f32, levels);
+        Rgba([channel(0), channel(1), channel(2), pixel[3]])
+    })
+}
+
+fn multiply_color(pixel: Rgba<u8>, matrix: &[[f32; 3]; 3]) -> Rgba<u8> {
+    let [r, g, b] = [pixel[0] as f32, pixel[1] as f32, pixel[2] as f32];
+    let row = |[mr, mg, mb]: [f32; 3]| (r * mr + g * mg + b * mb).round().clamp(0.0, 255.0) as u8;
+
+    Rgba([row(matrix[0]), row(matrix[1]), row(matrix[2]), pixel[3]])
+}
+
+/// Multiplies the color of every pixel with a 3x3 matrix, keeping transparency.
+pub fn color_matrix(media: Media, matrix: [[f32; 3]; 3]) -> Media {
+    media.map_pixels(|pixel| multiply_color(pixel, &matrix))
+}
+
+/// The matrix rotating hues by the given angle while roughly keeping the brightness, the same one SVG's
+/// `hueRotate` uses.
+fn hue_rotation(degrees: f32) -> [[f32; 3]; 3] {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+
+    [
+        [0.213 + cos * 0.787 - sin * 0.213, 0.715 - cos * 0.715 - sin * 0.715, 0.072 - cos * 0.072 + sin * 0.928],
+        [0.213 - cos * 0.213 + sin * 0.143, 0.715 + cos * 0.285 + sin * 0.140, 0.072 - cos * 0.072 - sin * 0.283],
+        [0.213 - cos * 0.213 - sin * 0.787, 0.715 - cos * 0.715 + sin * 0.715, 0.072 + cos * 0.928 + sin * 0.072],
+    ]
+}
+
+/// Shifts the hue of every pixel by any angle.
+pub fn huerotate(media: Media, degrees: f32) -> Media {
+    color_matrix(media, hue_rotation(degrees))
+}
+
+/// How long each frame of a rainbow made from a still image is shown.
+const RAINBOW_FRAME_DELAY_MS: u32 = 60;
+
+/// Cycles the hue once around the color wheel over the animation, each frame shifted a little further
+/// than the last. Still images become a GIF of `frames` frames first.
+pub fn rainbow(media: Media, frames: usize) -> Media {
+    let media = media.into_animation(frames, RAINBOW_FRAME_DELAY_MS);
+    let count = media.frame_count() as f32;
+
+    media.map_images_indexed(|index, img| {
+        let matrix = hue_rotation(360.0 * index as f32 / count);
+        let mut out = img.clone();
+
+        out.pixels_mut().for_each(|pixel| *pixel = multiply_color(*pixel, &matrix));
+        out
+    })
+}
+
+/// Scales the saturation of every pixel in HSL space, 0 leaving only grays and above 1 making colors
+/// more vivid.
+pub fn saturate(media: Media, factor: f32) -> Media {
+    media.map_pixels(|pixel| {
+        let [hue, saturation, lightness] = rgb_to_hsl([0, 1, 2].map(|c| pixel[c] as f32 / 255.0));
+        let rgb = hsl_to_rgb([hue, (saturation * factor).min(1.0), lightness]);
+        let channel = |c: usize| (rgb[c] * 255.0).round().clamp(0.0, 255.0) as u8;
+
+        Rgba([channel(0), channel(1), channel(2), pixel[3]])
+    })
+}
+
+/// Applies the same mapping to the red, green and blue channel of every pixel through a lookup table.
+pub fn map_channels(media: Media, f: impl Fn(u8) -> u8) -> Media {
+    let table = (0..=255).map(f).collect::<Vec<_>>();
+    media.map_pixels(|pixel| Rgba([table[pixel[0] as usize], table[pixel[1] as usize], table[pixel[2] as usize], pixel[3]]))
+}
+
+/// Linear brightness and contrast adjustments, both in percent from -100 to 100. Brightness shifts every
+/// channel by that much of the full range, contrast scales the distance to the middle gray.
+pub fn brightness_contrast(media: Media, brightness: f32, contrast: f32) -> Media {
+    let offset = brightness / 100.0 * 255.0;
+    let factor = 1.0 + contrast / 100.0;
+
+    map_channels(media, |value| ((value as f32 - 127.5) * factor + 127.5 + offset).round().clamp(0.0, 255.0) as u8)
+}
+
+/// Gamma correction, values above 1 brightening the midtones and values below darkening them.
+pub fn gamma(media: Media, gamma: f32) -> Media {
+    map_channels(media, |value| (255.0 * (value as f32 / 255.0).powf(1.0 / gamma)).round() as u8)
+}
+
+const SEPIA: [[f32; 3]; 3] = [
+    [0.393, 0.769, 0.189],
+    [0.349, 0.686, 0.168],
+    [0.272, 0.534, 0.131],
+];
+
+/// Tones an image sepia, `intensity` from 0 to 1 blending between the original and full sepia.
+pub fn sepia(media: Media, intensity: f32) -> Media {
+    let mut matrix = SEPIA;
+    for (row, values) in matrix.iter_mut().enumerate() {
+        for (column, value) in values.iter_mut().enumerate() {
+            let identity = if row == column { 1.0 } else { 0.0 };
+            *value = identity + (*value - identity) * intensity;
+        }
+    }
+
+    color_matrix(media, matrix)
+}
+
+/// How wide the ramp between kept and inverted values is when solarizing smoothly.
+const SOLARIZE_RAMP: f32 = 64.0;
+
+/// Inverts channel values above `threshold`, as if overexposing film. `smooth` ramps between kept and
+/// inverted values around the threshold rather than jumping.
+pub fn solarize(media: Media, threshold: u8, smooth: bool) -> Media {
+    map_channels(media, |value| {
+        let inverted = 255 - value;
+
+        if !smooth {
+            return if value > threshold { inverted } else { value };
+        }
+
+        let t = ((value as f32 - threshold as f32) / SOLARIZE_RAMP + 0.5).clamp(0.0, 1.0);
+        let weight = t * t * (3.0 - 2.0 * t);
+        (value as f32 + (inverted as f32 - value as f32) * weight).round() as u8
+    })
+}
+
+/// Picks the brightness best splitting the image into dark and light by Otsu's method, which maximizes
+/// the variance between the two. Transparent pixels are left out.
+pub fn otsu_threshold(img: &RgbaImage) -> u8 {
+    let mut histogram = [0u64; 256];
+    for pixel in img.pixels().filter(|pixel| pixel[3] > 0) {
+        histogram[luma(*pixel).round() as usize] += 1;
+    }
+
+    let total = histogram.iter().sum::<u64>() as f64;
+    let sum = histogram.iter().enumerate().map(|(value, &count)| value as f64 * count as f64).sum::<f64>();
+    let (mut dark, mut dark_sum) = (0.0, 0.0);
+    let (mut best, mut best_variance) = (0, -1.0);
+
+    for (value, &count) in histogram.iter().enumerate() {
+        dark += count as f64;
+        dark_sum += value as f64 * count as f64;
+        let light = total - dark;
+        if dark == 0.0 || light == 0.0 {
+            continue;
+        }
+
+        let difference = dark_sum / dark - (sum - dark_sum) / light;
+        let variance = dark * light * difference * difference;
+        if variance > best_variance {
+            (best, best_variance) = (value, variance);
+        }
+    }
+    best as u8
+}
+
+/// Turns pixels brighter than `level` white and the rest black, or the other way around when inverted.
+/// `None` picks the level with [`otsu_threshold`] on the first frame, so it doesn't change between frames.
+pub fn threshold(media: Media, level: Option<u8>, invert: bool) -> Media {
+    let level = level.unwrap_or_else(|| otsu_threshold(media.first_frame())) as f32;
+
+    media.map_pixels(|pixel| {
+        let value = match (luma(pixel).round() > level) != invert {
+            true => 255,
+            false => 0,
+        };
+        Rgba([value, value, value, pixel[3]])
+    })
+}
+
+/// The most colors the `gradientmap` filter takes.
+pub const MAX_GRADIENT_STOPS: usize = 16;
+
+/// Recolors every pixel by its brightness through a gradient of evenly spaced `stops`, from the color for
+/// black to the one for white. The stops' transparency is multiplied into the pixels'.
+pub fn gradient_map(media: Media, stops: &[Rgba<u8>]) -> Media {
+    assert!(!stops.is_empty(), "Gradients need at least one stop");
+
+    let segments = (stops.len() - 1).max(1) as f32;
+    let table = (0..=255)
+        .map(|value| {
+            let position = value as f32 / 255.0 * segments;
+            let index = (position.floor() as usize).min(stops.len().saturating_sub(2));
+            let (from, to) = (stops[index], stops[(index + 1).min(stops.len() - 1)]);
+            let t = position - index as f32;
+
+            [0, 1, 2, 3].map(|c| (from[c] as f32 + (to[c] as f32 - from[c] as f32) * t).round() as u8)
+        })
+        .collect::<Vec<_>>();
+
+    media.map_pixels(|pixel| {
+        let [r, g, b, a] = table[luma(pixel).round() as usize];
+        Rgba([r, g, b, (pixel[3] as u32 * a as u32 / 255) as u8])
+    })
+}
+
+/// A gradient map between two colors, `shadow` for black and `highlight` for white.
+pub fn duotone(media: Media, shadow: Rgba<u8>, highlight: Rgba<u8>) -> Media {
+    gradient_map(media, &[shadow, highlight])
+}
+
+/// Blends a color into every pixel, `strength` from 0 to 1 (further scaled by the color's own
+/// transparency). The pixels keep their transparency.
+pub fn tint(media: Media, color: Rgba<u8>, mode: BlendMode, strength: f32) -> Media {
+    let weight = strength * color[3] as f32 / 255.0;
+
+    media.map_pixels(|pixel| {
+        let mut out = pixel;
+        for c in 0..3 {
+            let (value, tint) = (pixel[c] as f32, color[c] as f32);
+            let blended = mode.channel(value, tint);
+            out[c] = (value + (blended - value) * weight).round() as u8;
+        }
+        out
+    })
+}
+
+/// Swaps `from` for `to` wherever a pixel looks within `tolerance` of it (see [`lab_distance`]), fading
+/// out over the next quarter of the tolerance so the edges stay smooth. Pixels are shifted by the
+/// difference between the colors rather than painted over, which keeps their shading, and the
+/// transparency of `to` is kept too so a color can be replaced with a transparent one.
+pub fn replace_color(media: Media, from: Rgba<u8>, to: Rgba<u8>, tolerance: f32) -> Media {
+    let feather = (tolerance / 4.0).max(1.0);
+
+    media.map_pixels(|pixel| {
+        let weight = ((tolerance + feather - lab_distance(pixel, from)) / feather).clamp(0.0, 1.0);
+        if weight == 0.0 {
+            return pixel;
+        }
+
+        let mut out = pixel;
+        for c in 0..3 {
+            let shifted = (pixel[c] as f32 + to[c] as f32 - from[c] as f32).clamp(0.0, 255.0);
+            out[c] = (pixel[c] as f32 + (shifted - pixel[c] as f32) * weight).round() as u8;
+        }
+        let alpha = pixel[3] as f32 * to[3] as f32 / 255.0;
+        out[3] = (pixel[3] as f32 + (alpha - pixel[3] as f32) * weight).round() as u8;
+        out
+    })
+}
+
+/// Grades every frame with a 3D LUT.
+pub fn apply_lut(media: Media, lut: &Lut) -> Media {
+    media.map_frames(|img| lut.apply(img))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(media: &Media) -> Rgba<u8> {
+        *media.first_frame().get_pixel(0, 0)
+    }
+
+    #[test]
+    fn inverts_colors() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([10, 20, 30, 255])));
+        assert_eq!(pixel(&invert(media)), Rgba([245, 235, 225, 255]));
+    }
+
+    #[test]
+    fn grayscale_keeps_alpha() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 100])));
+        assert_eq!(pixel(&grayscale(media, 256)), Rgba([54, 54, 54, 100]));
+    }
+
+    #[test]
+    fn grayscale_levels_posterize() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([100, 100, 100, 255])));
+        assert_eq!(pixel(&grayscale(media.clone(), 2)), Rgba([0, 0, 0, 255]));
+        assert_eq!(pixel(&grayscale(media, 3)), Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn posterizes_each_channel() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([20, 100, 240, 90])));
+        assert_eq!(pixel(&posterize(media, 2)), Rgba([0, 0, 255, 90]));
+    }
+
+    #[test]
+    fn rotates_hues() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([200, 50, 50, 255])));
+
+        assert_eq!(pixel(&huerotate(media.clone(), 0.0)), Rgba([200, 50, 50, 255]));
+        assert_eq!(pixel(&huerotate(media.clone(), 360.0)), Rgba([200, 50, 50, 255]));
+
+        let [r, g, b, _] = pixel(&huerotate(media, 120.0)).0;
+        assert!(g > r && g > b, "red should turn green, got {:?}", (r, g, b));
+    }
+
+    #[test]
+    fn hue_rotation_keeps_grays() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([90, 90, 90, 255])));
+        assert_eq!(pixel(&huerotate(media, 73.0)), Rgba([90, 90, 90, 255]));
+    }
+
+    #[test]
+    fn cycles_hues_over_the_frames() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([200, 40, 40, 255])));
+        let cycled = rainbow(media, 6);
+
+        match cycled {
+            Media::Animated(frames) => {
+                assert_eq!(frames.len(), 6);
+                assert_eq!(*frames[0].buffer().get_pixel(0, 0), Rgba([200, 40, 40, 255]));
+                assert_ne!(frames[2].buffer().get_pixel(0, 0), frames[4].buffer().get_pixel(0, 0));
+            },
+            Media::Static(_) => panic!("expected an animation"),
+        }
+    }
+
+    #[test]
+    fn scales_saturation() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([150, 100, 100, 255])));
+
+        assert_eq!(pixel(&saturate(media.clone(), 1.0)), Rgba([150, 100, 100, 255]));
+        assert_eq!(pixel(&saturate(media.clone(), 0.0)), Rgba([125, 125, 125, 255]));
+        assert_eq!(pixel(&saturate(media, 2.0)), Rgba([175, 75, 75, 255]));
+    }
+
+    #[test]
+    fn adjusts_brightness_and_contrast() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([100, 200, 0, 255])));
+
+        assert_eq!(pixel(&brightness_contrast(media.clone(), 0.0, 0.0)), Rgba([100, 200, 0, 255]));
+        assert_eq!(pixel(&brightness_contrast(media.clone(), 20.0, 0.0)), Rgba([151, 251, 51, 255]));
+        assert_eq!(pixel(&brightness_contrast(media.clone(), 0.0, 100.0)), Rgba([73, 255, 0, 255]));
+        assert_eq!(pixel(&brightness_contrast(media, 0.0, -100.0)), Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn corrects_gamma() {
+        let media = Media::Static(RgbaImage::from_fn(3, 1, |x, _| {
+            let value = [0, 64, 255][x as usize];
+            Rgba([value, value, value, 255])
+        }));
+        let corrected = gamma(media, 2.0);
+
+        assert_eq!(corrected.first_frame().get_pixel(0, 0)[0], 0);
+        assert_eq!(corrected.first_frame().get_pixel(1, 0)[0], 128);
+        assert_eq!(corrected.first_frame().get_pixel(2, 0)[0], 255);
+    }
+
+    #[test]
+    fn tones_sepia() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([100, 100, 100, 200])));
+
+        assert_eq!(pixel(&sepia(media.clone(), 1.0)), Rgba([135, 120, 94, 200]));
+        assert_eq!(pixel(&sepia(media.clone(), 0.5)), Rgba([118, 110, 97, 200]));
+        assert_eq!(pixel(&sepia(media, 0.0)), Rgba([100, 100, 100, 200]));
+    }
+
+    #[test]
+    fn solarizes_above_the_threshold() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([80, 140, 250, 255])));
+
+        assert_eq!(pixel(&solarize(media.clone(), 128, false)), Rgba([80, 115, 5, 255]));
+
+        let smooth = pixel(&solarize(media, 128, true));
+        assert_eq!(smooth[0], 80);
+        assert!((115..140).contains(&smooth[1]), "expected a value between the two, got {}", smooth[1]);
+        assert_eq!(smooth[2], 5);
+    }
+
+    #[test]
+    fn maps_brightness_to_gradients() {
+        let (black, white, gray) = (Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 128]), Rgba([128, 128, 128, 255]));
+        let (navy, orange) = (Rgba([20, 20, 90, 255]), Rgba([255, 140, 40, 255]));
+
+        assert_eq!(pixel(&duotone(Media::Static(RgbaImage::from_pixel(1, 1, black)), navy, orange)), navy);
+        assert_eq!(pixel(&duotone(Media::Static(RgbaImage::from_pixel(1, 1, white)), navy, orange)), Rgba([255, 140, 40, 128]));
+
+        let stops = [navy, Rgba([255, 0, 0, 255]), orange];
+        assert_eq!(pixel(&gradient_map(Media::Static(RgbaImage::from_pixel(1, 1, gray)), &stops)), Rgba([255, 1, 0, 255]));
+    }
+
+    #[test]
+    fn tints_in_every_mode() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([100, 200, 0, 128])));
+        let red = Rgba([255, 0, 0, 255]);
+
+        assert_eq!(pixel(&tint(media.clone(), red, BlendMode::Normal, 0.5)), Rgba([178, 100, 0, 128]));
+        assert_eq!(pixel(&tint(media.clone(), red, BlendMode::Multiply, 1.0)), Rgba([100, 0, 0, 128]));
+        assert_eq!(pixel(&tint(media.clone(), red, BlendMode::Screen, 1.0)), Rgba([255, 200, 0, 128]));
+        assert_eq!(pixel(&tint(media, Rgba([255, 0, 0, 0]), BlendMode::Normal, 1.0)), Rgba([100, 200, 0, 128]));
+    }
+
+    #[test]
+    fn splits_by_otsu_thresholds() {
+        // Dark grays on the left and light ones on the right, with slightly more light ones.
+        let img = RgbaImage::from_fn(10, 1, |x, _| {
+            let value = [20, 30, 40, 30, 200, 210, 220, 230, 210, 200][x as usize];
+            Rgba([value, value, value, 255])
+        });
+        let level = otsu_threshold(&img);
+        assert!((40..200).contains(&level), "{}", level);
+
+        let binarized = threshold(Media::Static(img.clone()), None, false);
+        assert_eq!(*binarized.first_frame().get_pixel(2, 0), Rgba([0, 0, 0, 255]));
+        assert_eq!(*binarized.first_frame().get_pixel(4, 0), Rgba([255, 255, 255, 255]));
+
+        let inverted = threshold(Media::Static(img), Some(210), true);
+        assert_eq!(*inverted.first_frame().get_pixel(5, 0), Rgba([255, 255, 255, 255]));
+        assert_eq!(*inverted.first_frame().get_pixel(6, 0), Rgba([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn replaces_similar_colors() {
+        let img = RgbaImage::from_fn(3, 1, |x, _| [Rgba([200, 0, 0, 255]), Rgba([180, 10, 10, 255]), Rgba([0, 0, 200, 255])][x as usize]);
+        let replaced = replace_color(Media::Static(img), Rgba([200, 0, 0, 255]), Rgba([0, 200, 0, 255]), 20.0);
+        let replaced = replaced.first_frame();
+
+        assert_eq!(*replaced.get_pixel(0, 0), Rgba([0, 200, 0, 255]));
+        // Close enough to be replaced, and shifted so it's still a little darker.
+        assert_eq!(*replaced.get_pixel(1, 0), Rgba([0, 210, 10, 255]));
+        assert_eq!(*replaced.get_pixel(2, 0), Rgba([0, 0, 200, 255]));
+
+        let white = Rgba([255, 255, 255, 255]);
+        let cleared = replace_color(Media::Static(RgbaImage::from_pixel(1, 1, white)), white, Rgba([0, 0, 0, 0]), 0.0);
+        assert_eq!(pixel(&cleared)[3], 0);
+    }
+
+    #[test]
+    fn quantizes_to_even_steps() {
+        assert_eq!(quantize(0.0, 4), 0);
+        assert_eq!(quantize(90.0, 4), 85);
+        assert_eq!(quantize(200.0, 4), 170);
+        assert_eq!(quantize(255.0, 4), 255);
+    }
+}
@@ -0,0 +1,505 @@
+//! Effects that make a GIF out of a still image, moving the whole picture around from frame to frame.
+//! Animated inputs get the same motion spread over their own frames instead.
+
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+use super::{circle, remap};
+use crate::geometry::{blend_over, cover, mix, overlay, sample_bilinear};
+use crate::random::Rng;
+use crate::text::{Align, Emojis, Font, TextBlock, TextStyle};
+use crate::{Media, Result};
+
+/// How long each frame of a generated zoom is shown.
+const ZOOM_FRAME_DELAY_MS: u32 = 50;
+
+/// How many frames one turn of a spin takes at normal speed, and how long each is shown.
+const SPIN_FRAMES: f32 = 24.0;
+const SPIN_FRAME_DELAY_MS: u32 = 40;
+
+/// How many frames a shake or a wiggle has, and how long each is shown.
+const SHAKE_FRAMES: usize = 10;
+const SHAKE_FRAME_DELAY_MS: u32 = 30;
+const WIGGLE_FRAMES: usize = 16;
+const WIGGLE_FRAME_DELAY_MS: u32 = 40;
+
+/// How many frames one bounce takes at normal speed, and how long each is shown.
+const BOUNCE_FRAMES: f32 = 20.0;
+const BOUNCE_FRAME_DELAY_MS: u32 = 30;
+/// How much wider and flatter the image gets when it lands.
+const BOUNCE_SQUASH: f32 = 0.25;
+/// How much taller and thinner it gets at its fastest, just after leaving the ground.
+const BOUNCE_STRETCH: f32 = 0.1;
+/// The part of the bounce, at either end, spent squashed against the ground.
+const BOUNCE_IMPACT: f32 = 0.12;
+
+const TRIGGERED_FRAMES: usize = 8;
+const TRIGGERED_FRAME_DELAY_MS: u32 = 20;
+/// The red laid over the image, with how much of it shows.
+const TRIGGERED_TINT: Rgba<u8> = Rgba([255, 20, 20, 90]);
+const TRIGGERED_BANNER: Rgba<u8> = Rgba([230, 20, 20, 255]);
+/// How tall the banner is compared to the whole image.
+const TRIGGERED_BANNER_HEIGHT: f32 = 0.2;
+
+const CROSSFADE_FRAME_DELAY_MS: u32 = 50;
+const FADE_FRAME_DELAY_MS: u32 = 50;
+
+/// The frames of the patting hand side by side, each a square this large.
+const PETPET_HAND: &[u8] = include_bytes!("../../assets/petpet.png");
+pub const PETPET_SIZE: u32 = 112;
+const PETPET_FRAME_DELAY_MS: f32 = 60.0;
+/// How much the image is squashed on each frame of the pat, following the hand pressing down.
+const PETPET_SQUISH: [f32; 5] = [0.0, 0.1, 0.22, 0.12, 0.03];
+
+/// The image magnified `scale` times around its center, at the same size.
+pub fn zoom_image(img: &RgbaImage, scale: f32) -> RgbaImage {
+    let (cx, cy) = (img.width() as f32 / 2.0, img.height() as f32 / 2.0);
+    let scale = scale.max(f32::EPSILON);
+
+    remap(img, |x, y| (cx + (x - cx) / scale, cy + (y - cy) / scale))
+}
+
+/// Zooms into the center over `frames` frames until the image is magnified `factor` times, or starts
+/// there and zooms back out with `out`. The scale grows by the same ratio every frame, so the motion
+/// looks steady instead of slowing down.
+pub fn zoom(media: Media, frames: usize, factor: f32, out: bool) -> Media {
+    let media = media.into_animation(frames, ZOOM_FRAME_DELAY_MS);
+    let steps = media.frame_count().saturating_sub(1).max(1) as f32;
+
+    media.map_images_indexed(|index, img| {
+        let progress = index as f32 / steps;
+        let progress = if out { 1.0 - progress } else { progress };
+        zoom_image(img, factor.powf(progress))
+    })
+}
+
+/// Rotates the image clockwise around its center without growing the canvas, so whatever is turned
+/// past the edges is cut off.
+pub fn spin_image(img: &RgbaImage, degrees: f32) -> RgbaImage {
+    let (cx, cy) = (img.width() as f32 / 2.0, img.height() as f32 / 2.0);
+    let (sin, cos) = degrees.to_radians().sin_cos();
+
+    RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+        sample_bilinear(img, dx * cos + dy * sin + cx, -dx * sin + dy * cos + cy)
+    })
+}
+
+/// Centers the image on a transparent square as wide as its diagonal, room for it to turn in.
+fn pad_for_spinning(img: &RgbaImage) -> RgbaImage {
+    let side = (img.width() as f32).hypot(img.height() as f32).ceil() as u32;
+    let mut canvas = RgbaImage::new(side, side);
+
+    overlay(&mut canvas, img, (side - img.width()) as i64 / 2, (side - img.height()) as i64 / 2);
+    canvas
+}
+
+/// Makes a GIF of the image turning once around its center. It is cropped to a circle first unless
+/// `square`, which spins the whole image on a larger canvas instead. Higher speeds take fewer frames.
+pub fn spin(media: Media, speed: f32, square: bool) -> Media {
+    let media = match square {
+        true => media.map_images(pad_for_spinning),
+        false => circle(media, None),
+    };
+
+    let frames = (SPIN_FRAMES / speed.max(0.1)).round().max(2.0) as usize;
+    let media = media.into_animation(frames, SPIN_FRAME_DELAY_MS);
+    let count = media.frame_count() as f32;
+
+    media.map_images_indexed(|index, img| spin_image(img, 360.0 * index as f32 / count))
+}
+
+/// The image moved by `(dx, dy)` pixels. The edges repeat into the space left behind, so the frame
+/// stays filled.
+pub fn shift_image(img: &RgbaImage, dx: f32, dy: f32) -> RgbaImage {
+    remap(img, |x, y| (x - dx, y - dy))
+}
+
+/// How far a shake or wiggle of the given intensity moves the image, in pixels. An intensity of 10 is
+/// a tenth of the shorter side.
+fn sway_distance(img: &RgbaImage, intensity: f32) -> f32 {
+    img.width().min(img.height()) as f32 * intensity / 100.0
+}
+
+/// Makes a GIF of the image jittering around by up to `intensity` percent of its shorter side, in a
+/// random direction every frame.
+pub fn shake(media: Media, intensity: f32) -> Media {
+    media.into_animation(SHAKE_FRAMES, SHAKE_FRAME_DELAY_MS).map_images_indexed(|index, img| {
+        let mut rng = Rng::new(index as u64);
+        let distance = sway_distance(img, intensity);
+
+        shift_image(img, (rng.next_f32() * 2.0 - 1.0) * distance, (rng.next_f32() * 2.0 - 1.0) * distance)
+    })
+}
+
+/// Makes a GIF of the image swaying smoothly from side to side, bobbing up and down twice as often, so
+/// it traces a figure eight.
+pub fn wiggle(media: Media, intensity: f32) -> Media {
+    let media = media.into_animation(WIGGLE_FRAMES, WIGGLE_FRAME_DELAY_MS);
+    let count = media.frame_count() as f32;
+
+    media.map_images_indexed(|index, img| {
+        let phase = std::f32::consts::TAU * index as f32 / count;
+        let distance = sway_distance(img, intensity);
+
+        shift_image(img, phase.sin() * distance, (phase * 2.0).sin() * distance / 2.0)
+    })
+}
+
+/// How much the image is scaled across and down `progress` (0 to 1) of the way through a bounce,
+/// squashed on the ground and stretched by how fast it moves.
+fn squash_and_stretch(progress: f32) -> (f32, f32) {
+    let from_ground = progress.min(1.0 - progress);
+
+    match from_ground < BOUNCE_IMPACT {
+        true => {
+            let squash = BOUNCE_SQUASH * (1.0 - from_ground / BOUNCE_IMPACT);
+            (1.0 + squash, 1.0 - squash)
+        },
+        false => {
+            let stretch = BOUNCE_STRETCH * (1.0 - 2.0 * progress).abs();
+            (1.0 - stretch / 2.0, 1.0 + stretch)
+        },
+    }
+}
+
+/// One frame of a bounce: the image `progress` of the way through a jump as high as `height` times its
+/// own height, standing on the bottom of a canvas with room for the whole bounce.
+fn bounce_frame(img: &RgbaImage, height: f32, progress: f32) -> RgbaImage {
+    let (width, image_height) = (img.width() as f32, img.height() as f32);
+    let canvas_width = (width * (1.0 + BOUNCE_SQUASH)).ceil() as u32;
+    let canvas_height = (image_height * (1.0 + BOUNCE_STRETCH + height)).ceil() as u32;
+
+    let (scale_x, scale_y) = squash_and_stretch(progress);
+    let (scaled_width, scaled_height) = ((width * scale_x).round().max(1.0), (image_height * scale_y).round().max(1.0));
+    let scaled = imageops::resize(img, scaled_width as u32, scaled_height as u32, FilterType::Triangle);
+
+    // A parabola, on the ground at both ends and at the top halfway through.
+    let lift = 4.0 * progress * (1.0 - progress) * height * image_height;
+
+    let mut canvas = RgbaImage::new(canvas_width, canvas_height);
+    let x = (canvas_width as f32 - scaled_width) / 2.0;
+    let y = canvas_height as f32 - scaled_height - lift;
+    overlay(&mut canvas, &scaled, x.round() as i64, y.round() as i64);
+    canvas
+}
+
+/// Makes a GIF of the image bouncing up to `height` percent of its height, squashing as it lands.
+/// Higher speeds take fewer frames per bounce.
+pub fn bounce(media: Media, height: f32, speed: f32) -> Media {
+    let frames = (BOUNCE_FRAMES / speed.max(0.1)).round().max(4.0) as usize;
+    let media = media.into_animation(frames, BOUNCE_FRAME_DELAY_MS);
+    let count = media.frame_count() as f32;
+
+    media.map_images_indexed(|index, img| bounce_frame(img, height / 100.0, index as f32 / count))
+}
+
+/// The "TRIGGERED" banner, white text on red across the whole width.
+fn triggered_banner(width: u32, height: u32, fonts: &[&Font<'static>]) -> RgbaImage {
+    let mut banner = RgbaImage::from_pixel(width, height, TRIGGERED_BANNER);
+    let style = TextStyle::new(fonts, height as f32 * 0.8, Rgba([255, 255, 255, 255]))
+        .align(Align::Center)
+        .stroke((height / 16).max(1), Rgba([0, 0, 0, 255]));
+
+    let block = TextBlock::fit(style, "TRIGGERED", width as f32 * 0.9, height as f32 * 0.9, 4.0);
+    let (x, y) = ((width as f32 * 0.05).round(), ((height as f32 - block.height()) / 2.0).round());
+    block.draw(&mut banner, x as i32, y as i32, &Emojis::new());
+    banner
+}
+
+/// The classic triggered meme: the image tinted red and shaking by up to `intensity` percent of its
+/// shorter side above a "TRIGGERED" banner along the bottom, drawn with the first of `fonts` that has its glyphs.
+pub fn triggered(media: Media, intensity: f32, fonts: &[&Font<'static>]) -> Media {
+    let (width, height) = media.dimensions();
+    let banner_height = ((height as f32 * TRIGGERED_BANNER_HEIGHT).round() as u32).max(1);
+    let banner = triggered_banner(width, banner_height, fonts);
+
+    media.into_animation(TRIGGERED_FRAMES, TRIGGERED_FRAME_DELAY_MS).map_images_indexed(|index, img| {
+        let mut rng = Rng::new(index as u64);
+        let reach = width.min(height) as f32 * intensity / 100.0;
+        let mut jitter = || (rng.next_f32() * 2.0 - 1.0) * reach;
+
+        // Zoomed in a little, so the shake never shows the edges.
+        let mut frame = shift_image(&zoom_image(img, 1.15), jitter(), jitter());
+        for pixel in frame.pixels_mut() {
+            let alpha = pixel[3];
+            *pixel = blend_over(*pixel, TRIGGERED_TINT);
+            pixel[3] = alpha;
+        }
+
+        overlay(&mut frame, &banner, 0, (height - banner_height) as i64);
+        frame
+    })
+}
+
+/// The frames of the bundled patting hand.
+pub fn petpet_hand() -> Result<Vec<RgbaImage>> {
+    let sheet = image::load_from_memory(PETPET_HAND)?.into_rgba8();
+
+    Ok((0..sheet.width() / PETPET_SIZE)
+        .map(|index| imageops::crop_imm(&sheet, index * PETPET_SIZE, 0, PETPET_SIZE, PETPET_SIZE).to_image())
+        .collect())
+}
+
+/// One frame of a pat: the image cropped square and squashed by `squish` under the hand, on a
+/// transparent canvas.
+fn pat_frame(img: &RgbaImage, hand: &RgbaImage, squish: f32) -> RgbaImage {
+    let base = PETPET_SIZE as f32 * 0.8;
+    let (width, height) = ((base * (1.0 + squish / 2.0)).round() as u32, (base * (1.0 - squish)).round() as u32);
+    let squashed = imageops::resize(&cover(img, base as u32, base as u32, FilterType::Triangle), width, height, FilterType::Triangle);
+
+    let mut canvas = RgbaImage::new(PETPET_SIZE, PETPET_SIZE);
+    overlay(&mut canvas, &squashed, (PETPET_SIZE as i64 - width as i64) / 2 + 6, (PETPET_SIZE - height) as i64);
+    overlay(&mut canvas, hand, 0, 0);
+    canvas
+}
+
+/// Makes a GIF of `hand` (see [`petpet_hand`]) patting the image, which squashes under it. Higher
+/// speeds show each frame for less time, animations keep their own timing.
+pub fn petpet(media: Media, hand: &[RgbaImage], speed: f32) -> Media {
+    let delay = (PETPET_FRAME_DELAY_MS / speed.max(0.1)).round().max(20.0) as u32;
+    let media = media.into_animation(hand.len(), delay);
+
+    media.map_images_indexed(|index, img| {
+        let frame = index % hand.len();
+        pat_frame(img, &hand[frame], PETPET_SQUISH.get(frame).copied().unwrap_or(0.0))
+    })
+}
+
+/// Thresholds for dithering alpha, in sixteenths.
+const BAYER: [[u8; 4]; 4] = [[0, 8, 2, 10], [12, 4, 14, 6], [3, 11, 1, 9], [15, 7, 13, 5]];
+
+/// GIFs show any alpha but 0 as fully opaque, so fading alpha has to be dithered to either, dissolving
+/// the image pixel by pixel instead of snapping away on the last frame.
+fn dither_alpha(alpha: u8, x: u32, y: u32) -> u8 {
+    let threshold = (BAYER[y as usize % 4][x as usize % 4] as f32 + 0.5) / 16.0 * 255.0;
+
+    match alpha as f32 > threshold {
+        true => 255,
+        false => 0,
+    }
+}
+
+/// The pixel `amount` of the way to `color`. Fading to a transparent color only fades out the alpha,
+/// so the colors don't turn dark on the way.
+fn fade_pixel(pixel: Rgba<u8>, color: Rgba<u8>, amount: f32) -> Rgba<u8> {
+    let towards = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * amount).round() as u8;
+
+    match color[3] {
+        0 => Rgba([pixel[0], pixel[1], pixel[2], towards(pixel[3], 0)]),
+        _ => Rgba(std::array::from_fn(|c| towards(pixel[c], color[c]))),
+    }
+}
+
+/// Fades the image in from `color` over `frames` frames, or out to it with `out`. Animations fade over
+/// their own frames. Fades to and from transparent dissolve the image, see [`dither_alpha`].
+pub fn fade(media: Media, frames: usize, color: Rgba<u8>, out: bool) -> Media {
+    let media = media.into_animation(frames, FADE_FRAME_DELAY_MS);
+    let steps = media.frame_count().saturating_sub(1).max(1) as f32;
+
+    media.map_images_indexed(|index, img| {
+        let progress = index as f32 / steps;
+        let amount = if out { progress } else { 1.0 - progress };
+
+        let mut img = img.clone();
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            *pixel = fade_pixel(*pixel, color, amount);
+            if color[3] == 0 {
+                pixel[3] = dither_alpha(pixel[3], x, y);
+            }
+        }
+        img
+    })
+}
+
+/// Fades from one image into the other over `frames` frames and back again over as many. The fade
+/// eases in and out, so it lingers on both images. The second image is cropped to fill the size of the
+/// first.
+pub fn crossfade(from: &RgbaImage, to: &RgbaImage, frames: usize) -> Media {
+    let to = match to.dimensions() == from.dimensions() {
+        true => to.clone(),
+        false => cover(to, from.width(), from.height(), FilterType::Lanczos3),
+    };
+    let frames = frames.max(1);
+
+    Media::Static(from.clone()).into_animation(frames * 2, CROSSFADE_FRAME_DELAY_MS).map_images_indexed(|index, from| {
+        let amount = (1.0 - (std::f32::consts::PI * index as f32 / frames as f32).cos()) / 2.0;
+        mix(from, &to, amount)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn zooms_into_the_center() {
+        let img = RgbaImage::from_fn(32, 32, |x, y| Rgba([(x * 8) as u8, (y * 8) as u8, 0, 255]));
+
+        assert_eq!(zoom_image(&img, 1.0), img);
+        let zoomed = zoom_image(&img, 2.0);
+        // The corner now shows what was a quarter of the way in.
+        assert!((56..=72).contains(&zoomed.get_pixel(0, 0)[0]), "{:?}", zoomed.get_pixel(0, 0));
+        assert_eq!(zoomed.dimensions(), img.dimensions());
+    }
+
+    #[test]
+    fn zooms_in_or_out() {
+        let img = RgbaImage::from_fn(32, 32, |x, y| Rgba([(x * 8) as u8, (y * 8) as u8, 0, 255]));
+
+        let zoomed = zoom(Media::Static(img.clone()), 5, 2.0, false);
+        assert_eq!(zoomed.frame_count(), 5);
+        assert_eq!(zoomed.first_frame(), &img);
+
+        let zoomed = zoom(Media::Static(img.clone()), 5, 2.0, true);
+        assert_eq!(zoomed.first_frame(), &zoom_image(&img, 2.0));
+    }
+
+    #[test]
+    fn spins_a_full_turn() {
+        let img = RgbaImage::from_fn(20, 10, |x, _| Rgba([(x * 12) as u8, 0, 0, 255]));
+
+        let spun = spin(Media::Static(img.clone()), 2.0, false);
+        assert_eq!(spun.frame_count(), 12);
+        assert_eq!(spun.dimensions(), (10, 10));
+        assert_eq!(spun.first_frame().get_pixel(0, 0)[3], 0);
+
+        // Spinning the whole image needs room for its corners.
+        let spun = spin(Media::Static(img), 1.0, true);
+        assert_eq!(spun.frame_count(), 24);
+        assert_eq!(spun.dimensions(), (23, 23));
+    }
+
+    #[test]
+    fn shifts_with_repeating_edges() {
+        let img = RgbaImage::from_fn(8, 8, |x, _| Rgba([(x * 30) as u8, 0, 0, 255]));
+        let shifted = shift_image(&img, 2.0, 0.0);
+
+        assert_eq!(shifted.get_pixel(4, 0), img.get_pixel(2, 0));
+        assert_eq!(shifted.get_pixel(0, 0), img.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn shakes_and_wiggles() {
+        let img = RgbaImage::from_fn(20, 20, |x, y| Rgba([(x * 12) as u8, (y * 12) as u8, 0, 255]));
+
+        let shaken = shake(Media::Static(img.clone()), 10.0);
+        assert_eq!(shaken.frame_count(), SHAKE_FRAMES);
+        assert_eq!(shaken.first_frame(), shake(Media::Static(img.clone()), 10.0).first_frame());
+
+        let wiggled = wiggle(Media::Static(img.clone()), 10.0);
+        assert_eq!(wiggled.frame_count(), WIGGLE_FRAMES);
+        assert_eq!(wiggled.first_frame(), &img);
+    }
+
+    #[test]
+    fn squashes_on_impact() {
+        let (x, y) = squash_and_stretch(0.0);
+        assert!(x > 1.0 && y < 1.0);
+
+        let (x, y) = squash_and_stretch(0.2);
+        assert!(x < 1.0 && y > 1.0);
+        assert_eq!(squash_and_stretch(0.5), (1.0, 1.0));
+    }
+
+    #[test]
+    fn bounces_on_a_taller_canvas() {
+        let img = RgbaImage::from_pixel(20, 20, Rgba([255, 0, 0, 255]));
+        let bounced = bounce(Media::Static(img), 50.0, 1.0);
+
+        assert_eq!(bounced.frame_count(), 20);
+        assert_eq!(bounced.dimensions(), (25, 32));
+        // Squashed on the ground, the image touches the bottom but not the top.
+        let first = bounced.first_frame();
+        assert_eq!(first.get_pixel(12, 31)[3], 255);
+        assert_eq!(first.get_pixel(12, 10)[3], 0);
+    }
+
+    #[test]
+    fn triggers() {
+        let font = crate::text::bold_font();
+        let img = RgbaImage::from_pixel(40, 40, Rgba([255, 255, 255, 255]));
+        let triggered = triggered(Media::Static(img), 4.0, &[&font]);
+
+        assert_eq!(triggered.frame_count(), TRIGGERED_FRAMES);
+        assert_eq!(triggered.dimensions(), (40, 40));
+
+        // Tinted red above, the banner below.
+        let first = triggered.first_frame();
+        assert!(first.get_pixel(20, 5)[1] < 255 && first.get_pixel(20, 5)[0] == 255);
+        assert_eq!(*first.get_pixel(0, 39), TRIGGERED_BANNER);
+    }
+
+    #[test]
+    fn pats() {
+        let hand = (0..5).map(|_| RgbaImage::from_fn(PETPET_SIZE, PETPET_SIZE, |_, y| match y < 10 {
+            true => Rgba([255, 200, 150, 255]),
+            false => Rgba([0, 0, 0, 0]),
+        })).collect::<Vec<_>>();
+        let img = RgbaImage::from_pixel(50, 30, Rgba([0, 0, 255, 255]));
+        let patted = petpet(Media::Static(img), &hand, 1.0);
+
+        assert_eq!(patted.frame_count(), 5);
+        assert_eq!(patted.dimensions(), (PETPET_SIZE, PETPET_SIZE));
+
+        let first = patted.first_frame();
+        assert_eq!(*first.get_pixel(50, 0), Rgba([255, 200, 150, 255]));
+        assert_eq!(*first.get_pixel(60, 100), Rgba([0, 0, 255, 255]));
+        assert_eq!(first.get_pixel(0, 100)[3], 0);
+    }
+
+    #[test]
+    fn crossfades_there_and_back() {
+        let from = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        let to = RgbaImage::from_pixel(8, 2, Rgba([200, 100, 0, 255]));
+
+        let faded = crossfade(&from, &to, 4);
+        assert_eq!(faded.frame_count(), 8);
+        assert_eq!(faded.dimensions(), (4, 4));
+        match faded {
+            Media::Animated(frames) => {
+                assert_eq!(frames[0].buffer(), &from);
+                assert_eq!(frames[2].buffer().get_pixel(1, 1), &Rgba([100, 50, 0, 255]));
+                assert_eq!(frames[4].buffer().get_pixel(1, 1), &Rgba([200, 100, 0, 255]));
+                assert_eq!(frames[6].buffer(), frames[2].buffer());
+            },
+            Media::Static(_) => panic!("expected an animation"),
+        }
+    }
+
+    #[test]
+    fn fades_in_and_out() {
+        let img = RgbaImage::from_pixel(2, 2, Rgba([200, 100, 50, 255]));
+
+        let faded = fade(Media::Static(img.clone()), 3, Rgba([0, 0, 0, 255]), false);
+        assert_eq!(faded.frame_count(), 3);
+        assert_eq!(faded.first_frame().get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+
+        let faded = fade(Media::Static(img), 3, Rgba([0, 0, 0, 255]), true);
+        assert_eq!(faded.first_frame().get_pixel(0, 0), &Rgba([200, 100, 50, 255]));
+    }
+
+    #[test]
+    fn dissolves_transparent_fades() {
+        let img = RgbaImage::from_pixel(16, 16, Rgba([200, 100, 50, 255]));
+        let (encoded, _) = fade(Media::Static(img), 5, Rgba([0, 0, 0, 0]), true).encode(crate::OutputFormat::Gif).unwrap();
+
+        // What matters is what survives the GIF encoder, which only keeps alpha of 0 and 255.
+        let frames = match Media::decode(&encoded, &crate::Quality::full()).unwrap() {
+            Media::Animated(frames) => frames,
+            Media::Static(_) => panic!("expected an animation"),
+        };
+        let opaque = frames
+            .iter()
+            .map(|frame| frame.buffer().pixels().filter(|pixel| pixel[3] == 255).count() as f32 / 256.0)
+            .collect::<Vec<_>>();
+
+        assert_eq!(frames.len(), 5);
+        assert!(frames.iter().all(|frame| frame.buffer().pixels().all(|pixel| pixel[3] == 0 || pixel[3] == 255)));
+        assert_eq!(opaque[0], 1.0);
+        assert_eq!(opaque[4], 0.0);
+        assert!(opaque.windows(2).all(|pair| pair[1] < pair[0]), "{:?}", opaque);
+        assert!((opaque[2] - 0.5).abs() < 0.1, "{:?}", opaque);
+    }
+}
@@ -0,0 +1,154 @@
+//! Content-aware resizing by seam carving. Here it's mostly used for the look of squashing an image
+//! down and stretching it back out, which mangles everything but the most detailed parts.
+
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+use super::luma;
+use crate::{fit_dimensions, Media};
+
+/// Frames are carved at most this large, as every removed seam costs a pass over the whole image.
+pub const MAX_MAGIK_DIMENSION: u32 = 320;
+/// Animations are cut down to this many frames before carving.
+pub const MAX_MAGIK_FRAMES: usize = 24;
+
+/// How much each pixel stands out from its neighbours. Seams go through the lowest ones.
+fn energy(pixels: &[Rgba<u8>], width: usize) -> Vec<f32> {
+    let height = pixels.len() / width;
+    let brightness = pixels.iter().map(|pixel| luma(*pixel) * pixel[3] as f32 / 255.0).collect::<Vec<_>>();
+    let at = |x: usize, y: usize| brightness[y * width + x];
+
+    (0..pixels.len())
+        .map(|index| {
+            let (x, y) = (index % width, index / width);
+            let here = at(x, y);
+            let dx = (at((x + 1).min(width - 1), y) - here).abs() + (here - at(x.saturating_sub(1), y)).abs();
+            let dy = (at(x, (y + 1).min(height - 1)) - here).abs() + (here - at(x, y.saturating_sub(1))).abs();
+            dx + dy
+        })
+        .collect()
+}
+
+/// The path from the top row to the bottom one with the least total energy, as the column it takes
+/// in every row. Each step moves at most one column to either side.
+fn find_seam(energy: &[f32], width: usize) -> Vec<usize> {
+    let height = energy.len() / width;
+    let mut cost = energy.to_vec();
+    let neighbours = |x: usize| x.saturating_sub(1)..=(x + 1).min(width - 1);
+
+    for y in 1..height {
+        let (done, rest) = cost.split_at_mut(y * width);
+        let above = &done[(y - 1) * width..];
+
+        for (x, cost) in rest[..width].iter_mut().enumerate() {
+            *cost += neighbours(x).map(|x| above[x]).fold(f32::INFINITY, f32::min);
+        }
+    }
+
+    let cheapest = |row: usize, columns: std::ops::RangeInclusive<usize>| {
+        columns.min_by(|a, b| cost[row * width + a].total_cmp(&cost[row * width + b])).unwrap()
+    };
+
+    let mut seam = vec![0; height];
+    seam[height - 1] = cheapest(height - 1, 0..=width - 1);
+    for y in (0..height - 1).rev() {
+        seam[y] = cheapest(y, neighbours(seam[y + 1]));
+    }
+    seam
+}
+
+/// Removes the given amount of seams from pixels laid out in rows of `width`, returning the new width.
+fn carve_columns(mut pixels: Vec<Rgba<u8>>, mut width: usize, columns: usize) -> (Vec<Rgba<u8>>, usize) {
+    for _ in 0..columns.min(width - 1) {
+        let seam = find_seam(&energy(&pixels, width), width);
+
+        pixels = pixels
+            .chunks_exact(width)
+            .zip(&seam)
+            .flat_map(|(row, &skip)| row[..skip].iter().chain(&row[skip + 1..]).copied())
+            .collect();
+        width -= 1;
+    }
+    (pixels, width)
+}
+
+/// Swaps rows for columns, so carving the result removes horizontal seams.
+fn transpose(pixels: &[Rgba<u8>], width: usize) -> Vec<Rgba<u8>> {
+    let height = pixels.len() / width;
+    (0..pixels.len()).map(|index| pixels[(index % height) * width + index / height]).collect()
+}
+
+/// Seam carves the image down to `1 - intensity` of its size on both sides, then stretches it back out.
+pub fn magik_image(img: &RgbaImage, intensity: f32) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    if width < 2 || height < 2 {
+        return img.clone();
+    }
+
+    let small = match width > MAX_MAGIK_DIMENSION || height > MAX_MAGIK_DIMENSION {
+        true => {
+            let (width, height) = fit_dimensions(width, height, MAX_MAGIK_DIMENSION, MAX_MAGIK_DIMENSION);
+            imageops::resize(img, width, height, FilterType::Triangle)
+        },
+        false => img.clone(),
+    };
+
+    let (small_width, small_height) = (small.width() as usize, small.height() as usize);
+    let removed = |size: usize| size - ((size as f32 * (1.0 - intensity)).round() as usize).max(1);
+
+    let (pixels, carved_width) = carve_columns(small.pixels().copied().collect(), small_width, removed(small_width));
+    let (pixels, carved_height) = carve_columns(transpose(&pixels, carved_width), small_height, removed(small_height));
+    let pixels = transpose(&pixels, carved_height);
+
+    let carved = RgbaImage::from_fn(carved_width as u32, carved_height as u32, |x, y| {
+        pixels[y as usize * carved_width + x as usize]
+    });
+    imageops::resize(&carved, width, height, FilterType::Triangle)
+}
+
+/// Seam carves every frame, see [`magik_image`]. Animations keep at most [`MAX_MAGIK_FRAMES`] frames.
+pub fn magik(media: Media, intensity: f32) -> Media {
+    media.limit_frames(MAX_MAGIK_FRAMES).par_map_images(|img| magik_image(img, intensity))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seams_avoid_detail() {
+        // A bright line down column 2 and a dark background, the seam should stay out of the line.
+        let pixels = (0..5 * 4).map(|i| if i % 5 == 2 { Rgba([255; 4]) } else { Rgba([0, 0, 0, 255]) }).collect::<Vec<_>>();
+        let seam = find_seam(&energy(&pixels, 5), 5);
+
+        assert_eq!(seam.len(), 4);
+        assert!(seam.iter().all(|&x| x == 0 || x == 4), "{:?}", seam);
+    }
+
+    #[test]
+    fn carving_keeps_the_detailed_column() {
+        let pixels = (0..8 * 3).map(|i| if i % 8 == 3 { Rgba([255; 4]) } else { Rgba([0, 0, 0, 255]) }).collect::<Vec<_>>();
+        let (carved, width) = carve_columns(pixels, 8, 4);
+
+        assert_eq!(width, 4);
+        assert_eq!(carved.len(), 12);
+        assert!(carved.chunks_exact(4).all(|row| row.contains(&Rgba([255; 4]))));
+    }
+
+    #[test]
+    fn transposes() {
+        let pixels = (0..6).map(|i| Rgba([i, 0, 0, 255])).collect::<Vec<_>>();
+        let columns = transpose(&pixels, 3).iter().map(|pixel| pixel[0]).collect::<Vec<_>>();
+
+        assert_eq!(columns, [0, 3, 1, 4, 2, 5]);
+        assert_eq!(transpose(&transpose(&pixels, 3), 2), pixels);
+    }
+
+    #[test]
+    fn keeps_the_original_size() {
+        let img = RgbaImage::from_fn(40, 30, |x, y| Rgba([(x * 6) as u8, (y * 8) as u8, 0, 255]));
+
+        assert_eq!(magik_image(&img, 0.5).dimensions(), (40, 30));
+        assert_eq!(magik_image(&RgbaImage::new(1, 5), 0.5).dimensions(), (1, 5));
+    }
+}
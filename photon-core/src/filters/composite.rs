@@ -0,0 +1,436 @@
+//! Filters that combine an image with another one.
+
+use image::imageops::{self, FilterType};
+use image::{Frame, Rgba, RgbaImage};
+
+use super::{circle_image, Gravity};
+use crate::geometry::{self, blend_over, cover};
+use crate::text::{Align, Emojis, Font, TextBlock, TextStyle};
+use crate::{fit_dimensions, BlendMode, Media};
+
+/// How large the avatars of a ship are. The heart sits between them in a space as wide.
+pub const SHIP_AVATAR_SIZE: u32 = 256;
+/// The largest cells of a grid get, on either side.
+pub const MAX_GRID_CELL: u32 = 512;
+/// How large a watermark is compared to the image it's stamped on.
+const WATERMARK_SCALE: f32 = 0.25;
+/// How far a watermark is kept from the edges, compared to the shorter side of the image.
+const WATERMARK_MARGIN: f32 = 0.02;
+
+/// The color of the heart at 0% and 100%, it goes from one to the other with the percentage.
+const SHIP_COLD: [f32; 3] = [130.0, 130.0, 140.0];
+const SHIP_WARM: [f32; 3] = [235.0, 40.0, 85.0];
+/// The band the labels of [`compare`] are written on.
+const LABEL_BACKGROUND: Rgba<u8> = Rgba([32, 32, 32, 255]);
+
+/// Where [`overlay`] puts the image on top.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placement {
+    /// The part of the image below it's lined up with, the center or an edge or corner.
+    pub anchor: Gravity,
+    /// How far it's moved from there in pixels, right and down.
+    pub x: i64,
+    pub y: i64,
+    /// How large it is compared to the image below, 1 fitting it just inside.
+    pub scale: f32,
+}
+
+impl Default for Placement {
+    fn default() -> Self {
+        Self { anchor: Gravity::Center, x: 0, y: 0, scale: 1.0 }
+    }
+}
+
+/// Blends `top` over every frame in the given mode, at `opacity` from 0 to 1. The image below decides
+/// the size, and `top` is scaled and cropped to cover all of it.
+pub fn blend(media: Media, top: &RgbaImage, mode: BlendMode, opacity: f32) -> Media {
+    let (width, height) = media.dimensions();
+    let top = match top.dimensions() == (width, height) {
+        true => top.clone(),
+        false => cover(top, width, height, FilterType::Lanczos3),
+    };
+
+    media.map_frames(|img| {
+        for (below, above) in img.pixels_mut().zip(top.pixels()) {
+            *below = mode.blend(*below, *above, opacity);
+        }
+    })
+}
+
+/// Fills in the transparent parts of every frame with `color`, so the result looks the same without
+/// transparency when it's opaque.
+pub fn fill_background(media: Media, color: Rgba<u8>) -> Media {
+    media.map_frames(|img| {
+        for pixel in img.pixels_mut() {
+            *pixel = blend_over(color, *pixel);
+        }
+    })
+}
+
+/// Puts every frame in front of `backdrop`, which is scaled and cropped to cover it like [`blend`] does.
+pub fn background_image(media: Media, backdrop: &RgbaImage) -> Media {
+    let (width, height) = media.dimensions();
+    let backdrop = match backdrop.dimensions() == (width, height) {
+        true => backdrop.clone(),
+        false => cover(backdrop, width, height, FilterType::Lanczos3),
+    };
+
+    media.map_frames(|img| {
+        for (pixel, below) in img.pixels_mut().zip(backdrop.pixels()) {
+            *pixel = blend_over(*below, *pixel);
+        }
+    })
+}
+
+/// Draws `top` over every frame at the given placement, keeping the aspect ratio of both. When `top` is
+/// animated its frames are played over the ones below in turn, and over a still image they make the
+/// result an animation with their timing.
+pub fn overlay(media: Media, top: Media, placement: Placement) -> Media {
+    let (width, height) = media.dimensions();
+    let scaled = |side: u32| ((side as f32 * placement.scale).round() as u32).max(1);
+    let (top_width, top_height) = top.dimensions();
+    let (top_width, top_height) = fit_dimensions(top_width, top_height, scaled(width), scaled(height));
+
+    let (horizontal, vertical) = placement.anchor.offsets();
+    let x = ((width as f64 - top_width as f64) * horizontal).round() as i64 + placement.x;
+    let y = ((height as f64 - top_height as f64) * vertical).round() as i64 + placement.y;
+
+    let (media, tops) = match (media, top.resize(top_width, top_height, FilterType::Lanczos3)) {
+        (Media::Static(img), Media::Animated(frames)) => {
+            let tops = frames.iter().map(|frame| frame.buffer().clone()).collect::<Vec<_>>();
+            let below = frames.into_iter().map(|frame| Frame::from_parts(img.clone(), 0, 0, frame.delay())).collect();
+            (Media::Animated(below), tops)
+        },
+        (media, Media::Animated(frames)) => (media, frames.into_iter().map(Frame::into_buffer).collect()),
+        (media, Media::Static(top)) => (media, vec![top]),
+    };
+
+    media.map_images_indexed(|index, img| {
+        let mut img = img.clone();
+        geometry::overlay(&mut img, &tops[index % tops.len()], x, y);
+        img
+    })
+}
+
+/// Stamps `mark` onto every frame at `anchor`, a little away from the edges, fit to
+/// [`WATERMARK_SCALE`] of the image and at `opacity` from 0 to 1.
+pub fn watermark(media: Media, mark: &RgbaImage, anchor: Gravity, opacity: f32) -> Media {
+    let (width, height) = media.dimensions();
+    let margin = width.min(height) as f64 * WATERMARK_MARGIN as f64;
+    let (horizontal, vertical) = anchor.offsets();
+
+    let mut mark = mark.clone();
+    for pixel in mark.pixels_mut() {
+        pixel[3] = (pixel[3] as f32 * opacity.clamp(0.0, 1.0)).round() as u8;
+    }
+
+    // Edges on the left and top push the mark in by the margin, those on the right and bottom pull it.
+    let placement = Placement {
+        anchor,
+        x: (margin * (1.0 - horizontal * 2.0)).round() as i64,
+        y: (margin * (1.0 - vertical * 2.0)).round() as i64,
+        scale: WATERMARK_SCALE,
+    };
+    overlay(media, Media::Static(mark), placement)
+}
+
+/// White outlined text on a transparent image just large enough for it, for use as a watermark.
+pub fn watermark_text(text: &str, fonts: &[&Font<'static>]) -> RgbaImage {
+    let style = TextStyle::new(fonts, 64.0, Rgba([255, 255, 255, 255])).stroke(3, Rgba([0, 0, 0, 255]));
+    let block = TextBlock::new(style, text, 4096.0, 1);
+    let padding = 4;
+
+    let mut img = RgbaImage::new(block.width().ceil() as u32 + padding * 2, block.height().ceil() as u32 + padding * 2);
+    block.draw(&mut img, padding as i32, padding as i32, &Emojis::new());
+    img
+}
+
+/// A heart of the given color on a transparent square, anti-aliased by sampling each pixel a few times.
+fn heart(size: u32, color: Rgba<u8>) -> RgbaImage {
+    const SAMPLES: u32 = 4;
+
+    let inside = |x: f32, y: f32| {
+        // The heart curve (x² + y² - 1)³ = x²y³, which spans about -1.14 to 1.14 across.
+        let (x, y) = (x * 2.6 - 1.3, 1.25 - y * 2.5);
+        (x * x + y * y - 1.0).powi(3) - x * x * y.powi(3) <= 0.0
+    };
+
+    RgbaImage::from_fn(size, size, |x, y| {
+        let hits = (0..SAMPLES * SAMPLES)
+            .filter(|i| {
+                let dx = (i % SAMPLES) as f32 + 0.5;
+                let dy = (i / SAMPLES) as f32 + 0.5;
+                inside((x as f32 + dx / SAMPLES as f32) / size as f32, (y as f32 + dy / SAMPLES as f32) / size as f32)
+            })
+            .count();
+
+        Rgba([color[0], color[1], color[2], (255 * hits / (SAMPLES * SAMPLES) as usize) as u8])
+    })
+}
+
+/// Two avatars cut into circles side by side, with a heart between them that's redder the higher the
+/// `percent`, which is written on it with the first of `fonts` that has the glyphs.
+pub fn ship(left: &RgbaImage, right: &RgbaImage, percent: u8, fonts: &[&Font<'static>]) -> RgbaImage {
+    let size = SHIP_AVATAR_SIZE;
+    let avatar = |img: &RgbaImage| circle_image(&cover(img, size, size, FilterType::Lanczos3), None);
+
+    let mut canvas = RgbaImage::new(size * 3, size);
+    geometry::overlay(&mut canvas, &avatar(left), 0, 0);
+    geometry::overlay(&mut canvas, &avatar(right), size as i64 * 2, 0);
+
+    let warmth = percent.min(100) as f32 / 100.0;
+    let channel = |c: usize| (SHIP_COLD[c] + (SHIP_WARM[c] - SHIP_COLD[c]) * warmth).round() as u8;
+    let heart_size = size * 3 / 4;
+    let offset = (size - heart_size) / 2;
+    geometry::overlay(
+        &mut canvas,
+        &heart(heart_size, Rgba([channel(0), channel(1), channel(2), 255])),
+        (size + offset) as i64,
+        offset as i64,
+    );
+
+    let style = TextStyle::new(fonts, heart_size as f32 * 0.3, Rgba([255, 255, 255, 255]))
+        .align(Align::Center)
+        .stroke((size / 64).max(1), Rgba([0, 0, 0, 255]));
+    let block = TextBlock::fit(style, &format!("{}%", percent), heart_size as f32 * 0.7, heart_size as f32 * 0.35, 8.0);
+    let y = (size as f32 * 0.47 - block.height() / 2.0).round() as i32;
+    let x = (size + offset) as f32 + heart_size as f32 * 0.15;
+    block.draw(&mut canvas, x.round() as i32, y, &Emojis::new());
+
+    canvas
+}
+
+/// Lays the images out in a grid about as wide as it is tall, on a `background` with `gap` pixels
+/// around and between them. Every cell is a square as large as the smallest image allows (up to
+/// [`MAX_GRID_CELL`]), with the image cropped to fill it. A last row that isn't full is centered.
+pub fn grid(images: &[RgbaImage], gap: u32, background: Rgba<u8>) -> RgbaImage {
+    let count = images.len().max(1) as u32;
+    let columns = (count as f64).sqrt().ceil() as u32;
+    let rows = count.div_ceil(columns);
+    let cell = images.iter().map(|img| img.width().min(img.height())).min().unwrap_or(1).clamp(1, MAX_GRID_CELL);
+
+    let side = |cells: u32| cells * cell + (cells + 1) * gap;
+    let mut canvas = RgbaImage::from_pixel(side(columns), side(rows), background);
+
+    for (index, img) in images.iter().enumerate() {
+        let (column, row) = (index as u32 % columns, index as u32 / columns);
+        let in_row = match row == rows - 1 {
+            true => count - row * columns,
+            false => columns,
+        };
+        let indent = (columns - in_row) * (cell + gap) / 2;
+
+        let x = indent + gap + column * (cell + gap);
+        let y = gap + row * (cell + gap);
+        geometry::overlay(&mut canvas, &cover(img, cell, cell, FilterType::Lanczos3), x as i64, y as i64);
+    }
+
+    canvas
+}
+
+/// Puts two images next to each other for comparing them, or one above the other when `vertical`.
+/// Both are scaled to the smaller of their heights (or widths when stacked) so neither gets blown up.
+/// `labels` are written in a band above each image.
+pub fn compare(
+    first: &RgbaImage,
+    second: &RgbaImage,
+    vertical: bool,
+    labels: Option<[&str; 2]>,
+    fonts: &[&Font<'static>],
+) -> RgbaImage {
+    let matched = |img: &RgbaImage| if vertical { img.width() } else { img.height() };
+    let side = matched(first).min(matched(second)).max(1);
+    let scaled = [first, second].map(|img| {
+        let (width, height) = img.dimensions();
+        let other = |along: u32, across: u32| (along as u64 * side as u64 / across.max(1) as u64).max(1) as u32;
+        let (width, height) = match vertical {
+            true => (side, other(height, width)),
+            false => (other(width, height), side),
+        };
+        imageops::resize(img, width, height, FilterType::Lanczos3)
+    });
+
+    let band = labels.map_or(0, |_| (side / 10).clamp(24, 96));
+    let mut canvas = match vertical {
+        true => RgbaImage::new(side, scaled[0].height() + scaled[1].height() + band * 2),
+        false => RgbaImage::new(scaled[0].width() + scaled[1].width(), side + band),
+    };
+
+    let mut offset = 0;
+    for (index, img) in scaled.iter().enumerate() {
+        let (x, y) = match vertical {
+            true => (0, offset),
+            false => (offset, 0),
+        };
+
+        if let Some(labels) = labels {
+            geometry::overlay(&mut canvas, &RgbaImage::from_pixel(img.width(), band, LABEL_BACKGROUND), x as i64, y as i64);
+
+            let padding = band as f32 * 0.15;
+            let style = TextStyle::new(fonts, band as f32 * 0.6, Rgba([255, 255, 255, 255])).align(Align::Center);
+            let block = TextBlock::fit(style, labels[index], img.width() as f32 - padding * 2.0, band as f32 - padding * 2.0, 8.0);
+            let top = y as f32 + (band as f32 - block.height()) / 2.0;
+            block.draw(&mut canvas, (x as f32 + padding).round() as i32, top.round() as i32, &Emojis::new());
+        }
+
+        geometry::overlay(&mut canvas, img, x as i64, (y + band) as i64);
+        offset += match vertical {
+            true => band + img.height(),
+            false => img.width(),
+        };
+    }
+
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn blends_onto_the_canvas_below() {
+        let media = Media::Static(RgbaImage::from_pixel(4, 2, Rgba([200, 100, 50, 255])));
+        let top = RgbaImage::from_pixel(2, 2, Rgba([100, 100, 100, 255]));
+
+        let blended = blend(media.clone(), &top, BlendMode::Difference, 1.0);
+        assert_eq!(blended.dimensions(), (4, 2));
+        assert_eq!(blended.first_frame().get_pixel(3, 1), &Rgba([100, 0, 50, 255]));
+
+        let unchanged = blend(media.clone(), &top, BlendMode::Normal, 0.0);
+        assert_eq!(unchanged.first_frame(), media.first_frame());
+    }
+
+    #[test]
+    fn fills_in_transparency() {
+        let img = RgbaImage::from_fn(2, 1, |x, _| [Rgba([0, 0, 0, 0]), Rgba([255, 0, 0, 255])][x as usize]);
+        let media = Media::Static(img);
+
+        let filled = fill_background(media.clone(), Rgba([0, 0, 255, 255]));
+        assert_eq!(filled.first_frame().get_pixel(0, 0), &Rgba([0, 0, 255, 255]));
+        assert_eq!(filled.first_frame().get_pixel(1, 0), &Rgba([255, 0, 0, 255]));
+
+        // Wider than the image, so its middle covers it.
+        let backdrop = RgbaImage::from_fn(4, 1, |x, _| Rgba([0, x as u8 * 50, 0, 255]));
+        let behind = background_image(media, &backdrop);
+        assert_eq!(behind.first_frame().get_pixel(0, 0), &Rgba([0, 50, 0, 255]));
+        assert_eq!(behind.first_frame().get_pixel(1, 0), &Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn overlays_at_the_placement() {
+        let below = Media::Static(RgbaImage::from_pixel(10, 10, Rgba([0, 0, 0, 255])));
+        let top = Media::Static(RgbaImage::from_pixel(4, 2, Rgba([255, 255, 255, 255])));
+        let placement = Placement { anchor: Gravity::BottomRight, x: -1, y: 0, scale: 0.4 };
+
+        let img = overlay(below.clone(), top.clone(), placement).first_frame().clone();
+        assert_eq!(img.get_pixel(8, 9)[0], 255);
+        assert_eq!(img.get_pixel(5, 8)[0], 255);
+        assert_eq!(img.get_pixel(9, 9)[0], 0);
+        assert_eq!(img.get_pixel(8, 7)[0], 0);
+
+        let fitted = overlay(below, top, Placement::default()).first_frame().clone();
+        assert_eq!((fitted.get_pixel(0, 3)[0], fitted.get_pixel(0, 2)[0]), (255, 0));
+    }
+
+    #[test]
+    fn animated_tops_animate_still_images() {
+        let below = Media::Static(RgbaImage::from_pixel(2, 2, Rgba([0, 0, 0, 255])));
+        let delay = image::Delay::from_numer_denom_ms(70, 1);
+        let frames = (1..=3)
+            .map(|value| Frame::from_parts(RgbaImage::from_pixel(2, 2, Rgba([value, 0, 0, 255])), 0, 0, delay))
+            .collect();
+
+        match overlay(below, Media::Animated(frames), Placement::default()) {
+            Media::Animated(frames) => {
+                assert_eq!(frames.len(), 3);
+                assert_eq!(frames[2].buffer().get_pixel(1, 1)[0], 3);
+                assert_eq!(crate::delay_ms(frames[0].delay()), 70.0);
+            },
+            Media::Static(_) => panic!("expected an animation"),
+        }
+    }
+
+    #[test]
+    fn ships_two_avatars() {
+        let font = crate::text::bold_font();
+        let left = RgbaImage::from_pixel(64, 64, Rgba([0, 0, 255, 255]));
+        let right = RgbaImage::from_pixel(32, 48, Rgba([0, 255, 0, 255]));
+
+        let shipped = ship(&left, &right, 100, &[&font]);
+        let size = SHIP_AVATAR_SIZE;
+        assert_eq!(shipped.dimensions(), (size * 3, size));
+        assert_eq!(*shipped.get_pixel(size / 2, size / 2), Rgba([0, 0, 255, 255]));
+        assert_eq!(*shipped.get_pixel(size * 5 / 2, size / 2), Rgba([0, 255, 0, 255]));
+        // Outside the circles, and a lobe of the heart away from the text.
+        assert_eq!(shipped.get_pixel(0, 0)[3], 0);
+        assert_eq!(*shipped.get_pixel(size + size * 3 / 8, size * 3 / 10), Rgba([235, 40, 85, 255]));
+    }
+
+    #[test]
+    fn lays_out_a_grid() {
+        let images = (0..5).map(|i| RgbaImage::from_pixel(10 + i, 20, Rgba([i as u8 * 50, 0, 0, 255]))).collect::<Vec<_>>();
+        let background = Rgba([255, 255, 255, 255]);
+        let grid = grid(&images, 2, background);
+
+        // Three columns of 10 pixel cells, and the last row of two centered.
+        assert_eq!(grid.dimensions(), (3 * 10 + 4 * 2, 2 * 10 + 3 * 2));
+        assert_eq!(*grid.get_pixel(0, 0), background);
+        assert_eq!(grid.get_pixel(2 + 12 + 5, 5)[0], 50);
+        assert_eq!(grid.get_pixel(2 + 6 + 5, 12 + 5)[0], 150);
+        assert_eq!(*grid.get_pixel(3, 12 + 5), background);
+    }
+
+    #[test]
+    fn compares_at_matched_sizes() {
+        let first = RgbaImage::from_pixel(40, 20, Rgba([255, 0, 0, 255]));
+        let second = RgbaImage::from_pixel(10, 10, Rgba([0, 0, 255, 255]));
+
+        let side_by_side = compare(&first, &second, false, None, &[]);
+        assert_eq!(side_by_side.dimensions(), (20 + 10, 10));
+        assert_eq!(*side_by_side.get_pixel(5, 5), Rgba([255, 0, 0, 255]));
+        assert_eq!(*side_by_side.get_pixel(25, 5), Rgba([0, 0, 255, 255]));
+
+        let stacked = compare(&first, &second, true, None, &[]);
+        assert_eq!(stacked.dimensions(), (10, 5 + 10));
+        assert_eq!(*stacked.get_pixel(5, 10), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn labels_comparisons() {
+        let font = crate::text::bold_font();
+        let img = RgbaImage::from_pixel(100, 100, Rgba([0, 255, 0, 255]));
+
+        let labeled = compare(&img, &img, true, Some(["before", "after"]), &[&font]);
+        assert_eq!(labeled.dimensions(), (100, 2 * (24 + 100)));
+        assert_eq!(*labeled.get_pixel(0, 0), LABEL_BACKGROUND);
+        assert_eq!(*labeled.get_pixel(0, 124), LABEL_BACKGROUND);
+        assert_eq!(*labeled.get_pixel(50, 24 + 50), Rgba([0, 255, 0, 255]));
+        assert!((0..100).flat_map(|x| (0..24).map(move |y| (x, y))).any(|(x, y)| labeled.get_pixel(x, y)[0] > 200));
+    }
+
+    #[test]
+    fn watermarks_the_corner() {
+        let media = Media::Static(RgbaImage::from_pixel(100, 100, Rgba([0, 0, 0, 255])));
+        let mark = RgbaImage::from_pixel(10, 10, Rgba([255, 255, 255, 255]));
+
+        let img = watermark(media, &mark, Gravity::BottomRight, 0.5).first_frame().clone();
+        // Fit to 25 pixels and kept 2 pixels from the edges.
+        assert!((120..=136).contains(&img.get_pixel(80, 80)[0]));
+        assert!((120..=136).contains(&img.get_pixel(73, 97)[0]));
+        assert_eq!(img.get_pixel(72, 80)[0], 0);
+        assert_eq!(img.get_pixel(98, 98)[0], 0);
+        assert_eq!(img.get_pixel(50, 50)[0], 0);
+    }
+
+    #[test]
+    fn renders_watermark_text() {
+        let font = crate::text::bold_font();
+        let short = watermark_text("hi", &[&font]);
+        let long = watermark_text("photon bot", &[&font]);
+
+        assert!(long.width() > short.width());
+        assert_eq!(long.height(), short.height());
+        assert!(long.pixels().any(|pixel| pixel[3] == 255 && pixel[0] == 255));
+    }
+}
@@ -0,0 +1,255 @@
+//! Filters that mix each pixel with its neighbours.
+
+use image::{Rgba, RgbaImage};
+
+use crate::Media;
+
+/// Pixels as premultiplied floats, so transparent pixels don't bleed their color into their neighbours.
+fn premultiply(img: &RgbaImage) -> Vec<[f32; 4]> {
+    img.pixels()
+        .map(|pixel| {
+            let alpha = pixel[3] as f32 / 255.0;
+            [pixel[0] as f32 * alpha, pixel[1] as f32 * alpha, pixel[2] as f32 * alpha, pixel[3] as f32]
+        })
+        .collect()
+}
+
+fn unpremultiply(width: u32, height: u32, data: &[[f32; 4]]) -> RgbaImage {
+    let mut img = RgbaImage::new(width, height);
+
+    for (pixel, [r, g, b, a]) in img.pixels_mut().zip(data) {
+        if *a <= 0.0 {
+            continue;
+        }
+
+        let alpha = a / 255.0;
+        let channel = |value: f32| (value / alpha).round().clamp(0.0, 255.0) as u8;
+        *pixel = Rgba([channel(*r), channel(*g), channel(*b), a.round().clamp(0.0, 255.0) as u8]);
+    }
+    img
+}
+
+/// The sizes of three box blurs that together approximate a Gaussian with the given standard deviation.
+fn box_sizes(sigma: f32) -> [usize; 3] {
+    let n = 3.0;
+    let ideal = (12.0 * sigma * sigma / n + 1.0).sqrt();
+
+    let mut lower = ideal.floor() as i64;
+    if lower % 2 == 0 {
+        lower -= 1;
+    }
+    let lower = lower.max(1) as f32;
+
+    // How many of the boxes use the lower size, the rest use the next odd size up.
+    let count = ((12.0 * sigma * sigma - n * lower * lower - 4.0 * n * lower - 3.0 * n) / (-4.0 * lower - 4.0)).round();
+
+    [0.0, 1.0, 2.0].map(|i| if i < count { lower as usize } else { lower as usize + 2 })
+}
+
+/// Averages every pixel of each row with the `radius` pixels on either side, repeating the edge pixels.
+fn box_blur_rows(src: &[[f32; 4]], dst: &mut [[f32; 4]], width: usize, radius: usize) {
+    let scale = 1.0 / (radius * 2 + 1) as f32;
+
+    for (row, out) in src.chunks_exact(width).zip(dst.chunks_exact_mut(width)) {
+        let at = |i: isize| row[i.clamp(0, width as isize - 1) as usize];
+        let mut sum = [0.0_f32; 4];
+
+        for i in -(radius as isize)..=radius as isize {
+            let pixel = at(i);
+            (0..4).for_each(|c| sum[c] += pixel[c]);
+        }
+
+        for (x, out) in out.iter_mut().enumerate() {
+            *out = sum.map(|value| value * scale);
+
+            let (entering, leaving) = (at((x + radius + 1) as isize), at(x as isize - radius as isize));
+            (0..4).for_each(|c| sum[c] += entering[c] - leaving[c]);
+        }
+    }
+}
+
+fn transpose(src: &[[f32; 4]], dst: &mut [[f32; 4]], width: usize, height: usize) {
+    for y in 0..height {
+        for x in 0..width {
+            dst[x * height + y] = src[y * width + x];
+        }
+    }
+}
+
+/// A fast approximation of a Gaussian blur: three box blurs, each split into a horizontal and a vertical
+/// pass, which cost the same no matter how large `sigma` is.
+pub fn gaussian_blur(img: &RgbaImage, sigma: f32) -> RgbaImage {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    if sigma <= 0.0 || width == 0 || height == 0 {
+        return img.clone();
+    }
+
+    let mut data = premultiply(img);
+    let mut scratch = vec![[0.0; 4]; data.len()];
+
+    for size in box_sizes(sigma) {
+        let radius = (size - 1) / 2;
+
+        box_blur_rows(&data, &mut scratch, width, radius);
+        transpose(&scratch, &mut data, width, height);
+        box_blur_rows(&data, &mut scratch, height, radius);
+        transpose(&scratch, &mut data, height, width);
+    }
+
+    unpremultiply(img.width(), img.height(), &data)
+}
+
+/// Blurs every frame. The radius is roughly how far, in pixels, colors spread.
+pub fn blur(media: Media, radius: u32) -> Media {
+    media.map_images(|img| gaussian_blur(img, radius as f32))
+}
+
+/// Convolves the color channels with a square kernel of odd size, given row by row, adding `bias` to the
+/// result. Pixels beyond the edges repeat the edge pixels and transparency is kept as it is.
+pub fn convolve(img: &RgbaImage, kernel: &[f32], bias: f32) -> RgbaImage {
+    let size = (kernel.len() as f64).sqrt() as usize;
+    assert!(size * size == kernel.len() && size % 2 == 1, "Kernels must be square with an odd size");
+
+    let half = (size / 2) as i64;
+    let (width, height) = (img.width() as i64, img.height() as i64);
+
+    RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        let mut sum = [bias; 3];
+
+        for (index, weight) in kernel.iter().enumerate() {
+            if *weight == 0.0 {
+                continue;
+            }
+
+            let sx = (x as i64 + (index % size) as i64 - half).clamp(0, width - 1);
+            let sy = (y as i64 + (index / size) as i64 - half).clamp(0, height - 1);
+            let pixel = img.get_pixel(sx as u32, sy as u32);
+            (0..3).for_each(|c| sum[c] += pixel[c] as f32 * weight);
+        }
+
+        let channel = |c: usize| sum[c].round().clamp(0.0, 255.0) as u8;
+        Rgba([channel(0), channel(1), channel(2), img.get_pixel(x, y)[3]])
+    })
+}
+
+/// An emboss kernel lit from the given direction, in degrees clockwise from the right.
+fn emboss_kernel(direction: f32, strength: f32) -> [f32; 9] {
+    let (sin, cos) = direction.to_radians().sin_cos();
+    let mut kernel = [0.0; 9];
+
+    for (index, weight) in kernel.iter_mut().enumerate() {
+        let (dx, dy) = ((index % 3) as f32 - 1.0, (index / 3) as f32 - 1.0);
+        // Neighbours facing the light count positively, the ones opposite negatively.
+        *weight = (dx * cos + dy * sin) * strength;
+    }
+    kernel
+}
+
+/// Turns every frame into a gray relief, as if pressed into metal and lit from `direction`.
+pub fn emboss(media: Media, direction: f32, strength: f32) -> Media {
+    let kernel = emboss_kernel(direction, strength);
+    media.map_images(|img| convolve(img, &kernel, 127.5))
+}
+
+/// How far the blur used for sharpening reaches, small enough to bring out details rather than halos.
+const SHARPEN_SIGMA: f32 = 2.0;
+
+/// Sharpens by unsharp masking: the difference to a blurred copy, scaled by `amount`, is added back
+/// wherever it is larger than `threshold`, so flat areas and noise can be left alone.
+pub fn unsharp_mask(img: &RgbaImage, amount: f32, threshold: u8) -> RgbaImage {
+    let blurred = gaussian_blur(img, SHARPEN_SIGMA);
+    let mut out = img.clone();
+
+    for (pixel, blurred) in out.pixels_mut().zip(blurred.pixels()) {
+        for c in 0..3 {
+            let diff = pixel[c] as f32 - blurred[c] as f32;
+
+            if diff.abs() > threshold as f32 {
+                pixel[c] = (pixel[c] as f32 + diff * amount).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    out
+}
+
+pub fn sharpen(media: Media, amount: f32, threshold: u8) -> Media {
+    media.map_images(|img| unsharp_mask(img, amount, threshold))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_sizes_approximate_the_gaussian() {
+        assert_eq!(box_sizes(1.0), [1, 1, 3]);
+        assert_eq!(box_sizes(5.0), [9, 9, 11]);
+
+        // Three boxes of width w have a variance of 3 (w² - 1) / 12.
+        let variance = box_sizes(10.0).iter().map(|&w| (w * w - 1) as f32 / 12.0).sum::<f32>();
+        assert!((variance - 100.0).abs() < 10.0);
+    }
+
+    #[test]
+    fn blur_keeps_flat_images_flat() {
+        let img = RgbaImage::from_pixel(9, 5, Rgba([10, 200, 30, 255]));
+        assert_eq!(gaussian_blur(&img, 3.0), img);
+    }
+
+    #[test]
+    fn blur_spreads_a_point() {
+        let mut img = RgbaImage::from_pixel(21, 21, Rgba([0, 0, 0, 255]));
+        img.put_pixel(10, 10, Rgba([255, 255, 255, 255]));
+
+        let blurred = gaussian_blur(&img, 2.0);
+        assert!(blurred.get_pixel(10, 10)[0] < 255);
+        assert!(blurred.get_pixel(11, 10)[0] > 0);
+        assert_eq!(blurred.get_pixel(11, 10), blurred.get_pixel(10, 9));
+        assert_eq!(blurred.get_pixel(0, 0)[0], 0);
+    }
+
+    #[test]
+    fn transparent_pixels_do_not_bleed() {
+        let mut img = RgbaImage::from_pixel(8, 8, Rgba([255, 255, 255, 0]));
+        img.put_pixel(4, 4, Rgba([255, 0, 0, 255]));
+
+        let blurred = gaussian_blur(&img, 1.5);
+        assert_eq!(&blurred.get_pixel(5, 4).0[..3], &[255, 0, 0]);
+        assert!(blurred.get_pixel(5, 4)[3] > 0);
+    }
+
+    #[test]
+    fn convolves_with_kernels() {
+        let img = RgbaImage::from_fn(3, 3, |x, y| Rgba([(x * 10 + y) as u8, 0, 0, 200]));
+
+        let identity = [0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0];
+        assert_eq!(convolve(&img, &identity, 0.0), img);
+
+        // Sampling the left neighbour, which repeats at the edge.
+        let shift = [0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0];
+        let shifted = convolve(&img, &shift, 5.0);
+        assert_eq!(*shifted.get_pixel(0, 1), Rgba([6, 5, 5, 200]));
+        assert_eq!(*shifted.get_pixel(2, 1), Rgba([16, 5, 5, 200]));
+    }
+
+    #[test]
+    fn embossing_flat_areas_gives_gray() {
+        let media = Media::Static(RgbaImage::from_fn(6, 6, |x, _| if x < 3 { Rgba([0, 0, 0, 255]) } else { Rgba([255, 255, 255, 255]) }));
+        let embossed = emboss(media, 0.0, 1.0);
+
+        assert_eq!(*embossed.first_frame().get_pixel(0, 0), Rgba([128, 128, 128, 255]));
+        assert_eq!(embossed.first_frame().get_pixel(2, 0)[0], 255);
+        assert_eq!(*embossed.first_frame().get_pixel(5, 5), Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn sharpening_increases_edge_contrast() {
+        let img = RgbaImage::from_fn(16, 4, |x, _| if x < 8 { Rgba([100, 100, 100, 255]) } else { Rgba([150, 150, 150, 255]) });
+        let sharpened = unsharp_mask(&img, 1.0, 0);
+
+        assert!(sharpened.get_pixel(7, 0)[0] < 100);
+        assert!(sharpened.get_pixel(8, 0)[0] > 150);
+        assert_eq!(sharpened.get_pixel(0, 0)[0], 100);
+        assert_eq!(unsharp_mask(&img, 1.0, 50), img);
+    }
+}
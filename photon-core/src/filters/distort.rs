@@ -0,0 +1,255 @@
+//! Filters that move pixels around rather than change their color.
+
+use image::RgbaImage;
+
+use rayon::prelude::*;
+
+use crate::geometry::sample_bilinear;
+use crate::Media;
+
+/// Builds an image of the same size where every pixel is sampled from wherever `source` maps its center
+/// to. Coordinates are clamped to the image, so nothing turns transparent at the edges.
+pub fn remap(img: &RgbaImage, source: impl Fn(f32, f32) -> (f32, f32) + Sync) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let (max_x, max_y) = (width as f32 - 0.5, height as f32 - 0.5);
+    let mut out = RgbaImage::new(width, height);
+
+    out.par_chunks_mut(width as usize * 4).enumerate().for_each(|(y, row)| {
+        for (x, pixel) in row.chunks_exact_mut(4).enumerate() {
+            let (sx, sy) = source(x as f32 + 0.5, y as f32 + 0.5);
+            pixel.copy_from_slice(&sample_bilinear(img, sx.clamp(0.5, max_x), sy.clamp(0.5, max_y)).0);
+        }
+    });
+    out
+}
+
+/// Reflects a wedge of the image around its center `segments` times, like a kaleidoscope. `angle` (in
+/// degrees clockwise from the right) turns which wedge is used.
+pub fn kaleidoscope_image(img: &RgbaImage, segments: u32, angle: f32) -> RgbaImage {
+    let (cx, cy) = (img.width() as f32 / 2.0, img.height() as f32 / 2.0);
+    let wedge = std::f32::consts::TAU / segments.max(1) as f32;
+    let offset = angle.to_radians();
+
+    remap(img, |x, y| {
+        let (dx, dy) = (x - cx, y - cy);
+        let theta = (dy.atan2(dx) - offset).rem_euclid(std::f32::consts::TAU);
+
+        // Every other wedge is mirrored so neighbours meet seamlessly.
+        let mut within = theta % wedge;
+        if (theta / wedge) as u32 % 2 == 1 {
+            within = wedge - within;
+        }
+
+        let (sin, cos) = (within + offset).sin_cos();
+        let distance = dx.hypot(dy);
+        (cx + distance * cos, cy + distance * sin)
+    })
+}
+
+pub fn kaleidoscope(media: Media, segments: u32, angle: f32) -> Media {
+    media.par_map_images(|img| kaleidoscope_image(img, segments, angle))
+}
+
+/// Bulges the image out from the point at `center` (0 to 1 across each side), or pinches it in with a
+/// negative `strength`. The distortion reaches half the shorter side, fading out towards it.
+pub fn bulge_image(img: &RgbaImage, strength: f32, center: (f32, f32)) -> RgbaImage {
+    let (cx, cy) = (img.width() as f32 * center.0, img.height() as f32 * center.1);
+    let radius = img.width().min(img.height()) as f32 / 2.0;
+
+    remap(img, |x, y| {
+        let (dx, dy) = (x - cx, y - cy);
+        let distance = dx.hypot(dy) / radius;
+        if distance >= 1.0 || distance <= 0.0 {
+            return (x, y);
+        }
+
+        // Distances under 1 shrink when raised to a positive power, pulling the middle in from closer.
+        let scale = distance.powf(strength);
+        (cx + dx * scale, cy + dy * scale)
+    })
+}
+
+pub fn bulge(media: Media, strength: f32, center: (f32, f32)) -> Media {
+    media.par_map_images(|img| bulge_image(img, strength, center))
+}
+
+/// ImageMagick's implode: pixels inside the ellipse touching the image's sides are pulled towards its
+/// center, harder the larger `amount` is. A negative `amount` pushes them out instead, exploding it.
+pub fn implode_image(img: &RgbaImage, amount: f32) -> RgbaImage {
+    let (cx, cy) = (img.width() as f32 / 2.0, img.height() as f32 / 2.0);
+
+    remap(img, |x, y| {
+        let (dx, dy) = (x - cx, y - cy);
+        let distance = (dx / cx).hypot(dy / cy);
+        if distance >= 1.0 || distance <= 0.0 {
+            return (x, y);
+        }
+
+        let factor = (std::f32::consts::FRAC_PI_2 * distance).sin().powf(-amount);
+        (cx + dx * factor, cy + dy * factor)
+    })
+}
+
+pub fn implode(media: Media, amount: f32) -> Media {
+    media.par_map_images(|img| implode_image(img, amount))
+}
+
+/// The opposite of [`implode`], magnifying the middle of every frame.
+pub fn explode(media: Media, amount: f32) -> Media {
+    media.par_map_images(|img| implode_image(img, -amount))
+}
+
+/// How many frames an animated swirl has, and how far apart.
+const SWIRL_FRAMES: usize = 16;
+const SWIRL_FRAME_DELAY_MS: u32 = 60;
+
+/// Twists the image around its center by up to `degrees`, the most in the middle and fading out to
+/// nothing at `radius` pixels, 0 meaning half the shorter side.
+pub fn swirl_image(img: &RgbaImage, degrees: f32, radius: f32) -> RgbaImage {
+    let (cx, cy) = (img.width() as f32 / 2.0, img.height() as f32 / 2.0);
+    let radius = match radius > 0.0 {
+        true => radius,
+        false => img.width().min(img.height()) as f32 / 2.0,
+    };
+
+    remap(img, |x, y| {
+        let (dx, dy) = (x - cx, y - cy);
+        let distance = dx.hypot(dy);
+        if distance >= radius {
+            return (x, y);
+        }
+
+        let falloff = 1.0 - distance / radius;
+        let (sin, cos) = (degrees.to_radians() * falloff * falloff).sin_cos();
+        (cx + dx * cos - dy * sin, cy + dx * sin + dy * cos)
+    })
+}
+
+/// Swirls every frame. With `animate`, static images become a GIF that twists further on every frame,
+/// and animations twist over their length.
+pub fn swirl(media: Media, degrees: f32, radius: f32, animate: bool) -> Media {
+    if !animate {
+        return media.par_map_images(|img| swirl_image(img, degrees, radius));
+    }
+
+    let media = media.into_animation(SWIRL_FRAMES, SWIRL_FRAME_DELAY_MS);
+    let steps = media.frame_count().saturating_sub(1).max(1) as f32;
+    media.map_images_indexed(|index, img| swirl_image(img, degrees * index as f32 / steps, radius))
+}
+
+/// How many frames an animated wave has, one full period, and how far apart.
+const WAVE_FRAMES: usize = 12;
+const WAVE_FRAME_DELAY_MS: u32 = 60;
+
+/// Along which axis a wave moves pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WaveAxis {
+    /// Rows shift sideways.
+    X,
+    /// Columns shift up and down.
+    Y,
+}
+
+/// Displaces rows (or columns) by a sine wave `amplitude` pixels high and `wavelength` pixels long,
+/// starting `phase` (0 to 1) of a period in.
+pub fn wave_image(img: &RgbaImage, amplitude: f32, wavelength: f32, axis: WaveAxis, phase: f32) -> RgbaImage {
+    let frequency = std::f32::consts::TAU / wavelength.max(1.0);
+    let shift = |position: f32| amplitude * (position * frequency + phase * std::f32::consts::TAU).sin();
+
+    remap(img, |x, y| match axis {
+        WaveAxis::X => (x + shift(y), y),
+        WaveAxis::Y => (x, y + shift(x)),
+    })
+}
+
+/// Waves every frame. With `animate`, static images become a GIF rippling through one period, and
+/// animations ripple over their length.
+pub fn wave(media: Media, amplitude: f32, wavelength: f32, axis: WaveAxis, animate: bool) -> Media {
+    if !animate {
+        return media.par_map_images(|img| wave_image(img, amplitude, wavelength, axis, 0.0));
+    }
+
+    let media = media.into_animation(WAVE_FRAMES, WAVE_FRAME_DELAY_MS);
+    let count = media.frame_count() as f32;
+    media.map_images_indexed(|index, img| wave_image(img, amplitude, wavelength, axis, index as f32 / count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    #[test]
+    fn remaps_coordinates() {
+        let img = RgbaImage::from_fn(4, 4, |x, y| Rgba([x as u8, y as u8, 0, 255]));
+
+        assert_eq!(remap(&img, |x, y| (x, y)), img);
+        // Shifted by a pixel, clamping at the edge.
+        let shifted = remap(&img, |x, y| (x + 1.0, y));
+        assert_eq!(shifted.get_pixel(0, 0)[0], 1);
+        assert_eq!(shifted.get_pixel(3, 0)[0], 3);
+    }
+
+    #[test]
+    fn bulges_and_pinches() {
+        let img = RgbaImage::from_fn(33, 33, |x, y| Rgba([(x * 7) as u8, (y * 7) as u8, 0, 255]));
+        let (bulged, pinched) = (bulge_image(&img, 0.5, (0.5, 0.5)), bulge_image(&img, -0.5, (0.5, 0.5)));
+
+        // Near the middle, a bulge samples closer to the center and a pinch further from it.
+        assert!(bulged.get_pixel(20, 16)[0] < img.get_pixel(20, 16)[0]);
+        assert!(pinched.get_pixel(20, 16)[0] > img.get_pixel(20, 16)[0]);
+        assert_eq!(bulged.get_pixel(16, 16), img.get_pixel(16, 16));
+        assert_eq!(bulged.get_pixel(0, 0), img.get_pixel(0, 0));
+    }
+
+    #[test]
+    fn implodes_and_explodes() {
+        let img = RgbaImage::from_fn(33, 33, |x, y| Rgba([(x * 7) as u8, (y * 7) as u8, 0, 255]));
+        let (imploded, exploded) = (implode_image(&img, 0.5), implode_image(&img, -0.5));
+
+        // Imploding samples further from the center, exploding closer to it.
+        assert!(imploded.get_pixel(22, 16)[0] > img.get_pixel(22, 16)[0]);
+        assert!(exploded.get_pixel(22, 16)[0] < img.get_pixel(22, 16)[0]);
+        assert_eq!(imploded.get_pixel(0, 0), img.get_pixel(0, 0));
+        assert_eq!(implode_image(&img, 0.0), img);
+    }
+
+    #[test]
+    fn swirls_around_the_center() {
+        let img = RgbaImage::from_fn(33, 33, |x, y| Rgba([(x * 7) as u8, (y * 7) as u8, 0, 255]));
+        let swirled = swirl_image(&img, 90.0, 0.0);
+
+        assert_ne!(swirled.get_pixel(20, 16), img.get_pixel(20, 16));
+        assert_eq!(swirled.get_pixel(0, 0), img.get_pixel(0, 0));
+        assert_eq!(swirl_image(&img, 0.0, 0.0), img);
+
+        let animated = swirl(Media::Static(img.clone()), 90.0, 0.0, true);
+        assert_eq!(animated.frame_count(), SWIRL_FRAMES);
+        assert_eq!(animated.first_frame(), &img);
+    }
+
+    #[test]
+    fn waves_rows_and_columns() {
+        let img = RgbaImage::from_fn(16, 16, |x, y| Rgba([(x * 16) as u8, (y * 16) as u8, 0, 255]));
+
+        // A quarter of the wavelength down, the sine peaks.
+        let waved = wave_image(&img, 2.0, 40.0, WaveAxis::X, 0.0);
+        assert_eq!(waved.get_pixel(4, 9)[0], img.get_pixel(6, 0)[0]);
+        assert_eq!(waved.get_pixel(4, 9)[1], img.get_pixel(0, 9)[1]);
+
+        let waved = wave_image(&img, 2.0, 40.0, WaveAxis::Y, 0.0);
+        assert_eq!(waved.get_pixel(9, 4)[1], img.get_pixel(0, 6)[1]);
+
+        assert_eq!(wave(Media::Static(img), 2.0, 40.0, WaveAxis::X, true).frame_count(), WAVE_FRAMES);
+    }
+
+    #[test]
+    fn kaleidoscopes_are_symmetric() {
+        let img = RgbaImage::from_fn(32, 32, |x, y| Rgba([(x * 8) as u8, (y * 8) as u8, 0, 255]));
+        let mirrored = kaleidoscope_image(&img, 4, 0.0);
+
+        // Wedges meet mirrored, so the image reflects across both axes.
+        assert_eq!(mirrored.get_pixel(20, 10), mirrored.get_pixel(20, 21));
+        assert_eq!(mirrored.get_pixel(20, 10), mirrored.get_pixel(11, 10));
+    }
+}
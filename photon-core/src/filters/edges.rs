@@ -0,0 +1,225 @@
+//! Edge detection.
+
+use image::{Rgba, RgbaImage};
+
+use super::shadow::glow_within;
+use super::{gaussian_blur, luma};
+use crate::geometry;
+use crate::Media;
+
+/// The high threshold Canny uses when none is given, the low one being half of it.
+const DEFAULT_CANNY_THRESHOLD: f32 = 60.0;
+/// How much the image is smoothed before looking for edges with Canny, to ignore noise.
+const CANNY_SIGMA: f32 = 1.4;
+/// What neon edges are drawn on.
+const NEON_BACKGROUND: Rgba<u8> = Rgba([10, 8, 20, 255]);
+/// How much stronger the glow of neon edges is than an ordinary glow, since the edges are so thin.
+const NEON_GLOW_STRENGTH: f32 = 6.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeMethod {
+    /// The gradient strength at every pixel, soft and quick.
+    Sobel,
+    /// Thin, connected edges only.
+    Canny,
+}
+
+/// The horizontal and vertical Sobel gradients of the image's brightness, scaled down so that the largest
+/// possible magnitude is about 360.
+fn gradients(img: &RgbaImage) -> (Vec<f32>, Vec<f32>) {
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    let brightness = img.pixels().map(|pixel| luma(*pixel) * pixel[3] as f32 / 255.0).collect::<Vec<_>>();
+    let at = |x: i64, y: i64| brightness[(y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize];
+
+    let mut gx = Vec::with_capacity(brightness.len());
+    let mut gy = Vec::with_capacity(brightness.len());
+
+    for y in 0..height {
+        for x in 0..width {
+            gx.push((at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1) - at(x - 1, y - 1) - 2.0 * at(x - 1, y) - at(x - 1, y + 1)) / 4.0);
+            gy.push((at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1) - at(x - 1, y - 1) - 2.0 * at(x, y - 1) - at(x + 1, y - 1)) / 4.0);
+        }
+    }
+    (gx, gy)
+}
+
+/// How strong an edge each pixel is on, from 0 to 255. A `threshold` above 0 turns it into either 0 or 255.
+fn sobel(img: &RgbaImage, threshold: f32) -> Vec<u8> {
+    let (gx, gy) = gradients(img);
+
+    gx.iter()
+        .zip(&gy)
+        .map(|(x, y)| {
+            let magnitude = x.hypot(*y);
+            match threshold > 0.0 {
+                true => if magnitude >= threshold { 255 } else { 0 },
+                false => magnitude.round().min(255.0) as u8,
+            }
+        })
+        .collect()
+}
+
+/// Canny edge detection: gradients of a smoothed copy thinned down to their ridges, keeping those above
+/// the `high` threshold along with the ones above half of it that connect to them.
+fn canny(img: &RgbaImage, high: f32) -> Vec<u8> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let (gx, gy) = gradients(&gaussian_blur(img, CANNY_SIGMA));
+    let magnitude = gx.iter().zip(&gy).map(|(x, y)| x.hypot(*y)).collect::<Vec<_>>();
+    let at = |x: i64, y: i64| match x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height {
+        true => magnitude[y as usize * width + x as usize],
+        false => 0.0,
+    };
+
+    // Only keep pixels that are the strongest across the edge.
+    let mut thin = vec![0.0; magnitude.len()];
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            let index = y as usize * width + x as usize;
+            let angle = gy[index].atan2(gx[index]).to_degrees().rem_euclid(180.0);
+
+            let (dx, dy) = match angle {
+                a if !(22.5..157.5).contains(&a) => (1, 0),
+                a if a < 67.5 => (1, 1),
+                a if a < 112.5 => (0, 1),
+                _ => (-1, 1),
+            };
+
+            let value = magnitude[index];
+            if value >= at(x + dx, y + dy) && value >= at(x - dx, y - dy) {
+                thin[index] = value;
+            }
+        }
+    }
+
+    let low = high / 2.0;
+    let mut edges = vec![0_u8; magnitude.len()];
+    let mut stack = (0..thin.len()).filter(|&i| thin[i] >= high).collect::<Vec<_>>();
+
+    // Follow strong edges through the weaker pixels connected to them.
+    while let Some(index) = stack.pop() {
+        if edges[index] != 0 {
+            continue;
+        }
+        edges[index] = 255;
+
+        let (x, y) = ((index % width) as i64, (index / width) as i64);
+        for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx as usize >= width || ny as usize >= height {
+                continue;
+            }
+
+            let neighbour = ny as usize * width + nx as usize;
+            if edges[neighbour] == 0 && thin[neighbour] >= low {
+                stack.push(neighbour);
+            }
+        }
+    }
+    edges
+}
+
+/// An edge map of the image, white edges on black. With `overlay` the edges are drawn over the image instead.
+pub fn detect_edges(img: &RgbaImage, method: EdgeMethod, threshold: f32, overlay: bool) -> RgbaImage {
+    let strengths = match method {
+        EdgeMethod::Sobel => sobel(img, threshold),
+        EdgeMethod::Canny => canny(img, if threshold > 0.0 { threshold } else { DEFAULT_CANNY_THRESHOLD }),
+    };
+
+    let mut out = img.clone();
+    for (pixel, strength) in out.pixels_mut().zip(strengths) {
+        *pixel = match overlay {
+            true => {
+                let mix = |c: usize| (pixel[c] as u32 + (255 - pixel[c] as u32) * strength as u32 / 255) as u8;
+                Rgba([mix(0), mix(1), mix(2), pixel[3].max(strength)])
+            },
+            false => Rgba([strength, strength, strength, pixel[3]]),
+        };
+    }
+    out
+}
+
+/// Detects edges in every frame, see [`detect_edges`].
+pub fn edges(media: Media, method: EdgeMethod, threshold: f32, overlay: bool) -> Media {
+    media.map_images(|img| detect_edges(img, method, threshold, overlay))
+}
+
+/// Traces the edges of the image as tubes of `color` on a dark background, glowing about `size` pixels
+/// out. Like real ones, the tubes are whiter in the middle.
+pub fn neon_image(img: &RgbaImage, color: Rgba<u8>, size: u32) -> RgbaImage {
+    let strengths = canny(img, DEFAULT_CANNY_THRESHOLD);
+    let core = [0, 1, 2].map(|c| color[c] + (255 - color[c]) / 2);
+    let tubes = RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        Rgba([core[0], core[1], core[2], strengths[(y * img.width() + x) as usize]])
+    });
+
+    let mut out = RgbaImage::from_pixel(img.width(), img.height(), NEON_BACKGROUND);
+    geometry::overlay(&mut out, &glow_within(&tubes, color, size, NEON_GLOW_STRENGTH), 0, 0);
+    out
+}
+
+/// Turns the edges of every frame into neon, see [`neon_image`].
+pub fn neon(media: Media, color: Rgba<u8>, size: u32) -> Media {
+    media.par_map_images(|img| neon_image(img, color, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> RgbaImage {
+        RgbaImage::from_fn(16, 16, |x, y| match (4..12).contains(&x) && (4..12).contains(&y) {
+            true => Rgba([255, 255, 255, 255]),
+            false => Rgba([0, 0, 0, 255]),
+        })
+    }
+
+    #[test]
+    fn sobel_finds_the_outline() {
+        let edges = detect_edges(&square(), EdgeMethod::Sobel, 0.0, false);
+
+        assert_eq!(*edges.get_pixel(0, 0), Rgba([0, 0, 0, 255]));
+        assert_eq!(*edges.get_pixel(8, 8), Rgba([0, 0, 0, 255]));
+        assert!(edges.get_pixel(4, 8)[0] > 100);
+    }
+
+    #[test]
+    fn sobel_thresholds() {
+        let edges = detect_edges(&square(), EdgeMethod::Sobel, 50.0, false);
+        assert!(edges.pixels().all(|pixel| pixel[0] == 0 || pixel[0] == 255));
+    }
+
+    #[test]
+    fn canny_finds_thin_edges() {
+        let edges = detect_edges(&square(), EdgeMethod::Canny, 0.0, false);
+        let row = (0..16).map(|x| edges.get_pixel(x, 8)[0]).collect::<Vec<_>>();
+
+        assert_eq!(row.iter().filter(|&&v| v == 255).count(), 2, "expected one edge on either side, got {:?}", row);
+        assert_eq!(edges.get_pixel(8, 8)[0], 0);
+    }
+
+    #[test]
+    fn overlays_on_the_original() {
+        let img = RgbaImage::from_fn(8, 8, |x, _| if x < 4 { Rgba([200, 0, 0, 255]) } else { Rgba([0, 0, 200, 255]) });
+        let edges = detect_edges(&img, EdgeMethod::Sobel, 20.0, true);
+
+        assert_eq!(*edges.get_pixel(0, 0), Rgba([200, 0, 0, 255]));
+        assert_eq!(*edges.get_pixel(4, 0), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn neon_glows_along_the_edges() {
+        let img = RgbaImage::from_fn(40, 40, |x, y| match (10..30).contains(&x) && (10..30).contains(&y) {
+            true => Rgba([255, 255, 255, 255]),
+            false => Rgba([0, 0, 0, 255]),
+        });
+        let lit = neon_image(&img, Rgba([255, 0, 128, 255]), 6);
+
+        assert_eq!(lit.dimensions(), (40, 40));
+        assert_eq!(*lit.get_pixel(0, 0), NEON_BACKGROUND);
+        assert_eq!(*lit.get_pixel(20, 20), NEON_BACKGROUND);
+        // Pink around the edge, and like the tubes' core right on it.
+        let near = lit.get_pixel(7, 20);
+        assert!(near[0] > 100 && near[1] < 60 && near[0] > near[2], "{:?}", near);
+        assert!((8..12).map(|x| lit.get_pixel(x, 20)[1]).max().unwrap() > 100);
+    }
+}
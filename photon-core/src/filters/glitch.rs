@@ -0,0 +1,414 @@
+//! Effects imitating broken or old hardware and formats.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::{ColorType, DynamicImage, Rgba, RgbaImage};
+
+use super::{brightness_contrast, luma, saturate, sharpen};
+use crate::geometry::sample_bilinear;
+use crate::random::Rng;
+use crate::{Media, Result};
+
+/// How many frames `--animate` makes out of a static image, and how far apart.
+const ANIMATED_FRAMES: usize = 8;
+const ANIMATED_FRAME_DELAY_MS: u32 = 80;
+
+/// Shifts the pixels of rows `top..bottom` to the right by `offset` (left when negative), wrapping around.
+fn shift_rows(img: &mut RgbaImage, top: u32, bottom: u32, offset: i64) {
+    let width = img.width() as i64;
+
+    for y in top..bottom.min(img.height()) {
+        let row = (0..img.width()).map(|x| *img.get_pixel(x, y)).collect::<Vec<_>>();
+        for x in 0..width {
+            img.put_pixel(x as u32, y, row[(x - offset).rem_euclid(width) as usize]);
+        }
+    }
+}
+
+/// Datamosh style corruption, `intensity` from 0 to 1: bands of rows shifted sideways, the red and blue
+/// channels pulled apart and blocks copied to the wrong place. The same seed gives the same artifacts.
+pub fn glitch_image(img: &RgbaImage, intensity: f32, seed: u64) -> RgbaImage {
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    let mut rng = Rng::new(seed);
+    let mut out = img.clone();
+
+    if intensity <= 0.0 || width == 0 || height == 0 {
+        return out;
+    }
+
+    let max_offset = (width as f32 * intensity / 5.0).ceil() as i64;
+    for _ in 0..(1.0 + intensity * 10.0) as usize {
+        let band = rng.range(1, (height / 8).max(1));
+        let top = rng.range(0, height - 1);
+        shift_rows(&mut out, top as u32, (top + band) as u32, rng.range(-max_offset, max_offset));
+    }
+
+    let channel_offset = (width as f32 * intensity / 40.0).ceil() as i64;
+    let (red, blue) = (rng.range(-channel_offset, channel_offset), rng.range(-channel_offset, channel_offset));
+    let shifted = out.clone();
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        let at = |offset: i64| shifted.get_pixel((x as i64 + offset).clamp(0, width - 1) as u32, y);
+        *pixel = Rgba([at(red)[0], pixel[1], at(blue)[2], pixel[3]]);
+    }
+
+    for _ in 0..(intensity * 8.0) as usize {
+        let (block_width, block_height) = (rng.range(1, (width / 4).max(1)), rng.range(1, (height / 10).max(1)));
+        let (sx, sy) = (rng.range(0, width - block_width), rng.range(0, height - block_height));
+        let (dx, dy) = (rng.range(0, width - block_width), rng.range(0, height - block_height));
+        // Some blocks also come out with their channels swapped.
+        let swap = rng.chance(0.5);
+
+        for y in 0..block_height {
+            for x in 0..block_width {
+                let source = *out.get_pixel((sx + x) as u32, (sy + y) as u32);
+                let pixel = if swap { Rgba([source[2], source[0], source[1], source[3]]) } else { source };
+                out.put_pixel((dx + x) as u32, (dy + y) as u32, pixel);
+            }
+        }
+    }
+    out
+}
+
+/// Glitches every frame differently. With `animate`, static images become a short GIF first so the
+/// artifacts move.
+pub fn glitch(media: Media, intensity: f32, animate: bool) -> Media {
+    let media = match animate {
+        true => media.into_animation(ANIMATED_FRAMES, ANIMATED_FRAME_DELAY_MS),
+        false => media,
+    };
+
+    media.map_images_indexed(|index, img| glitch_image(img, intensity, index as u64))
+}
+
+/// Chromatic aberration: the red channel moved `offset` pixels to the left and the blue one to the right.
+/// With `radial` they are pulled apart from the center instead, by up to `offset` pixels at the corners,
+/// like a cheap lens.
+pub fn aberrate(img: &RgbaImage, offset: f32, radial: bool) -> RgbaImage {
+    let (width, height) = (img.width() as f32, img.height() as f32);
+    let (cx, cy) = (width / 2.0, height / 2.0);
+    let reach = cx.hypot(cy).max(1.0);
+
+    // Clamped to the middle of the edge pixels, so the edges repeat rather than fading out.
+    let sample = |x: f32, y: f32| sample_bilinear(img, x.clamp(0.5, width - 0.5), y.clamp(0.5, height - 0.5));
+
+    let mut out = img.clone();
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        let (dx, dy) = match radial {
+            true => ((px - cx) / reach * offset, (py - cy) / reach * offset),
+            false => (offset, 0.0),
+        };
+
+        let (red, blue) = (sample(px - dx, py - dy), sample(px + dx, py + dy));
+        *pixel = Rgba([red[0], pixel[1], blue[2], pixel[3]]);
+    }
+    out
+}
+
+pub fn aberration(media: Media, offset: u32, radial: bool) -> Media {
+    media.map_images(|img| aberrate(img, offset as f32, radial))
+}
+
+/// How much of their brightness scanlines keep.
+const SCANLINE_BRIGHTNESS: f32 = 0.55;
+/// How much of their brightness the other two channels keep on each column of subpixels.
+const SUBPIXEL_BRIGHTNESS: f32 = 0.75;
+
+/// Makes the image look like it's on a CRT: bulged out from the center by `curvature` (0 to 1), with a dark
+/// scanline after every `line_gap` rows and the columns striped red, green and blue like phosphors.
+/// The corners pushed off the screen by the curvature become transparent.
+pub fn crt_image(img: &RgbaImage, curvature: f32, line_gap: u32) -> RgbaImage {
+    let (width, height) = (img.width() as f32, img.height() as f32);
+
+    RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        // Barrel distortion, in coordinates from -1 to 1 where the middle of each edge stays in place.
+        let (u, v) = ((x as f32 + 0.5) / width * 2.0 - 1.0, (y as f32 + 0.5) / height * 2.0 - 1.0);
+        let scale = (1.0 + curvature * (u * u + v * v)) / (1.0 + curvature);
+        let mut pixel = sample_bilinear(img, (u * scale + 1.0) / 2.0 * width, (v * scale + 1.0) / 2.0 * height);
+
+        let scanline = line_gap > 0 && y % (line_gap + 1) == line_gap;
+        for c in 0..3 {
+            let mut value = pixel[c] as f32;
+            if c != x as usize % 3 {
+                value *= SUBPIXEL_BRIGHTNESS;
+            }
+            if scanline {
+                value *= SCANLINE_BRIGHTNESS;
+            }
+            pixel[c] = value.round() as u8;
+        }
+        pixel
+    })
+}
+
+pub fn crt(media: Media, curvature: f32, line_gap: u32) -> Media {
+    media.map_images(|img| crt_image(img, curvature, line_gap))
+}
+
+/// The rows of a 3 by 5 pixel glyph for the on-screen display, the highest of the three bits on the left.
+fn osd_glyph(c: char) -> [u8; 5] {
+    match c {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        _ => [0; 5],
+    }
+}
+
+/// Draws blocky white text with a drop shadow, each glyph pixel `scale` pixels wide.
+pub(super) fn draw_osd(img: &mut RgbaImage, text: &str, x: u32, y: u32, scale: u32) {
+    for (offset, color) in [(scale, Rgba([0, 0, 0, 255])), (0, Rgba([255, 255, 255, 255]))] {
+        for (index, c) in text.chars().enumerate() {
+            for (row, bits) in osd_glyph(c).iter().enumerate() {
+                for column in (0..3).filter(|column| bits & (0b100 >> column) != 0) {
+                    let left = x + (index as u32 * 4 + column) * scale + offset;
+                    let top = y + row as u32 * scale + offset;
+
+                    for (px, py) in (left..left + scale).flat_map(|px| (top..top + scale).map(move |py| (px, py))) {
+                        if px < img.width() && py < img.height() {
+                            img.put_pixel(px, py, color);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A worn VHS tape: colors smeared sideways past the edges they belong to, unsteady rows, a band of
+/// tracking noise and the player's on-screen display. The same seed gives the same artifacts.
+pub fn vhs_image(img: &RgbaImage, seed: u64) -> RgbaImage {
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    let mut rng = Rng::new(seed);
+    let mut out = img.clone();
+
+    if width == 0 || height == 0 {
+        return out;
+    }
+
+    // Tape keeps far less color than brightness, so chroma is blurred and lags behind.
+    let bleed = (width / 120).max(1);
+    for y in 0..img.height() {
+        let chroma = (0..img.width())
+            .map(|x| {
+                let pixel = *img.get_pixel(x, y);
+                let brightness = luma(pixel);
+                [0, 1, 2].map(|c| pixel[c] as f32 - brightness)
+            })
+            .collect::<Vec<_>>();
+
+        for x in 0..width {
+            let window = (x - bleed * 2..=x).map(|sx| chroma[sx.clamp(0, width - 1) as usize]);
+            let sum = window.fold([0.0; 3], |sum, c| [sum[0] + c[0], sum[1] + c[1], sum[2] + c[2]]);
+
+            let pixel = out.get_pixel_mut(x as u32, y);
+            let brightness = luma(*pixel);
+            for c in 0..3 {
+                pixel[c] = (brightness + sum[c] / (bleed * 2 + 1) as f32).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+
+    let jitter = (width / 150).max(1);
+    for y in 0..height {
+        if rng.chance(0.3) {
+            shift_rows(&mut out, y as u32, y as u32 + 1, rng.range(-jitter, jitter));
+        }
+    }
+
+    let band = (height / 12).max(1);
+    let top = rng.range(0, height - band);
+    for y in top..top + band {
+        shift_rows(&mut out, y as u32, y as u32 + 1, rng.range(-jitter * 4, jitter * 4));
+
+        for x in 0..img.width() {
+            if rng.chance(0.25) {
+                let noise = rng.range(0, 255) as u32;
+                let pixel = out.get_pixel_mut(x, y as u32);
+                for c in 0..3 {
+                    pixel[c] = ((pixel[c] as u32 + noise) / 2) as u8;
+                }
+            }
+        }
+    }
+
+    let scale = (img.height() / 100).max(1);
+    let margin = scale * 4;
+    draw_osd(&mut out, "PLAY", margin, margin, scale);
+    draw_osd(&mut out, "AM 12:00", margin, img.height().saturating_sub(margin + scale * 5), scale);
+    out
+}
+
+/// Puts every frame on a VHS tape, see [`vhs_image`]. A `frames` count above 0 turns static images into
+/// a GIF of that many frames first, so the picture wobbles like a real one.
+pub fn vhs(media: Media, frames: usize) -> Media {
+    let media = match frames {
+        0 => media,
+        frames => media.into_animation(frames, ANIMATED_FRAME_DELAY_MS),
+    };
+
+    media.map_images_indexed(|index, img| vhs_image(img, index as u64))
+}
+
+/// Compresses the image as a JPEG at the given quality (1 to 100) `passes` times over, for the blocky
+/// artifacts. Transparency is kept as it was, JPEG has none.
+pub fn jpeg_artifacts(img: &RgbaImage, quality: u8, passes: u32) -> Result<RgbaImage> {
+    let (width, height) = img.dimensions();
+    let mut rgb = DynamicImage::ImageRgba8(img.clone()).into_rgb8();
+
+    for _ in 0..passes {
+        let mut encoded = Vec::new();
+        JpegEncoder::new_with_quality(&mut encoded, quality).encode(rgb.as_raw(), width, height, ColorType::Rgb8)?;
+        rgb = image::load_from_memory(&encoded)?.into_rgb8();
+    }
+
+    Ok(RgbaImage::from_fn(width, height, |x, y| {
+        let [r, g, b] = rgb.get_pixel(x, y).0;
+        Rgba([r, g, b, img.get_pixel(x, y)[3]])
+    }))
+}
+
+/// Runs every frame through [`jpeg_artifacts`].
+pub fn jpeg(media: Media, quality: u8, passes: u32) -> Result<Media> {
+    media.try_map_images(|img| jpeg_artifacts(img, quality, passes))
+}
+
+/// The color deep fried images are tinted toward.
+const FRIED: [f32; 3] = [255.0, 100.0, 0.0];
+
+/// Deep fries the image, `level` from 1 to 3: oversaturated, contrasty, oversharpened, tinted orange and
+/// compressed into mush.
+pub fn deepfry(media: Media, level: u32) -> Result<Media> {
+    let level = level.clamp(1, 3);
+    let strength = level as f32;
+
+    let media = saturate(media, 1.0 + strength);
+    let media = brightness_contrast(media, 5.0, 25.0 * strength);
+    let media = media.map_pixels(|pixel| {
+        let tint = 0.12 * strength;
+        let channel = |c: usize| (pixel[c] as f32 + (FRIED[c] - pixel[c] as f32) * tint).round() as u8;
+        Rgba([channel(0), channel(1), channel(2), pixel[3]])
+    });
+    let media = sharpen(media, 1.0 + strength, 0);
+
+    jpeg(media, [14, 8, 3][level as usize - 1], level * 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient() -> RgbaImage {
+        RgbaImage::from_fn(64, 64, |x, y| Rgba([x as u8 * 4, y as u8 * 4, 128, 255]))
+    }
+
+    #[test]
+    fn shifts_rows_with_wrapping() {
+        let mut img = RgbaImage::from_fn(4, 2, |x, _| Rgba([x as u8, 0, 0, 255]));
+        shift_rows(&mut img, 0, 1, 1);
+
+        assert_eq!((0..4).map(|x| img.get_pixel(x, 0)[0]).collect::<Vec<_>>(), [3, 0, 1, 2]);
+        assert_eq!(img.get_pixel(0, 1)[0], 0);
+    }
+
+    #[test]
+    fn glitches_deterministically() {
+        let img = gradient();
+        let glitched = glitch_image(&img, 0.8, 5);
+
+        assert_ne!(glitched, img);
+        assert_eq!(glitched, glitch_image(&img, 0.8, 5));
+        assert_eq!(glitch_image(&img, 0.0, 5), img);
+    }
+
+    #[test]
+    fn aberration_pulls_channels_apart() {
+        let mut img = RgbaImage::from_pixel(9, 9, Rgba([0, 0, 0, 255]));
+        img.put_pixel(4, 4, Rgba([255, 255, 255, 255]));
+
+        let shifted = aberrate(&img, 2.0, false);
+        assert_eq!(*shifted.get_pixel(4, 4), Rgba([0, 255, 0, 255]));
+        assert_eq!(shifted.get_pixel(6, 4)[0], 255);
+        assert_eq!(shifted.get_pixel(2, 4)[2], 255);
+
+        // Nothing moves at the center of a radial aberration.
+        assert_eq!(aberrate(&img, 4.0, true).get_pixel(4, 4), img.get_pixel(4, 4));
+    }
+
+    #[test]
+    fn crt_draws_scanlines_and_subpixels() {
+        let img = RgbaImage::from_pixel(12, 12, Rgba([200, 200, 200, 255]));
+        let screen = crt_image(&img, 0.0, 2);
+
+        assert_eq!(*screen.get_pixel(0, 0), Rgba([200, 150, 150, 255]));
+        assert_eq!(*screen.get_pixel(1, 0), Rgba([150, 200, 150, 255]));
+        assert_eq!(*screen.get_pixel(0, 2), Rgba([110, 83, 83, 255]));
+
+        // Curving the screen pushes the corners off it.
+        assert_eq!(crt_image(&img, 0.5, 2).get_pixel(0, 0)[3], 0);
+        assert_eq!(crt_image(&img, 0.5, 2).get_pixel(6, 6)[3], 255);
+    }
+
+    #[test]
+    fn vhs_draws_the_display() {
+        let img = RgbaImage::from_pixel(100, 100, Rgba([0, 0, 0, 255]));
+        let tape = vhs_image(&img, 3);
+
+        // The top left pixel of the P in PLAY, then its shadow.
+        assert_eq!(*tape.get_pixel(4, 4), Rgba([255, 255, 255, 255]));
+        assert_eq!(*tape.get_pixel(5, 9), Rgba([0, 0, 0, 255]));
+        assert_eq!(tape, vhs_image(&img, 3));
+    }
+
+    #[test]
+    fn vhs_bleeds_color_sideways() {
+        let img = RgbaImage::from_fn(240, 100, |x, _| if x < 120 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 0, 255, 255]) });
+        let tape = vhs_image(&img, 0);
+
+        // Just past the edge, red lingers over the blue on (nearly) every row.
+        assert!((0..100).filter(|&y| tape.get_pixel(122, y)[0] > 0).count() > 80);
+    }
+
+    #[test]
+    fn jpeg_artifacts_keep_size_and_alpha() {
+        let img = RgbaImage::from_fn(16, 8, |x, _| Rgba([200, 30, 30, if x < 8 { 255 } else { 0 }]));
+        let compressed = jpeg_artifacts(&img, 5, 2).unwrap();
+
+        assert_eq!(compressed.dimensions(), (16, 8));
+        assert_eq!(compressed.get_pixel(0, 0)[3], 255);
+        assert_eq!(compressed.get_pixel(15, 0)[3], 0);
+    }
+
+    #[test]
+    fn deep_frying_gets_more_orange() {
+        let media = || Media::Static(RgbaImage::from_pixel(8, 8, Rgba([128, 128, 128, 255])));
+        let pixel = |level| *deepfry(media(), level).unwrap().first_frame().get_pixel(4, 4);
+
+        assert!(pixel(1)[0] > 128 && pixel(1)[2] < 128);
+        assert!(pixel(3)[0] >= pixel(1)[0] && pixel(3)[2] <= pixel(1)[2]);
+    }
+
+    #[test]
+    fn animates_static_images() {
+        match glitch(Media::Static(gradient()), 0.5, true) {
+            Media::Animated(frames) => {
+                assert_eq!(frames.len(), ANIMATED_FRAMES);
+                assert_ne!(frames[0].buffer(), frames[1].buffer());
+            },
+            Media::Static(_) => panic!("expected an animation"),
+        }
+        assert!(!glitch(Media::Static(gradient()), 0.5, false).is_animated());
+    }
+}
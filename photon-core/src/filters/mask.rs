@@ -0,0 +1,424 @@
+//! Cutting images into shapes with anti-aliased edges, and subjects out of their backgrounds.
+
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Rgba, RgbaImage};
+
+use std::collections::HashMap;
+
+use super::{crop_to_aspect, shadow_image, Gravity};
+use crate::geometry::{self, blend_over};
+use crate::{fit_dimensions, Media};
+
+/// The side of the square canvas stickers are made on.
+pub const STICKER_SIZE: u32 = 512;
+/// How much room is left around a sticker's subject for its outline and shadow.
+const STICKER_MARGIN: u32 = 32;
+const STICKER_OUTLINE: f32 = 12.0;
+const STICKER_SHADOW: Rgba<u8> = Rgba([0, 0, 0, 90]);
+
+/// How much of a pixel centered at distance `distance` is within an edge at `radius`, 0 to 1.
+fn coverage(radius: f32, distance: f32) -> f32 {
+    (radius - distance + 0.5).clamp(0.0, 1.0)
+}
+
+/// Masks a square image to the circle touching its sides. A `border` color draws a ring of the given
+/// width along the edge, 0 picking one from the size.
+pub fn circle_image(img: &RgbaImage, border: Option<(Rgba<u8>, u32)>) -> RgbaImage {
+    let side = img.width().min(img.height()) as f32;
+    let (cx, cy) = (img.width() as f32 / 2.0, img.height() as f32 / 2.0);
+    let radius = side / 2.0;
+
+    let ring = border.map(|(color, width)| match width {
+        0 => (color, (side / 20.0).ceil()),
+        width => (color, width as f32),
+    });
+
+    let mut out = img.clone();
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        let distance = (x as f32 + 0.5 - cx).hypot(y as f32 + 0.5 - cy);
+        let outer = coverage(radius, distance);
+
+        if let Some((color, width)) = ring {
+            let inner = coverage(radius - width, distance);
+            let alpha = color[3] as f32 * (1.0 - inner);
+            *pixel = blend_over(*pixel, Rgba([color[0], color[1], color[2], alpha.round() as u8]));
+        }
+        pixel[3] = (pixel[3] as f32 * outer).round() as u8;
+    }
+    out
+}
+
+/// Crops every frame to a centered square and masks it to a circle, see [`circle_image`].
+pub fn circle(media: Media, border: Option<(Rgba<u8>, u32)>) -> Media {
+    crop_to_aspect(media, 1.0, Gravity::Center).map_images(|img| circle_image(img, border))
+}
+
+/// Rounds off the corners of the image with the given radius, at most half the shorter side.
+pub fn round_corners(img: &RgbaImage, radius: f32) -> RgbaImage {
+    let (width, height) = (img.width() as f32, img.height() as f32);
+    let radius = radius.min(width.min(height) / 2.0);
+
+    let mut out = img.clone();
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        let (px, py) = (x as f32 + 0.5, y as f32 + 0.5);
+        // The center of the corner's circle this pixel is closest to, only pixels beyond it get cut.
+        let (cx, cy) = (px.clamp(radius, width - radius), py.clamp(radius, height - radius));
+
+        if (cx, cy) != (px, py) {
+            pixel[3] = (pixel[3] as f32 * coverage(radius, (px - cx).hypot(py - cy))).round() as u8;
+        }
+    }
+    out
+}
+
+/// Rounds the corners of every frame, see [`round_corners`].
+pub fn round(media: Media, radius: f32) -> Media {
+    media.map_images(|img| round_corners(img, radius))
+}
+
+/// Whether the image has been cut out already, going by some of its edges being transparent.
+pub fn is_cut_out(img: &RgbaImage) -> bool {
+    let (width, height) = img.dimensions();
+    let edges = (0..width).flat_map(|x| [(x, 0), (x, height - 1)]).chain((0..height).flat_map(|y| [(0, y), (width - 1, y)]));
+    edges.into_iter().any(|(x, y)| img.get_pixel(x, y)[3] < 16)
+}
+
+fn color_distance(a: Rgba<u8>, b: Rgba<u8>) -> f32 {
+    (0..3).map(|c| (a[c] as f32 - b[c] as f32).powi(2)).sum::<f32>().sqrt()
+}
+
+/// The most common color along the edges of the image, which is the backdrop's for most photos of
+/// things on one. Colors are grouped coarsely so noise doesn't split them up.
+pub fn backdrop_color(img: &RgbaImage) -> Rgba<u8> {
+    let (width, height) = img.dimensions();
+    let mut groups = HashMap::<[u8; 3], (u32, [u32; 3])>::new();
+
+    for (x, y, pixel) in img.enumerate_pixels() {
+        if x != 0 && y != 0 && x != width - 1 && y != height - 1 {
+            continue;
+        }
+
+        let (count, sums) = groups.entry([pixel[0] >> 4, pixel[1] >> 4, pixel[2] >> 4]).or_default();
+        *count += 1;
+        (0..3).for_each(|c| sums[c] += pixel[c] as u32);
+    }
+
+    let (count, sums) = groups.into_values().max_by_key(|(count, _)| *count).unwrap_or_default();
+    let count = count.max(1);
+    Rgba([(sums[0] / count) as u8, (sums[1] / count) as u8, (sums[2] / count) as u8, 255])
+}
+
+/// Makes the backdrop of an image transparent, for subjects on a flat background. Starting from the
+/// edges, every pixel connected to them within `tolerance` of the backdrop's color is removed, that
+/// being a distance between RGB colors of up to about 441. The pixels just past the tolerance along
+/// the cut are left partly transparent, so it isn't jagged.
+pub fn remove_background(img: &RgbaImage, tolerance: f32) -> RgbaImage {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let backdrop = backdrop_color(img);
+    let distance = img.pixels().map(|pixel| color_distance(*pixel, backdrop)).collect::<Vec<_>>();
+
+    let mut removed = vec![false; distance.len()];
+    let mut stack = (0..distance.len())
+        .filter(|&i| {
+            let (x, y) = (i % width, i / width);
+            (x == 0 || y == 0 || x == width - 1 || y == height - 1) && distance[i] <= tolerance
+        })
+        .collect::<Vec<_>>();
+
+    while let Some(index) = stack.pop() {
+        if removed[index] {
+            continue;
+        }
+        removed[index] = true;
+
+        let (x, y) = (index % width, index / width);
+        let neighbours = [
+            (x > 0).then(|| index - 1),
+            (x + 1 < width).then(|| index + 1),
+            (y > 0).then(|| index - width),
+            (y + 1 < height).then(|| index + width),
+        ];
+        stack.extend(neighbours.into_iter().flatten().filter(|&i| !removed[i] && distance[i] <= tolerance));
+    }
+
+    let mut out = img.clone();
+    for (index, pixel) in out.pixels_mut().enumerate() {
+        if removed[index] {
+            pixel[3] = 0;
+            continue;
+        }
+
+        let (x, y) = (index % width, index / width);
+        let on_cut = (x > 0 && removed[index - 1])
+            || (x + 1 < width && removed[index + 1])
+            || (y > 0 && removed[index - width])
+            || (y + 1 < height && removed[index + width]);
+        if on_cut && tolerance > 0.0 {
+            let kept = ((distance[index] - tolerance) / tolerance).clamp(0.0, 1.0);
+            pixel[3] = (pixel[3] as f32 * kept).round() as u8;
+        }
+    }
+    out
+}
+
+/// Keys out `color` wherever it is in the image, holes in the subject included, for green screens and the
+/// like. Colors within `tolerance` of it become transparent and those up to twice as far partly so.
+pub fn chroma_key(img: &RgbaImage, color: Rgba<u8>, tolerance: f32) -> RgbaImage {
+    let mut out = img.clone();
+    for pixel in out.pixels_mut() {
+        let distance = color_distance(*pixel, color);
+        let kept = match tolerance > 0.0 {
+            true => ((distance - tolerance) / tolerance).clamp(0.0, 1.0),
+            false => (distance > 0.0) as u8 as f32,
+        };
+        pixel[3] = (pixel[3] as f32 * kept).round() as u8;
+    }
+    out
+}
+
+/// Multiplies the transparency of the image by a mask of how much of every pixel to keep, such as one
+/// predicted by a segmentation model. The mask is stretched to the size of the image.
+pub fn apply_mask(img: &RgbaImage, mask: &GrayImage) -> RgbaImage {
+    let mask = match mask.dimensions() == img.dimensions() {
+        true => mask.clone(),
+        false => imageops::resize(mask, img.width(), img.height(), FilterType::Triangle),
+    };
+
+    let mut out = img.clone();
+    for (pixel, keep) in out.pixels_mut().zip(mask.pixels()) {
+        pixel[3] = (pixel[3] as u32 * keep[0] as u32 / 255) as u8;
+    }
+    out
+}
+
+/// Roughly how far every pixel is from the nearest mostly opaque one, by a chamfer distance transform
+/// which stays within a few percent of the real distance.
+fn distance_to_opaque(img: &RgbaImage) -> Vec<f32> {
+    let (width, height) = (img.width() as usize, img.height() as usize);
+    let mut distance = img.pixels().map(|pixel| if pixel[3] >= 128 { 0 } else { u32::MAX / 2 }).collect::<Vec<_>>();
+
+    // Steps across cost 3 and diagonal ones 4, about 1 and √2 scaled by 3.
+    let forward = [(-1, 0, 3), (-1, -1, 4), (0, -1, 3), (1, -1, 4)];
+    let backward = [(1, 0, 3), (1, 1, 4), (0, 1, 3), (-1, 1, 4)];
+    let mut relax = |x: usize, y: usize, steps: &[(isize, isize, u32)]| {
+        for &(dx, dy, cost) in steps {
+            let (nx, ny) = (x as isize + dx, y as isize + dy);
+            if nx >= 0 && ny >= 0 && (nx as usize) < width && (ny as usize) < height {
+                let through = distance[ny as usize * width + nx as usize] + cost;
+                let here = &mut distance[y * width + x];
+                *here = (*here).min(through);
+            }
+        }
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            relax(x, y, &forward);
+        }
+    }
+    for y in (0..height).rev() {
+        for x in (0..width).rev() {
+            relax(x, y, &backward);
+        }
+    }
+
+    distance.into_iter().map(|distance| distance as f32 / 3.0).collect()
+}
+
+/// Crops the image to the smallest rectangle holding everything that isn't fully transparent.
+/// The bounds of the pixels that aren't fully transparent, as the left, top, right and bottom ones.
+fn opaque_bounds(img: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+    let opaque = img.enumerate_pixels().filter(|(_, _, pixel)| pixel[3] > 0).map(|(x, y, _)| (x, y));
+    opaque.fold(None, |bounds, (x, y)| match bounds {
+        Some((left, top, right, bottom)) => Some((left.min(x), top.min(y), right.max(x), bottom.max(y))),
+        None => Some((x, y, x, y)),
+    })
+}
+
+/// Turns an image into a sticker: the subject is cut out (unless it already is, see [`is_cut_out`] and
+/// [`remove_background`]) and scaled to fit a transparent [`STICKER_SIZE`] square, with a thick white
+/// outline and a faint shadow. Every frame of an animation is cropped to where the subject is in any of
+/// them, so it doesn't jump around or change size.
+pub fn stickerify(media: Media, tolerance: f32) -> Media {
+    let cut = match is_cut_out(media.first_frame()) {
+        true => media,
+        false => media.par_map_images(|img| remove_background(img, tolerance)),
+    };
+    let bounds = cut.images().into_iter().filter_map(opaque_bounds).reduce(|(left, top, right, bottom), other| {
+        (left.min(other.0), top.min(other.1), right.max(other.2), bottom.max(other.3))
+    });
+
+    match bounds {
+        Some((left, top, right, bottom)) => cut.par_map_images(|img| {
+            sticker(&imageops::crop_imm(img, left, top, right - left + 1, bottom - top + 1).to_image())
+        }),
+        None => cut.par_map_images(sticker),
+    }
+}
+
+/// Outlines a subject already cut out and trimmed and puts it on the sticker canvas, see [`stickerify`].
+fn sticker(subject: &RgbaImage) -> RgbaImage {
+    let room = STICKER_SIZE - STICKER_MARGIN * 2;
+    let (width, height) = fit_dimensions(subject.width(), subject.height(), room, room);
+    let subject = imageops::resize(subject, width, height, FilterType::Lanczos3);
+
+    let pad = STICKER_OUTLINE.ceil() as u32 + 1;
+    let mut outlined = RgbaImage::new(width + pad * 2, height + pad * 2);
+    geometry::overlay(&mut outlined, &subject, pad as i64, pad as i64);
+    let distance = distance_to_opaque(&outlined);
+
+    let mut outline = RgbaImage::from_fn(outlined.width(), outlined.height(), |x, y| {
+        let distance = distance[(y * outlined.width() + x) as usize];
+        Rgba([255, 255, 255, (coverage(STICKER_OUTLINE, distance) * 255.0).round() as u8])
+    });
+    geometry::overlay(&mut outline, &outlined, 0, 0);
+
+    // The shadow grows the image as much on the top and left as the blur spreads it, which is what
+    // centering with the margin leaves room for.
+    let shadowed = shadow_image(&outline, 6, 4, STICKER_SHADOW);
+    let spread = (shadowed.width() - outline.width() - 4) / 2;
+    let mut sticker = RgbaImage::new(STICKER_SIZE, STICKER_SIZE);
+    let x = (STICKER_SIZE as i64 - outline.width() as i64) / 2 - spread as i64;
+    let y = (STICKER_SIZE as i64 - outline.height() as i64) / 2 - spread as i64;
+    geometry::overlay(&mut sticker, &shadowed, x, y);
+    sticker
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_to_a_circle() {
+        let media = Media::Static(RgbaImage::from_pixel(30, 20, Rgba([0, 255, 0, 255])));
+        let masked = circle(media, None);
+        let img = masked.first_frame();
+
+        assert_eq!(img.dimensions(), (20, 20));
+        assert_eq!(img.get_pixel(0, 0)[3], 0);
+        assert_eq!(*img.get_pixel(10, 10), Rgba([0, 255, 0, 255]));
+        // Anti-aliased along the edge.
+        assert!((1..255).contains(&img.get_pixel(2, 3)[3]));
+    }
+
+    #[test]
+    fn rounds_corners() {
+        let img = RgbaImage::from_pixel(40, 20, Rgba([0, 255, 0, 255]));
+        let rounded = round_corners(&img, 8.0);
+
+        for (x, y) in [(0, 0), (39, 0), (0, 19), (39, 19)] {
+            assert_eq!(rounded.get_pixel(x, y)[3], 0);
+        }
+        assert_eq!(rounded.get_pixel(8, 0)[3], 255);
+        assert_eq!(rounded.get_pixel(0, 10)[3], 255);
+
+        // Radii past half the shorter side make the short sides semicircles.
+        assert_eq!(round_corners(&img, 100.0), round_corners(&img, 10.0));
+    }
+
+    #[test]
+    fn draws_borders() {
+        let img = RgbaImage::from_pixel(40, 40, Rgba([0, 255, 0, 255]));
+        let ringed = circle_image(&img, Some((Rgba([255, 0, 0, 255]), 4)));
+
+        assert_eq!(*ringed.get_pixel(20, 1), Rgba([255, 0, 0, 255]));
+        assert_eq!(*ringed.get_pixel(20, 10), Rgba([0, 255, 0, 255]));
+        assert_eq!(ringed.get_pixel(0, 0)[3], 0);
+    }
+
+    /// A red square on a white backdrop with a little noise.
+    fn product_shot() -> RgbaImage {
+        RgbaImage::from_fn(60, 40, |x, y| match (20..40).contains(&x) && (10..30).contains(&y) {
+            true => Rgba([220, 20, 20, 255]),
+            false => Rgba([250 - (x % 3) as u8, 250, 248 + (y % 2) as u8, 255]),
+        })
+    }
+
+    #[test]
+    fn removes_flat_backgrounds() {
+        let img = product_shot();
+        assert!(!is_cut_out(&img));
+
+        let cut = remove_background(&img, 40.0);
+        assert!(is_cut_out(&cut));
+        assert_eq!(cut.get_pixel(0, 0)[3], 0);
+        assert_eq!(cut.get_pixel(50, 35)[3], 0);
+        assert_eq!(*cut.get_pixel(30, 20), Rgba([220, 20, 20, 255]));
+        assert_eq!(cut.get_pixel(20, 10)[3], 255);
+    }
+
+    #[test]
+    fn keys_out_colors_everywhere() {
+        // Green all around, with red on either side of a greenish pixel in the middle row.
+        let img = RgbaImage::from_fn(5, 3, |x, y| match (x, y) {
+            (1 | 3, 1) => Rgba([200, 0, 0, 255]),
+            (2, 1) => Rgba([27, 219, 0, 255]),
+            _ => Rgba([0, 255, 0, 255]),
+        });
+        let keyed = chroma_key(&img, backdrop_color(&img), 30.0);
+
+        assert_eq!(backdrop_color(&img), Rgba([0, 255, 0, 255]));
+        assert_eq!(keyed.get_pixel(0, 0)[3], 0);
+        assert_eq!(keyed.get_pixel(1, 1)[3], 255);
+        // Enclosed, but keyed out anyway, and 15 past the tolerance so half transparent.
+        assert_eq!(keyed.get_pixel(2, 1)[3], 128);
+    }
+
+    #[test]
+    fn applies_masks() {
+        let img = RgbaImage::from_pixel(4, 4, Rgba([10, 20, 30, 200]));
+        let mask = GrayImage::from_fn(2, 2, |x, _| image::Luma([if x == 0 { 0 } else { 255 }]));
+        let masked = apply_mask(&img, &mask);
+
+        assert_eq!(masked.dimensions(), (4, 4));
+        assert_eq!(*masked.get_pixel(0, 1), Rgba([10, 20, 30, 0]));
+        assert_eq!(*masked.get_pixel(3, 2), Rgba([10, 20, 30, 200]));
+    }
+
+    #[test]
+    fn measures_distances_to_opaque_pixels() {
+        let img = RgbaImage::from_fn(9, 1, |x, _| Rgba([0, 0, 0, if x == 4 { 255 } else { 0 }]));
+        let distance = distance_to_opaque(&img);
+
+        assert_eq!(distance, [4.0, 3.0, 2.0, 1.0, 0.0, 1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn makes_stickers() {
+        let sticker = stickerify(Media::Static(product_shot()), 40.0);
+        let sticker = sticker.first_frame();
+        let center = STICKER_SIZE / 2;
+
+        assert_eq!(sticker.dimensions(), (STICKER_SIZE, STICKER_SIZE));
+        assert_eq!(sticker.get_pixel(0, 0)[3], 0);
+        assert_eq!(*sticker.get_pixel(center, center), Rgba([220, 20, 20, 255]));
+        // The subject fills the room left by the margin, and the outline goes around it.
+        assert_eq!(*sticker.get_pixel(STICKER_MARGIN + 2, center), Rgba([220, 20, 20, 255]));
+        assert_eq!(*sticker.get_pixel(STICKER_MARGIN - 6, center), Rgba([255, 255, 255, 255]));
+        let past = sticker.get_pixel(STICKER_MARGIN - 24, center);
+        assert!(past[3] < 90 && past[0] < 50, "{:?}", past);
+    }
+
+    #[test]
+    fn keeps_animated_stickers_in_place() {
+        // A red square moving from the left of a white backdrop to the right.
+        let frame = |start: u32| {
+            let img = RgbaImage::from_fn(60, 40, |x, y| match (start..start + 10).contains(&x) && (10..30).contains(&y) {
+                true => Rgba([220, 20, 20, 255]),
+                false => Rgba([250, 250, 250, 255]),
+            });
+            image::Frame::new(img)
+        };
+        let stickers = stickerify(Media::Animated(vec![frame(10), frame(40)]), 40.0);
+        let (first, last) = (stickers.images()[0], stickers.images()[1]);
+
+        // Both frames share the subject's bounds over the whole animation, 40 by 20 pixels scaled to fit.
+        assert_eq!(stickers.frame_count(), 2);
+        let center = STICKER_SIZE / 2;
+        let (left, right) = (STICKER_MARGIN + 56, STICKER_SIZE - STICKER_MARGIN - 56);
+        assert_eq!(*first.get_pixel(left, center), Rgba([220, 20, 20, 255]));
+        assert!(first.get_pixel(right, center)[3] < 100);
+        assert_eq!(*last.get_pixel(right, center), Rgba([220, 20, 20, 255]));
+        assert!(last.get_pixel(left, center)[3] < 100);
+    }
+}
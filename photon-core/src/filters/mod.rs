@@ -0,0 +1,1378 @@
+//! The filters Photon offers, each usable directly or looked up by name through [`FILTERS`].
+
+mod adjust;
+mod animate;
+mod carve;
+mod composite;
+mod convolve;
+mod distort;
+mod edges;
+mod glitch;
+mod mask;
+mod shadow;
+mod stylize;
+mod timing;
+mod transform;
+
+pub use adjust::{
+    apply_lut,
+    brightness_contrast,
+    color_matrix,
+    duotone,
+    gamma,
+    gradient_map,
+    grayscale,
+    huerotate,
+    invert,
+    luma,
+    map_channels,
+    otsu_threshold,
+    posterize,
+    quantize,
+    rainbow,
+    replace_color,
+    saturate,
+    sepia,
+    solarize,
+    threshold,
+    tint,
+    MAX_GRADIENT_STOPS,
+};
+pub use animate::{
+    bounce,
+    crossfade,
+    fade,
+    petpet,
+    petpet_hand,
+    shake,
+    shift_image,
+    spin,
+    spin_image,
+    triggered,
+    wiggle,
+    zoom,
+    zoom_image,
+    PETPET_SIZE,
+};
+pub use carve::{magik, magik_image, MAX_MAGIK_DIMENSION, MAX_MAGIK_FRAMES};
+pub use composite::{
+    background_image,
+    blend,
+    compare,
+    fill_background,
+    grid,
+    overlay,
+    ship,
+    watermark,
+    watermark_text,
+    Placement,
+    MAX_GRID_CELL,
+    SHIP_AVATAR_SIZE,
+};
+pub use convolve::{blur, convolve, emboss, gaussian_blur, sharpen, unsharp_mask};
+pub use distort::{
+    bulge,
+    bulge_image,
+    explode,
+    implode,
+    implode_image,
+    kaleidoscope,
+    kaleidoscope_image,
+    remap,
+    swirl,
+    swirl_image,
+    wave,
+    wave_image,
+    WaveAxis,
+};
+pub use edges::{detect_edges, edges, neon, neon_image, EdgeMethod};
+pub use glitch::{
+    aberrate,
+    aberration,
+    crt,
+    crt_image,
+    deepfry,
+    glitch,
+    glitch_image,
+    jpeg,
+    jpeg_artifacts,
+    vhs,
+    vhs_image,
+};
+pub use mask::{
+    apply_mask,
+    backdrop_color,
+    chroma_key,
+    circle,
+    circle_image,
+    is_cut_out,
+    remove_background,
+    round,
+    round_corners,
+    stickerify,
+    STICKER_SIZE,
+};
+pub use shadow::{glow, glow_image, shadow, shadow_image};
+pub use stylize::{
+    halftone,
+    halftone_image,
+    noise,
+    noise_image,
+    oil,
+    oil_paint,
+    pixelate,
+    pixelate_image,
+    vignette,
+    vignette_image,
+    NoiseKind,
+    MAX_OIL_DIMENSION,
+};
+pub use timing::{
+    boomerang,
+    freeze,
+    makegif,
+    reverse,
+    speed,
+    spritesheet,
+    MAX_BOOMERANG_FRAMES,
+    MIN_FRAME_DELAY_MS,
+};
+pub use transform::{
+    border,
+    crop,
+    crop_to_aspect,
+    flip,
+    flop,
+    mirror,
+    mirror_image,
+    resize,
+    rotate,
+    scale,
+    stretch,
+    tile,
+    Gravity,
+    MirrorSide,
+    ResizeMode,
+    MAX_RESIZE_DIMENSION,
+    MAX_TOTAL_PIXELS,
+};
+
+use image::imageops::FilterType;
+
+use crate::text::bold_font;
+use crate::{BlendMode, Media, ParamKind, ParamSpec, Params, Result};
+
+/// A named filter along with the parameters it takes.
+pub struct Filter {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub params: &'static [ParamSpec],
+    run: fn(Media, &Params) -> Result<Media>,
+}
+
+impl Filter {
+    /// Applies the filter to every frame. `params` should have been parsed from this filter's [`Filter::params`].
+    pub fn apply(&self, media: Media, params: &Params) -> Result<Media> {
+        (self.run)(media, params)
+    }
+}
+
+pub static FILTERS: &[Filter] = &[
+    Filter {
+        name: "invert",
+        description: "Inverts the colors of an image.",
+        params: &[],
+        run: |media, _| Ok(invert(media)),
+    },
+    Filter {
+        name: "grayscale",
+        description: "Converts an image to grayscale.",
+        params: &[
+            ParamSpec {
+                name: "levels",
+                description: "How many shades of gray to use, fewer giving a posterized look.",
+                kind: ParamKind::Int { min: 2, max: 256, default: 256 },
+            },
+        ],
+        run: |media, params| Ok(grayscale(media, params.int("levels") as u32)),
+    },
+    Filter {
+        name: "posterize",
+        description: "Reduces the number of colors in an image.",
+        params: &[
+            ParamSpec {
+                name: "levels",
+                description: "How many values each color channel is reduced to.",
+                kind: ParamKind::Int { min: 2, max: 16, default: 4 },
+            },
+        ],
+        run: |media, params| Ok(posterize(media, params.int("levels") as u32)),
+    },
+    Filter {
+        name: "huerotate",
+        description: "Shifts the hues of an image.",
+        params: &[
+            ParamSpec {
+                name: "degrees",
+                description: "How far to rotate hues around the color wheel.",
+                kind: ParamKind::Float { min: -360.0, max: 360.0, default: 180.0 },
+            },
+        ],
+        run: |media, params| Ok(huerotate(media, params.float("degrees") as f32)),
+    },
+    Filter {
+        name: "rainbow",
+        description: "Cycles the hues of an image through the rainbow.",
+        params: &[
+            ParamSpec {
+                name: "frames",
+                description: "How many frames a still image gets, ignored for GIFs.",
+                kind: ParamKind::Int { min: 2, max: 60, default: 12 },
+            },
+        ],
+        run: |media, params| Ok(rainbow(media, params.int("frames") as usize)),
+    },
+    Filter {
+        name: "saturate",
+        description: "Makes the colors of an image more or less vivid.",
+        params: &[
+            ParamSpec {
+                name: "factor",
+                description: "What to multiply the saturation by, below 1 to desaturate.",
+                kind: ParamKind::Float { min: 0.0, max: 3.0, default: 1.5 },
+            },
+        ],
+        run: |media, params| Ok(saturate(media, params.float("factor") as f32)),
+    },
+    Filter {
+        name: "desaturate",
+        description: "Makes the colors of an image less vivid.",
+        params: &[
+            ParamSpec {
+                name: "factor",
+                description: "What to multiply the saturation by, 0 leaving only grays.",
+                kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.5 },
+            },
+        ],
+        run: |media, params| Ok(saturate(media, params.float("factor") as f32)),
+    },
+    Filter {
+        name: "adjust",
+        description: "Adjusts the brightness and contrast of an image.",
+        params: &[
+            ParamSpec {
+                name: "brightness",
+                description: "How much brighter to make the image in percent, negative to darken it.",
+                kind: ParamKind::Float { min: -100.0, max: 100.0, default: 0.0 },
+            },
+            ParamSpec {
+                name: "contrast",
+                description: "How much to increase the contrast in percent, negative to flatten it.",
+                kind: ParamKind::Float { min: -100.0, max: 100.0, default: 0.0 },
+            },
+        ],
+        run: |media, params| Ok(brightness_contrast(media, params.float("brightness") as f32, params.float("contrast") as f32)),
+    },
+    Filter {
+        name: "gamma",
+        description: "Gamma corrects an image.",
+        params: &[
+            ParamSpec {
+                name: "gamma",
+                description: "Above 1 brightens the midtones, below 1 darkens them.",
+                kind: ParamKind::Float { min: 0.1, max: 5.0, default: 2.0 },
+            },
+        ],
+        run: |media, params| Ok(gamma(media, params.float("gamma") as f32)),
+    },
+    Filter {
+        name: "sepia",
+        description: "Tones an image sepia.",
+        params: &[
+            ParamSpec {
+                name: "intensity",
+                description: "How much of the sepia toning to blend in.",
+                kind: ParamKind::Float { min: 0.0, max: 1.0, default: 1.0 },
+            },
+        ],
+        run: |media, params| Ok(sepia(media, params.float("intensity") as f32)),
+    },
+    Filter {
+        name: "solarize",
+        description: "Inverts the brighter colors of an image, like overexposed film.",
+        params: &[
+            ParamSpec {
+                name: "threshold",
+                description: "Channel values above this are inverted.",
+                kind: ParamKind::Int { min: 0, max: 255, default: 128 },
+            },
+            ParamSpec {
+                name: "smooth",
+                description: "Ramp into the inverted values instead of jumping at the threshold.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| Ok(solarize(media, params.int("threshold") as u8, params.bool("smooth"))),
+    },
+    Filter {
+        name: "threshold",
+        description: "Turns an image black and white, splitting at a brightness.",
+        params: &[
+            ParamSpec {
+                name: "threshold",
+                description: "Brighter pixels turn white, 0 to pick one with Otsu's method.",
+                kind: ParamKind::Int { min: 0, max: 255, default: 0 },
+            },
+            ParamSpec {
+                name: "invert",
+                description: "Turn the brighter pixels black instead.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| {
+            let level = Some(params.int("threshold") as u8).filter(|&level| level > 0);
+            Ok(threshold(media, level, params.bool("invert")))
+        },
+    },
+    Filter {
+        name: "duotone",
+        description: "Recolors an image with two colors by brightness.",
+        params: &[
+            ParamSpec {
+                name: "shadow",
+                description: "The color for the darkest parts.",
+                kind: ParamKind::Color { default: [25, 20, 90, 255] },
+            },
+            ParamSpec {
+                name: "highlight",
+                description: "The color for the brightest parts.",
+                kind: ParamKind::Color { default: [255, 140, 40, 255] },
+            },
+        ],
+        run: |media, params| Ok(duotone(media, params.color("shadow"), params.color("highlight"))),
+    },
+    Filter {
+        name: "gradientmap",
+        description: "Recolors an image through a gradient by brightness.",
+        params: &[
+            ParamSpec {
+                name: "colors",
+                description: "The colors of the gradient from dark to bright, separated by commas.",
+                kind: ParamKind::Colors {
+                    min: 2,
+                    max: MAX_GRADIENT_STOPS,
+                    default: &[[0, 0, 0, 255], [120, 20, 140, 255], [255, 110, 60, 255], [255, 240, 180, 255]],
+                },
+            },
+        ],
+        run: |media, params| Ok(gradient_map(media, params.colors("colors"))),
+    },
+    Filter {
+        name: "tint",
+        description: "Blends a color into an image.",
+        params: &[
+            ParamSpec {
+                name: "color",
+                description: "The color to tint with.",
+                kind: ParamKind::Color { default: [255, 0, 0, 255] },
+            },
+            ParamSpec {
+                name: "strength",
+                description: "How much of the color to blend in.",
+                kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.5 },
+            },
+            ParamSpec {
+                name: "mode",
+                description: "How to blend the color in, such as over the image (normal), darkening (multiply) or lightening (screen).",
+                kind: ParamKind::Choice { options: BlendMode::NAMES, default: "normal" },
+            },
+        ],
+        run: |media, params| {
+            let mode = BlendMode::from_name(params.choice("mode")).unwrap_or(BlendMode::Normal);
+            Ok(tint(media, params.color("color"), mode, params.float("strength") as f32))
+        },
+    },
+    Filter {
+        name: "replacecolor",
+        description: "Swaps one color for another, along with the colors that look close to it.",
+        params: &[
+            ParamSpec {
+                name: "from",
+                description: "The color to replace.",
+                kind: ParamKind::Color { default: [255, 255, 255, 255] },
+            },
+            ParamSpec {
+                name: "to",
+                description: "The color to replace it with, transparent to remove it.",
+                kind: ParamKind::Color { default: [0, 0, 0, 255] },
+            },
+            ParamSpec {
+                name: "tolerance",
+                description: "How different colors can look and still be replaced, 100 being as far apart as black and white.",
+                kind: ParamKind::Float { min: 0.0, max: 100.0, default: 15.0 },
+            },
+        ],
+        run: |media, params| Ok(replace_color(media, params.color("from"), params.color("to"), params.float("tolerance") as f32)),
+    },
+    Filter {
+        name: "blur",
+        description: "Blurs an image.",
+        params: &[
+            ParamSpec {
+                name: "radius",
+                description: "How far colors spread, in pixels.",
+                kind: ParamKind::Int { min: 1, max: 50, default: 5 },
+            },
+        ],
+        run: |media, params| Ok(blur(media, params.int("radius") as u32)),
+    },
+    Filter {
+        name: "sharpen",
+        description: "Sharpens an image by unsharp masking.",
+        params: &[
+            ParamSpec {
+                name: "amount",
+                description: "How strongly to sharpen.",
+                kind: ParamKind::Float { min: 0.0, max: 5.0, default: 1.0 },
+            },
+            ParamSpec {
+                name: "threshold",
+                description: "How different a pixel must be from its surroundings to be sharpened, leaving flat areas alone.",
+                kind: ParamKind::Int { min: 0, max: 255, default: 0 },
+            },
+        ],
+        run: |media, params| Ok(sharpen(media, params.float("amount") as f32, params.int("threshold") as u8)),
+    },
+    Filter {
+        name: "emboss",
+        description: "Turns an image into a gray relief.",
+        params: &[
+            ParamSpec {
+                name: "direction",
+                description: "Where the light comes from, in degrees clockwise from the right.",
+                kind: ParamKind::Float { min: -360.0, max: 360.0, default: 225.0 },
+            },
+            ParamSpec {
+                name: "strength",
+                description: "How deep the relief is.",
+                kind: ParamKind::Float { min: 0.1, max: 10.0, default: 1.0 },
+            },
+        ],
+        run: |media, params| Ok(emboss(media, params.float("direction") as f32, params.float("strength") as f32)),
+    },
+    Filter {
+        name: "edges",
+        description: "Finds the edges in an image.",
+        params: &[
+            ParamSpec {
+                name: "method",
+                description: "Sobel shows how strong every edge is, Canny finds thin connected edges only.",
+                kind: ParamKind::Choice { options: &["sobel", "canny"], default: "sobel" },
+            },
+            ParamSpec {
+                name: "threshold",
+                description: "How strong an edge has to be to show, 0 to show all of them or pick one for Canny.",
+                kind: ParamKind::Int { min: 0, max: 255, default: 0 },
+            },
+            ParamSpec {
+                name: "overlay",
+                description: "Draw the edges over the image instead of on black.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| {
+            let method = match params.choice("method") {
+                "canny" => EdgeMethod::Canny,
+                _ => EdgeMethod::Sobel,
+            };
+
+            Ok(edges(media, method, params.int("threshold") as f32, params.bool("overlay")))
+        },
+    },
+    Filter {
+        name: "neon",
+        description: "Traces the edges in an image as glowing neon on a dark background.",
+        params: &[
+            ParamSpec {
+                name: "color",
+                description: "The color of the neon.",
+                kind: ParamKind::Color { default: [255, 40, 200, 255] },
+            },
+            ParamSpec {
+                name: "size",
+                description: "About how far the glow reaches in pixels.",
+                kind: ParamKind::Int { min: 2, max: 64, default: 8 },
+            },
+        ],
+        run: |media, params| Ok(neon(media, params.color("color"), params.int("size") as u32)),
+    },
+    Filter {
+        name: "pixelate",
+        description: "Pixelates an image.",
+        params: &[
+            ParamSpec {
+                name: "size",
+                description: "The size of the blocks in pixels, 0 to pick one from the image size.",
+                kind: ParamKind::Int { min: 0, max: 1024, default: 0 },
+            },
+        ],
+        run: |media, params| Ok(pixelate(media, params.int("size") as u32)),
+    },
+    Filter {
+        name: "halftone",
+        description: "Prints an image in dots of ink, like a newspaper.",
+        params: &[
+            ParamSpec {
+                name: "size",
+                description: "The size of the grid cells the dots are in, in pixels, 0 to pick one from the image size.",
+                kind: ParamKind::Int { min: 0, max: 128, default: 0 },
+            },
+            ParamSpec {
+                name: "angle",
+                description: "How far the grid is turned, in degrees.",
+                kind: ParamKind::Float { min: -180.0, max: 180.0, default: 45.0 },
+            },
+            ParamSpec {
+                name: "cmyk",
+                description: "Print in color with cyan, magenta, yellow and black dots rather than just black.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| Ok(halftone(media, params.int("size") as u32, params.float("angle") as f32, params.bool("cmyk"))),
+    },
+    Filter {
+        name: "oil",
+        description: "Makes an image look like an oil painting.",
+        params: &[
+            ParamSpec {
+                name: "radius",
+                description: "The size of the brush in pixels.",
+                kind: ParamKind::Int { min: 1, max: 10, default: 4 },
+            },
+            ParamSpec {
+                name: "levels",
+                description: "How many brightness levels the paint is mixed from, fewer giving flatter strokes.",
+                kind: ParamKind::Int { min: 2, max: 64, default: 20 },
+            },
+        ],
+        run: |media, params| Ok(oil(media, params.int("radius") as u32, params.int("levels") as u32)),
+    },
+    Filter {
+        name: "vignette",
+        description: "Darkens, or tints, an image toward its corners.",
+        params: &[
+            ParamSpec {
+                name: "strength",
+                description: "How much of the color the corners end up with.",
+                kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.7 },
+            },
+            ParamSpec {
+                name: "color",
+                description: "The color to fade toward.",
+                kind: ParamKind::Color { default: [0, 0, 0, 255] },
+            },
+        ],
+        run: |media, params| Ok(vignette(media, params.float("strength") as f32, params.color("color"))),
+    },
+    Filter {
+        name: "shadow",
+        description: "Casts a soft shadow behind an image's opaque parts, growing the canvas to fit it.",
+        params: &[
+            ParamSpec {
+                name: "blur",
+                description: "How soft the shadow is, roughly how far it spreads in pixels.",
+                kind: ParamKind::Int { min: 0, max: 64, default: 8 },
+            },
+            ParamSpec {
+                name: "offset",
+                description: "How far the shadow falls down and to the right in pixels, negative for up and left.",
+                kind: ParamKind::Int { min: -128, max: 128, default: 8 },
+            },
+            ParamSpec {
+                name: "color",
+                description: "The color of the shadow, its transparency setting how dark it is.",
+                kind: ParamKind::Color { default: [0, 0, 0, 180] },
+            },
+        ],
+        run: |media, params| Ok(shadow(media, params.int("blur") as u32, params.int("offset"), params.color("color"))),
+    },
+    Filter {
+        name: "glow",
+        description: "Makes an image's opaque parts glow, growing the canvas to fit it.",
+        params: &[
+            ParamSpec {
+                name: "color",
+                description: "The color of the glow.",
+                kind: ParamKind::Color { default: [255, 255, 255, 255] },
+            },
+            ParamSpec {
+                name: "size",
+                description: "About how far the glow reaches in pixels.",
+                kind: ParamKind::Int { min: 1, max: 128, default: 16 },
+            },
+        ],
+        run: |media, params| Ok(glow(media, params.color("color"), params.int("size") as u32)),
+    },
+    Filter {
+        name: "noise",
+        description: "Adds noise to an image, different on every frame of a GIF.",
+        params: &[
+            ParamSpec {
+                name: "amount",
+                description: "How much noise to add.",
+                kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.25 },
+            },
+            ParamSpec {
+                name: "type",
+                description: "Film grain (gaussian) or random black and white pixels (salt).",
+                kind: ParamKind::Choice { options: &["gaussian", "salt"], default: "gaussian" },
+            },
+        ],
+        run: |media, params| {
+            let kind = match params.choice("type") {
+                "salt" => NoiseKind::SaltAndPepper,
+                _ => NoiseKind::Gaussian,
+            };
+
+            Ok(noise(media, kind, params.float("amount") as f32))
+        },
+    },
+    Filter {
+        name: "glitch",
+        description: "Corrupts an image with datamosh style artifacts.",
+        params: &[
+            ParamSpec {
+                name: "intensity",
+                description: "How broken the image gets.",
+                kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.5 },
+            },
+            ParamSpec {
+                name: "animate",
+                description: "Turn a static image into a short GIF glitching differently on every frame.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| Ok(glitch(media, params.float("intensity") as f32, params.bool("animate"))),
+    },
+    Filter {
+        name: "deepfry",
+        description: "Deep fries an image.",
+        params: &[
+            ParamSpec {
+                name: "level",
+                description: "How cursed the result gets.",
+                kind: ParamKind::Int { min: 1, max: 3, default: 1 },
+            },
+        ],
+        run: |media, params| deepfry(media, params.int("level") as u32),
+    },
+    Filter {
+        name: "jpeg",
+        description: "Compresses an image into JPEG artifacts.",
+        params: &[
+            ParamSpec {
+                name: "quality",
+                description: "The JPEG quality, lower being blockier.",
+                kind: ParamKind::Int { min: 1, max: 20, default: 5 },
+            },
+            ParamSpec {
+                name: "passes",
+                description: "How many times to compress the image over.",
+                kind: ParamKind::Int { min: 1, max: 10, default: 3 },
+            },
+        ],
+        run: |media, params| jpeg(media, params.int("quality") as u8, params.int("passes") as u32),
+    },
+    Filter {
+        name: "aberration",
+        description: "Pulls the red and blue channels of an image apart.",
+        params: &[
+            ParamSpec {
+                name: "offset",
+                description: "How many pixels the channels move.",
+                kind: ParamKind::Int { min: 0, max: 50, default: 5 },
+            },
+            ParamSpec {
+                name: "radial",
+                description: "Pull the channels apart from the center, like a lens.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| Ok(aberration(media, params.int("offset") as u32, params.bool("radial"))),
+    },
+    Filter {
+        name: "crt",
+        description: "Makes an image look like it's on an old CRT screen.",
+        params: &[
+            ParamSpec {
+                name: "curvature",
+                description: "How much the screen bulges out.",
+                kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.2 },
+            },
+            ParamSpec {
+                name: "line-gap",
+                description: "How many rows of pixels are between scanlines, 0 for none.",
+                kind: ParamKind::Int { min: 0, max: 16, default: 2 },
+            },
+        ],
+        run: |media, params| Ok(crt(media, params.float("curvature") as f32, params.int("line-gap") as u32)),
+    },
+    Filter {
+        name: "vhs",
+        description: "Makes an image look like it was played off a worn VHS tape.",
+        params: &[
+            ParamSpec {
+                name: "animate",
+                description: "How many frames of GIF to make out of a static image, 0 to keep it still.",
+                kind: ParamKind::Int { min: 0, max: 30, default: 0 },
+            },
+        ],
+        run: |media, params| Ok(vhs(media, params.int("animate") as usize)),
+    },
+    Filter {
+        name: "resize",
+        description: "Resizes an image. Leaving out either side keeps the aspect ratio.",
+        params: &[
+            ParamSpec {
+                name: "width",
+                description: "The new width in pixels.",
+                kind: ParamKind::Int { min: 0, max: MAX_RESIZE_DIMENSION, default: 0 },
+            },
+            ParamSpec {
+                name: "height",
+                description: "The new height in pixels.",
+                kind: ParamKind::Int { min: 0, max: MAX_RESIZE_DIMENSION, default: 0 },
+            },
+            ParamSpec {
+                name: "scale",
+                description: "Scale by this percentage instead, 0 to leave it to width and height.",
+                kind: ParamKind::Float { min: 0.0, max: 1000.0, default: 0.0 },
+            },
+            ParamSpec {
+                name: "mode",
+                description: "When both sides are given, either distort the image (stretch), fit it within them (fit) or crop it to cover them (fill).",
+                kind: ParamKind::Choice { options: &["stretch", "fit", "fill"], default: "stretch" },
+            },
+            ParamSpec {
+                name: "filter",
+                description: "How to resample: blocky (nearest), smooth (bilinear) or sharp (lanczos).",
+                kind: ParamKind::Choice { options: &["nearest", "bilinear", "lanczos"], default: "lanczos" },
+            },
+        ],
+        run: |media, params| {
+            let filter = match params.choice("filter") {
+                "nearest" => FilterType::Nearest,
+                "bilinear" => FilterType::Triangle,
+                _ => FilterType::Lanczos3,
+            };
+            let mode = match params.choice("mode") {
+                "fit" => ResizeMode::Fit,
+                "fill" => ResizeMode::Fill,
+                _ => ResizeMode::Stretch,
+            };
+
+            let (width, height) = (params.int("width") as u32, params.int("height") as u32);
+            Ok(match params.float("scale") {
+                percent if percent > 0.0 && width == 0 && height == 0 => scale(media, percent, filter),
+                _ => resize(media, width, height, mode, filter),
+            })
+        },
+    },
+    Filter {
+        name: "crop",
+        description: "Crops an image to an area or an aspect ratio.",
+        params: &[
+            ParamSpec {
+                name: "x",
+                description: "How far from the left the area starts.",
+                kind: ParamKind::Int { min: 0, max: MAX_RESIZE_DIMENSION, default: 0 },
+            },
+            ParamSpec {
+                name: "y",
+                description: "How far from the top the area starts.",
+                kind: ParamKind::Int { min: 0, max: MAX_RESIZE_DIMENSION, default: 0 },
+            },
+            ParamSpec {
+                name: "width",
+                description: "The width of the area, 0 to reach the right edge.",
+                kind: ParamKind::Int { min: 0, max: MAX_RESIZE_DIMENSION, default: 0 },
+            },
+            ParamSpec {
+                name: "height",
+                description: "The height of the area, 0 to reach the bottom edge.",
+                kind: ParamKind::Int { min: 0, max: MAX_RESIZE_DIMENSION, default: 0 },
+            },
+            ParamSpec {
+                name: "aspect",
+                description: "Crop to this aspect ratio (width over height) instead, 0 to use the area.",
+                kind: ParamKind::Float { min: 0.0, max: 100.0, default: 0.0 },
+            },
+            ParamSpec {
+                name: "gravity",
+                description: "Which part of the image to keep when cropping to an aspect ratio.",
+                kind: ParamKind::Choice { options: Gravity::NAMES, default: "center" },
+            },
+        ],
+        run: |media, params| {
+            let gravity = Gravity::from_name(params.choice("gravity")).unwrap_or(Gravity::Center);
+
+            Ok(match params.float("aspect") {
+                aspect if aspect > 0.0 => crop_to_aspect(media, aspect, gravity),
+                _ => {
+                    let side = |name| params.int(name) as u32;
+                    crop(media, side("x"), side("y"), side("width"), side("height"))
+                },
+            })
+        },
+    },
+    Filter {
+        name: "rotate",
+        description: "Rotates an image by any angle.",
+        params: &[
+            ParamSpec {
+                name: "degrees",
+                description: "How far to rotate clockwise, negative for counterclockwise.",
+                kind: ParamKind::Float { min: -360.0, max: 360.0, default: 30.0 },
+            },
+        ],
+        run: |media, params| Ok(rotate(media, params.float("degrees") as f32)),
+    },
+    Filter {
+        name: "flip",
+        description: "Flips an image upside down.",
+        params: &[],
+        run: |media, _| Ok(flip(media)),
+    },
+    Filter {
+        name: "flop",
+        description: "Mirrors an image left to right.",
+        params: &[],
+        run: |media, _| Ok(flop(media)),
+    },
+    Filter {
+        name: "mirror",
+        description: "Reflects one half of an image onto the other.",
+        params: &[
+            ParamSpec {
+                name: "side",
+                description: "The half to keep.",
+                kind: ParamKind::Choice { options: &["left", "right", "top", "bottom"], default: "left" },
+            },
+        ],
+        run: |media, params| {
+            let side = match params.choice("side") {
+                "right" => MirrorSide::Right,
+                "top" => MirrorSide::Top,
+                "bottom" => MirrorSide::Bottom,
+                _ => MirrorSide::Left,
+            };
+
+            Ok(mirror(media, side))
+        },
+    },
+    Filter {
+        name: "kaleidoscope",
+        description: "Reflects a wedge of an image around its center like a kaleidoscope.",
+        params: &[
+            ParamSpec {
+                name: "segments",
+                description: "How many times the wedge repeats.",
+                kind: ParamKind::Int { min: 2, max: 32, default: 6 },
+            },
+            ParamSpec {
+                name: "angle",
+                description: "Turns which wedge of the image is used, in degrees.",
+                kind: ParamKind::Float { min: -360.0, max: 360.0, default: 0.0 },
+            },
+        ],
+        run: |media, params| Ok(kaleidoscope(media, params.int("segments") as u32, params.float("angle") as f32)),
+    },
+    Filter {
+        name: "tile",
+        description: "Repeats an image in a grid.",
+        params: &[
+            ParamSpec {
+                name: "columns",
+                description: "How many times to repeat the image across.",
+                kind: ParamKind::Int { min: 1, max: 16, default: 3 },
+            },
+            ParamSpec {
+                name: "rows",
+                description: "How many times to repeat the image down, 0 for as many as the columns.",
+                kind: ParamKind::Int { min: 0, max: 16, default: 0 },
+            },
+            ParamSpec {
+                name: "mirror",
+                description: "Mirror every other tile, for seamless patterns.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| {
+            let columns = params.int("columns") as u32;
+            let rows = match params.int("rows") {
+                0 => columns,
+                rows => rows as u32,
+            };
+
+            Ok(tile(media, columns, rows, params.bool("mirror")))
+        },
+    },
+    Filter {
+        name: "border",
+        description: "Puts a solid or gradient border around an image, growing the canvas.",
+        params: &[
+            ParamSpec {
+                name: "width",
+                description: "How wide the border is in pixels.",
+                kind: ParamKind::Int { min: 1, max: 256, default: 16 },
+            },
+            ParamSpec {
+                name: "color",
+                description: "The color of a solid border.",
+                kind: ParamKind::Color { default: [0, 0, 0, 255] },
+            },
+            ParamSpec {
+                name: "gradient",
+                description: "Two colors separated by a comma for the border to fade between instead, from the top left.",
+                kind: ParamKind::Colors { min: 2, max: 2, default: &[] },
+            },
+        ],
+        run: |media, params| {
+            let width = params.int("width") as u32;
+            let (from, to) = match params.colors("gradient") {
+                [from, to] => (*from, *to),
+                _ => (params.color("color"), params.color("color")),
+            };
+
+            Ok(border(media, width, from, to))
+        },
+    },
+    Filter {
+        name: "stretch",
+        description: "Stretches or squishes an image.",
+        params: &[
+            ParamSpec {
+                name: "x",
+                description: "The new width as a percentage of the current one.",
+                kind: ParamKind::Float { min: 1.0, max: 1000.0, default: 200.0 },
+            },
+            ParamSpec {
+                name: "y",
+                description: "The new height as a percentage of the current one.",
+                kind: ParamKind::Float { min: 1.0, max: 1000.0, default: 50.0 },
+            },
+        ],
+        run: |media, params| Ok(stretch(media, params.float("x"), params.float("y"))),
+    },
+    Filter {
+        name: "bulge",
+        description: "Bulges an image out from a point, or pinches it in.",
+        params: &[
+            ParamSpec {
+                name: "strength",
+                description: "How much to bulge, negative to pinch.",
+                kind: ParamKind::Float { min: -1.0, max: 1.0, default: 0.5 },
+            },
+            ParamSpec {
+                name: "x",
+                description: "Where the center is across, from 0 (left) to 1 (right).",
+                kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.5 },
+            },
+            ParamSpec {
+                name: "y",
+                description: "Where the center is down, from 0 (top) to 1 (bottom).",
+                kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.5 },
+            },
+        ],
+        run: |media, params| {
+            let center = (params.float("x") as f32, params.float("y") as f32);
+            Ok(bulge(media, params.float("strength") as f32, center))
+        },
+    },
+    Filter {
+        name: "implode",
+        description: "Pulls an image in towards its center.",
+        params: &[
+            ParamSpec {
+                name: "amount",
+                description: "How hard pixels are pulled in.",
+                kind: ParamKind::Float { min: 0.0, max: 2.0, default: 0.6 },
+            },
+        ],
+        run: |media, params| Ok(implode(media, params.float("amount") as f32)),
+    },
+    Filter {
+        name: "explode",
+        description: "Pushes an image out from its center.",
+        params: &[
+            ParamSpec {
+                name: "amount",
+                description: "How hard pixels are pushed out.",
+                kind: ParamKind::Float { min: 0.0, max: 2.0, default: 0.8 },
+            },
+        ],
+        run: |media, params| Ok(explode(media, params.float("amount") as f32)),
+    },
+    Filter {
+        name: "swirl",
+        description: "Twists an image around its center.",
+        params: &[
+            ParamSpec {
+                name: "degrees",
+                description: "How far the middle turns, negative for counterclockwise.",
+                kind: ParamKind::Float { min: -1080.0, max: 1080.0, default: 270.0 },
+            },
+            ParamSpec {
+                name: "radius",
+                description: "How far from the center the twist reaches in pixels, 0 for half the shorter side.",
+                kind: ParamKind::Int { min: 0, max: MAX_RESIZE_DIMENSION, default: 0 },
+            },
+            ParamSpec {
+                name: "animate",
+                description: "Make a GIF that twists further on every frame.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| {
+            let (degrees, radius) = (params.float("degrees") as f32, params.int("radius") as f32);
+            Ok(swirl(media, degrees, radius, params.bool("animate")))
+        },
+    },
+    Filter {
+        name: "wave",
+        description: "Ripples an image with a sine wave.",
+        params: &[
+            ParamSpec {
+                name: "amplitude",
+                description: "How far pixels move in pixels.",
+                kind: ParamKind::Float { min: 0.0, max: 200.0, default: 10.0 },
+            },
+            ParamSpec {
+                name: "wavelength",
+                description: "How long one wave is in pixels.",
+                kind: ParamKind::Float { min: 1.0, max: 2000.0, default: 60.0 },
+            },
+            ParamSpec {
+                name: "axis",
+                description: "Shift rows sideways (x) or columns up and down (y).",
+                kind: ParamKind::Choice { options: &["x", "y"], default: "x" },
+            },
+            ParamSpec {
+                name: "animate",
+                description: "Make a GIF where the wave moves.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| {
+            let axis = match params.choice("axis") {
+                "y" => WaveAxis::Y,
+                _ => WaveAxis::X,
+            };
+            let (amplitude, wavelength) = (params.float("amplitude") as f32, params.float("wavelength") as f32);
+
+            Ok(wave(media, amplitude, wavelength, axis, params.bool("animate")))
+        },
+    },
+    Filter {
+        name: "magik",
+        description: "Squashes an image with seam carving and stretches it back out.",
+        params: &[
+            ParamSpec {
+                name: "intensity",
+                description: "How much of the image is carved away before stretching it back.",
+                kind: ParamKind::Float { min: 0.1, max: 0.9, default: 0.5 },
+            },
+        ],
+        run: |media, params| Ok(magik(media, params.float("intensity") as f32)),
+    },
+    Filter {
+        name: "zoom",
+        description: "Makes a GIF zooming into the center of an image.",
+        params: &[
+            ParamSpec {
+                name: "frames",
+                description: "How many frames the zoom takes.",
+                kind: ParamKind::Int { min: 2, max: 60, default: 20 },
+            },
+            ParamSpec {
+                name: "factor",
+                description: "How many times larger the image is at the end.",
+                kind: ParamKind::Float { min: 1.0, max: 10.0, default: 3.0 },
+            },
+            ParamSpec {
+                name: "out",
+                description: "Start zoomed in and zoom back out.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| Ok(zoom(media, params.int("frames") as usize, params.float("factor") as f32, params.bool("out"))),
+    },
+    Filter {
+        name: "spin",
+        description: "Makes a GIF of an image spinning around.",
+        params: &[
+            ParamSpec {
+                name: "speed",
+                description: "How fast it spins, fewer frames making up one turn the faster it is.",
+                kind: ParamKind::Float { min: 0.25, max: 4.0, default: 1.0 },
+            },
+            ParamSpec {
+                name: "square",
+                description: "Spin the whole image instead of cropping it to a circle.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| Ok(spin(media, params.float("speed") as f32, params.bool("square"))),
+    },
+    Filter {
+        name: "shake",
+        description: "Makes a GIF of an image shaking about.",
+        params: &[
+            ParamSpec {
+                name: "intensity",
+                description: "How far the image jumps, in percent of its shorter side.",
+                kind: ParamKind::Float { min: 1.0, max: 50.0, default: 5.0 },
+            },
+        ],
+        run: |media, params| Ok(shake(media, params.float("intensity") as f32)),
+    },
+    Filter {
+        name: "wiggle",
+        description: "Makes a GIF of an image swaying from side to side.",
+        params: &[
+            ParamSpec {
+                name: "intensity",
+                description: "How far the image sways, in percent of its shorter side.",
+                kind: ParamKind::Float { min: 1.0, max: 50.0, default: 8.0 },
+            },
+        ],
+        run: |media, params| Ok(wiggle(media, params.float("intensity") as f32)),
+    },
+    Filter {
+        name: "bounce",
+        description: "Makes a GIF of an image bouncing up and down.",
+        params: &[
+            ParamSpec {
+                name: "height",
+                description: "How high it bounces, in percent of its own height.",
+                kind: ParamKind::Float { min: 5.0, max: 300.0, default: 50.0 },
+            },
+            ParamSpec {
+                name: "speed",
+                description: "How fast it bounces, fewer frames making up one bounce the faster it is.",
+                kind: ParamKind::Float { min: 0.25, max: 4.0, default: 1.0 },
+            },
+        ],
+        run: |media, params| Ok(bounce(media, params.float("height") as f32, params.float("speed") as f32)),
+    },
+    Filter {
+        name: "petpet",
+        description: "Makes a GIF of a hand patting an image.",
+        params: &[
+            ParamSpec {
+                name: "speed",
+                description: "How fast the hand pats, 2 being twice as fast.",
+                kind: ParamKind::Float { min: 0.25, max: 3.0, default: 1.0 },
+            },
+        ],
+        run: |media, params| Ok(petpet(media, &petpet_hand()?, params.float("speed") as f32)),
+    },
+    Filter {
+        name: "triggered",
+        description: "Makes the triggered GIF: the image shaking in a red haze above a \"TRIGGERED\" banner.",
+        params: &[
+            ParamSpec {
+                name: "intensity",
+                description: "How far the image shakes, in percent of its shorter side.",
+                kind: ParamKind::Float { min: 1.0, max: 20.0, default: 4.0 },
+            },
+        ],
+        run: |media, params| Ok(triggered(media, params.float("intensity") as f32, &[&bold_font()])),
+    },
+    Filter {
+        name: "fadein",
+        description: "Fades an image in from black or another color over a GIF.",
+        params: &[
+            ParamSpec {
+                name: "frames",
+                description: "How many frames the fade takes. Animations fade over their own frames.",
+                kind: ParamKind::Int { min: 2, max: 60, default: 15 },
+            },
+            ParamSpec {
+                name: "from",
+                description: "The color to fade in from, `transparent` to fade in the image itself.",
+                kind: ParamKind::Color { default: [0, 0, 0, 255] },
+            },
+        ],
+        run: |media, params| Ok(fade(media, params.int("frames") as usize, params.color("from"), false)),
+    },
+    Filter {
+        name: "fadeout",
+        description: "Fades an image out to black or another color over a GIF.",
+        params: &[
+            ParamSpec {
+                name: "frames",
+                description: "How many frames the fade takes. Animations fade over their own frames.",
+                kind: ParamKind::Int { min: 2, max: 60, default: 15 },
+            },
+            ParamSpec {
+                name: "to",
+                description: "The color to fade out to, `transparent` to fade out the image itself.",
+                kind: ParamKind::Color { default: [0, 0, 0, 255] },
+            },
+        ],
+        run: |media, params| Ok(fade(media, params.int("frames") as usize, params.color("to"), true)),
+    },
+    Filter {
+        name: "reverse",
+        description: "Plays a GIF backwards.",
+        params: &[],
+        run: |media, _| reverse(media),
+    },
+    Filter {
+        name: "boomerang",
+        description: "Plays a GIF forwards and then backwards.",
+        params: &[],
+        run: |media, _| boomerang(media),
+    },
+    Filter {
+        name: "speed",
+        description: "Speeds a GIF up or slows it down.",
+        params: &[
+            ParamSpec {
+                name: "factor",
+                description: "How many times faster to play it, below 1 to slow it down.",
+                kind: ParamKind::Float { min: 0.1, max: 10.0, default: 2.0 },
+            },
+        ],
+        run: |media, params| speed(media, params.float("factor")),
+    },
+    Filter {
+        name: "freeze",
+        description: "Picks a single frame out of a GIF.",
+        params: &[
+            ParamSpec {
+                name: "frame",
+                description: "The frame to keep, counting from 1, or back from the last frame if negative.",
+                kind: ParamKind::Int { min: -100_000, max: 100_000, default: 1 },
+            },
+        ],
+        run: |media, params| freeze(media, params.int("frame")),
+    },
+    Filter {
+        name: "spritesheet",
+        description: "Lays out the frames of a GIF in a grid.",
+        params: &[
+            ParamSpec {
+                name: "columns",
+                description: "How many frames go in a row, 0 to pick about as many as there are rows.",
+                kind: ParamKind::Int { min: 0, max: 64, default: 0 },
+            },
+            ParamSpec {
+                name: "numbered",
+                description: "Draw each frame's number in its corner.",
+                kind: ParamKind::Bool,
+            },
+        ],
+        run: |media, params| spritesheet(media, params.int("columns") as u32, params.bool("numbered")),
+    },
+    Filter {
+        name: "circle",
+        description: "Crops an image to a circle.",
+        params: &[
+            ParamSpec {
+                name: "border",
+                description: "The color of a ring around the circle, none when transparent.",
+                kind: ParamKind::Color { default: [0, 0, 0, 0] },
+            },
+            ParamSpec {
+                name: "border-width",
+                description: "How wide the ring is in pixels, 0 to pick one from the size.",
+                kind: ParamKind::Int { min: 0, max: 512, default: 0 },
+            },
+        ],
+        run: |media, params| {
+            let border = params.color("border");
+            let border = (border[3] > 0).then(|| (border, params.int("border-width") as u32));
+
+            Ok(circle(media, border))
+        },
+    },
+    Filter {
+        name: "round",
+        description: "Rounds the corners of an image.",
+        params: &[
+            ParamSpec {
+                name: "radius",
+                description: "The radius of the corners in pixels.",
+                kind: ParamKind::Int { min: 0, max: MAX_RESIZE_DIMENSION / 2, default: 0 },
+            },
+            ParamSpec {
+                name: "percent",
+                description: "The radius as a percentage of the shorter side instead, used when no radius is given.",
+                kind: ParamKind::Float { min: 0.0, max: 50.0, default: 10.0 },
+            },
+        ],
+        run: |media, params| {
+            let (width, height) = media.dimensions();
+            let radius = match params.int("radius") {
+                0 => width.min(height) as f32 * params.float("percent") as f32 / 100.0,
+                radius => radius as f32,
+            };
+
+            Ok(round(media, radius))
+        },
+    },
+    Filter {
+        name: "stickerify",
+        description: "Cuts out the subject of an image and makes a sticker of it, with a white outline.",
+        params: &[ParamSpec {
+            name: "tolerance",
+            description: "How different from the background colors can be and still be cut away, when it isn't transparent already.",
+            kind: ParamKind::Float { min: 0.0, max: 200.0, default: 40.0 },
+        }],
+        run: |media, params| Ok(stickerify(media, params.float("tolerance") as f32)),
+    },
+    Filter {
+        name: "background",
+        description: "Fills in the transparent parts of an image with a color.",
+        params: &[ParamSpec {
+            name: "color",
+            description: "The color to fill in with.",
+            kind: ParamKind::Color { default: [255, 255, 255, 255] },
+        }],
+        run: |media, params| Ok(fill_background(media, params.color("color"))),
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Filter> {
+    FILTERS.iter().find(|filter| filter.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    #[test]
+    fn finds_filters_by_name() {
+        assert_eq!(find("Invert").map(|f| f.name), Some("invert"));
+        assert!(find("nope").is_none());
+    }
+
+    #[test]
+    fn names_are_unique() {
+        for (index, filter) in FILTERS.iter().enumerate() {
+            assert!(FILTERS[..index].iter().all(|other| other.name != filter.name), "`{}` is registered twice", filter.name);
+        }
+    }
+
+    #[test]
+    fn applies_through_the_registry() {
+        let media = Media::Static(RgbaImage::from_pixel(1, 1, Rgba([10, 20, 30, 255])));
+        let params = Params::parse(find("invert").unwrap().params, std::iter::empty()).unwrap();
+
+        let inverted = find("invert").unwrap().apply(media, &params).unwrap();
+        assert_eq!(inverted.first_frame().get_pixel(0, 0), &Rgba([245, 235, 225, 255]));
+    }
+}
@@ -0,0 +1,122 @@
+//! Shadows and glows cast by the opaque parts of an image, behind it on a larger transparent canvas.
+
+use image::imageops;
+use image::{Rgba, RgbaImage};
+
+use super::gaussian_blur;
+use crate::geometry;
+use crate::Media;
+
+/// How far a blur spreads a silhouette out before it has about faded out.
+fn spread(blur: f32) -> i64 {
+    (blur * 2.0).ceil() as i64
+}
+
+/// Draws a blurred silhouette of the image in `color` behind it, moved by `offset` and with its
+/// transparency scaled by `strength`. The canvas grows to fit both the silhouette and how far the blur
+/// spreads it.
+fn cast(img: &RgbaImage, color: Rgba<u8>, blur: f32, offset: (i64, i64), strength: f32) -> RgbaImage {
+    let spread = spread(blur);
+    let (x, y) = offset;
+    let (left, top) = (spread + (-x).max(0), spread + (-y).max(0));
+    let (right, bottom) = (spread + x.max(0), spread + y.max(0));
+
+    let (width, height) = (img.width() + (left + right) as u32, img.height() + (top + bottom) as u32);
+    let silhouette = RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        let alpha = img.get_pixel(x, y)[3] as f32 * color[3] as f32 / 255.0;
+        Rgba([color[0], color[1], color[2], alpha.round() as u8])
+    });
+
+    let mut canvas = RgbaImage::new(width, height);
+    geometry::overlay(&mut canvas, &silhouette, left + x, top + y);
+    let mut canvas = gaussian_blur(&canvas, blur);
+
+    if strength != 1.0 {
+        for pixel in canvas.pixels_mut() {
+            pixel[3] = (pixel[3] as f32 * strength).round().min(255.0) as u8;
+        }
+    }
+
+    geometry::overlay(&mut canvas, img, left, top);
+    canvas
+}
+
+/// Casts a shadow of the image `offset` pixels down and to the right, or up and to the left when negative.
+pub fn shadow_image(img: &RgbaImage, blur: u32, offset: i64, color: Rgba<u8>) -> RgbaImage {
+    cast(img, color, blur as f32, (offset, offset), 1.0)
+}
+
+/// Casts a shadow behind every frame, see [`shadow_image`].
+pub fn shadow(media: Media, blur: u32, offset: i64, color: Rgba<u8>) -> Media {
+    media.par_map_images(|img| shadow_image(img, blur, offset, color))
+}
+
+/// Surrounds the image with a glow of `color` reaching about `size` pixels out. It's stronger than a
+/// shadow, as the blur would leave it faint around thin shapes.
+pub fn glow_image(img: &RgbaImage, color: Rgba<u8>, size: u32) -> RgbaImage {
+    cast(img, color, size as f32 / 2.0, (0, 0), 2.0)
+}
+
+/// Like [`glow_image`] at `strength` times the opacity, but cut to the size of the image. For images
+/// that fill their canvas, where the glow is all over rather than around them.
+pub(super) fn glow_within(img: &RgbaImage, color: Rgba<u8>, size: u32, strength: f32) -> RgbaImage {
+    let blur = size as f32 / 2.0;
+    let spread = spread(blur) as u32;
+    let glowing = cast(img, color, blur, (0, 0), strength);
+
+    imageops::crop_imm(&glowing, spread, spread, img.width(), img.height()).to_image()
+}
+
+/// Makes every frame glow, see [`glow_image`].
+pub fn glow(media: Media, color: Rgba<u8>, size: u32) -> Media {
+    media.par_map_images(|img| glow_image(img, color, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A white square in the middle of a transparent image.
+    fn square() -> RgbaImage {
+        RgbaImage::from_fn(20, 20, |x, y| match (5..15).contains(&x) && (5..15).contains(&y) {
+            true => Rgba([255, 255, 255, 255]),
+            false => Rgba([0, 0, 0, 0]),
+        })
+    }
+
+    #[test]
+    fn casts_shadows_on_a_larger_canvas() {
+        let shadowed = shadow_image(&square(), 2, 6, Rgba([0, 0, 0, 255]));
+
+        // Grown by the spread of the blur on every side and the offset down and right.
+        assert_eq!(shadowed.dimensions(), (20 + 8 + 6, 20 + 8 + 6));
+        assert_eq!(*shadowed.get_pixel(4 + 10, 4 + 10), Rgba([255, 255, 255, 255]));
+        assert!(shadowed.get_pixel(4 + 17, 4 + 17)[3] > 240);
+        assert_eq!(shadowed.get_pixel(4 + 17, 4 + 17)[0], 0);
+        assert_eq!(shadowed.get_pixel(4 + 2, 4 + 2)[3], 0);
+
+        let up = shadow_image(&square(), 0, -3, Rgba([0, 0, 0, 255]));
+        assert_eq!(up.dimensions(), (23, 23));
+        assert_eq!(*up.get_pixel(6, 6), Rgba([0, 0, 0, 255]));
+        assert_eq!(*up.get_pixel(3 + 10, 3 + 10), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn glows_all_around() {
+        let glowing = glow_image(&square(), Rgba([255, 0, 0, 255]), 4);
+        let (width, height) = glowing.dimensions();
+
+        assert_eq!((width, height), (28, 28));
+        assert_eq!(*glowing.get_pixel(14, 14), Rgba([255, 255, 255, 255]));
+        // Just outside the square on either side.
+        for (x, y) in [(8, 14), (19, 14), (14, 8), (14, 19)] {
+            let pixel = glowing.get_pixel(x, y);
+            assert!(pixel[0] == 255 && pixel[1] == 0 && pixel[3] > 100, "{:?} at {}, {}", pixel, x, y);
+        }
+        assert_eq!(glowing.get_pixel(0, 0)[3], 0);
+
+        let within = glow_within(&square(), Rgba([255, 0, 0, 255]), 4, 2.0);
+        assert_eq!(within.dimensions(), (20, 20));
+        assert_eq!(within.get_pixel(4, 10), glowing.get_pixel(8, 14));
+    }
+}
@@ -0,0 +1,398 @@
+//! Filters that redraw an image in a different style.
+
+use image::imageops::FilterType;
+use image::{Rgba, RgbaImage};
+
+use rayon::prelude::*;
+
+use super::{gaussian_blur, luma};
+use crate::random::Rng;
+use crate::Media;
+
+/// The block size `pixelate` uses when none is given, as a fraction of the shorter side.
+const DEFAULT_BLOCKS: u32 = 32;
+
+/// Larger images are scaled down to this size before `oil` paints them, it gets slow quickly.
+pub const MAX_OIL_DIMENSION: u32 = 768;
+
+/// The dot size `halftone` uses when none is given, as a fraction of the shorter side.
+const DEFAULT_HALFTONE_DOTS: u32 = 96;
+
+/// The angles of the cyan, magenta, yellow and black screens relative to the angle given, which is the
+/// black one's. These are the usual ones in print, keeping the dots of different inks from forming moiré.
+const CMYK_SCREENS: [f32; 4] = [-30.0, 30.0, -45.0, 0.0];
+/// How much of the red, green and blue light each ink lets through.
+const CMYK_INKS: [[f32; 3]; 4] = [[0.0, 1.0, 1.0], [1.0, 0.0, 1.0], [1.0, 1.0, 0.0], [0.0, 0.0, 0.0]];
+
+/// Fills every `size` by `size` block with its average color, as if scaled down and back up with nearest
+/// neighbour sampling but keeping the blocks aligned to the top left corner.
+pub fn pixelate_image(img: &RgbaImage, size: u32) -> RgbaImage {
+    let mut out = RgbaImage::new(img.width(), img.height());
+
+    for top in (0..img.height()).step_by(size as usize) {
+        for left in (0..img.width()).step_by(size as usize) {
+            let (right, bottom) = ((left + size).min(img.width()), (top + size).min(img.height()));
+            let mut sum = [0_u64; 4];
+
+            for y in top..bottom {
+                for x in left..right {
+                    let pixel = img.get_pixel(x, y);
+                    let alpha = pixel[3] as u64;
+                    (0..3).for_each(|c| sum[c] += pixel[c] as u64 * alpha);
+                    sum[3] += alpha;
+                }
+            }
+
+            let count = ((right - left) * (bottom - top)) as u64;
+            let color = match sum[3] {
+                0 => Rgba([0, 0, 0, 0]),
+                alpha => Rgba([
+                    (sum[0] / alpha) as u8,
+                    (sum[1] / alpha) as u8,
+                    (sum[2] / alpha) as u8,
+                    (alpha / count) as u8,
+                ]),
+            };
+
+            for y in top..bottom {
+                for x in left..right {
+                    out.put_pixel(x, y, color);
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Pixelates every frame with blocks of `size` pixels, where 0 picks one based on the image size.
+/// The size is capped at the shorter side.
+pub fn pixelate(media: Media, size: u32) -> Media {
+    let (width, height) = media.dimensions();
+    let shorter = width.min(height).max(1);
+
+    let size = match size {
+        0 => shorter / DEFAULT_BLOCKS,
+        size => size,
+    };
+
+    media.map_images(|img| pixelate_image(img, size.clamp(1, shorter)))
+}
+
+/// The oil painting effect: every pixel takes the average color of the most common brightness among
+/// the pixels within `radius`, with brightness split into `levels` buckets.
+pub fn oil_paint(img: &RgbaImage, radius: u32, levels: u32) -> RgbaImage {
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    let radius = radius as i64;
+    let buckets = img
+        .pixels()
+        .map(|pixel| ((luma(*pixel) / 256.0 * levels as f32) as usize).min(levels as usize - 1))
+        .collect::<Vec<_>>();
+
+    let mut out = RgbaImage::new(img.width(), img.height());
+    out.par_chunks_mut(img.width() as usize * 4).enumerate().for_each(|(y, row)| {
+        let y = y as i64;
+        let mut counts = vec![0_u32; levels as usize];
+        let mut sums = vec![[0_u32; 4]; levels as usize];
+
+        for (x, out) in row.chunks_exact_mut(4).enumerate() {
+            counts.iter_mut().for_each(|count| *count = 0);
+            sums.iter_mut().for_each(|sum| *sum = [0; 4]);
+
+            for sy in (y - radius).max(0)..=(y + radius).min(height - 1) {
+                for sx in (x as i64 - radius).max(0)..=(x as i64 + radius).min(width - 1) {
+                    let index = (sy * width + sx) as usize;
+                    let pixel = img.get_pixel(sx as u32, sy as u32);
+
+                    counts[buckets[index]] += 1;
+                    (0..4).for_each(|c| sums[buckets[index]][c] += pixel[c] as u32);
+                }
+            }
+
+            let (bucket, count) = counts.iter().enumerate().max_by_key(|(_, count)| **count).unwrap();
+            for c in 0..4 {
+                out[c] = (sums[bucket][c] / count) as u8;
+            }
+        }
+    });
+    out
+}
+
+/// Paints every frame like an oil painting, see [`oil_paint`]. Frames larger than [`MAX_OIL_DIMENSION`]
+/// are scaled down first.
+pub fn oil(media: Media, radius: u32, levels: u32) -> Media {
+    let (width, height) = media.dimensions();
+    let media = match width.max(height) > MAX_OIL_DIMENSION {
+        true => media.resize_to_fit(MAX_OIL_DIMENSION, MAX_OIL_DIMENSION, FilterType::Triangle),
+        false => media,
+    };
+
+    media.par_map_images(|img| oil_paint(img, radius, levels))
+}
+
+/// Splits a color into the amounts of cyan, magenta, yellow and black ink printing it takes, from 0 to
+/// 1 and scaled by its opacity.
+fn cmyk(pixel: Rgba<u8>) -> [f32; 4] {
+    let [r, g, b] = [0, 1, 2].map(|c| pixel[c] as f32 / 255.0);
+    let black = 1.0 - r.max(g).max(b);
+    let opacity = pixel[3] as f32 / 255.0;
+
+    match black < 1.0 {
+        true => [(1.0 - r - black) / (1.0 - black), (1.0 - g - black) / (1.0 - black), (1.0 - b - black) / (1.0 - black), black],
+        false => [0.0, 0.0, 0.0, 1.0],
+    }.map(|ink| ink * opacity)
+}
+
+/// Prints the image as dots on a grid of `size` pixel cells turned by `angle` degrees, every dot as
+/// large as the ink where it's centered calls for. In black on white, or with the four `cmyk` inks in
+/// screens at different angles like color print. Transparency is kept.
+pub fn halftone_image(img: &RgbaImage, size: u32, angle: f32, cmyk_inks: bool) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let size = size.max(2) as f32;
+
+    // Blurred so each dot gets about the average of its cell rather than whatever pixel it's centered on.
+    let blurred = gaussian_blur(img, size / 3.0);
+    let amounts = blurred
+        .pixels()
+        .map(|&pixel| match cmyk_inks {
+            true => cmyk(pixel),
+            false => [0.0, 0.0, 0.0, (1.0 - luma(pixel) / 255.0) * pixel[3] as f32 / 255.0],
+        })
+        .collect::<Vec<_>>();
+    let screens = match cmyk_inks {
+        true => 0..4,
+        false => 3..4,
+    };
+    let screens = screens.map(|ink| (ink, (angle + CMYK_SCREENS[ink]).to_radians().sin_cos())).collect::<Vec<_>>();
+
+    // How much of the pixel at (x, y) the dots of an ink cover, checking the cells around it as large
+    // dots spill over into them.
+    let coverage = |ink: usize, (sin, cos): (f32, f32), x: f32, y: f32| {
+        let (u, v) = (x * cos + y * sin, y * cos - x * sin);
+        let (column, row) = ((u / size).floor(), (v / size).floor());
+        let mut covered = 0.0_f32;
+
+        for (dx, dy) in (-1..=1).flat_map(|dx| (-1..=1).map(move |dy| (dx as f32, dy as f32))) {
+            let (cu, cv) = ((column + dx + 0.5) * size, (row + dy + 0.5) * size);
+            let (cx, cy) = (cu * cos - cv * sin, cu * sin + cv * cos);
+            let (sx, sy) = ((cx.max(0.0) as u32).min(width - 1), (cy.max(0.0) as u32).min(height - 1));
+
+            // A full dot reaches just past the corners of its cell, so solid colors come out solid.
+            let radius = (size / std::f32::consts::SQRT_2 + 0.5) * amounts[(sy * width + sx) as usize][ink].sqrt();
+            let distance = ((u - cu).powi(2) + (v - cv).powi(2)).sqrt();
+            covered = covered.max((radius - distance + 0.5).clamp(0.0, (radius * 2.0).min(1.0)));
+        }
+        covered
+    };
+
+    let mut out = RgbaImage::new(width, height);
+    out.par_chunks_mut(width as usize * 4).enumerate().for_each(|(y, row)| {
+        for (x, out) in row.chunks_exact_mut(4).enumerate() {
+            let mut light = [1.0_f32; 3];
+            for &(ink, rotation) in &screens {
+                let covered = coverage(ink, rotation, x as f32 + 0.5, y as f32 + 0.5);
+                for c in 0..3 {
+                    light[c] *= 1.0 - covered * (1.0 - CMYK_INKS[ink][c]);
+                }
+            }
+
+            for c in 0..3 {
+                out[c] = (light[c] * 255.0).round() as u8;
+            }
+            out[3] = img.get_pixel(x as u32, y as u32)[3];
+        }
+    });
+    out
+}
+
+/// Prints every frame in halftone dots, see [`halftone_image`]. The grid stays put between frames so
+/// only the dots change in size, and 0 picks a dot size based on the image size.
+pub fn halftone(media: Media, size: u32, angle: f32, cmyk: bool) -> Media {
+    let (width, height) = media.dimensions();
+    let size = match size {
+        0 => (width.min(height) / DEFAULT_HALFTONE_DOTS).max(4),
+        size => size,
+    };
+
+    media.par_map_images(|img| halftone_image(img, size, angle, cmyk))
+}
+
+/// How far from the center, relative to the corners, the vignette starts to show.
+const VIGNETTE_START: f32 = 0.35;
+
+/// Fades the image toward `color` from the center outward, `strength` from 0 to 1 being how much of the
+/// color the corners end up with.
+pub fn vignette_image(img: &RgbaImage, strength: f32, color: Rgba<u8>) -> RgbaImage {
+    let (cx, cy) = (img.width() as f32 / 2.0, img.height() as f32 / 2.0);
+    let amount = strength * color[3] as f32 / 255.0;
+    let mut out = img.clone();
+
+    for (x, y, pixel) in out.enumerate_pixels_mut() {
+        // Scaled per axis so the falloff follows the image's shape, reaching 1 in the corners.
+        let (dx, dy) = ((x as f32 + 0.5 - cx) / cx, (y as f32 + 0.5 - cy) / cy);
+        let distance = ((dx * dx + dy * dy) / 2.0).sqrt();
+
+        let t = ((distance - VIGNETTE_START) / (1.0 - VIGNETTE_START)).clamp(0.0, 1.0);
+        let weight = t * t * (3.0 - 2.0 * t) * amount;
+
+        for c in 0..3 {
+            pixel[c] = (pixel[c] as f32 + (color[c] as f32 - pixel[c] as f32) * weight).round() as u8;
+        }
+    }
+    out
+}
+
+pub fn vignette(media: Media, strength: f32, color: Rgba<u8>) -> Media {
+    media.map_images(|img| vignette_image(img, strength, color))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NoiseKind {
+    /// Film grain: every pixel gets brighter or darker by a normally distributed amount.
+    Gaussian,
+    /// Random pixels turn black or white.
+    SaltAndPepper,
+}
+
+/// Adds noise to the image, `amount` from 0 to 1. The same seed gives the same noise.
+pub fn noise_image(img: &RgbaImage, kind: NoiseKind, amount: f32, seed: u64) -> RgbaImage {
+    let mut rng = Rng::new(seed);
+    let mut out = img.clone();
+
+    for pixel in out.pixels_mut() {
+        match kind {
+            NoiseKind::Gaussian => {
+                let offset = rng.gaussian() * amount * 128.0;
+                for c in 0..3 {
+                    pixel[c] = (pixel[c] as f32 + offset).round().clamp(0.0, 255.0) as u8;
+                }
+            },
+            NoiseKind::SaltAndPepper => {
+                if rng.chance(amount / 2.0) {
+                    let value = if rng.chance(0.5) { 255 } else { 0 };
+                    *pixel = Rgba([value, value, value, pixel[3]]);
+                }
+            },
+        }
+    }
+    out
+}
+
+/// Adds noise to every frame, different on each so animations get a shifting grain.
+pub fn noise(media: Media, kind: NoiseKind, amount: f32) -> Media {
+    media.map_images_indexed(|index, img| noise_image(img, kind, amount, index as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_blocks() {
+        let img = RgbaImage::from_fn(4, 3, |x, _| if x % 2 == 0 { Rgba([0, 0, 0, 255]) } else { Rgba([200, 100, 50, 255]) });
+        let pixelated = pixelate_image(&img, 2);
+
+        assert_eq!(*pixelated.get_pixel(0, 0), Rgba([100, 50, 25, 255]));
+        assert_eq!(pixelated.get_pixel(0, 0), pixelated.get_pixel(1, 1));
+        // The last row is a partial block.
+        assert_eq!(*pixelated.get_pixel(3, 2), Rgba([100, 50, 25, 255]));
+    }
+
+    #[test]
+    fn ignores_transparent_colors() {
+        let img = RgbaImage::from_fn(2, 1, |x, _| if x == 0 { Rgba([255, 0, 0, 255]) } else { Rgba([0, 255, 0, 0]) });
+        assert_eq!(*pixelate_image(&img, 2).get_pixel(1, 0), Rgba([255, 0, 0, 127]));
+    }
+
+    #[test]
+    fn halftones_with_dots_by_brightness() {
+        let white = RgbaImage::from_pixel(32, 32, Rgba([255, 255, 255, 255]));
+        assert_eq!(halftone_image(&white, 8, 45.0, false), white);
+
+        let black = RgbaImage::from_pixel(32, 32, Rgba([0, 0, 0, 255]));
+        assert!(halftone_image(&black, 8, 45.0, false).pixels().all(|pixel| pixel[0] == 0));
+
+        // Half of the ink leaves cells with a dot in the middle and paper around it.
+        let gray = RgbaImage::from_pixel(32, 32, Rgba([128, 128, 128, 255]));
+        let dotted = halftone_image(&gray, 8, 0.0, false);
+        assert_eq!(*dotted.get_pixel(12, 12), Rgba([0, 0, 0, 255]));
+        assert_eq!(*dotted.get_pixel(16, 16), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn halftones_in_cmyk() {
+        assert_eq!(cmyk(Rgba([255, 0, 0, 255])), [0.0, 1.0, 1.0, 0.0]);
+        assert_eq!(cmyk(Rgba([0, 0, 0, 128])), [0.0, 0.0, 0.0, 128.0 / 255.0]);
+
+        let red = RgbaImage::from_pixel(32, 32, Rgba([255, 0, 0, 255]));
+        let printed = halftone_image(&red, 6, 45.0, true);
+        let (mut reds, mut others) = (0, 0);
+        for pixel in printed.pixels() {
+            match pixel[0] > 200 && pixel[1] < 60 && pixel[2] < 60 {
+                true => reds += 1,
+                false => others += 1,
+            }
+        }
+        // Magenta and yellow dots overlap into red, with their gaps between them.
+        assert!(reds > others, "{} red, {} other", reds, others);
+        assert!(printed.pixels().all(|pixel| pixel[0] > 200));
+    }
+
+    #[test]
+    fn oil_paint_takes_the_most_common_color() {
+        let mut img = RgbaImage::from_pixel(5, 5, Rgba([200, 40, 40, 255]));
+        img.put_pixel(2, 2, Rgba([0, 0, 0, 255]));
+        img.put_pixel(0, 0, Rgba([250, 250, 250, 255]));
+
+        let painted = oil_paint(&img, 1, 8);
+        assert_eq!(*painted.get_pixel(2, 2), Rgba([200, 40, 40, 255]));
+        assert_eq!(*painted.get_pixel(0, 0), Rgba([200, 40, 40, 255]));
+    }
+
+    #[test]
+    fn oil_caps_the_size() {
+        let media = Media::Static(RgbaImage::new(MAX_OIL_DIMENSION * 2, 10));
+        assert_eq!(oil(media, 1, 4).dimensions(), (MAX_OIL_DIMENSION, 5));
+    }
+
+    #[test]
+    fn vignette_darkens_the_corners() {
+        let img = RgbaImage::from_pixel(20, 10, Rgba([200, 200, 200, 255]));
+        let vignetted = vignette_image(&img, 1.0, Rgba([0, 0, 0, 255]));
+
+        assert_eq!(*vignetted.get_pixel(10, 5), Rgba([200, 200, 200, 255]));
+        assert!(vignetted.get_pixel(0, 0)[0] < 10);
+        assert!(vignetted.get_pixel(0, 5)[0] > vignetted.get_pixel(0, 0)[0]);
+
+        let tinted = vignette_image(&img, 0.5, Rgba([255, 0, 0, 255]));
+        assert!(tinted.get_pixel(0, 0)[0] > 200 && tinted.get_pixel(0, 0)[1] < 120);
+    }
+
+    #[test]
+    fn noise_is_seeded() {
+        let img = RgbaImage::from_pixel(16, 16, Rgba([128, 128, 128, 255]));
+
+        let noisy = noise_image(&img, NoiseKind::Gaussian, 0.5, 1);
+        assert_eq!(noisy, noise_image(&img, NoiseKind::Gaussian, 0.5, 1));
+        assert_ne!(noisy, noise_image(&img, NoiseKind::Gaussian, 0.5, 2));
+        assert_ne!(noisy, img);
+        assert_eq!(noise_image(&img, NoiseKind::Gaussian, 0.0, 1), img);
+    }
+
+    #[test]
+    fn salt_and_pepper_only_uses_black_and_white() {
+        let img = RgbaImage::from_pixel(32, 32, Rgba([128, 128, 128, 255]));
+        let noisy = noise_image(&img, NoiseKind::SaltAndPepper, 1.0, 0);
+
+        assert!(noisy.pixels().all(|pixel| [0, 128, 255].contains(&pixel[0])));
+        assert!(noisy.pixels().any(|pixel| pixel[0] == 0) && noisy.pixels().any(|pixel| pixel[0] == 255));
+    }
+
+    #[test]
+    fn clamps_the_block_size() {
+        let media = Media::Static(RgbaImage::from_fn(8, 4, |x, y| Rgba([x as u8 * 30, y as u8 * 60, 0, 255])));
+        let pixelated = pixelate(media, 100);
+
+        assert_eq!(pixelated.first_frame().get_pixel(0, 0), pixelated.first_frame().get_pixel(3, 3));
+        assert_ne!(pixelated.first_frame().get_pixel(0, 0), pixelated.first_frame().get_pixel(4, 0));
+    }
+}
@@ -0,0 +1,228 @@
+//! Filters that rearrange or retime the frames of an animation, leaving their pixels alone.
+
+use image::imageops::{self, FilterType};
+use image::{Delay, Frame, RgbaImage};
+
+use super::glitch::draw_osd;
+use super::MAX_RESIZE_DIMENSION;
+use crate::geometry::contain;
+use crate::{delay_ms, Error, Media, Result};
+
+/// The shortest delay frames are given. GIFs store delays in hundredths of a second, and most viewers
+/// play anything shorter than two of them at a tenth of a second instead, which would slow GIFs down.
+pub const MIN_FRAME_DELAY_MS: f64 = 20.0;
+
+/// Animations longer than this are cut down before making a boomerang, which doubles the frames.
+pub const MAX_BOOMERANG_FRAMES: usize = 150;
+
+/// The frames of an animation, failing for still images since there is nothing to rearrange.
+fn frames(media: Media) -> Result<Vec<Frame>> {
+    match media {
+        Media::Animated(frames) => Ok(frames),
+        Media::Static(_) => Err(Error::NotAnimated),
+    }
+}
+
+/// Plays the animation backwards. Every frame keeps its own delay, so pauses stay on the frame they
+/// were on.
+pub fn reverse(media: Media) -> Result<Media> {
+    let mut frames = frames(media)?;
+    frames.reverse();
+
+    Ok(Media::Animated(frames))
+}
+
+/// Plays the animation `factor` times as fast, dividing every delay by it. Delays that would drop below
+/// [`MIN_FRAME_DELAY_MS`] have their frames merged into the one before instead, so it still speeds up.
+pub fn speed(media: Media, factor: f64) -> Result<Media> {
+    let mut kept = Vec::<(Frame, f64)>::new();
+
+    for frame in frames(media)? {
+        let delay = delay_ms(frame.delay()) / factor;
+        match kept.last_mut() {
+            Some((_, total)) if *total < MIN_FRAME_DELAY_MS => *total += delay,
+            _ => kept.push((frame, delay)),
+        }
+    }
+
+    Ok(Media::Animated(
+        kept.into_iter()
+            .map(|(frame, total)| {
+                let delay = Delay::from_numer_denom_ms(total.max(MIN_FRAME_DELAY_MS).round() as u32, 1);
+                let (left, top) = (frame.left(), frame.top());
+                Frame::from_parts(frame.into_buffer(), left, top, delay)
+            })
+            .collect()
+    ))
+}
+
+/// Plays the animation forwards and then backwards, so it loops back and forth. The first and last
+/// frames aren't repeated on the way back, which would make them linger.
+pub fn boomerang(media: Media) -> Result<Media> {
+    let mut frames = frames(media.limit_frames(MAX_BOOMERANG_FRAMES))?;
+    let back = frames.iter().rev().skip(1).take(frames.len().saturating_sub(2)).cloned().collect::<Vec<_>>();
+    frames.extend(back);
+
+    Ok(Media::Animated(frames))
+}
+
+/// Picks a single frame out of the animation as a still image. `frame` counts from 1, or back from the
+/// last frame if negative.
+pub fn freeze(media: Media, frame: i64) -> Result<Media> {
+    let mut frames = frames(media)?;
+    let count = frames.len() as i64;
+
+    let index = match frame {
+        frame if (1..=count).contains(&frame) => frame - 1,
+        frame if (-count..=-1).contains(&frame) => count + frame,
+        frame => return Err(Error::OutOfRange { name: "frame", min: 1.0, max: count as f64, value: frame as f64 }),
+    };
+
+    Ok(Media::Static(frames.swap_remove(index as usize).into_buffer()))
+}
+
+/// Lays every frame out in a grid, left to right and then down, as a single still image. `columns` of
+/// 0 picks about as many columns as rows. Frames are scaled down to keep the sheet within
+/// [`MAX_RESIZE_DIMENSION`], and with `numbered` each gets its number (from 1) in the top left corner.
+pub fn spritesheet(media: Media, columns: u32, numbered: bool) -> Result<Media> {
+    let frames = frames(media)?;
+    let count = frames.len() as u32;
+    let columns = match columns {
+        0 => (count as f64).sqrt().ceil() as u32,
+        columns => columns.min(count),
+    };
+    let rows = count.div_ceil(columns);
+
+    let (width, height) = frames[0].buffer().dimensions();
+    let max = MAX_RESIZE_DIMENSION as f64;
+    let scale = (max / (width * columns) as f64).min(max / (height * rows) as f64).min(1.0);
+    let (width, height) = (((width as f64 * scale) as u32).max(1), ((height as f64 * scale) as u32).max(1));
+
+    let mut sheet = RgbaImage::new(width * columns, height * rows);
+    for (index, frame) in frames.iter().enumerate() {
+        let (x, y) = (index as u32 % columns * width, index as u32 / columns * height);
+        let mut cell = match scale < 1.0 {
+            true => imageops::resize(frame.buffer(), width, height, FilterType::Triangle),
+            false => frame.buffer().clone(),
+        };
+
+        if numbered {
+            let size = (width.min(height) / 48).max(1);
+            draw_osd(&mut cell, &(index + 1).to_string(), size * 2, size * 2, size);
+        }
+        imageops::replace(&mut sheet, &cell, x, y);
+    }
+
+    Ok(Media::Static(sheet))
+}
+
+/// Turns still images into the frames of an animation, shown `delay` milliseconds apart in the order
+/// given. The first image decides the size and the others are scaled to fit in it, centered.
+pub fn makegif(images: &[RgbaImage], delay: u32) -> Media {
+    let (width, height) = images[0].dimensions();
+    let delay = Delay::from_numer_denom_ms(delay.max(MIN_FRAME_DELAY_MS as u32), 1);
+
+    Media::Animated(
+        images
+            .iter()
+            .map(|img| match img.dimensions() == (width, height) {
+                true => img.clone(),
+                false => contain(img, width, height, FilterType::Lanczos3),
+            })
+            .map(|img| Frame::from_parts(img, 0, 0, delay))
+            .collect()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delay_ms;
+    use image::{Delay, Rgba, RgbaImage};
+
+    fn frame(value: u8, delay: u32) -> Frame {
+        Frame::from_parts(RgbaImage::from_pixel(1, 1, Rgba([value, 0, 0, 255])), 0, 0, Delay::from_numer_denom_ms(delay, 1))
+    }
+
+    fn summary(media: &Media) -> Vec<(u8, f64)> {
+        match media {
+            Media::Animated(frames) => frames.iter().map(|f| (f.buffer().get_pixel(0, 0)[0], delay_ms(f.delay()))).collect(),
+            Media::Static(_) => panic!("expected an animation"),
+        }
+    }
+
+    #[test]
+    fn reverses_frames_with_their_delays() {
+        let media = Media::Animated(vec![frame(1, 20), frame(2, 500), frame(3, 40)]);
+        assert_eq!(summary(&reverse(media).unwrap()), [(3, 40.0), (2, 500.0), (1, 20.0)]);
+
+        assert!(matches!(reverse(Media::Static(RgbaImage::new(1, 1))), Err(Error::NotAnimated)));
+    }
+
+    #[test]
+    fn plays_back_and_forth() {
+        let media = Media::Animated((1..=4).map(|value| frame(value, 40)).collect());
+        let values = summary(&boomerang(media).unwrap()).into_iter().map(|(value, _)| value).collect::<Vec<_>>();
+        assert_eq!(values, [1, 2, 3, 4, 3, 2]);
+
+        let short = Media::Animated(vec![frame(1, 40), frame(2, 40)]);
+        assert_eq!(summary(&boomerang(short).unwrap()).len(), 2);
+    }
+
+    #[test]
+    fn freezes_a_frame() {
+        let media = Media::Animated((1..=4).map(|value| frame(value, 40)).collect());
+
+        assert_eq!(freeze(media.clone(), 2).unwrap().first_frame().get_pixel(0, 0)[0], 2);
+        assert_eq!(freeze(media.clone(), -1).unwrap().first_frame().get_pixel(0, 0)[0], 4);
+        assert!(!freeze(media.clone(), -4).unwrap().is_animated());
+        assert!(matches!(freeze(media.clone(), 0), Err(Error::OutOfRange { .. })));
+        assert!(matches!(freeze(media, 5), Err(Error::OutOfRange { .. })));
+    }
+
+    #[test]
+    fn lays_frames_out_in_a_grid() {
+        let media = Media::Animated((1..=5).map(|value| frame(value, 40)).collect());
+
+        let sheet = spritesheet(media.clone(), 0, false).unwrap();
+        assert_eq!(sheet.dimensions(), (3, 2));
+        let sheet = sheet.first_frame();
+        assert_eq!(sheet.get_pixel(2, 0)[0], 3);
+        assert_eq!(sheet.get_pixel(1, 1)[0], 5);
+        assert_eq!(sheet.get_pixel(2, 1)[3], 0);
+
+        assert_eq!(spritesheet(media, 5, false).unwrap().dimensions(), (5, 1));
+    }
+
+    #[test]
+    fn makes_a_gif_on_the_first_image_canvas() {
+        let images = [RgbaImage::new(4, 2), RgbaImage::from_pixel(2, 2, Rgba([9, 0, 0, 255]))];
+        let media = makegif(&images, 5);
+
+        assert_eq!(media.dimensions(), (4, 2));
+        assert_eq!(summary(&media).iter().map(|(_, delay)| *delay).collect::<Vec<_>>(), [20.0, 20.0]);
+        match media {
+            Media::Animated(frames) => {
+                assert_eq!(frames[1].buffer().dimensions(), (4, 2));
+                assert_eq!(frames[1].buffer().get_pixel(0, 0)[3], 0);
+                assert_eq!(frames[1].buffer().get_pixel(2, 1)[0], 9);
+            },
+            Media::Static(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn changes_speed() {
+        let media = Media::Animated(vec![frame(1, 100), frame(2, 200)]);
+        assert_eq!(summary(&speed(media.clone(), 2.0).unwrap()), [(1, 50.0), (2, 100.0)]);
+        assert_eq!(summary(&speed(media, 0.5).unwrap()), [(1, 200.0), (2, 400.0)]);
+    }
+
+    #[test]
+    fn drops_frames_that_would_be_too_quick() {
+        let media = Media::Animated((1..=6).map(|value| frame(value, 40)).collect());
+
+        // 10ms each, so every other frame is merged into the one before it.
+        assert_eq!(summary(&speed(media, 4.0).unwrap()), [(1, 20.0), (3, 20.0), (5, 20.0)]);
+    }
+}
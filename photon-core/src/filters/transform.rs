@@ -0,0 +1,451 @@
+//! Filters that change an image's size or shape.
+
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+use crate::geometry::{self, cover};
+use crate::{fit_dimensions, Media};
+
+/// The largest width or height `resize` will produce.
+pub const MAX_RESIZE_DIMENSION: i64 = 4096;
+
+/// The most pixels, over all frames, that growing an animation may produce. Frames are dropped to stay
+/// within it, 4096x4096 still leaving room for two of them.
+pub const MAX_TOTAL_PIXELS: u64 = 1 << 25;
+
+/// What to do when a new width and height don't match the image's aspect ratio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResizeMode {
+    /// Distort the image to exactly that size.
+    Stretch,
+    /// Shrink or grow the image to the largest size within the bounds, keeping its aspect ratio.
+    Fit,
+    /// Cover the whole size, cropping off what sticks out.
+    Fill,
+}
+
+/// Drops frames (see [`Media::limit_frames`]) so that growing every frame to `width` by `height` stays
+/// within [`MAX_TOTAL_PIXELS`]. Shrinking is always fine, the input itself fit in memory.
+fn limit_growth(media: Media, width: u32, height: u32) -> Media {
+    let (current_width, current_height) = media.dimensions();
+    let (before, after) = (current_width as u64 * current_height as u64, width as u64 * height as u64);
+
+    match after > before {
+        true => media.limit_frames((MAX_TOTAL_PIXELS / after.max(1)).max(1) as usize),
+        false => media,
+    }
+}
+
+/// Resizes to `width` by `height`, where a side of 0 follows from the other and the aspect ratio.
+pub fn resize(media: Media, width: u32, height: u32, mode: ResizeMode, filter: FilterType) -> Media {
+    let (current_width, current_height) = media.dimensions();
+
+    let (width, height) = match (width, height) {
+        (0, 0) => return media,
+        (0, height) => fit_dimensions(current_width, current_height, u32::MAX, height),
+        (width, 0) => fit_dimensions(current_width, current_height, width, u32::MAX),
+        size => size,
+    };
+
+    let media = match mode {
+        ResizeMode::Fit => {
+            let (fit_width, fit_height) = fit_dimensions(current_width, current_height, width, height);
+            limit_growth(media, fit_width, fit_height)
+        },
+        ResizeMode::Stretch | ResizeMode::Fill => limit_growth(media, width, height),
+    };
+
+    match mode {
+        ResizeMode::Stretch => media.resize(width, height, filter),
+        ResizeMode::Fit => media.resize_to_fit(width, height, filter),
+        ResizeMode::Fill => media.map_images(|img| cover(img, width, height, filter)),
+    }
+}
+
+/// Scales by a percentage, keeping the result within [`MAX_RESIZE_DIMENSION`].
+pub fn scale(media: Media, percent: f64, filter: FilterType) -> Media {
+    let (width, height) = media.dimensions();
+    let scaled = |side: u32| ((side as f64 * percent / 100.0).round() as u32).max(1);
+
+    let max = MAX_RESIZE_DIMENSION as u32;
+    let (width, height) = match scaled(width).max(scaled(height)) > max {
+        true => fit_dimensions(width, height, max, max),
+        false => (scaled(width), scaled(height)),
+    };
+
+    limit_growth(media, width, height).resize(width, height, filter)
+}
+
+/// Scales the width and height by separate percentages, shrinking both by the same amount if the result
+/// would be larger than [`MAX_RESIZE_DIMENSION`].
+pub fn stretch(media: Media, x_percent: f64, y_percent: f64) -> Media {
+    let (width, height) = media.dimensions();
+    let (width, height) = (width as f64 * x_percent / 100.0, height as f64 * y_percent / 100.0);
+
+    let fit = (MAX_RESIZE_DIMENSION as f64 / width.max(height)).min(1.0);
+    let side = |size: f64| ((size * fit).round() as u32).max(1);
+    let (width, height) = (side(width), side(height));
+
+    limit_growth(media, width, height).resize(width, height, FilterType::Lanczos3)
+}
+
+/// Crops every frame to `width` by `height` pixels from `x`, `y`, clamped to the image. A size of 0
+/// reaches to the edge.
+pub fn crop(media: Media, x: u32, y: u32, width: u32, height: u32) -> Media {
+    let (current_width, current_height) = media.dimensions();
+    let (x, y) = (x.min(current_width.saturating_sub(1)), y.min(current_height.saturating_sub(1)));
+
+    let side = |size: u32, remaining: u32| if size == 0 { remaining } else { size.min(remaining) };
+    let (width, height) = (side(width, current_width - x), side(height, current_height - y));
+
+    if (x, y, width, height) == (0, 0, current_width, current_height) {
+        return media;
+    }
+    media.map_images(|img| imageops::crop_imm(img, x, y, width, height).to_image())
+}
+
+/// Which part of an image to keep when cropping it to an aspect ratio.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gravity {
+    Center,
+    Top,
+    Bottom,
+    Left,
+    Right,
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl Gravity {
+    /// The names gravities are given by, in the order of the variants.
+    pub const NAMES: &'static [&'static str] =
+        &["center", "top", "bottom", "left", "right", "top-left", "top-right", "bottom-left", "bottom-right"];
+    const ALL: [Self; 9] = [
+        Self::Center,
+        Self::Top,
+        Self::Bottom,
+        Self::Left,
+        Self::Right,
+        Self::TopLeft,
+        Self::TopRight,
+        Self::BottomLeft,
+        Self::BottomRight,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::NAMES.iter().position(|n| n.eq_ignore_ascii_case(name)).map(|index| Self::ALL[index])
+    }
+
+    pub fn name(self) -> &'static str {
+        Self::NAMES[self as usize]
+    }
+
+    /// How far along the leftover space the crop sits, from 0 (left or top) to 1 (right or bottom).
+    pub(super) fn offsets(self) -> (f64, f64) {
+        match self {
+            Self::Center => (0.5, 0.5),
+            Self::Top => (0.5, 0.0),
+            Self::Bottom => (0.5, 1.0),
+            Self::Left => (0.0, 0.5),
+            Self::Right => (1.0, 0.5),
+            Self::TopLeft => (0.0, 0.0),
+            Self::TopRight => (1.0, 0.0),
+            Self::BottomLeft => (0.0, 1.0),
+            Self::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+/// Crops to the largest area with the given aspect ratio (width over height), placed by `gravity`.
+pub fn crop_to_aspect(media: Media, aspect: f64, gravity: Gravity) -> Media {
+    let (width, height) = media.dimensions();
+
+    let (crop_width, crop_height) = match width as f64 / height as f64 > aspect {
+        true => (((height as f64 * aspect).round() as u32).clamp(1, width), height),
+        false => (width, ((width as f64 / aspect).round() as u32).clamp(1, height)),
+    };
+
+    let (horizontal, vertical) = gravity.offsets();
+    let x = ((width - crop_width) as f64 * horizontal).round() as u32;
+    let y = ((height - crop_height) as f64 * vertical).round() as u32;
+
+    crop(media, x, y, crop_width, crop_height)
+}
+
+/// Rotates every frame clockwise by any angle, on a transparent canvas grown to fit the corners.
+pub fn rotate(media: Media, degrees: f32) -> Media {
+    media.map_images(|img| geometry::rotate(img, degrees))
+}
+
+/// Mirrors every frame upside down.
+pub fn flip(media: Media) -> Media {
+    media.map_images(imageops::flip_vertical)
+}
+
+/// Mirrors every frame left to right.
+pub fn flop(media: Media) -> Media {
+    media.map_images(imageops::flip_horizontal)
+}
+
+/// The half of the image that `mirror` keeps.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MirrorSide {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+/// Reflects one half of the image onto the other.
+pub fn mirror_image(img: &RgbaImage, side: MirrorSide) -> RgbaImage {
+    let (width, height) = img.dimensions();
+
+    RgbaImage::from_fn(width, height, |x, y| {
+        let (x, y) = match side {
+            MirrorSide::Left if x >= width / 2 => (width - 1 - x, y),
+            MirrorSide::Right if x < width / 2 => (width - 1 - x, y),
+            MirrorSide::Top if y >= height / 2 => (x, height - 1 - y),
+            MirrorSide::Bottom if y < height / 2 => (x, height - 1 - y),
+            _ => (x, y),
+        };
+        *img.get_pixel(x, y)
+    })
+}
+
+pub fn mirror(media: Media, side: MirrorSide) -> Media {
+    media.map_images(|img| mirror_image(img, side))
+}
+
+/// Repeats every frame in a grid of `columns` by `rows`, scaled down so the result stays within
+/// [`MAX_RESIZE_DIMENSION`]. With `mirror`, every other tile is mirrored so their edges line up.
+pub fn tile(media: Media, columns: u32, rows: u32, mirror: bool) -> Media {
+    let (width, height) = media.dimensions();
+    let max = MAX_RESIZE_DIMENSION as u32;
+
+    let media = match width * columns > max || height * rows > max {
+        true => media.resize_to_fit(max / columns, max / rows, FilterType::Lanczos3),
+        false => media,
+    };
+    let (width, height) = media.dimensions();
+
+    limit_growth(media, width * columns, height * rows).map_images(|img| {
+        let mut out = RgbaImage::new(width * columns, height * rows);
+
+        for row in 0..rows {
+            for column in 0..columns {
+                let mut tile = img.clone();
+                if mirror && column % 2 == 1 {
+                    tile = imageops::flip_horizontal(&tile);
+                }
+                if mirror && row % 2 == 1 {
+                    tile = imageops::flip_vertical(&tile);
+                }
+                imageops::replace(&mut out, &tile, column * width, row * height);
+            }
+        }
+        out
+    })
+}
+
+/// Puts a border `width` pixels wide around every frame, growing the canvas so none of the image is
+/// covered. The border fades from `from` in the top left corner to `to` in the bottom right one, so
+/// it's solid when they're the same. Images too large for the border are scaled down to make room.
+pub fn border(media: Media, width: u32, from: Rgba<u8>, to: Rgba<u8>) -> Media {
+    let max = MAX_RESIZE_DIMENSION as u32;
+    let room = max.saturating_sub(width * 2).max(1);
+    let (image_width, image_height) = media.dimensions();
+
+    let media = match image_width > room || image_height > room {
+        true => media.resize_to_fit(room, room, FilterType::Lanczos3),
+        false => media,
+    };
+
+    media.map_images(|img| {
+        let (outer_width, outer_height) = (img.width() + width * 2, img.height() + width * 2);
+        let span = (outer_width + outer_height - 2).max(1) as f32;
+
+        RgbaImage::from_fn(outer_width, outer_height, |x, y| {
+            let inside = (width..width + img.width()).contains(&x) && (width..width + img.height()).contains(&y);
+            if inside {
+                return *img.get_pixel(x - width, y - width);
+            }
+
+            let along = (x + y) as f32 / span;
+            Rgba([0, 1, 2, 3].map(|c| (from[c] as f32 + (to[c] as f32 - from[c] as f32) * along).round() as u8))
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resizes_keeping_aspect_ratio() {
+        let media = Media::Static(RgbaImage::new(40, 20));
+
+        assert_eq!(resize(media.clone(), 20, 0, ResizeMode::Stretch, FilterType::Nearest).dimensions(), (20, 10));
+        assert_eq!(resize(media.clone(), 0, 40, ResizeMode::Stretch, FilterType::Nearest).dimensions(), (80, 40));
+        assert_eq!(resize(media, 0, 0, ResizeMode::Stretch, FilterType::Nearest).dimensions(), (40, 20));
+    }
+
+    #[test]
+    fn resizes_in_every_mode() {
+        let media = Media::Static(RgbaImage::new(40, 20));
+
+        assert_eq!(resize(media.clone(), 30, 30, ResizeMode::Stretch, FilterType::Nearest).dimensions(), (30, 30));
+        assert_eq!(resize(media.clone(), 30, 30, ResizeMode::Fit, FilterType::Nearest).dimensions(), (30, 15));
+        assert_eq!(resize(media, 30, 30, ResizeMode::Fill, FilterType::Nearest).dimensions(), (30, 30));
+    }
+
+    #[test]
+    fn limits_the_frames_of_grown_animations() {
+        let frames = |count: usize| Media::Animated((0..count).map(|_| image::Frame::new(RgbaImage::new(64, 64))).collect());
+
+        assert_eq!(limit_growth(frames(20), 4096, 4096).frame_count(), 2);
+        assert_eq!(limit_growth(frames(20), 1024, 1024).frame_count(), 20);
+        assert_eq!(scale(frames(20), 1600.0, FilterType::Nearest).frame_count(), 20);
+        assert_eq!(scale(frames(40), 1600.0, FilterType::Nearest).frame_count(), 20);
+
+        // Animations that stay small, or shrink, keep all their frames.
+        assert_eq!(resize(frames(20), 128, 128, ResizeMode::Fit, FilterType::Nearest).frame_count(), 20);
+        assert_eq!(resize(frames(20), 32, 0, ResizeMode::Stretch, FilterType::Nearest).frame_count(), 20);
+    }
+
+    #[test]
+    fn limits_the_frames_of_tiled_animations() {
+        let frames = (0..40).map(|_| image::Frame::new(RgbaImage::new(256, 256))).collect();
+        let tiled = tile(Media::Animated(frames), 4, 4, false);
+
+        assert_eq!(tiled.dimensions(), (1024, 1024));
+        assert_eq!(tiled.frame_count(), 20);
+    }
+
+    #[test]
+    fn limits_the_frames_of_stretched_animations() {
+        let frames = (0..40).map(|_| image::Frame::new(RgbaImage::new(256, 256))).collect();
+        let stretched = stretch(Media::Animated(frames), 1600.0, 25.0);
+
+        assert_eq!(stretched.dimensions(), (4096, 64));
+        assert_eq!(stretched.frame_count(), 40);
+
+        let frames = (0..40).map(|_| image::Frame::new(RgbaImage::new(256, 256))).collect();
+        assert_eq!(stretch(Media::Animated(frames), 400.0, 400.0).frame_count(), 20);
+    }
+
+    #[test]
+    fn crops_within_bounds() {
+        let media = Media::Static(RgbaImage::from_fn(40, 20, |x, y| image::Rgba([x as u8, y as u8, 0, 255])));
+
+        let cropped = crop(media.clone(), 10, 5, 8, 4);
+        assert_eq!(cropped.dimensions(), (8, 4));
+        assert_eq!(cropped.first_frame().get_pixel(0, 0).0, [10, 5, 0, 255]);
+
+        assert_eq!(crop(media.clone(), 30, 0, 100, 0).dimensions(), (10, 20));
+        assert_eq!(crop(media, 50, 50, 0, 0).dimensions(), (1, 1));
+    }
+
+    #[test]
+    fn crops_to_aspect_ratios() {
+        let media = Media::Static(RgbaImage::from_fn(40, 20, |x, y| image::Rgba([x as u8, y as u8, 0, 255])));
+
+        let square = crop_to_aspect(media.clone(), 1.0, Gravity::Center);
+        assert_eq!(square.dimensions(), (20, 20));
+        assert_eq!(square.first_frame().get_pixel(0, 0).0, [10, 0, 0, 255]);
+
+        let right = crop_to_aspect(media.clone(), 1.0, Gravity::BottomRight);
+        assert_eq!(right.first_frame().get_pixel(0, 0).0, [20, 0, 0, 255]);
+
+        let wide = crop_to_aspect(media, 4.0, Gravity::Bottom);
+        assert_eq!(wide.dimensions(), (40, 10));
+        assert_eq!(wide.first_frame().get_pixel(0, 0).0, [0, 10, 0, 255]);
+    }
+
+    #[test]
+    fn rotates_onto_larger_canvases() {
+        let media = Media::Static(RgbaImage::from_pixel(40, 20, image::Rgba([255, 0, 0, 255])));
+
+        assert_eq!(rotate(media.clone(), 90.0).dimensions(), (20, 40));
+        assert_eq!(rotate(media.clone(), -360.0).dimensions(), (40, 20));
+
+        let rotated = rotate(media, 45.0);
+        assert_eq!(rotated.dimensions(), (43, 43));
+        assert_eq!(rotated.first_frame().get_pixel(0, 0)[3], 0);
+        assert_eq!(*rotated.first_frame().get_pixel(21, 21), image::Rgba([255, 0, 0, 255]));
+    }
+
+    #[test]
+    fn mirrors() {
+        let media = Media::Static(RgbaImage::from_fn(2, 2, |x, y| image::Rgba([x as u8, y as u8, 0, 255])));
+
+        assert_eq!(flip(media.clone()).first_frame().get_pixel(0, 0).0, [0, 1, 0, 255]);
+        assert_eq!(flop(media).first_frame().get_pixel(0, 0).0, [1, 0, 0, 255]);
+    }
+
+    #[test]
+    fn mirrors_halves() {
+        let img = RgbaImage::from_fn(4, 3, |x, y| image::Rgba([x as u8, y as u8, 0, 255]));
+        let column = |img: &RgbaImage| (0..4).map(|x| img.get_pixel(x, 0)[0]).collect::<Vec<_>>();
+        let row = |img: &RgbaImage| (0..3).map(|y| img.get_pixel(0, y)[1]).collect::<Vec<_>>();
+
+        assert_eq!(column(&mirror_image(&img, MirrorSide::Left)), [0, 1, 1, 0]);
+        assert_eq!(column(&mirror_image(&img, MirrorSide::Right)), [3, 2, 2, 3]);
+        // The middle row of odd heights stays where it is.
+        assert_eq!(row(&mirror_image(&img, MirrorSide::Top)), [0, 1, 0]);
+        assert_eq!(row(&mirror_image(&img, MirrorSide::Bottom)), [2, 1, 2]);
+    }
+
+    #[test]
+    fn tiles_in_a_grid() {
+        let media = Media::Static(RgbaImage::from_fn(4, 2, |x, y| image::Rgba([x as u8, y as u8, 0, 255])));
+
+        let tiled = tile(media.clone(), 3, 2, false);
+        assert_eq!(tiled.dimensions(), (12, 4));
+        assert_eq!(tiled.first_frame().get_pixel(5, 3).0, [1, 1, 0, 255]);
+
+        let mirrored = tile(media, 2, 2, true);
+        assert_eq!(mirrored.first_frame().get_pixel(4, 0).0, [3, 0, 0, 255]);
+        assert_eq!(mirrored.first_frame().get_pixel(0, 2).0, [0, 1, 0, 255]);
+
+        let large = Media::Static(RgbaImage::new(2048, 1024));
+        assert_eq!(tile(large, 4, 4, false).dimensions(), (4096, 2048));
+    }
+
+    #[test]
+    fn stretches_each_side() {
+        let media = Media::Static(RgbaImage::new(40, 20));
+
+        assert_eq!(stretch(media.clone(), 200.0, 50.0).dimensions(), (80, 10));
+        assert_eq!(stretch(media, 100_000.0, 100.0).dimensions(), (4096, 2));
+    }
+
+    #[test]
+    fn scales_by_percentages() {
+        let media = Media::Static(RgbaImage::new(40, 20));
+
+        assert_eq!(scale(media.clone(), 50.0, FilterType::Nearest).dimensions(), (20, 10));
+        assert_eq!(scale(media.clone(), 1.0, FilterType::Nearest).dimensions(), (1, 1));
+        assert_eq!(scale(media, 100_000.0, FilterType::Nearest).dimensions(), (4096, 2048));
+    }
+
+    #[test]
+    fn borders_around_the_image() {
+        let media = Media::Static(RgbaImage::from_pixel(4, 2, Rgba([10, 20, 30, 255])));
+        let (black, white) = (Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255]));
+
+        let solid = border(media.clone(), 3, black, black);
+        let img = solid.first_frame();
+        assert_eq!(img.dimensions(), (10, 8));
+        assert_eq!(*img.get_pixel(0, 0), black);
+        assert_eq!(*img.get_pixel(3, 3), Rgba([10, 20, 30, 255]));
+        assert_eq!(*img.get_pixel(6, 4), Rgba([10, 20, 30, 255]));
+        assert_eq!(*img.get_pixel(7, 4), black);
+
+        let faded = border(media, 3, black, white);
+        let img = faded.first_frame();
+        assert_eq!((img.get_pixel(0, 0)[0], img.get_pixel(9, 7)[0]), (0, 255));
+        assert!((100..156).contains(&img.get_pixel(9, 0)[0]));
+    }
+}
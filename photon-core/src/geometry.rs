@@ -0,0 +1,188 @@
+//! Placing, rotating and sampling images, shared by compositing and distortion effects.
+
+use image::imageops::{self, FilterType};
+use image::{Rgba, RgbaImage};
+
+/// Samples the image at fractional coordinates with bilinear interpolation.
+///
+/// Coordinates outside the image are transparent, fading out over the edge pixels so shapes drawn
+/// at an angle don't end up with jagged borders.
+pub fn sample_bilinear(img: &RgbaImage, x: f32, y: f32) -> Rgba<u8> {
+    let (width, height) = (img.width() as i64, img.height() as i64);
+    let (x, y) = (x - 0.5, y - 0.5);
+    let (x0, y0) = (x.floor() as i64, y.floor() as i64);
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+
+    let mut sum = [0.0_f32; 4];
+    for (dx, dy, weight) in [(0, 0, (1.0 - fx) * (1.0 - fy)), (1, 0, fx * (1.0 - fy)), (0, 1, (1.0 - fx) * fy), (1, 1, fx * fy)] {
+        let (px, py) = (x0 + dx, y0 + dy);
+        if weight == 0.0 || px < 0 || py < 0 || px >= width || py >= height {
+            continue;
+        }
+
+        // Premultiplied, otherwise transparent neighbours bleed their (meaningless) color in.
+        let pixel = img.get_pixel(px as u32, py as u32);
+        let alpha = pixel[3] as f32 * weight;
+        for c in 0..3 {
+            sum[c] += pixel[c] as f32 * alpha;
+        }
+        sum[3] += alpha;
+    }
+
+    if sum[3] <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    Rgba([
+        (sum[0] / sum[3]).round().clamp(0.0, 255.0) as u8,
+        (sum[1] / sum[3]).round().clamp(0.0, 255.0) as u8,
+        (sum[2] / sum[3]).round().clamp(0.0, 255.0) as u8,
+        sum[3].round().clamp(0.0, 255.0) as u8,
+    ])
+}
+
+/// Rotates the image clockwise by any angle, growing the canvas so nothing is cut off.
+pub fn rotate(img: &RgbaImage, degrees: f32) -> RgbaImage {
+    let degrees = degrees.rem_euclid(360.0);
+
+    // Right angles are exact and much cheaper.
+    match degrees {
+        d if d == 0.0 => return img.clone(),
+        d if d == 90.0 => return imageops::rotate90(img),
+        d if d == 180.0 => return imageops::rotate180(img),
+        d if d == 270.0 => return imageops::rotate270(img),
+        _ => (),
+    }
+
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (width, height) = (img.width() as f32, img.height() as f32);
+    let new_width = (width * cos.abs() + height * sin.abs()).ceil().max(1.0);
+    let new_height = (width * sin.abs() + height * cos.abs()).ceil().max(1.0);
+
+    let (cx, cy) = (width / 2.0, height / 2.0);
+    let (ncx, ncy) = (new_width / 2.0, new_height / 2.0);
+
+    RgbaImage::from_fn(new_width as u32, new_height as u32, |x, y| {
+        // Map each output pixel back onto the source by rotating the other way.
+        let (dx, dy) = (x as f32 + 0.5 - ncx, y as f32 + 0.5 - ncy);
+        sample_bilinear(img, dx * cos + dy * sin + cx, -dx * sin + dy * cos + cy)
+    })
+}
+
+/// Draws `top` onto `canvas` with its top left corner at the given position, blending by alpha.
+/// Parts outside the canvas are clipped.
+pub fn overlay(canvas: &mut RgbaImage, top: &RgbaImage, x: i64, y: i64) {
+    let (width, height) = (canvas.width() as i64, canvas.height() as i64);
+
+    for (tx, ty, pixel) in top.enumerate_pixels() {
+        let (cx, cy) = (x + tx as i64, y + ty as i64);
+        if pixel[3] == 0 || cx < 0 || cy < 0 || cx >= width || cy >= height {
+            continue;
+        }
+
+        let below = canvas.get_pixel_mut(cx as u32, cy as u32);
+        *below = blend_over(*below, *pixel);
+    }
+}
+
+/// Composites `top` over `below` ("source over").
+pub fn blend_over(below: Rgba<u8>, top: Rgba<u8>) -> Rgba<u8> {
+    let top_alpha = top[3] as f32 / 255.0;
+    let below_alpha = below[3] as f32 / 255.0;
+    let alpha = top_alpha + below_alpha * (1.0 - top_alpha);
+
+    if alpha <= 0.0 {
+        return Rgba([0, 0, 0, 0]);
+    }
+
+    let mut out = [0; 4];
+    for c in 0..3 {
+        let value = (top[c] as f32 * top_alpha + below[c] as f32 * below_alpha * (1.0 - top_alpha)) / alpha;
+        out[c] = value.round().clamp(0.0, 255.0) as u8;
+    }
+    out[3] = (alpha * 255.0).round() as u8;
+
+    Rgba(out)
+}
+
+/// Mixes two images of the same size pixel by pixel, `amount` of 0 giving `from` and 1 giving `to`.
+pub fn mix(from: &RgbaImage, to: &RgbaImage, amount: f32) -> RgbaImage {
+    RgbaImage::from_fn(from.width(), from.height(), |x, y| {
+        let (a, b) = (from.get_pixel(x, y), to.get_pixel(x, y));
+        Rgba(std::array::from_fn(|c| (a[c] as f32 + (b[c] as f32 - a[c] as f32) * amount).round() as u8))
+    })
+}
+
+/// Scales the image to fill the given size and crops whatever sticks out, keeping it centered.
+pub fn cover(img: &RgbaImage, width: u32, height: u32, filter: FilterType) -> RgbaImage {
+    let scale = (width as f64 / img.width() as f64).max(height as f64 / img.height() as f64);
+    let scaled_width = ((img.width() as f64 * scale).round() as u32).max(width);
+    let scaled_height = ((img.height() as f64 * scale).round() as u32).max(height);
+
+    let scaled = imageops::resize(img, scaled_width, scaled_height, filter);
+    imageops::crop_imm(&scaled, (scaled_width - width) / 2, (scaled_height - height) / 2, width, height).to_image()
+}
+
+/// Scales the image to fit within the given size, centered on a transparent canvas of exactly that size.
+pub fn contain(img: &RgbaImage, width: u32, height: u32, filter: FilterType) -> RgbaImage {
+    let (fit_width, fit_height) = crate::fit_dimensions(img.width(), img.height(), width, height);
+    let scaled = imageops::resize(img, fit_width.min(width), fit_height.min(height), filter);
+
+    let mut canvas = RgbaImage::new(width, height);
+    overlay(&mut canvas, &scaled, ((width - scaled.width()) / 2) as i64, ((height - scaled.height()) / 2) as i64);
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    #[test]
+    fn rotates_right_angles_exactly() {
+        let mut img = RgbaImage::new(4, 2);
+        img.put_pixel(0, 0, RED);
+
+        let rotated = rotate(&img, 90.0);
+        assert_eq!(rotated.dimensions(), (2, 4));
+        assert_eq!(*rotated.get_pixel(1, 0), RED);
+
+        assert_eq!(rotate(&img, -270.0), rotated);
+        assert_eq!(rotate(&img, 360.0), img);
+    }
+
+    #[test]
+    fn rotating_grows_the_canvas() {
+        let img = RgbaImage::from_pixel(10, 10, RED);
+        let rotated = rotate(&img, 45.0);
+
+        assert_eq!(rotated.dimensions(), (15, 15));
+        assert_eq!(*rotated.get_pixel(7, 7), RED);
+        assert_eq!(rotated.get_pixel(0, 0)[3], 0);
+    }
+
+    #[test]
+    fn overlay_clips_and_blends() {
+        let mut canvas = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 255, 255]));
+        let top = RgbaImage::from_pixel(2, 2, Rgba([255, 0, 0, 128]));
+
+        overlay(&mut canvas, &top, -1, 3);
+        assert_eq!(*canvas.get_pixel(0, 3), Rgba([128, 0, 127, 255]));
+        assert_eq!(*canvas.get_pixel(1, 3), Rgba([0, 0, 255, 255]));
+        assert_eq!(*canvas.get_pixel(0, 2), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn cover_and_contain_produce_the_requested_size() {
+        let img = RgbaImage::from_pixel(40, 10, RED);
+
+        assert_eq!(cover(&img, 20, 20, FilterType::Triangle).dimensions(), (20, 20));
+        assert_eq!(cover(&img, 20, 20, FilterType::Triangle).get_pixel(0, 0)[3], 255);
+
+        let contained = contain(&img, 20, 20, FilterType::Triangle);
+        assert_eq!(contained.dimensions(), (20, 20));
+        assert_eq!(contained.get_pixel(0, 0)[3], 0);
+        assert_eq!(*contained.get_pixel(10, 10), RED);
+    }
+}
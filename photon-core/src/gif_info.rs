@@ -0,0 +1,262 @@
+//! Reads and tweaks the structure of a GIF without decoding its pixels: frame delays, palettes and how
+//! often it loops, none of which survive decoding into [`Media`](crate::Media).
+
+use crate::{is_gif, Error, Result};
+
+/// What a GIF is made of, as stored in the file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GifInfo {
+    /// The size of the logical screen the frames are drawn on.
+    pub width: u16,
+    pub height: u16,
+    /// The delay of every frame in milliseconds, as stored rather than as most viewers play them.
+    pub delays: Vec<u32>,
+    /// How many times the animation repeats after playing once, 0 meaning forever. `None` when the GIF
+    /// has no looping extension, in which case it plays just once.
+    pub loop_count: Option<u16>,
+    /// Entries in the palette shared by all frames.
+    pub global_palette: Option<usize>,
+    /// How many frames bring a palette of their own and the largest of those.
+    pub local_palettes: usize,
+    pub largest_local_palette: Option<usize>,
+    /// The size of the whole file in bytes.
+    pub size: usize,
+}
+
+impl GifInfo {
+    pub fn frame_count(&self) -> usize {
+        self.delays.len()
+    }
+
+    /// How long one run through the animation takes, in milliseconds.
+    pub fn duration_ms(&self) -> u64 {
+        self.delays.iter().map(|&delay| delay as u64).sum()
+    }
+
+    /// The shortest and longest frame delay.
+    pub fn delay_range(&self) -> (u32, u32) {
+        let min = self.delays.iter().copied().min().unwrap_or_default();
+        let max = self.delays.iter().copied().max().unwrap_or_default();
+        (min, max)
+    }
+
+    pub fn average_delay(&self) -> f64 {
+        self.duration_ms() as f64 / self.frame_count().max(1) as f64
+    }
+}
+
+/// Entries in a color table, given the packed flags byte it is announced in.
+fn palette_size(flags: u8) -> Option<usize> {
+    match flags & 0x80 {
+        0 => None,
+        _ => Some(2 << (flags & 0x07)),
+    }
+}
+
+/// A block of a GIF along with where it starts and (past its data) ends.
+enum Block<'a> {
+    Extension { label: u8, body: &'a [u8] },
+    /// An image descriptor and the frame data after it, with the flags of the descriptor.
+    Image { flags: u8 },
+}
+
+/// Where the blocks start, after the header and the global palette.
+fn first_block(data: &[u8]) -> usize {
+    13 + palette_size(data[10]).unwrap_or(0) * 3
+}
+
+/// Goes through the blocks of a GIF in order. Stops at the trailer, or at a block cut off by the end
+/// of the data so it isn't visited.
+fn walk<'a>(data: &'a [u8], mut visit: impl FnMut(Block<'a>, usize, usize)) {
+    // Skips a run of data sub-blocks, returning the position after the terminator.
+    let skip_blocks = |mut at: usize| {
+        while let Some(&length) = data.get(at) {
+            at += 1 + length as usize;
+            if length == 0 {
+                return Some(at);
+            }
+        }
+        None
+    };
+
+    let mut at = first_block(data);
+    while let Some(&introducer) = data.get(at) {
+        let (block, end) = match introducer {
+            0x21 => {
+                let (Some(&label), Some(&length)) = (data.get(at + 1), data.get(at + 2)) else { break };
+                let body = data.get(at + 3..at + 3 + length as usize).unwrap_or_default();
+                let Some(end) = skip_blocks(at + 3 + length as usize) else { break };
+
+                (Block::Extension { label, body }, end)
+            },
+            // The byte after the descriptor and its palette is the LZW code size, then come the data blocks.
+            0x2C => {
+                let Some(&flags) = data.get(at + 9) else { break };
+                let Some(end) = skip_blocks(at + 10 + palette_size(flags).unwrap_or(0) * 3 + 1) else { break };
+
+                (Block::Image { flags }, end)
+            },
+            // The trailer, or garbage.
+            _ => break,
+        };
+
+        visit(block, at, end);
+        at = end;
+    }
+}
+
+/// Whether an application extension is the one holding the loop count.
+fn is_loop_extension(body: &[u8]) -> bool {
+    body == b"NETSCAPE2.0" || body == b"ANIMEXTS1.0"
+}
+
+/// Reads through a GIF block by block. Anything after a truncated block is ignored like decoders do,
+/// but there has to be at least one frame.
+pub fn gif_info(data: &[u8]) -> Result<GifInfo> {
+    if !is_gif(data) {
+        return Err(Error::NotAnimated);
+    }
+    if data.len() < 13 {
+        return Err(Error::NoFrames);
+    }
+
+    let word = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let mut info = GifInfo {
+        width: word(6),
+        height: word(8),
+        delays: Vec::new(),
+        loop_count: None,
+        global_palette: palette_size(data[10]),
+        local_palettes: 0,
+        largest_local_palette: None,
+        size: data.len(),
+    };
+
+    // The delay of the next frame, set by the graphic control extension before it.
+    let mut delay = 0;
+
+    walk(data, |block, at, _| match block {
+        Block::Extension { label: 0xF9, body } if body.len() >= 3 => {
+            delay = u16::from_le_bytes([body[1], body[2]]) as u32 * 10;
+        },
+        Block::Extension { label: 0xFF, body } if is_loop_extension(body) => {
+            if let Some(&[3, 1, low, high]) = data.get(at + 14..at + 18) {
+                info.loop_count = Some(u16::from_le_bytes([low, high]));
+            }
+        },
+        Block::Extension { .. } => {},
+        Block::Image { flags } => {
+            if let Some(entries) = palette_size(flags) {
+                info.local_palettes += 1;
+                info.largest_local_palette = info.largest_local_palette.max(Some(entries));
+            }
+            info.delays.push(delay);
+            delay = 0;
+        },
+    });
+
+    match info.delays.is_empty() {
+        true => Err(Error::NoFrames),
+        false => Ok(info),
+    }
+}
+
+/// Changes how often a GIF repeats without decoding it, see [`GifInfo::loop_count`] for what the count
+/// means. `None` removes the looping extension so it plays once.
+pub fn set_loop_count(data: &[u8], count: Option<u16>) -> Result<Vec<u8>> {
+    gif_info(data)?;
+
+    let mut existing = Vec::new();
+    walk(data, |block, at, end| {
+        if let Block::Extension { label: 0xFF, body } = block {
+            if is_loop_extension(body) {
+                existing.push(at..end);
+            }
+        }
+    });
+
+    // The extension goes right before the first block, where decoders expect it.
+    let start = first_block(data);
+    let mut output = Vec::with_capacity(data.len() + 19);
+    output.extend_from_slice(&data[..start]);
+    if let Some(count) = count {
+        output.extend_from_slice(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01");
+        output.extend_from_slice(&count.to_le_bytes());
+        output.push(0);
+    }
+
+    let mut at = start;
+    for range in existing {
+        output.extend_from_slice(&data[at..range.start]);
+        at = range.end;
+    }
+    output.extend_from_slice(&data[at..]);
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A 2x1 GIF with a 4 color global palette, looping forever, and two frames of which the second has
+    /// a palette of its own.
+    fn sample() -> Vec<u8> {
+        let mut data = b"GIF89a".to_vec();
+        data.extend([2, 0, 1, 0, 0x81, 0, 0]);
+        data.extend([0; 12]);
+        data.extend(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00");
+
+        data.extend([0x21, 0xF9, 4, 0, 5, 0, 0, 0]);
+        data.extend([0x2C, 0, 0, 0, 0, 2, 0, 1, 0, 0]);
+        data.extend([2, 2, 0x4C, 0x01, 0]);
+
+        data.extend([0x21, 0xF9, 4, 0, 12, 0, 0, 0]);
+        data.extend([0x2C, 0, 0, 0, 0, 2, 0, 1, 0, 0x82]);
+        data.extend([0; 24]);
+        data.extend([3, 1, 0x44, 0]);
+        data.push(0x3B);
+        data
+    }
+
+    #[test]
+    fn reads_the_structure() {
+        let data = sample();
+        let info = gif_info(&data).unwrap();
+
+        assert_eq!((info.width, info.height), (2, 1));
+        assert_eq!(info.delays, [50, 120]);
+        assert_eq!(info.loop_count, Some(0));
+        assert_eq!(info.global_palette, Some(4));
+        assert_eq!((info.local_palettes, info.largest_local_palette), (1, Some(8)));
+        assert_eq!(info.size, data.len());
+
+        assert_eq!(info.duration_ms(), 170);
+        assert_eq!(info.delay_range(), (50, 120));
+        assert_eq!(info.average_delay(), 85.0);
+    }
+
+    #[test]
+    fn tolerates_truncation() {
+        let data = sample();
+        assert_eq!(gif_info(&data[..data.len() - 8]).unwrap().frame_count(), 1);
+
+        assert!(matches!(gif_info(&data[..40]), Err(Error::NoFrames)));
+        assert!(matches!(gif_info(b"\x89PNG\r\n\x1a\n"), Err(Error::NotAnimated)));
+    }
+
+    #[test]
+    fn rewrites_the_loop_count() {
+        let data = sample();
+
+        let thrice = set_loop_count(&data, Some(2)).unwrap();
+        assert_eq!(thrice.len(), data.len());
+        assert_eq!(gif_info(&thrice).unwrap().loop_count, Some(2));
+
+        let once = set_loop_count(&data, None).unwrap();
+        let info = gif_info(&once).unwrap();
+        assert_eq!((info.loop_count, info.delays), (None, vec![50, 120]));
+
+        assert_eq!(set_loop_count(&once, Some(0)).unwrap(), data);
+    }
+}
@@ -0,0 +1,40 @@
+//! The imaging half of Photon: decoding (including animated GIFs), filters, text rendering and encoders,
+//! free of anything Discord specific.
+//!
+//! ```no_run
+//! use photon_core::{filters, Media, OutputFormat, Params, Quality};
+//!
+//! # fn main() -> photon_core::Result<()> {
+//! let input = std::fs::read("in.gif")?;
+//! let filter = filters::find("invert").unwrap();
+//! let params = Params::parse(filter.params, std::iter::empty())?;
+//!
+//! let media = filter.apply(Media::decode(&input, &Quality::full())?, &params)?;
+//! let (output, format) = media.encode(OutputFormat::Png)?;
+//! std::fs::write(format!("out.{}", format.extension()), output)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod blend;
+pub mod color;
+mod encode;
+mod error;
+pub mod filters;
+pub mod geometry;
+mod gif_info;
+mod lut;
+mod media;
+mod params;
+mod random;
+pub mod text;
+
+pub use blend::BlendMode;
+pub use color::parse_color;
+pub use encode::{encode_image, OutputFormat, JPEG_QUALITY};
+pub use error::{Error, Result};
+pub use filters::Filter;
+pub use gif_info::{gif_info, set_loop_count, GifInfo};
+pub use lut::{Lut, MAX_LUT_SIZE};
+pub use media::{delay_ms, fit_dimensions, is_gif, Media, Quality};
+pub use params::{ParamKind, ParamSpec, Params};
@@ -0,0 +1,182 @@
+//! 3D color lookup tables in the `.cube` format popularized by Adobe and Resolve.
+
+use image::RgbaImage;
+
+use crate::{Error, Result};
+
+/// The largest `LUT_3D_SIZE` accepted, larger tables are rarely any more accurate.
+pub const MAX_LUT_SIZE: usize = 65;
+
+#[derive(Clone, Debug)]
+pub struct Lut {
+    pub title: Option<String>,
+    size: usize,
+    domain_min: [f32; 3],
+    domain_max: [f32; 3],
+    /// Output colors with red changing fastest, then green, then blue.
+    table: Vec<[f32; 3]>,
+}
+
+fn parse_triple(parts: &[&str]) -> Option<[f32; 3]> {
+    match parts {
+        [r, g, b] => Some([r.parse().ok()?, g.parse().ok()?, b.parse().ok()?]),
+        _ => None,
+    }
+}
+
+impl Lut {
+    pub fn parse(source: &str) -> Result<Self> {
+        let invalid = |line: usize, reason: &'static str| Error::InvalidLut { line, reason };
+
+        let mut title = None;
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut table = Vec::new();
+
+        for (index, line) in source.lines().enumerate() {
+            let number = index + 1;
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+
+            match parts.first().copied() {
+                None => {},
+                Some("TITLE") => title = Some(line["TITLE".len()..].trim().trim_matches('"').to_string()),
+                Some("LUT_1D_SIZE") => return Err(invalid(number, "1D LUTs are not supported")),
+                Some("LUT_3D_SIZE") => {
+                    let value = parts.get(1).and_then(|s| s.parse::<usize>().ok());
+
+                    size = match value {
+                        Some(value) if (2..=MAX_LUT_SIZE).contains(&value) => Some(value),
+                        _ => return Err(invalid(number, "LUT_3D_SIZE must be between 2 and 65")),
+                    };
+                },
+                Some("DOMAIN_MIN") => domain_min = parse_triple(&parts[1..]).ok_or_else(|| invalid(number, "expected three numbers"))?,
+                Some("DOMAIN_MAX") => domain_max = parse_triple(&parts[1..]).ok_or_else(|| invalid(number, "expected three numbers"))?,
+                // Resolve writes the domain as a single range.
+                Some("LUT_3D_INPUT_RANGE") => {
+                    let range = match &parts[1..] {
+                        [min, max] => min.parse::<f32>().ok().zip(max.parse::<f32>().ok()),
+                        _ => None,
+                    };
+                    let (min, max) = range.ok_or_else(|| invalid(number, "expected two numbers"))?;
+
+                    domain_min = [min; 3];
+                    domain_max = [max; 3];
+                },
+                Some(first) if first.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') => {
+                    table.push(parse_triple(&parts).ok_or_else(|| invalid(number, "expected three numbers"))?);
+                },
+                // Other keywords don't change how the table is applied.
+                Some(_) => {},
+            }
+        }
+
+        let lines = source.lines().count();
+        let size = size.ok_or_else(|| invalid(lines, "missing LUT_3D_SIZE"))?;
+
+        if table.len() != size * size * size {
+            return Err(invalid(lines, "the table doesn't have LUT_3D_SIZE³ entries"));
+        }
+        if (0..3).any(|i| domain_max[i] <= domain_min[i]) {
+            return Err(invalid(lines, "DOMAIN_MAX must be greater than DOMAIN_MIN"));
+        }
+
+        Ok(Self { title, size, domain_min, domain_max, table })
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    fn entry(&self, r: usize, g: usize, b: usize) -> [f32; 3] {
+        self.table[r + g * self.size + b * self.size * self.size]
+    }
+
+    /// Looks up a color with components in `0.0..=1.0`, interpolating trilinearly between entries.
+    pub fn lookup(&self, color: [f32; 3]) -> [f32; 3] {
+        let max = (self.size - 1) as f32;
+        let axis = |i: usize| {
+            let scaled = (color[i] - self.domain_min[i]) / (self.domain_max[i] - self.domain_min[i]);
+            let position = (scaled * max).clamp(0.0, max);
+
+            // Keep one entry of room above the base so the upper neighbour always exists.
+            let base = (position.floor() as usize).min(self.size - 2);
+            (base, position - base as f32)
+        };
+
+        let (r, fr) = axis(0);
+        let (g, fg) = axis(1);
+        let (b, fb) = axis(2);
+        let lerp = |a: [f32; 3], b: [f32; 3], t: f32| [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t];
+
+        let c00 = lerp(self.entry(r, g, b), self.entry(r + 1, g, b), fr);
+        let c10 = lerp(self.entry(r, g + 1, b), self.entry(r + 1, g + 1, b), fr);
+        let c01 = lerp(self.entry(r, g, b + 1), self.entry(r + 1, g, b + 1), fr);
+        let c11 = lerp(self.entry(r, g + 1, b + 1), self.entry(r + 1, g + 1, b + 1), fr);
+
+        lerp(lerp(c00, c10, fg), lerp(c01, c11, fg), fb)
+    }
+
+    /// Maps every pixel through the table, leaving alpha alone.
+    pub fn apply(&self, img: &mut RgbaImage) {
+        for pixel in img.pixels_mut() {
+            let [r, g, b, _] = &mut pixel.0;
+            let color = self.lookup([*r as f32 / 255.0, *g as f32 / 255.0, *b as f32 / 255.0]);
+            let to_byte = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+            *r = to_byte(color[0]);
+            *g = to_byte(color[1]);
+            *b = to_byte(color[2]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn identity(size: usize) -> String {
+        let max = (size - 1) as f32;
+        let mut source = format!("TITLE \"Identity\"\nLUT_3D_SIZE {}\n", size);
+
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    source += &format!("{} {} {}\n", r as f32 / max, g as f32 / max, b as f32 / max);
+                }
+            }
+        }
+
+        source
+    }
+
+    #[test]
+    fn identity_keeps_colors() {
+        let lut = Lut::parse(&identity(5)).unwrap();
+        assert_eq!(lut.title.as_deref(), Some("Identity"));
+
+        let mut img = RgbaImage::from_fn(16, 16, |x, y| image::Rgba([(x * 16) as u8, (y * 16) as u8, 200, 100]));
+        let original = img.clone();
+        lut.apply(&mut img);
+
+        assert_eq!(img, original);
+    }
+
+    #[test]
+    fn interpolates_between_entries() {
+        // Swaps red and blue.
+        let lut = Lut::parse("LUT_3D_SIZE 2\n0 0 0\n0 0 1\n0 1 0\n0 1 1\n1 0 0\n1 0 1\n1 1 0\n1 1 1\n").unwrap();
+        let [r, g, b] = lut.lookup([0.25, 0.5, 0.75]);
+
+        assert!((r - 0.75).abs() < 1e-6 && (g - 0.5).abs() < 1e-6 && (b - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn rejects_malformed_tables() {
+        assert!(matches!(Lut::parse("0 0 0\n"), Err(Error::InvalidLut { .. })));
+        assert!(matches!(Lut::parse("LUT_3D_SIZE 2\n0 0 0\n"), Err(Error::InvalidLut { .. })));
+        assert!(matches!(Lut::parse("LUT_3D_SIZE 2\n0 0\n"), Err(Error::InvalidLut { line: 2, .. })));
+        assert!(matches!(Lut::parse("LUT_1D_SIZE 2\n"), Err(Error::InvalidLut { line: 1, .. })));
+    }
+}
@@ -0,0 +1,343 @@
+use image::codecs::gif::{GifDecoder, GifEncoder};
+use image::imageops::FilterType;
+use image::{AnimationDecoder, Delay, Frame, Rgba, RgbaImage};
+
+use rayon::prelude::*;
+
+use crate::{encode_image, Error, OutputFormat, Result};
+
+/// How much of the input is kept when decoding, so callers can trade fidelity for speed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quality {
+    pub degraded: bool,
+    /// Inputs larger than this (on either side) are downscaled before processing.
+    pub max_dimension: Option<u32>,
+    /// Only every n-th frame of animated inputs is kept.
+    pub frame_step: usize,
+}
+
+impl Quality {
+    pub fn full() -> Self {
+        Self { degraded: false, max_dimension: None, frame_step: 1 }
+    }
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+/// A decoded input, either a single static image or the frames of an animated GIF.
+#[derive(Clone)]
+pub enum Media {
+    Static(RgbaImage),
+    Animated(Vec<Frame>),
+}
+
+pub fn is_gif(data: &[u8]) -> bool {
+    data.len() >= 6 && (&data[0..6] == b"\x47\x49\x46\x38\x39\x61" || &data[0..6] == b"\x47\x49\x46\x38\x37\x61")
+}
+
+pub fn delay_ms(delay: Delay) -> f64 {
+    let (numer, denom) = delay.numer_denom_ms();
+    numer as f64 / denom as f64
+}
+
+/// Keeps every `step`-th frame, stretching the kept frames' delays over the dropped ones.
+fn subsample(frames: Vec<Frame>, step: usize) -> Vec<Frame> {
+    if step <= 1 {
+        return frames;
+    }
+
+    frames
+        .chunks(step)
+        .map(|chunk| {
+            let total = chunk.iter().map(|f| delay_ms(f.delay())).sum::<f64>();
+            let first = &chunk[0];
+
+            Frame::from_parts(
+                first.buffer().clone(),
+                first.left(),
+                first.top(),
+                Delay::from_numer_denom_ms(total.round() as u32, 1),
+            )
+        })
+        .collect()
+}
+
+/// Scales `(width, height)` down or up to fit within `(max_width, max_height)`, keeping the aspect ratio.
+pub fn fit_dimensions(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let scale = (max_width as f64 / width as f64).min(max_height as f64 / height as f64);
+
+    (
+        ((width as f64 * scale).round() as u32).max(1),
+        ((height as f64 * scale).round() as u32).max(1),
+    )
+}
+
+fn downscale(img: RgbaImage, max_dimension: Option<u32>) -> RgbaImage {
+    let max = match max_dimension {
+        Some(max) => max,
+        None => return img,
+    };
+
+    let (width, height) = img.dimensions();
+    if width <= max && height <= max {
+        return img;
+    }
+
+    let (new_width, new_height) = fit_dimensions(width, height, max, max);
+    image::imageops::resize(&img, new_width, new_height, FilterType::Triangle)
+}
+
+impl Media {
+    /// Decodes an image, applying the frame subsampling and size limits of the given quality.
+    pub fn decode(data: &[u8], quality: &Quality) -> Result<Self> {
+        if is_gif(data) {
+            let frames = GifDecoder::new(data)?
+                .into_frames()
+                .filter_map(|frame| frame.ok())
+                .collect::<Vec<_>>();
+
+            if frames.is_empty() {
+                return Err(Error::NoFrames);
+            }
+
+            let frames = subsample(frames, quality.frame_step)
+                .into_iter()
+                .map(|frame| {
+                    let delay = frame.delay();
+                    Frame::from_parts(downscale(frame.into_buffer(), quality.max_dimension), 0, 0, delay)
+                })
+                .collect();
+
+            return Ok(Self::Animated(frames));
+        }
+
+        Ok(Self::Static(downscale(image::load_from_memory(data)?.into_rgba8(), quality.max_dimension)))
+    }
+
+    pub fn is_animated(&self) -> bool {
+        matches!(self, Self::Animated(_))
+    }
+
+    /// How many frames there are, 1 for static images.
+    pub fn frame_count(&self) -> usize {
+        match self {
+            Self::Static(_) => 1,
+            Self::Animated(frames) => frames.len(),
+        }
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            Self::Static(img) => img.dimensions(),
+            Self::Animated(frames) => frames[0].buffer().dimensions(),
+        }
+    }
+
+    /// Every frame, the only one for static images.
+    pub fn images(&self) -> Vec<&RgbaImage> {
+        match self {
+            Self::Static(img) => vec![img],
+            Self::Animated(frames) => frames.iter().map(Frame::buffer).collect(),
+        }
+    }
+
+    /// The first (or only) frame.
+    pub fn first_frame(&self) -> &RgbaImage {
+        match self {
+            Self::Static(img) => img,
+            Self::Animated(frames) => frames[0].buffer(),
+        }
+    }
+
+    /// Replaces every frame with the result of the given function, keeping frame delays.
+    pub fn map_images(self, f: impl Fn(&RgbaImage) -> RgbaImage) -> Self {
+        match self {
+            Self::Static(img) => Self::Static(f(&img)),
+            Self::Animated(frames) => Self::Animated(
+                frames
+                    .into_iter()
+                    .map(|frame| Frame::from_parts(f(frame.buffer()), 0, 0, frame.delay()))
+                    .collect()
+            ),
+        }
+    }
+
+    /// Turns a static image into `count` copies of it shown `delay_ms` apart, for effects that animate
+    /// still images. Animations are returned as they are.
+    pub fn into_animation(self, count: usize, delay_ms: u32) -> Self {
+        match self {
+            Self::Static(img) => Self::Animated(
+                (0..count.max(1))
+                    .map(|_| Frame::from_parts(img.clone(), 0, 0, Delay::from_numer_denom_ms(delay_ms, 1)))
+                    .collect()
+            ),
+            animated => animated,
+        }
+    }
+
+    /// Drops frames from animations longer than `max` until they fit, stretching the delays of the kept
+    /// frames so the animation plays for as long as before.
+    pub fn limit_frames(self, max: usize) -> Self {
+        match self {
+            Self::Animated(frames) if frames.len() > max.max(1) => {
+                let step = frames.len().div_ceil(max.max(1));
+                Self::Animated(subsample(frames, step))
+            },
+            media => media,
+        }
+    }
+
+    /// Like [`map_images`](Self::map_images), for transformations that can fail.
+    pub fn try_map_images(self, f: impl Fn(&RgbaImage) -> Result<RgbaImage>) -> Result<Self> {
+        Ok(match self {
+            Self::Static(img) => Self::Static(f(&img)?),
+            Self::Animated(frames) => Self::Animated(
+                frames
+                    .into_iter()
+                    .map(|frame| Ok(Frame::from_parts(f(frame.buffer())?, 0, 0, frame.delay())))
+                    .collect::<Result<_>>()?
+            ),
+        })
+    }
+
+    /// Like [`map_images`](Self::map_images), also passing the index of the frame.
+    pub fn map_images_indexed(self, f: impl Fn(usize, &RgbaImage) -> RgbaImage) -> Self {
+        match self {
+            Self::Static(img) => Self::Static(f(0, &img)),
+            Self::Animated(frames) => Self::Animated(
+                frames
+                    .into_iter()
+                    .enumerate()
+                    .map(|(index, frame)| Frame::from_parts(f(index, frame.buffer()), 0, 0, frame.delay()))
+                    .collect()
+            ),
+        }
+    }
+
+    /// Like [`map_images`](Self::map_images), but works on several frames at once. Worth it for the
+    /// slower filters.
+    pub fn par_map_images(self, f: impl Fn(&RgbaImage) -> RgbaImage + Sync) -> Self {
+        match self {
+            Self::Static(img) => Self::Static(f(&img)),
+            Self::Animated(frames) => Self::Animated(
+                frames
+                    .into_par_iter()
+                    .map(|frame| Frame::from_parts(f(frame.buffer()), 0, 0, frame.delay()))
+                    .collect()
+            ),
+        }
+    }
+
+    pub fn resize(self, width: u32, height: u32, filter: FilterType) -> Self {
+        self.map_images(|img| image::imageops::resize(img, width, height, filter))
+    }
+
+    /// Resizes every frame to fit within the given bounds, keeping the aspect ratio.
+    pub fn resize_to_fit(self, max_width: u32, max_height: u32, filter: FilterType) -> Self {
+        let (width, height) = self.dimensions();
+        let (width, height) = fit_dimensions(width, height, max_width, max_height);
+
+        self.resize(width, height, filter)
+    }
+
+    /// Applies a transformation to every frame in place.
+    pub fn map_frames(mut self, f: impl Fn(&mut RgbaImage)) -> Self {
+        match &mut self {
+            Self::Static(img) => f(img),
+            Self::Animated(frames) => frames.iter_mut().for_each(|frame| f(frame.buffer_mut())),
+        }
+
+        self
+    }
+
+    /// Replaces every pixel of every frame with the result of the given function.
+    pub fn map_pixels(self, f: impl Fn(Rgba<u8>) -> Rgba<u8>) -> Self {
+        self.map_frames(|img| img.pixels_mut().for_each(|pixel| *pixel = f(*pixel)))
+    }
+
+    /// Encodes the media, static images in the given format and animated ones as GIF.
+    pub fn encode(self, format: OutputFormat) -> Result<(Vec<u8>, OutputFormat)> {
+        match self {
+            Self::Static(img) => Ok((encode_image(&img, format)?, format)),
+            Self::Animated(frames) => {
+                let mut buffer = std::io::Cursor::new(vec![]);
+                GifEncoder::new(&mut buffer).encode_frames(frames)?;
+
+                Ok((buffer.into_inner(), OutputFormat::Gif))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(delay: u32) -> Frame {
+        Frame::from_parts(RgbaImage::new(2, 2), 0, 0, Delay::from_numer_denom_ms(delay, 1))
+    }
+
+    #[test]
+    fn fits_dimensions_keeping_aspect_ratio() {
+        assert_eq!(fit_dimensions(400, 200, 100, 100), (100, 50));
+        assert_eq!(fit_dimensions(10, 20, 100, 100), (50, 100));
+        assert_eq!(fit_dimensions(1000, 1, 10, 10), (10, 1));
+    }
+
+    #[test]
+    fn subsampling_keeps_total_duration() {
+        let frames = subsample((0..5).map(|_| frame(20)).collect(), 2);
+        let delays = frames.iter().map(|f| delay_ms(f.delay())).collect::<Vec<_>>();
+
+        assert_eq!(delays, [40.0, 40.0, 20.0]);
+    }
+
+    #[test]
+    fn limits_frames() {
+        let media = Media::Animated((0..10).map(|_| frame(20)).collect()).limit_frames(4);
+        assert_eq!(media.frame_count(), 4);
+
+        let short = Media::Animated(vec![frame(20), frame(20)]).limit_frames(4);
+        assert_eq!(short.frame_count(), 2);
+    }
+
+    #[test]
+    fn animates_static_images() {
+        match Media::Static(RgbaImage::new(2, 2)).into_animation(4, 60) {
+            Media::Animated(frames) => {
+                assert_eq!(frames.len(), 4);
+                assert_eq!(delay_ms(frames[3].delay()), 60.0);
+            },
+            Media::Static(_) => panic!("expected an animation"),
+        }
+
+        let animated = Media::Animated(vec![frame(20), frame(20)]).into_animation(4, 60);
+        assert_eq!(animated.frame_count(), 2);
+    }
+
+    #[test]
+    fn round_trips_animated_media() {
+        let media = Media::Animated(vec![frame(50), frame(50), frame(50)]);
+        let (encoded, format) = media.encode(OutputFormat::Png).unwrap();
+        assert_eq!(format, OutputFormat::Gif);
+        assert!(is_gif(&encoded));
+
+        match Media::decode(&encoded, &Quality::full()).unwrap() {
+            Media::Animated(frames) => assert_eq!(frames.len(), 3),
+            Media::Static(_) => panic!("expected an animated result"),
+        }
+    }
+
+    #[test]
+    fn decoding_applies_quality_limits() {
+        let img = RgbaImage::new(64, 32);
+        let encoded = encode_image(&img, OutputFormat::Png).unwrap();
+        let quality = Quality { degraded: true, max_dimension: Some(16), frame_step: 1 };
+
+        assert_eq!(Media::decode(&encoded, &quality).unwrap().dimensions(), (16, 8));
+    }
+}
@@ -0,0 +1,260 @@
+use image::Rgba;
+
+use std::collections::HashMap;
+
+use crate::{parse_color, Error, Result};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ParamKind {
+    Int { min: i64, max: i64, default: i64 },
+    Float { min: f64, max: f64, default: f64 },
+    /// Off unless given. May be given without a value to switch it on.
+    Bool,
+    /// One of a fixed set of names, matched case-insensitively.
+    Choice { options: &'static [&'static str], default: &'static str },
+    /// A hex code or color name, see [`parse_color`].
+    Color { default: [u8; 4] },
+    /// Between `min` and `max` colors separated by commas.
+    Colors { min: usize, max: usize, default: &'static [[u8; 4]] },
+}
+
+/// A parameter a filter accepts.
+#[derive(Clone, Copy, Debug)]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: ParamKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Value {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Choice(&'static str),
+    Color(Rgba<u8>),
+    Colors(Vec<Rgba<u8>>),
+}
+
+impl ParamSpec {
+    /// Whether `raw` is a value of this parameter's type, e.g. to tell a leading positional value
+    /// apart from what follows it. Values out of range count, so that parsing them reports the range
+    /// instead of them being mistaken for what follows.
+    pub fn accepts(&self, raw: &str) -> bool {
+        !matches!(self.parse_value(Some(raw)), Err(Error::InvalidParam { .. }))
+    }
+
+    fn default_value(&self) -> Value {
+        match self.kind {
+            ParamKind::Int { default, .. } => Value::Int(default),
+            ParamKind::Float { default, .. } => Value::Float(default),
+            ParamKind::Bool => Value::Bool(false),
+            ParamKind::Choice { default, .. } => Value::Choice(default),
+            ParamKind::Color { default } => Value::Color(Rgba(default)),
+            ParamKind::Colors { default, .. } => Value::Colors(default.iter().copied().map(Rgba).collect()),
+        }
+    }
+
+    fn parse_value(&self, value: Option<&str>) -> Result<Value> {
+        let raw = match (value, self.kind) {
+            (None, ParamKind::Bool) => return Ok(Value::Bool(true)),
+            (None, _) => return Err(Error::MissingValue(self.name)),
+            (Some(raw), _) => raw.trim(),
+        };
+        let invalid = || Error::InvalidParam { name: self.name, value: raw.to_string() };
+
+        match self.kind {
+            ParamKind::Int { min, max, .. } => {
+                let value = raw.parse::<i64>().map_err(|_| invalid())?;
+
+                if !(min..=max).contains(&value) {
+                    return Err(Error::OutOfRange { name: self.name, min: min as f64, max: max as f64, value: value as f64 });
+                }
+                Ok(Value::Int(value))
+            },
+            ParamKind::Float { min, max, .. } => {
+                let value = raw.parse::<f64>().map_err(|_| invalid())?;
+
+                // Also rejects NaN.
+                if !(min..=max).contains(&value) {
+                    return Err(Error::OutOfRange { name: self.name, min, max, value });
+                }
+                Ok(Value::Float(value))
+            },
+            ParamKind::Bool => match raw.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Ok(Value::Bool(true)),
+                "false" | "no" | "off" | "0" => Ok(Value::Bool(false)),
+                _ => Err(invalid()),
+            },
+            ParamKind::Choice { options, .. } => options
+                .iter()
+                .find(|option| option.eq_ignore_ascii_case(raw))
+                .map(|option| Value::Choice(option))
+                .ok_or_else(invalid),
+            ParamKind::Color { .. } => parse_color(raw).map(Value::Color).ok_or_else(invalid),
+            ParamKind::Colors { min, max, .. } => {
+                let colors = raw.split(',').map(parse_color).collect::<Option<Vec<_>>>().ok_or_else(invalid)?;
+
+                if !(min..=max).contains(&colors.len()) {
+                    return Err(Error::OutOfRange { name: self.name, min: min as f64, max: max as f64, value: colors.len() as f64 });
+                }
+                Ok(Value::Colors(colors))
+            },
+        }
+    }
+}
+
+/// Validated parameter values for one run of a filter, with defaults filled in.
+#[derive(Clone, Debug, Default)]
+pub struct Params {
+    values: HashMap<&'static str, Value>,
+}
+
+impl Params {
+    /// Validates `(name, value)` pairs against `specs`. Names are matched case-insensitively.
+    pub fn parse<'a>(specs: &'static [ParamSpec], pairs: impl IntoIterator<Item = (&'a str, Option<&'a str>)>) -> Result<Self> {
+        let mut values = specs.iter().map(|spec| (spec.name, spec.default_value())).collect::<HashMap<_, _>>();
+
+        for (name, value) in pairs {
+            let spec = specs
+                .iter()
+                .find(|spec| spec.name.eq_ignore_ascii_case(name))
+                .ok_or_else(|| Error::UnknownParam(name.to_string()))?;
+
+            values.insert(spec.name, spec.parse_value(value)?);
+        }
+
+        Ok(Self { values })
+    }
+
+    fn value(&self, name: &str) -> &Value {
+        self.values.get(name).unwrap_or_else(|| panic!("parameter `{}` was not declared", name))
+    }
+
+    pub fn int(&self, name: &str) -> i64 {
+        match *self.value(name) {
+            Value::Int(value) => value,
+            Value::Float(value) => value.round() as i64,
+            Value::Bool(value) => value as i64,
+            Value::Choice(_) | Value::Color(_) | Value::Colors(_) => panic!("parameter `{}` is not a number", name),
+        }
+    }
+
+    pub fn float(&self, name: &str) -> f64 {
+        match *self.value(name) {
+            Value::Int(value) => value as f64,
+            Value::Float(value) => value,
+            Value::Bool(value) => value as u8 as f64,
+            Value::Choice(_) | Value::Color(_) | Value::Colors(_) => panic!("parameter `{}` is not a number", name),
+        }
+    }
+
+    pub fn bool(&self, name: &str) -> bool {
+        match *self.value(name) {
+            Value::Int(value) => value != 0,
+            Value::Float(value) => value != 0.0,
+            Value::Bool(value) => value,
+            Value::Choice(_) | Value::Color(_) | Value::Colors(_) => panic!("parameter `{}` is not a switch", name),
+        }
+    }
+
+    /// The option chosen, spelled as declared.
+    pub fn choice(&self, name: &str) -> &'static str {
+        match *self.value(name) {
+            Value::Choice(value) => value,
+            _ => panic!("parameter `{}` is not a choice", name),
+        }
+    }
+
+    pub fn color(&self, name: &str) -> Rgba<u8> {
+        match *self.value(name) {
+            Value::Color(value) => value,
+            _ => panic!("parameter `{}` is not a color", name),
+        }
+    }
+
+    pub fn colors(&self, name: &str) -> &[Rgba<u8>] {
+        match self.value(name) {
+            Value::Colors(value) => value,
+            _ => panic!("parameter `{}` is not a list of colors", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPECS: &[ParamSpec] = &[
+        ParamSpec { name: "radius", description: "", kind: ParamKind::Int { min: 1, max: 10, default: 3 } },
+        ParamSpec { name: "strength", description: "", kind: ParamKind::Float { min: 0.0, max: 1.0, default: 0.5 } },
+        ParamSpec { name: "loop", description: "", kind: ParamKind::Bool },
+        ParamSpec { name: "mode", description: "", kind: ParamKind::Choice { options: &["fast", "Best"], default: "fast" } },
+        ParamSpec { name: "tint", description: "", kind: ParamKind::Color { default: [0, 0, 0, 255] } },
+        ParamSpec { name: "stops", description: "", kind: ParamKind::Colors { min: 2, max: 3, default: &[[0, 0, 0, 255], [255, 255, 255, 255]] } },
+    ];
+
+    #[test]
+    fn fills_in_defaults() {
+        let params = Params::parse(SPECS, std::iter::empty()).unwrap();
+
+        assert_eq!(params.int("radius"), 3);
+        assert_eq!(params.float("strength"), 0.5);
+        assert!(!params.bool("loop"));
+        assert_eq!(params.choice("mode"), "fast");
+        assert_eq!(params.color("tint"), Rgba([0, 0, 0, 255]));
+        assert_eq!(params.colors("stops"), &[Rgba([0, 0, 0, 255]), Rgba([255, 255, 255, 255])]);
+    }
+
+    #[test]
+    fn parses_given_values() {
+        let params = Params::parse(
+            SPECS,
+            [
+                ("Radius", Some("7")),
+                ("strength", Some("0.25")),
+                ("loop", None),
+                ("mode", Some("best")),
+                ("tint", Some("#f00")),
+                ("stops", Some("red, blue,#0f0")),
+            ],
+        ).unwrap();
+
+        assert_eq!(params.int("radius"), 7);
+        assert_eq!(params.float("strength"), 0.25);
+        assert!(params.bool("loop"));
+        assert_eq!(params.choice("mode"), "Best");
+        assert_eq!(params.color("tint"), Rgba([255, 0, 0, 255]));
+        assert_eq!(params.colors("stops"), &[Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255]), Rgba([0, 255, 0, 255])]);
+    }
+
+    #[test]
+    fn rejects_bad_values() {
+        assert!(matches!(Params::parse(SPECS, [("radius", Some("11"))]), Err(Error::OutOfRange { .. })));
+        assert!(matches!(Params::parse(SPECS, [("strength", Some("NaN"))]), Err(Error::OutOfRange { .. })));
+        assert!(matches!(Params::parse(SPECS, [("radius", Some("big"))]), Err(Error::InvalidParam { .. })));
+        assert!(matches!(Params::parse(SPECS, [("radius", None)]), Err(Error::MissingValue("radius"))));
+        assert!(matches!(Params::parse(SPECS, [("size", Some("1"))]), Err(Error::UnknownParam(_))));
+        assert!(matches!(Params::parse(SPECS, [("mode", Some("slow"))]), Err(Error::InvalidParam { .. })));
+        assert!(matches!(Params::parse(SPECS, [("tint", Some("#12"))]), Err(Error::InvalidParam { .. })));
+        assert!(matches!(Params::parse(SPECS, [("stops", Some("red,nope"))]), Err(Error::InvalidParam { .. })));
+        assert!(matches!(Params::parse(SPECS, [("stops", Some("red"))]), Err(Error::OutOfRange { .. })));
+    }
+
+    #[test]
+    fn tells_whether_values_are_accepted() {
+        assert!(SPECS[0].accepts("10"));
+        assert!(!SPECS[0].accepts("@user"));
+        assert!(!SPECS[0].accepts("2.5"));
+        assert!(SPECS[2].accepts("off"));
+    }
+
+    #[test]
+    fn takes_values_out_of_range() {
+        // As in `blur 100 @user`, where 100 is the radius rather than the image, and too large.
+        assert!(SPECS[0].accepts("11"));
+        assert!(SPECS[1].accepts("NaN"));
+        assert!(SPECS[5].accepts("red"));
+        assert!(matches!(Params::parse(SPECS, [("radius", Some("11"))]), Err(Error::OutOfRange { .. })));
+    }
+}
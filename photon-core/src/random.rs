@@ -0,0 +1,82 @@
+//! A small seedable random number generator. Effects seed it themselves so the same input always gives
+//! the same output, which keeps them testable.
+
+/// xorshift64*, plenty for picking pixels and offsets.
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Spread similar seeds (like consecutive frame indices) apart, zero would get stuck.
+        let mut state = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        state = (state ^ (state >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        state = (state ^ (state >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+        Self((state ^ (state >> 31)).max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A float from 0 (inclusive) to 1 (exclusive).
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// An integer from `min` to `max`, both inclusive.
+    pub fn range(&mut self, min: i64, max: i64) -> i64 {
+        if max <= min {
+            return min;
+        }
+        min + (self.next_u64() % (max - min + 1) as u64) as i64
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+
+    /// A normally distributed value with a mean of 0 and a standard deviation of 1.
+    pub fn gaussian(&mut self) -> f32 {
+        // Box-Muller, the first sample can't be 0 so the log stays finite.
+        let u = 1.0 - self.next_f32();
+        let v = self.next_f32();
+        (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_deterministic() {
+        let (mut a, mut b) = (Rng::new(7), Rng::new(7));
+        assert!((0..100).all(|_| a.next_u64() == b.next_u64()));
+        assert_ne!(Rng::new(0).next_u64(), Rng::new(1).next_u64());
+    }
+
+    #[test]
+    fn stays_in_range() {
+        let mut rng = Rng::new(42);
+
+        for _ in 0..1000 {
+            assert!((0.0..1.0).contains(&rng.next_f32()));
+            assert!((-3..=3).contains(&rng.range(-3, 3)));
+        }
+        assert_eq!(rng.range(5, 5), 5);
+    }
+
+    #[test]
+    fn gaussian_is_centered() {
+        let mut rng = Rng::new(3);
+        let samples = (0..10_000).map(|_| rng.gaussian()).collect::<Vec<_>>();
+
+        let mean = samples.iter().sum::<f32>() / samples.len() as f32;
+        let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / samples.len() as f32;
+        assert!(mean.abs() < 0.05 && (variance - 1.0).abs() < 0.1, "mean {}, variance {}", mean, variance);
+    }
+}
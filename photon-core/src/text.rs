@@ -0,0 +1,544 @@
+//! Text layout and rendering: wrapping, alignment, outlines, falling back through a list of fonts
+//! for missing glyphs, and drawing emoji as images supplied by the caller.
+
+use image::imageops::FilterType;
+use image::{Rgba, RgbaImage};
+
+use rusttype::point;
+pub use rusttype::{Font, Scale};
+
+use std::collections::HashMap;
+
+use crate::geometry::{blend_over, overlay};
+
+/// Emoji images keyed by their sequence, as found by [`emojis`].
+pub type Emojis = HashMap<String, RgbaImage>;
+
+/// DejaVu Sans Bold, for the filters that draw text of their own. Its license is next to it.
+const BOLD_FONT: &[u8] = include_bytes!("../assets/fonts/DejaVuSans-Bold.ttf");
+
+/// The bundled bold font. Parsing it only reads the font's tables, so it's cheap enough to do whenever it's needed.
+pub fn bold_font() -> Font<'static> {
+    Font::try_from_bytes(BOLD_FONT).expect("Bundled bold font is invalid")
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+impl Default for Align {
+    fn default() -> Self {
+        Self::Left
+    }
+}
+
+/// An outline drawn around every glyph.
+#[derive(Clone, Copy, Debug)]
+pub struct Stroke {
+    pub width: u32,
+    pub color: Rgba<u8>,
+}
+
+#[derive(Clone, Copy)]
+pub struct TextStyle<'a> {
+    /// Glyphs missing from the first font are taken from the first of the rest that has them.
+    pub fonts: &'a [&'a Font<'static>],
+    /// The height of a line in pixels, not counting the font's line gap.
+    pub size: f32,
+    pub color: Rgba<u8>,
+    pub align: Align,
+    pub stroke: Option<Stroke>,
+    /// Multiplies the distance between lines.
+    pub line_spacing: f32,
+}
+
+impl<'a> TextStyle<'a> {
+    pub fn new(fonts: &'a [&'a Font<'static>], size: f32, color: Rgba<u8>) -> Self {
+        assert!(!fonts.is_empty(), "a text style needs at least one font");
+
+        Self { fonts, size, color, align: Align::Left, stroke: None, line_spacing: 1.0 }
+    }
+
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    pub fn stroke(mut self, width: u32, color: Rgba<u8>) -> Self {
+        self.stroke = (width > 0).then(|| Stroke { width, color });
+        self
+    }
+
+    pub fn line_spacing(mut self, spacing: f32) -> Self {
+        self.line_spacing = spacing;
+        self
+    }
+
+    fn scale(&self) -> Scale {
+        Scale::uniform(self.size)
+    }
+
+    fn primary(&self) -> &'a Font<'static> {
+        self.fonts[0]
+    }
+
+    fn ascent(&self) -> f32 {
+        self.primary().v_metrics(self.scale()).ascent
+    }
+
+    pub fn line_height(&self) -> f32 {
+        let metrics = self.primary().v_metrics(self.scale());
+        (metrics.ascent - metrics.descent + metrics.line_gap) * self.line_spacing
+    }
+
+    fn font_for(&self, c: char) -> &'a Font<'static> {
+        self.fonts
+            .iter()
+            .copied()
+            .find(|font| font.glyph(c).id().0 != 0)
+            .unwrap_or_else(|| self.primary())
+    }
+}
+
+fn is_regional_indicator(c: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&c)
+}
+
+/// Pictographs that are emoji by default.
+fn is_pictograph(c: char) -> bool {
+    matches!(c, '\u{1F000}'..='\u{1FAFF}') && !is_regional_indicator(c) && !is_skin_tone(c)
+}
+
+/// Symbols that are only emoji when followed by the emoji variation selector.
+fn is_text_symbol(c: char) -> bool {
+    matches!(c, '\u{00A9}' | '\u{00AE}' | '\u{203C}' | '\u{2049}' | '\u{2122}' | '\u{2139}' | '\u{2194}'..='\u{21AA}'
+        | '\u{231A}'..='\u{23FF}' | '\u{24C2}' | '\u{25AA}'..='\u{25FE}' | '\u{2600}'..='\u{27BF}'
+        | '\u{2934}' | '\u{2935}' | '\u{2B05}'..='\u{2B55}' | '\u{3030}' | '\u{303D}' | '\u{3297}' | '\u{3299}')
+}
+
+fn is_skin_tone(c: char) -> bool {
+    ('\u{1F3FB}'..='\u{1F3FF}').contains(&c)
+}
+
+const VARIATION_SELECTOR: char = '\u{FE0F}';
+const ZWJ: char = '\u{200D}';
+const KEYCAP: char = '\u{20E3}';
+
+/// The length in bytes of the emoji sequence `text` starts with, if any.
+pub fn emoji_len(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices().peekable();
+    let (_, first) = chars.next()?;
+    let next = chars.peek().map(|(_, c)| *c);
+
+    let mut end = match first {
+        c if is_regional_indicator(c) => match next {
+            Some(flag) if is_regional_indicator(flag) => return Some(first.len_utf8() + flag.len_utf8()),
+            _ => return None,
+        },
+        '0'..='9' | '#' | '*' => {
+            let rest = &text[1..];
+            let rest = rest.strip_prefix(VARIATION_SELECTOR).unwrap_or(rest);
+
+            return rest.starts_with(KEYCAP).then(|| text.len() - rest.len() + KEYCAP.len_utf8());
+        },
+        c if is_pictograph(c) => c.len_utf8(),
+        c if is_text_symbol(c) && next == Some(VARIATION_SELECTOR) => c.len_utf8(),
+        _ => return None,
+    };
+
+    // Modifiers, tags (subdivision flags) and zero width joined pictographs.
+    while let Some(&(index, c)) = chars.peek() {
+        if index < end {
+            chars.next();
+            continue;
+        }
+
+        match c {
+            VARIATION_SELECTOR | '\u{E0020}'..='\u{E007F}' => end += c.len_utf8(),
+            c if is_skin_tone(c) => end += c.len_utf8(),
+            ZWJ => match text[index + ZWJ.len_utf8()..].chars().next() {
+                Some(joined) if is_pictograph(joined) || is_text_symbol(joined) => end += ZWJ.len_utf8() + joined.len_utf8(),
+                _ => break,
+            },
+            _ => break,
+        }
+    }
+
+    Some(end)
+}
+
+/// Every distinct emoji sequence in the text.
+pub fn emojis(text: &str) -> Vec<&str> {
+    let mut found = Vec::<&str>::new();
+    let mut index = 0;
+
+    while index < text.len() {
+        match emoji_len(&text[index..]) {
+            Some(len) => {
+                let sequence = &text[index..index + len];
+                if !found.contains(&sequence) {
+                    found.push(sequence);
+                }
+                index += len;
+            },
+            None => index += text[index..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+
+    found
+}
+
+/// The codepoints of an emoji as dash separated hex, the way Twemoji and most emoji sets name their files.
+pub fn emoji_code(sequence: &str) -> String {
+    let keep_selector = sequence.contains(ZWJ);
+
+    sequence
+        .chars()
+        .filter(|c| keep_selector || *c != VARIATION_SELECTOR)
+        .map(|c| format!("{:x}", c as u32))
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// A run of text set in one font, or a single emoji.
+enum Segment<'a> {
+    Text(&'a Font<'static>, String),
+    Emoji(String),
+}
+
+fn segments<'a>(style: &TextStyle<'a>, text: &str) -> Vec<Segment<'a>> {
+    let mut segments = Vec::new();
+    let mut index = 0;
+
+    while index < text.len() {
+        if let Some(len) = emoji_len(&text[index..]) {
+            segments.push(Segment::Emoji(text[index..index + len].to_string()));
+            index += len;
+            continue;
+        }
+
+        let c = text[index..].chars().next().expect("index is on a char boundary");
+        let font = style.font_for(c);
+
+        match segments.last_mut() {
+            Some(Segment::Text(last, run)) if std::ptr::eq(*last, font) => run.push(c),
+            _ => segments.push(Segment::Text(font, c.to_string())),
+        }
+        index += c.len_utf8();
+    }
+
+    segments
+}
+
+fn run_width(font: &Font, scale: Scale, text: &str) -> f32 {
+    font.layout(text, scale, point(0.0, 0.0))
+        .last()
+        .map_or(0.0, |glyph| glyph.position().x + glyph.unpositioned().h_metrics().advance_width)
+}
+
+/// The width in pixels the text takes up when rendered on a single line.
+pub fn measure(style: &TextStyle, text: &str) -> f32 {
+    segments(style, text)
+        .iter()
+        .map(|segment| match segment {
+            Segment::Text(font, run) => run_width(font, style.scale(), run),
+            Segment::Emoji(_) => style.size,
+        })
+        .sum()
+}
+
+/// Splits a word too wide for a line into pieces that fit, never splitting an emoji.
+fn break_word(style: &TextStyle, word: &str, max_width: f32) -> Vec<String> {
+    let mut pieces = vec![String::new()];
+    let mut index = 0;
+
+    while index < word.len() {
+        let len = emoji_len(&word[index..]).unwrap_or_else(|| word[index..].chars().next().map_or(1, char::len_utf8));
+        let unit = &word[index..index + len];
+        let current = pieces.last_mut().expect("pieces is never empty");
+
+        if !current.is_empty() && measure(style, &format!("{}{}", current, unit)) > max_width {
+            pieces.push(unit.to_string());
+        } else {
+            current.push_str(unit);
+        }
+        index += len;
+    }
+
+    pieces
+}
+
+/// Greedily wraps the text on whitespace and newlines so every line fits within `max_width`,
+/// returning at most `max_lines` lines. An ellipsis is appended if text had to be cut off.
+pub fn wrap(style: &TextStyle, text: &str, max_width: f32, max_lines: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for paragraph in text.lines() {
+        let mut current = String::new();
+
+        for word in paragraph.split_whitespace() {
+            let candidate = if current.is_empty() { word.to_string() } else { format!("{} {}", current, word) };
+
+            if measure(style, &candidate) <= max_width {
+                current = candidate;
+                continue;
+            }
+
+            if !current.is_empty() {
+                lines.push(std::mem::take(&mut current));
+            }
+
+            let mut pieces = break_word(style, word, max_width);
+            current = pieces.pop().unwrap_or_default();
+            lines.extend(pieces);
+        }
+
+        lines.push(current);
+    }
+
+    if lines.len() > max_lines {
+        lines.truncate(max_lines);
+
+        if let Some(last) = lines.last_mut() {
+            while !last.is_empty() && measure(style, &format!("{}…", last)) > max_width {
+                last.pop();
+            }
+
+            last.truncate(last.trim_end().len());
+            last.push('…');
+        }
+    }
+
+    lines
+}
+
+/// Alpha-blends `color` with the given coverage over the pixel at `(x, y)`, ignoring out of bounds pixels.
+pub fn blend_pixel(img: &mut RgbaImage, x: i32, y: i32, color: Rgba<u8>, coverage: f32) {
+    if x < 0 || y < 0 || x >= img.width() as i32 || y >= img.height() as i32 {
+        return;
+    }
+
+    let alpha = (coverage.clamp(0.0, 1.0) * color[3] as f32).round() as u8;
+    let pixel = img.get_pixel_mut(x as u32, y as u32);
+
+    *pixel = blend_over(*pixel, Rgba([color[0], color[1], color[2], alpha]));
+}
+
+/// Glyph coverage over a rectangle of the target image.
+struct Mask {
+    x: i32,
+    y: i32,
+    width: usize,
+    height: usize,
+    coverage: Vec<f32>,
+}
+
+impl Mask {
+    fn new(x: i32, y: i32, width: usize, height: usize) -> Self {
+        Self { x, y, width, height, coverage: vec![0.0; width * height] }
+    }
+
+    fn add(&mut self, x: i32, y: i32, coverage: f32) {
+        let (mx, my) = (x - self.x, y - self.y);
+
+        if mx >= 0 && my >= 0 && (mx as usize) < self.width && (my as usize) < self.height {
+            let value = &mut self.coverage[my as usize * self.width + mx as usize];
+            *value = value.max(coverage);
+        }
+    }
+
+    /// Grows the covered area by `radius` pixels in every direction.
+    fn dilate(&self, radius: u32) -> Self {
+        let radius = radius as i32;
+        let offsets = (-radius..=radius)
+            .flat_map(|dy| (-radius..=radius).map(move |dx| (dx, dy)))
+            .filter(|(dx, dy)| dx * dx + dy * dy <= radius * radius)
+            .collect::<Vec<_>>();
+
+        let mut dilated = Self::new(self.x, self.y, self.width, self.height);
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                let coverage = offsets
+                    .iter()
+                    .filter_map(|(dx, dy)| {
+                        let (sx, sy) = (x + dx, y + dy);
+                        let inside = sx >= 0 && sy >= 0 && (sx as usize) < self.width && (sy as usize) < self.height;
+                        inside.then(|| self.coverage[sy as usize * self.width + sx as usize])
+                    })
+                    .fold(0.0, f32::max);
+
+                dilated.coverage[y as usize * self.width + x as usize] = coverage;
+            }
+        }
+
+        dilated
+    }
+
+    fn blend(&self, img: &mut RgbaImage, color: Rgba<u8>) {
+        for (index, coverage) in self.coverage.iter().enumerate() {
+            if *coverage > 0.0 {
+                let (x, y) = ((index % self.width) as i32, (index / self.width) as i32);
+                blend_pixel(img, self.x + x, self.y + y, color, *coverage);
+            }
+        }
+    }
+}
+
+/// Wrapped text ready to be drawn.
+pub struct TextBlock<'a> {
+    style: TextStyle<'a>,
+    lines: Vec<String>,
+    max_width: f32,
+}
+
+impl<'a> TextBlock<'a> {
+    pub fn new(style: TextStyle<'a>, text: &str, max_width: f32, max_lines: usize) -> Self {
+        Self { style, lines: wrap(&style, text, max_width, max_lines), max_width }
+    }
+
+    /// Lays out the text in the largest size up to the style's that fits in `width`x`height`,
+    /// going no smaller than `min_size` (and cutting the text off if it still doesn't fit then).
+    pub fn fit(style: TextStyle<'a>, text: &str, width: f32, height: f32, min_size: f32) -> Self {
+        let mut style = style;
+
+        loop {
+            let block = Self::new(style, text, width, usize::MAX);
+            if block.height() <= height || style.size <= min_size {
+                let max_lines = ((height / style.line_height()).floor() as usize).max(1);
+                return Self::new(style, text, width, max_lines);
+            }
+
+            style.size = (style.size * 0.9).max(min_size);
+        }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    pub fn style(&self) -> &TextStyle<'a> {
+        &self.style
+    }
+
+    /// The width of the longest line.
+    pub fn width(&self) -> f32 {
+        self.lines.iter().map(|line| measure(&self.style, line)).fold(0.0, f32::max)
+    }
+
+    pub fn height(&self) -> f32 {
+        self.lines.len() as f32 * self.style.line_height()
+    }
+
+    /// Draws the text with its top-left corner at `(x, y)`, aligned within the width it was wrapped to.
+    /// Emoji missing from `emojis` are drawn with the fonts instead.
+    pub fn draw(&self, img: &mut RgbaImage, x: i32, y: i32, emojis: &Emojis) {
+        let style = &self.style;
+        let scale = style.scale();
+        let ascent = style.ascent();
+        let margin = style.stroke.map_or(0, |stroke| stroke.width) as i32 + style.size.ceil() as i32;
+
+        let mut mask = Mask::new(
+            x - margin,
+            y - margin,
+            (self.max_width.ceil() as i32 + margin * 2).max(0) as usize,
+            (self.height().ceil() as i32 + margin * 2).max(0) as usize,
+        );
+        let mut images = Vec::new();
+
+        for (index, line) in self.lines.iter().enumerate() {
+            let top = y as f32 + index as f32 * style.line_height();
+            let mut cursor = x as f32 + match style.align {
+                Align::Left => 0.0,
+                Align::Center => (self.max_width - measure(style, line)) / 2.0,
+                Align::Right => self.max_width - measure(style, line),
+            };
+
+            for segment in segments(style, line) {
+                let (font, run) = match segment {
+                    Segment::Emoji(sequence) => match emojis.get(&sequence) {
+                        Some(emoji) => {
+                            images.push((emoji, cursor, top));
+                            cursor += style.size;
+                            continue;
+                        },
+                        None => (style.font_for(sequence.chars().next().unwrap_or(' ')), sequence),
+                    },
+                    Segment::Text(font, run) => (font, run),
+                };
+
+                for glyph in font.layout(&run, scale, point(cursor, top + ascent)) {
+                    if let Some(bounds) = glyph.pixel_bounding_box() {
+                        glyph.draw(|gx, gy, coverage| mask.add(bounds.min.x + gx as i32, bounds.min.y + gy as i32, coverage));
+                    }
+                }
+                cursor += run_width(font, scale, &run);
+            }
+        }
+
+        if let Some(stroke) = style.stroke {
+            mask.dilate(stroke.width).blend(img, stroke.color);
+        }
+        mask.blend(img, style.color);
+
+        let size = style.size.round().max(1.0) as u32;
+        for (emoji, x, y) in images {
+            let emoji = image::imageops::resize(emoji, size, size, FilterType::Triangle);
+            overlay(img, &emoji, x.round() as i64, y.round() as i64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn font() -> Font<'static> {
+        Font::try_from_bytes(include_bytes!("../../assets/fonts/DejaVuSans.ttf")).unwrap()
+    }
+
+    #[test]
+    fn wraps_within_width() {
+        let font = font();
+        let fonts = [&font];
+        let style = TextStyle::new(&fonts, 20.0, Rgba([0, 0, 0, 255]));
+
+        let lines = wrap(&style, "the quick brown fox jumps over the lazy dog\nsupercalifragilistic", 100.0, usize::MAX);
+        assert!(lines.len() > 3);
+        assert!(lines.iter().all(|line| measure(&style, line) <= 100.0));
+
+        let cut = wrap(&style, "the quick brown fox jumps over the lazy dog", 100.0, 2);
+        assert_eq!(cut.len(), 2);
+        assert!(cut[1].ends_with('…'));
+    }
+
+    #[test]
+    fn fitting_shrinks_text() {
+        let font = font();
+        let fonts = [&font];
+        let style = TextStyle::new(&fonts, 80.0, Rgba([0, 0, 0, 255]));
+
+        let block = TextBlock::fit(style, "a caption that is far too long for its box", 200.0, 60.0, 6.0);
+        assert!(block.style().size < 80.0);
+        assert!(block.height() <= 60.0);
+        assert!(block.width() <= 200.0);
+    }
+
+    #[test]
+    fn finds_emoji_sequences() {
+        assert_eq!(emojis("hi 😀 there 😀"), ["😀"]);
+        assert_eq!(emojis("👍🏽 and 🇳🇱"), ["👍🏽", "🇳🇱"]);
+        assert_eq!(emojis("👨‍👩‍👧 #️⃣ 1"), ["👨‍👩‍👧", "#️⃣"]);
+        // Text presentation unless asked otherwise.
+        assert_eq!(emojis("☀ ☀️"), ["☀️"]);
+    }
+
+    #[test]
+    fn names_emoji_by_codepoints() {
+        assert_eq!(emoji_code("😀"), "1f600");
+        assert_eq!(emoji_code("❤️"), "2764");
+        assert_eq!(emoji_code("👁️‍🗨️"), "1f441-fe0f-200d-1f5e8-fe0f");
+    }
+}
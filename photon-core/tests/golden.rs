@@ -0,0 +1,220 @@
+//! Runs every registered filter with its default parameters over each fixture in `tests/fixtures` and
+//! compares the output to its golden image in `tests/golden`, allowing small perceptual differences so
+//! e.g. a different resampling implementation doesn't fail everything.
+//!
+//! After an intended change to a filter's output, regenerate its goldens with
+//! `cargo test -p photon-core --test golden -- --bless [name]` (or `PHOTON_BLESS=1`) and review them in the diff.
+//! Goldens that don't exist yet count as failures, so every filter's output is committed and reviewed.
+//!
+//! Animated outputs are stored as their frames side by side. When an output doesn't match, it is written
+//! to the target directory along with an image highlighting the differing pixels.
+
+use image::{Rgba, RgbaImage};
+
+use photon_core::{filters, Error, Filter, Media, Params, Quality};
+
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+const GOLDEN: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden");
+const FAILURES: &str = concat!(env!("CARGO_TARGET_TMPDIR"), "/golden");
+
+/// How different two pixels may look (0 to 1, as in pixelmatch) before they count as differing.
+const PIXEL_THRESHOLD: f64 = 0.1;
+/// The fraction of pixels that may differ.
+const MAX_DIFFERING: f64 = 0.002;
+
+/// The largest YIQ distance between two colors, used to bring distances into 0 to 1.
+const MAX_YIQ_DELTA: f64 = 35215.0;
+
+enum Outcome {
+    Matched,
+    Recorded,
+    Failed(String),
+}
+
+struct Options {
+    bless: bool,
+    filter: Option<String>,
+}
+
+fn options() -> Options {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+
+    Options {
+        bless: args.iter().any(|arg| arg == "--bless")
+            || std::env::var("PHOTON_BLESS").map_or(false, |value| !value.is_empty() && value != "0"),
+        // Cargo passes libtest's flags along, so only take a plain argument as the name filter.
+        filter: args.into_iter().find(|arg| !arg.starts_with('-')),
+    }
+}
+
+fn fixtures() -> Vec<PathBuf> {
+    let mut fixtures = std::fs::read_dir(FIXTURES)
+        .expect("Could not read the fixtures directory")
+        .map(|entry| entry.expect("Could not read the fixtures directory").path())
+        .filter(|path| path.is_file())
+        .collect::<Vec<_>>();
+
+    fixtures.sort();
+    fixtures
+}
+
+/// The output as a single image, animated ones with their frames next to each other.
+fn sheet(media: Media) -> RgbaImage {
+    match media {
+        Media::Static(img) => img,
+        Media::Animated(frames) => {
+            let (width, height) = frames[0].buffer().dimensions();
+            let mut sheet = RgbaImage::new(width * frames.len() as u32, height);
+
+            for (index, frame) in frames.iter().enumerate() {
+                image::imageops::replace(&mut sheet, frame.buffer(), index as u32 * width, 0);
+            }
+            sheet
+        },
+    }
+}
+
+/// How different two pixels look, by their distance in YIQ space over both a white and a black
+/// background so differences in transparency count too.
+fn pixel_delta(a: Rgba<u8>, b: Rgba<u8>) -> f64 {
+    let over = |pixel: Rgba<u8>, background: f64| {
+        let alpha = pixel[3] as f64 / 255.0;
+        [0, 1, 2].map(|c| background + (pixel[c] as f64 - background) * alpha)
+    };
+    let yiq = |[r, g, b]: [f64; 3]| [
+        r * 0.29889531 + g * 0.58662247 + b * 0.11448223,
+        r * 0.59597799 - g * 0.27417610 - b * 0.32180189,
+        r * 0.21147017 - g * 0.52261711 + b * 0.31114694,
+    ];
+
+    [255.0, 0.0]
+        .iter()
+        .map(|&background| {
+            let (a, b) = (yiq(over(a, background)), yiq(over(b, background)));
+            let (y, i, q) = (a[0] - b[0], a[1] - b[1], a[2] - b[2]);
+            ((0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / MAX_YIQ_DELTA).sqrt()
+        })
+        .fold(0.0, f64::max)
+}
+
+/// Compares the images, returning why they don't match along with an image of the differing pixels.
+fn compare(expected: &RgbaImage, actual: &RgbaImage) -> Result<(), (String, Option<RgbaImage>)> {
+    if expected.dimensions() != actual.dimensions() {
+        return Err((format!("expected a {:?} image, got {:?}", expected.dimensions(), actual.dimensions()), None));
+    }
+
+    let mut diff = RgbaImage::new(actual.width(), actual.height());
+    let mut differing = 0;
+
+    for ((x, y, expected), actual) in expected.enumerate_pixels().zip(actual.pixels()) {
+        if pixel_delta(*expected, *actual) > PIXEL_THRESHOLD {
+            differing += 1;
+            diff.put_pixel(x, y, Rgba([255, 0, 0, 255]));
+        } else {
+            // A faded copy for context.
+            let gray = (expected[0] as u32 + expected[1] as u32 + expected[2] as u32) / 3;
+            diff.put_pixel(x, y, Rgba([gray as u8, gray as u8, gray as u8, 64]));
+        }
+    }
+
+    let fraction = differing as f64 / (actual.width() as f64 * actual.height() as f64).max(1.0);
+    match fraction <= MAX_DIFFERING {
+        true => Ok(()),
+        false => Err((format!("{} pixels ({:.2}%) differ", differing, fraction * 100.0), Some(diff))),
+    }
+}
+
+/// The filter's output on the fixture, `None` for filters that only take animations given a still one.
+fn run(filter: &Filter, fixture: &Path) -> Result<Option<RgbaImage>, String> {
+    let data = std::fs::read(fixture).map_err(|e| e.to_string())?;
+    let params = Params::parse(filter.params, std::iter::empty()).map_err(|e| e.to_string())?;
+    let media = Media::decode(&data, &Quality::full()).map_err(|e| e.to_string())?;
+
+    match filter.apply(media, &params) {
+        Ok(output) => Ok(Some(sheet(output))),
+        Err(Error::NotAnimated) => Ok(None),
+        Err(why) => Err(why.to_string()),
+    }
+}
+
+fn check(options: &Options, name: &str, actual: RgbaImage) -> Outcome {
+    let path = Path::new(GOLDEN).join(format!("{}.png", name));
+    let save = |path: &Path, img: &RgbaImage| -> Result<(), String> {
+        std::fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
+        img.save(path).map_err(|e| e.to_string())
+    };
+
+    if options.bless {
+        return match save(&path, &actual) {
+            Ok(()) => Outcome::Recorded,
+            Err(why) => Outcome::Failed(format!("could not write {}: {}", path.display(), why)),
+        };
+    }
+
+    if !path.exists() {
+        return Outcome::Failed(format!("{} has no golden yet", path.display()));
+    }
+    let expected = match image::open(&path) {
+        Ok(expected) => expected.into_rgba8(),
+        Err(why) => return Outcome::Failed(format!("could not read {}: {}", path.display(), why)),
+    };
+
+    match compare(&expected, &actual) {
+        Ok(()) => Outcome::Matched,
+        Err((why, diff)) => {
+            let failures = Path::new(FAILURES);
+            let _ = save(&failures.join(format!("{}.actual.png", name)), &actual);
+            if let Some(diff) = diff {
+                let _ = save(&failures.join(format!("{}.diff.png", name)), &diff);
+            }
+
+            Outcome::Failed(format!("{} (output written to {})", why, failures.display()))
+        },
+    }
+}
+
+fn main() {
+    let options = options();
+    let fixtures = fixtures();
+    let (mut matched, mut recorded, mut failed) = (0, Vec::new(), Vec::new());
+
+    for filter in filters::FILTERS {
+        for fixture in &fixtures {
+            let name = format!("{}-{}", filter.name, fixture.file_stem().unwrap().to_string_lossy());
+            if options.filter.as_ref().map_or(false, |filter| !name.contains(filter.as_str())) {
+                continue;
+            }
+
+            let output = panic::catch_unwind(AssertUnwindSafe(|| run(filter, fixture)))
+                .unwrap_or_else(|_| Err("panicked".to_string()));
+
+            let outcome = match output {
+                Ok(Some(actual)) => check(&options, &name, actual),
+                Ok(None) => continue,
+                Err(why) => Outcome::Failed(why),
+            };
+
+            match outcome {
+                Outcome::Matched => matched += 1,
+                Outcome::Recorded => recorded.push(name),
+                Outcome::Failed(why) => failed.push(format!("{}: {}", name, why)),
+            }
+        }
+    }
+
+    for name in &recorded {
+        println!("recorded {}", name);
+    }
+    for failure in &failed {
+        println!("FAILED {}", failure);
+    }
+    println!("\ngolden: {} matched, {} recorded, {} failed", matched, recorded.len(), failed.len());
+
+    if !failed.is_empty() {
+        println!("If the changes are intended or the goldens are missing, rerun with `--bless` and review the new goldens.");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,254 @@
+//! A REST API for running filters from websites and other bots.
+//!
+//! `POST /filters/<name>` takes a multipart body with the input as an `image` file field, an optional
+//! `format` for static outputs and the filter's parameters as further text fields. The response body is
+//! the encoded output. `GET /filters` lists the filters along with their parameters.
+
+use axum::body::Bytes;
+use axum::extract::{Extension, Multipart, Path};
+use axum::extract::multipart::Field;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{AddExtensionLayer, Json, Router};
+
+use futures_util::StreamExt;
+
+use photon_core::{filters, Media, OutputFormat, ParamKind, Params};
+
+use serde::Serialize;
+
+use serenity::framework::standard::CommandError;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::i18n::{self, Catalog, Text};
+use crate::jobs::JobQueue;
+use crate::quality::LoadMonitor;
+use crate::resolve_image::ImageResolver;
+use crate::tr;
+
+/// Parameter values are short, anything longer is someone poking at the API.
+const MAX_FIELD_LENGTH: usize = 256;
+const MAX_FIELDS: usize = 32;
+
+/// What handlers share with the bot. Work goes through the same job queue so the API can't starve commands.
+pub struct ApiState {
+    pub config: Arc<Config>,
+    pub jobs: Arc<JobQueue>,
+    pub monitor: Arc<LoadMonitor>,
+    /// Errors are reported in the bot's default language.
+    pub catalog: Arc<Catalog>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody {
+    error: String,
+}
+
+struct ApiError(StatusCode, String);
+
+impl ApiError {
+    fn new(status: StatusCode, catalog: &Catalog, text: Text) -> Self {
+        Self(status, catalog.format(&text))
+    }
+
+    fn from_command(catalog: &Catalog, why: CommandError) -> Self {
+        Self(StatusCode::BAD_REQUEST, i18n::format_error(catalog, why.as_ref()))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(ErrorBody { error: self.1 })).into_response()
+    }
+}
+
+#[derive(Serialize)]
+struct ParamInfo {
+    name: &'static str,
+    description: &'static str,
+    kind: &'static str,
+    min: Option<f64>,
+    max: Option<f64>,
+    default: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<&'static [&'static str]>,
+}
+
+#[derive(Serialize)]
+struct FilterInfo {
+    name: &'static str,
+    description: &'static str,
+    params: Vec<ParamInfo>,
+}
+
+/// Runs the API until the process exits.
+pub async fn serve(bind: SocketAddr, state: ApiState) {
+    let app = Router::new()
+        .route("/filters", get(list_filters))
+        .route("/filters/:name", post(apply_filter))
+        .layer(AddExtensionLayer::new(Arc::new(state)));
+
+    let server = match axum::Server::try_bind(&bind) {
+        Ok(server) => server,
+        Err(why) => return eprintln!("Could not bind the HTTP API to {}: {}", bind, why),
+    };
+
+    println!("HTTP API listening on {}", bind);
+    if let Err(why) = server.serve(app.into_make_service()).await {
+        eprintln!("HTTP API stopped: {}", why);
+    }
+}
+
+async fn list_filters() -> Json<Vec<FilterInfo>> {
+    Json(
+        filters::FILTERS
+            .iter()
+            .map(|filter| FilterInfo {
+                name: filter.name,
+                description: filter.description,
+                params: filter.params
+                    .iter()
+                    .map(|param| {
+                        let (kind, min, max, default, options) = match param.kind {
+                            ParamKind::Int { min, max, default } => ("int", Some(min as f64), Some(max as f64), Some(default.into()), None),
+                            ParamKind::Float { min, max, default } => ("float", Some(min), Some(max), Some(default.into()), None),
+                            ParamKind::Bool => ("bool", None, None, None, None),
+                            ParamKind::Choice { options, default } => ("choice", None, None, Some(default.into()), Some(options)),
+                            ParamKind::Color { default: [r, g, b, a] } => {
+                                ("color", None, None, Some(format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a).into()), None)
+                            },
+                            ParamKind::Colors { min, max, default } => {
+                                let default = default.iter().map(|[r, g, b, a]| format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)).collect::<Vec<_>>();
+                                ("colors", Some(min as f64), Some(max as f64), Some(default.into()), None)
+                            },
+                        };
+
+                        ParamInfo { name: param.name, description: param.description, kind, min, max, default, options }
+                    })
+                    .collect(),
+            })
+            .collect()
+    )
+}
+
+fn is_authorized(headers: &HeaderMap, tokens: &[String]) -> bool {
+    headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map_or(false, |token| !token.is_empty() && tokens.iter().any(|t| t == token))
+}
+
+/// Reads a field, giving up as soon as it grows past `limit` bytes.
+async fn read_field(field: &mut Field<'_>, limit: usize) -> Result<Option<Vec<u8>>, axum::extract::multipart::MultipartError> {
+    let mut data = Vec::new();
+
+    while let Some(chunk) = field.next().await {
+        let chunk: Bytes = chunk?;
+        if data.len() + chunk.len() > limit {
+            return Ok(None);
+        }
+
+        data.extend_from_slice(&chunk);
+    }
+
+    Ok(Some(data))
+}
+
+async fn apply_filter(
+    Path(name): Path<String>,
+    headers: HeaderMap,
+    Extension(state): Extension<Arc<ApiState>>,
+    multipart: Multipart,
+) -> Result<Response, ApiError> {
+    let catalog = &state.catalog;
+
+    if !is_authorized(&headers, &state.config.api.tokens) {
+        return Err(ApiError::new(StatusCode::UNAUTHORIZED, catalog, tr!("api-unauthorized")));
+    }
+
+    let filter = filters::find(&name)
+        .ok_or_else(|| ApiError::new(StatusCode::NOT_FOUND, catalog, tr!("filter-unknown", filter = &name)))?;
+
+    let (data, format, fields) = read_request(&state, multipart).await?;
+    let params = Params::parse(
+        filter.params,
+        fields.iter().map(|(name, value)| (name.as_str(), Some(value.as_str()).filter(|v| !v.is_empty()))),
+    ).map_err(|why| ApiError::from_command(catalog, why.into()))?;
+
+    let resolver = &state.config.resolver;
+    let (width, height) = image::io::Reader::new(std::io::Cursor::new(&data))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok())
+        .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, catalog, tr!("resolve-no-dimensions")))?;
+
+    if width as usize > resolver.max_width {
+        return Err(ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, catalog, tr!("resolve-too-wide", width = width, max = resolver.max_width)));
+    }
+    if height as usize > resolver.max_height {
+        return Err(ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, catalog, tr!("resolve-too-tall", height = height, max = resolver.max_height)));
+    }
+
+    let quality = state.monitor.quality();
+    let (encoded, format) = state.jobs.run(move || {
+        Ok(filter.apply(Media::decode(&data, &quality)?, &params)?.encode(format)?)
+    }).await.map_err(|why| ApiError::from_command(catalog, why))?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(format.mime_type()));
+    if quality.degraded {
+        headers.insert("x-photon-degraded", HeaderValue::from_static("1"));
+    }
+
+    Ok((headers, encoded).into_response())
+}
+
+/// Splits the body into the image, the output format and the remaining fields.
+async fn read_request(state: &ApiState, mut multipart: Multipart) -> Result<(Vec<u8>, OutputFormat, Vec<(String, String)>), ApiError> {
+    let catalog = &state.catalog;
+    let max_size = state.config.resolver.max_size;
+    let bad_multipart = |_| ApiError::new(StatusCode::BAD_REQUEST, catalog, tr!("api-bad-multipart"));
+
+    let mut image = None;
+    let mut format = OutputFormat::default();
+    let mut fields = Vec::new();
+
+    while let Some(mut field) = multipart.next_field().await.map_err(bad_multipart)? {
+        let name = field.name().unwrap_or_default().to_string();
+
+        if name == "image" {
+            let data = read_field(&mut field, max_size).await.map_err(bad_multipart)?.ok_or_else(|| ApiError::new(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                catalog,
+                tr!("api-image-too-big", max = ImageResolver::humanize_size(max_size as f64)),
+            ))?;
+
+            image = Some(data);
+            continue;
+        }
+
+        if fields.len() >= MAX_FIELDS {
+            return Err(ApiError::new(StatusCode::BAD_REQUEST, catalog, tr!("api-too-many-fields", max = MAX_FIELDS)));
+        }
+
+        let value = read_field(&mut field, MAX_FIELD_LENGTH)
+            .await
+            .map_err(bad_multipart)?
+            .and_then(|value| String::from_utf8(value).ok())
+            .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, catalog, tr!("api-bad-field", field = &name)))?;
+
+        if name == "format" {
+            format = value.parse().map_err(|why: photon_core::Error| ApiError::from_command(catalog, why.into()))?;
+        } else {
+            fields.push((name, value));
+        }
+    }
+
+    let image = image.ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, catalog, tr!("api-missing-image")))?;
+    Ok((image, format, fields))
+}
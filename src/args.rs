@@ -0,0 +1,178 @@
+use serenity::framework::standard::{Args, CommandError};
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use crate::tr;
+
+/// `--name=value`, `--name value` and `--switch` style flags, along with the positional arguments in between.
+///
+/// Flag names are case-insensitive and given without the leading dashes when looked up.
+#[derive(Clone, Debug, Default)]
+pub struct Flags {
+    values: HashMap<String, String>,
+    switches: Vec<String>,
+    positional: Vec<String>,
+}
+
+impl Flags {
+    /// Parses the arguments that haven't been consumed yet. `switches` are the flags that never take a value,
+    /// so that e.g. `--zip @user` doesn't treat `@user` as the value of `--zip`.
+    pub fn parse(args: &Args, switches: &[&str]) -> Result<Self, CommandError> {
+        let mut flags = Self::default();
+        let mut tokens = args.raw_quoted().skip(args.len() - args.remaining());
+
+        while let Some(token) = tokens.next() {
+            let name = match token.strip_prefix("--") {
+                Some(name) if !name.is_empty() => name,
+                _ => {
+                    flags.positional.push(token.to_string());
+                    continue;
+                },
+            };
+
+            let (name, inline) = match name.split_once('=') {
+                Some((name, value)) => (name.to_lowercase(), Some(value)),
+                None => (name.to_lowercase(), None),
+            };
+
+            if switches.contains(&name.as_str()) {
+                if inline.is_some() {
+                    return Err(tr!("flag-takes-no-value", flag = name).into());
+                }
+
+                flags.switches.push(name);
+                continue;
+            }
+
+            let value = match inline {
+                Some(value) => value,
+                None => tokens.next().ok_or_else(|| tr!("flag-missing-value", flag = &name))?,
+            };
+            flags.values.insert(name, value.to_string());
+        }
+
+        Ok(flags)
+    }
+
+    /// Whether the switch was given.
+    pub fn has(&self, name: &str) -> bool {
+        self.switches.iter().any(|s| s == name)
+    }
+
+    /// Every flag given with a value, in no particular order.
+    pub fn values(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    pub fn switches(&self) -> &[String] {
+        &self.switches
+    }
+
+    pub fn raw(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    pub fn get<T: FromStr>(&self, name: &str) -> Result<Option<T>, CommandError> {
+        self.raw(name)
+            .map(|value| value.parse().map_err(|_| tr!("flag-invalid-value", value = value, flag = name).into()))
+            .transpose()
+    }
+
+    /// Like [`get`](Self::get) with a default, but also rejects given values outside of `range`.
+    pub fn get_in<T>(&self, name: &str, range: RangeInclusive<T>, default: T) -> Result<T, CommandError>
+    where
+        T: FromStr + PartialOrd + Display,
+    {
+        match self.get(name)? {
+            Some(value) if range.contains(&value) => Ok(value),
+            Some(value) => Err(tr!(
+                "flag-out-of-range",
+                flag = name,
+                min = range.start(),
+                max = range.end(),
+                value = value,
+            ).into()),
+            None => Ok(default),
+        }
+    }
+
+    pub fn positional(&self) -> &[String] {
+        &self.positional
+    }
+
+    /// The first positional argument, which is usually the image query.
+    pub fn query(&self) -> Option<String> {
+        self.positional.first().cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::framework::standard::Delimiter;
+
+    use super::*;
+
+    fn parse(input: &str, switches: &[&str]) -> Result<Flags, CommandError> {
+        Flags::parse(&Args::new(input, &[Delimiter::Single(' ')]), switches)
+    }
+
+    #[test]
+    fn tells_switches_from_values() {
+        let flags = parse("--zip @user --Quality 80", &["zip"]).unwrap();
+        assert!(flags.has("zip"));
+        assert_eq!(flags.raw("zip"), None);
+        assert_eq!(flags.raw("quality"), Some("80"));
+        assert_eq!(flags.positional(), ["@user"]);
+
+        // Without knowing it's a switch, `--zip` takes the next token as its value.
+        let flags = parse("--zip @user", &[]).unwrap();
+        assert!(!flags.has("zip"));
+        assert_eq!(flags.raw("zip"), Some("@user"));
+
+        assert!(parse("--zip=yes", &["zip"]).is_err());
+    }
+
+    #[test]
+    fn parses_inline_and_quoted_values() {
+        let flags = parse(r#"--color=red --text "hello world""#, &[]).unwrap();
+        assert_eq!(flags.raw("color"), Some("red"));
+        assert_eq!(flags.raw("text"), Some("hello world"));
+        assert_eq!(flags.raw("missing"), None);
+
+        assert!(parse("image.png --color", &[]).is_err());
+    }
+
+    #[test]
+    fn keeps_unknown_flags_and_positionals() {
+        let flags = parse("first --whatever 1 second -- third", &[]).unwrap();
+        assert_eq!(flags.raw("whatever"), Some("1"));
+        assert_eq!(flags.values().count(), 1);
+        assert_eq!(flags.positional(), ["first", "second", "--", "third"]);
+        assert_eq!(flags.query().as_deref(), Some("first"));
+    }
+
+    #[test]
+    fn skips_consumed_arguments() {
+        let mut args = Args::new("10 --step 2 image", &[Delimiter::Single(' ')]);
+        args.single::<u32>().unwrap();
+
+        let flags = Flags::parse(&args, &[]).unwrap();
+        assert_eq!(flags.raw("step"), Some("2"));
+        assert_eq!(flags.positional(), ["image"]);
+    }
+
+    #[test]
+    fn checks_values() {
+        let flags = parse("--step 2 --size 900 --angle nope", &[]).unwrap();
+        assert_eq!(flags.get::<u32>("step").unwrap(), Some(2));
+        assert_eq!(flags.get::<u32>("missing").unwrap(), None);
+        assert!(flags.get::<u32>("angle").is_err());
+
+        assert_eq!(flags.get_in("size", 1..=1000, 50).unwrap(), 900);
+        assert_eq!(flags.get_in("missing", 1..=1000, 50).unwrap(), 50);
+        assert!(flags.get_in("size", 1..=512, 50).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba, RgbaImage};
+
+use photon_core::text::{Emojis, Font, TextBlock, TextStyle};
+
+use std::sync::Arc;
+
+use crate::fonts::{self, BOLD, REGULAR};
+
+pub const CARD_WIDTH: u32 = 800;
+const PADDING: u32 = 24;
+const ACCENT_WIDTH: u32 = 6;
+const FAVICON_SIZE: u32 = 32;
+const HERO_HEIGHT: u32 = 360;
+
+const BACKGROUND: Rgba<u8> = Rgba([47, 49, 54, 255]);
+const ACCENT: Rgba<u8> = Rgba([88, 101, 242, 255]);
+const SITE_COLOR: Rgba<u8> = Rgba([185, 187, 190, 255]);
+const TITLE_COLOR: Rgba<u8> = Rgba([255, 255, 255, 255]);
+const DESCRIPTION_COLOR: Rgba<u8> = Rgba([220, 221, 222, 255]);
+
+/// The metadata of a page to render a preview card for.
+#[derive(Default)]
+pub struct Preview {
+    pub site: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub favicon: Option<DynamicImage>,
+    pub hero: Option<DynamicImage>,
+    /// Images for emoji in the text.
+    pub emojis: Emojis,
+}
+
+/// Scales the image to cover `width`x`height` and crops away the overflow around the center.
+fn cover(img: &DynamicImage, width: u32, height: u32) -> RgbaImage {
+    img.resize_to_fill(width, height, FilterType::Triangle).into_rgba8()
+}
+
+fn font_refs(fonts: &[Arc<Font<'static>>]) -> Vec<&Font<'static>> {
+    fonts.iter().map(|font| &**font).collect()
+}
+
+pub fn render(preview: &Preview) -> RgbaImage {
+    let content_x = (ACCENT_WIDTH + PADDING) as i32;
+    let content_width = CARD_WIDTH - ACCENT_WIDTH - PADDING * 2;
+
+    let regular = fonts::with_fallbacks(REGULAR.clone());
+    let bold = fonts::with_fallbacks(BOLD.clone());
+    let (regular, bold) = (font_refs(&regular), font_refs(&bold));
+
+    let site_style = TextStyle::new(&regular, 18.0, SITE_COLOR);
+    let title = TextBlock::new(TextStyle::new(&bold, 30.0, TITLE_COLOR), &preview.title, content_width as f32, 2);
+    let description = preview.description
+        .as_deref()
+        .filter(|d| !d.trim().is_empty())
+        .map(|d| TextBlock::new(TextStyle::new(&regular, 20.0, DESCRIPTION_COLOR), d, content_width as f32, 3));
+
+    let mut height = PADDING + FAVICON_SIZE + PADDING / 2 + title.height().ceil() as u32;
+    if let Some(description) = &description {
+        height += PADDING / 2 + description.height().ceil() as u32;
+    }
+    if preview.hero.is_some() {
+        height += PADDING + HERO_HEIGHT;
+    }
+    height += PADDING;
+
+    let mut card = RgbaImage::from_pixel(CARD_WIDTH, height, BACKGROUND);
+    for y in 0..height {
+        for x in 0..ACCENT_WIDTH {
+            card.put_pixel(x, y, ACCENT);
+        }
+    }
+
+    let mut y = PADDING as i32;
+    let mut site_x = content_x;
+
+    if let Some(favicon) = &preview.favicon {
+        let icon = favicon.resize_exact(FAVICON_SIZE, FAVICON_SIZE, FilterType::Triangle).into_rgba8();
+        image::imageops::overlay(&mut card, &icon, content_x as u32, y as u32);
+        site_x += (FAVICON_SIZE + PADDING / 2) as i32;
+    }
+
+    let site_width = (content_width as i32 - (site_x - content_x)) as f32;
+    let site_offset = (FAVICON_SIZE as f32 - site_style.line_height()) / 2.0;
+    TextBlock::new(site_style, &preview.site, site_width, 1).draw(&mut card, site_x, y + site_offset as i32, &preview.emojis);
+    y += (FAVICON_SIZE + PADDING / 2) as i32;
+
+    title.draw(&mut card, content_x, y, &preview.emojis);
+    y += title.height().ceil() as i32;
+
+    if let Some(description) = &description {
+        y += (PADDING / 2) as i32;
+        description.draw(&mut card, content_x, y, &preview.emojis);
+        y += description.height().ceil() as i32;
+    }
+
+    if let Some(hero) = &preview.hero {
+        y += PADDING as i32;
+        image::imageops::overlay(&mut card, &cover(hero, content_width, HERO_HEIGHT), content_x as u32, y as u32);
+    }
+
+    card
+}
@@ -0,0 +1,110 @@
+use serenity::client::Context;
+use serenity::framework::standard::{
+    Args,
+    CommandResult,
+    macros::{command, group},
+};
+use serenity::model::channel::Message;
+
+use crate::db::Database;
+use crate::fonts::{self, MAX_FILE_SIZE, MAX_GUILD_FONTS};
+use crate::i18n;
+use crate::jobs::JobQueue;
+use crate::tr;
+
+#[group]
+#[prefixes("fonts", "font")]
+#[default_command(list)]
+#[commands(list, upload, remove)]
+struct Fonts;
+
+#[command]
+#[description("Lists the bundled fonts and the ones uploaded to this server.")]
+async fn list(ctx: &Context, message: &Message) -> CommandResult {
+    let lang = i18n::catalog(ctx, message).await;
+    let list = |names: Vec<&str>| names.iter().map(|n| format!("`{}`", n)).collect::<Vec<_>>().join(", ");
+
+    let uploaded = match message.guild_id {
+        Some(guild_id) => Database::get(ctx).await.guild_font_names(guild_id)?,
+        None => vec![],
+    };
+    let uploaded = match uploaded.is_empty() {
+        true => lang.format(&tr!("font-list-none")),
+        false => list(uploaded.iter().map(String::as_str).collect()),
+    };
+
+    message.channel_id.send_message(ctx, |m| m.embed(|e| e
+        .title(lang.format(&tr!("font-list-title")))
+        .field(lang.format(&tr!("font-list-bundled")), list(fonts::bundled_names().collect()), false)
+        .field(lang.format(&tr!("font-list-uploaded")), uploaded, false)
+    )).await?;
+
+    Ok(())
+}
+
+#[command]
+#[aliases("add")]
+#[description("Adds a font to this server from an attached `.ttf` or `.otf` file, replacing any with the same name.")]
+#[usage("<name>")]
+#[example("impact")]
+#[only_in(guilds)]
+#[required_permissions(MANAGE_GUILD)]
+#[num_args(1)]
+async fn upload(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>()?.to_lowercase();
+    if !fonts::is_valid_name(&name) {
+        return Err(tr!("font-invalid-name", name = name).into());
+    }
+
+    let attachment = message.attachments
+        .iter()
+        .find(|a| {
+            let filename = a.filename.to_lowercase();
+            filename.ends_with(".ttf") || filename.ends_with(".otf")
+        })
+        .ok_or_else(|| tr!("font-missing-file"))?;
+
+    if attachment.size > MAX_FILE_SIZE {
+        return Err(tr!("font-file-too-big", max = MAX_FILE_SIZE / 1024 / 1024).into());
+    }
+
+    let guild_id = message.guild_id.unwrap();
+    let db = Database::get(ctx).await;
+    let existing = db.guild_font_names(guild_id)?;
+
+    if existing.len() >= MAX_GUILD_FONTS && !existing.contains(&name) {
+        return Err(tr!("font-too-many", max = MAX_GUILD_FONTS).into());
+    }
+
+    let data = attachment.download().await?;
+    let data = JobQueue::get(ctx).await.run(move || fonts::validate(&data).map(|_| data)).await?;
+
+    db.set_guild_font(guild_id, &name, &data, message.author.id)?;
+    fonts::forget(guild_id, &name);
+
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&tr!("font-uploaded", name = name))).await?;
+
+    Ok(())
+}
+
+#[command]
+#[aliases("delete")]
+#[description("Removes a font uploaded to this server.")]
+#[usage("<name>")]
+#[only_in(guilds)]
+#[required_permissions(MANAGE_GUILD)]
+#[num_args(1)]
+async fn remove(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>()?.to_lowercase();
+    let guild_id = message.guild_id.unwrap();
+
+    let text = match Database::get(ctx).await.remove_guild_font(guild_id, &name)? {
+        true => tr!("font-removed", name = name),
+        false => tr!("font-unknown", name = name),
+    };
+    fonts::forget(guild_id, &name);
+
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,1944 @@
+use image::imageops::FilterType;
+use image::Rgba;
+
+use photon_core::{encode_image, filters, gif_info, parse_color, set_loop_count, BlendMode, Media, OutputFormat, Quality};
+
+use serenity::client::Context;
+use serenity::framework::standard::{
+    Args,
+    CommandError,
+    CommandResult,
+    macros::{command, group},
+};
+use serenity::model::channel::Message;
+use serenity::model::id::UserId;
+use serenity::model::user::User;
+use serenity::utils::ArgumentConvert;
+
+#[cfg(feature = "removebg")]
+use std::sync::Arc;
+
+use crate::args::Flags;
+use crate::config::Config;
+use crate::db::{Database, WatermarkMark};
+use crate::fonts;
+use crate::help;
+use crate::i18n::{self, Text};
+use crate::jobs::JobQueue;
+use crate::luts::{self, MAX_FILE_SIZE, MAX_GUILD_LUTS};
+use crate::output::{self, OutputFile, MAX_FILES};
+use crate::pipeline;
+use crate::quality::LoadMonitor;
+#[cfg(feature = "removebg")]
+use crate::removebg::SegmentationModel;
+use crate::resolve_image::{ImageResolver, SourcePolicy};
+use crate::tr;
+
+#[group]
+#[commands(try_image, invert, grayscale, posterize, huerotate, rainbow, saturate, desaturate, brightness, contrast, adjust, gamma, sepia, solarize, threshold, duotone, gradientmap, tint, replacecolor, blur, sharpen, emboss, edges, neon, pixelate, halftone, oil, vignette, shadow, glow, noise, glitch, deepfry, jpeg, aberration, crt, vhs, resize, crop, rotate, flip, flop, haah, waaw, woow, hooh, kaleidoscope, tile, border, stretch, bulge, implode, explode, swirl, wave, magik, zoom, spin, shake, wiggle, bounce, triggered, petpet, fadein, fadeout, reverse, boomerang, speed, freeze, spritesheet, makegif, crossfade, blend, overlay, compare, ship, grid, watermark, frames, gifinfo, loop_count, circle, round, stickerify, removebg, background, sizes, lut)]
+struct Imaging;
+
+/// What `help` shows for an imaging command on top of its usage and examples.
+pub struct HelpExtras {
+    /// Flags specific to the command along with the message keys of their descriptions. The parameters
+    /// of the filter of the same name are listed as flags from their [`ParamSpec`](photon_core::ParamSpec)s.
+    pub flags: &'static [(&'static str, &'static str)],
+    /// Applied to the bundled sample image for the before/after thumbnail.
+    pub preview: Option<fn(Media) -> Result<Media, CommandError>>,
+}
+
+/// Flags every imaging command accepts through the resolver.
+pub const COMMON_FLAGS: &[(&str, &str)] = &[
+    ("--source <query | attachment | reply | avatar>", "help-flag-source"),
+    ("--page <n>", "help-flag-page"),
+];
+
+pub fn help_extras(command: &str) -> Option<HelpExtras> {
+    Some(match command {
+        "invert" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::invert(media))),
+        },
+        "grayscale" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::grayscale(media, 256))),
+        },
+        "posterize" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::posterize(media, 4))),
+        },
+        "huerotate" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::huerotate(media, 180.0))),
+        },
+        "rainbow" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::rainbow(media, 6))),
+        },
+        "saturate" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::saturate(media, 2.0))),
+        },
+        "desaturate" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::saturate(media, 0.3))),
+        },
+        "brightness" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::brightness_contrast(media, 25.0, 0.0))),
+        },
+        "contrast" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::brightness_contrast(media, 0.0, 50.0))),
+        },
+        "adjust" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::brightness_contrast(media, -10.0, 40.0))),
+        },
+        "gamma" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::gamma(media, 2.0))),
+        },
+        "sepia" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::sepia(media, 1.0))),
+        },
+        "solarize" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::solarize(media, 128, false))),
+        },
+        "threshold" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::threshold(media, None, false))),
+        },
+        "duotone" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::duotone(media, Rgba([25, 20, 90, 255]), Rgba([255, 140, 40, 255])))),
+        },
+        "gradientmap" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| {
+                let stops = [Rgba([0, 0, 0, 255]), Rgba([120, 20, 140, 255]), Rgba([255, 110, 60, 255]), Rgba([255, 240, 180, 255])];
+                Ok(filters::gradient_map(media, &stops))
+            }),
+        },
+        "tint" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::tint(media, Rgba([255, 0, 0, 255]), BlendMode::Normal, 0.5))),
+        },
+        "replacecolor" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| {
+                let from = filters::backdrop_color(media.first_frame());
+                Ok(filters::replace_color(media, from, Rgba([88, 101, 242, 255]), 15.0))
+            }),
+        },
+        "blur" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::blur(media, 5))),
+        },
+        "sharpen" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::sharpen(media, 2.0, 0))),
+        },
+        "emboss" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::emboss(media, 225.0, 1.0))),
+        },
+        "edges" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::edges(media, filters::EdgeMethod::Sobel, 0.0, false))),
+        },
+        "neon" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::neon(media, Rgba([255, 40, 200, 255]), 4))),
+        },
+        "pixelate" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::pixelate(media, 0))),
+        },
+        "halftone" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::halftone(media, 0, 45.0, true))),
+        },
+        "oil" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::oil(media, 4, 20))),
+        },
+        "vignette" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::vignette(media, 0.7, Rgba([0, 0, 0, 255])))),
+        },
+        "shadow" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::shadow(media, 2, 2, Rgba([0, 0, 0, 180])))),
+        },
+        "glow" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::glow(media, Rgba([255, 255, 255, 255]), 4))),
+        },
+        "noise" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::noise(media, filters::NoiseKind::Gaussian, 0.25))),
+        },
+        "glitch" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::glitch(media, 0.5, false))),
+        },
+        "deepfry" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::deepfry(media, 2)?)),
+        },
+        "jpeg" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::jpeg(media, 5, 3)?)),
+        },
+        "aberration" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::aberration(media, 5, false))),
+        },
+        "crt" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::crt(media, 0.2, 2))),
+        },
+        "vhs" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::vhs(media, 0))),
+        },
+        "resize" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::resize(media, 0, 32, filters::ResizeMode::Stretch, FilterType::Nearest))),
+        },
+        "crop" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::crop_to_aspect(media, 1.0, filters::Gravity::Center))),
+        },
+        "rotate" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::rotate(media, 30.0))),
+        },
+        "flip" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::flip(media))),
+        },
+        "flop" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::flop(media))),
+        },
+        "haah" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::mirror(media, filters::MirrorSide::Left))),
+        },
+        "waaw" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::mirror(media, filters::MirrorSide::Right))),
+        },
+        "woow" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::mirror(media, filters::MirrorSide::Top))),
+        },
+        "hooh" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::mirror(media, filters::MirrorSide::Bottom))),
+        },
+        "kaleidoscope" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::kaleidoscope(media, 6, 0.0))),
+        },
+        "tile" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::tile(media, 3, 3, false))),
+        },
+        "border" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::border(media, 4, Rgba([255, 120, 40, 255]), Rgba([120, 40, 200, 255])))),
+        },
+        "stretch" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::stretch(media, 200.0, 50.0))),
+        },
+        "bulge" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::bulge(media, 0.5, (0.5, 0.5)))),
+        },
+        "implode" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::implode(media, 0.6))),
+        },
+        "explode" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::explode(media, 0.8))),
+        },
+        "swirl" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::swirl(media, 270.0, 0.0, false))),
+        },
+        "wave" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::wave(media, 3.0, 20.0, filters::WaveAxis::X, false))),
+        },
+        "magik" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::magik(media, 0.5))),
+        },
+        "zoom" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::zoom(media, 8, 3.0, false))),
+        },
+        "spin" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::spin(media, 3.0, false))),
+        },
+        "shake" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::shake(media, 5.0))),
+        },
+        "wiggle" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::wiggle(media, 8.0))),
+        },
+        "bounce" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::bounce(media, 50.0, 2.0))),
+        },
+        "triggered" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::triggered(media, 4.0, &[&**fonts::BOLD]))),
+        },
+        "petpet" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::petpet(media, &filters::petpet_hand()?, 1.0))),
+        },
+        // The sample image is still, so there is nothing to show.
+        "reverse" => HelpExtras {
+            flags: &[],
+            preview: None,
+        },
+        "boomerang" => HelpExtras {
+            flags: &[],
+            preview: None,
+        },
+        "speed" => HelpExtras {
+            flags: &[],
+            preview: None,
+        },
+        "freeze" => HelpExtras {
+            flags: &[],
+            preview: None,
+        },
+        "spritesheet" => HelpExtras {
+            flags: &[],
+            preview: None,
+        },
+        // The thumbnails only show the first frame, which is just the color for a fade in.
+        "fadein" => HelpExtras {
+            flags: &[],
+            preview: None,
+        },
+        "fadeout" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::fade(media, 15, Rgba([0, 0, 0, 255]), true))),
+        },
+        "makegif" => HelpExtras {
+            flags: &[("--delay <ms>", "help-flag-makegif-delay")],
+            preview: None,
+        },
+        "crossfade" => HelpExtras {
+            flags: &[("--frames <2-30>", "help-flag-crossfade-frames")],
+            preview: None,
+        },
+        "blend" => HelpExtras {
+            flags: &[
+                ("--mode <normal | multiply | screen | overlay | add | difference>", "help-flag-blend-mode"),
+                ("--opacity <0-1>", "help-flag-blend-opacity"),
+            ],
+            preview: None,
+        },
+        "overlay" => HelpExtras {
+            flags: &[
+                ("--anchor <center | top | bottom-right | ...>", "help-flag-overlay-anchor"),
+                ("--x <pixels>", "help-flag-overlay-x"),
+                ("--y <pixels>", "help-flag-overlay-y"),
+                ("--scale <0.01-4>", "help-flag-overlay-scale"),
+            ],
+            preview: None,
+        },
+        "compare" => HelpExtras {
+            flags: &[
+                ("--orientation <h | v>", "help-flag-compare-orientation"),
+                ("--labels <first,second>", "help-flag-compare-labels"),
+            ],
+            preview: None,
+        },
+        "grid" => HelpExtras {
+            flags: &[
+                ("--gap <0-64>", "help-flag-grid-gap"),
+                ("--background <color>", "help-flag-grid-background"),
+            ],
+            preview: None,
+        },
+        "watermark" => HelpExtras {
+            flags: &[
+                ("--corner <bottom-right | top-left | ...>", "help-flag-watermark-corner"),
+                ("--opacity <0.05-1>", "help-flag-watermark-opacity"),
+            ],
+            preview: None,
+        },
+        "frames" => HelpExtras {
+            flags: &[("--zip", "help-flag-frames-zip")],
+            preview: None,
+        },
+        "circle" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::circle(media, None))),
+        },
+        "round" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::round(media, 6.0))),
+        },
+        "stickerify" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::stickerify(media, 40.0))),
+        },
+        "background" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::fill_background(media, Rgba([88, 101, 242, 255])))),
+        },
+        "removebg" => HelpExtras {
+            flags: &[
+                ("--backend <flood | chroma | model>", "help-flag-removebg-backend"),
+                ("--color <color>", "help-flag-removebg-color"),
+                ("--tolerance <0-200>", "help-flag-removebg-tolerance"),
+            ],
+            preview: Some(|media| Ok(media.map_images(|img| filters::remove_background(img, 40.0)))),
+        },
+        "sizes" => HelpExtras {
+            flags: &[("--zip", "help-flag-zip")],
+            preview: Some(|media| Ok(media.resize_to_fit(32, 32, FilterType::Lanczos3))),
+        },
+        "lut" => HelpExtras {
+            flags: &[],
+            preview: Some(|media| Ok(filters::apply_lut(media, &luts::bundled("teal-orange").expect("Bundled LUT is missing")))),
+        },
+        _ => return None,
+    })
+}
+
+/// The most sizes `sizes` will produce at once.
+const MAX_SIZES: usize = 16;
+/// The most images `makegif` puts together.
+const MAX_MAKEGIF_IMAGES: usize = 50;
+/// The most images `grid` lays out.
+const MAX_GRID_IMAGES: usize = 9;
+/// The most frames `frames` extracts at once.
+const MAX_EXTRACTED_FRAMES: usize = 100;
+
+/// Parses a 1-indexed, inclusive frame range like `4`, `2-8`, `5-` or `-3`, the end being open in the third case.
+///
+/// Returns `None` for arguments that don't look like a range at all, so they can be the image query instead.
+fn parse_frame_range(raw: &str) -> Option<Result<(usize, Option<usize>), Text>> {
+    if !raw.contains(|c: char| c.is_ascii_digit()) || !raw.chars().all(|c| c.is_ascii_digit() || c == '-') {
+        return None;
+    }
+
+    let frame = |raw: &str| raw.parse::<usize>().ok().filter(|frame| *frame >= 1);
+    let range = match raw.split_once('-') {
+        Some(("", end)) => frame(end).map(|end| (1, Some(end))),
+        Some((start, "")) => frame(start).map(|start| (start, None)),
+        Some((start, end)) => frame(start)
+            .zip(frame(end))
+            .filter(|(start, end)| end >= start)
+            .map(|(start, end)| (start, Some(end))),
+        None => frame(raw).map(|frame| (frame, Some(frame))),
+    };
+
+    Some(range.ok_or_else(|| tr!("frames-invalid-range", range = raw)))
+}
+
+/// The first and last frame of the range in a GIF with this many frames.
+fn frame_bounds((start, end): (usize, Option<usize>), count: usize) -> Result<(usize, usize), Text> {
+    let end = end.unwrap_or(count);
+
+    match start <= count && end <= count {
+        true => Ok((start, end)),
+        false => Err(tr!("frames-out-of-range", count = count)),
+    }
+}
+
+#[command]
+async fn try_image(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    let query = resolver.parse_args(&args)?;
+    
+    let result = resolver.resolve(ctx, message, query).await?;
+    output::send_file(ctx, message, result.hash, result.nsfw, &result.data, "my_file.gif").await?;
+
+    Ok(())
+}
+
+#[command]
+#[description("Inverts the colors of an image.")]
+#[usage("[image]")]
+#[example("@user")]
+async fn invert(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    let query = resolver.parse_args(&args)?;
+
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let result = resolver.resolve(ctx, message, query).await?;
+
+    pipeline::process(ctx, message, result, "invert", |media| Ok(filters::invert(media))).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[aliases("greyscale", "gray", "grey")]
+#[description("Converts an image to grayscale. With `--levels`, only that many shades of gray are used for a posterized look.")]
+#[usage("[image] [--levels <2-256>]")]
+#[example("@user --levels 4")]
+async fn grayscale(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "grayscale", &[]).await
+}
+
+#[command]
+#[aliases("poster")]
+#[description("Reduces each color channel of an image to a few levels, 4 unless given.")]
+#[usage("[levels] [image]")]
+#[example("3 @user")]
+async fn posterize(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "posterize", &["levels"]).await
+}
+
+#[command]
+#[aliases("hue", "hueshift")]
+#[description("Shifts the hues of an image by any angle, 180 degrees unless given.")]
+#[usage("[degrees] [image]")]
+#[example("-45 @user")]
+async fn huerotate(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "huerotate", &["degrees"]).await
+}
+
+#[command]
+#[aliases("huecycle", "rgb")]
+#[description("Cycles the hues of an image around the color wheel. Still images become a GIF, GIFs are shifted a little further on every frame.")]
+#[usage("[image] [--frames <2-60>]")]
+#[example("@user --frames 24")]
+async fn rainbow(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "rainbow", &[]).await
+}
+
+#[command]
+#[description("Makes the colors of an image more vivid. The factor goes up to 3 and defaults to 1.5, values below 1 desaturate instead.")]
+#[usage("[factor] [image]")]
+#[example("2 @user")]
+async fn saturate(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "saturate", &["factor"]).await
+}
+
+#[command]
+#[description("Makes the colors of an image less vivid. The factor goes from 0, leaving only grays, to 1, and defaults to 0.5.")]
+#[usage("[factor] [image]")]
+#[example("0.2 @user")]
+async fn desaturate(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "desaturate", &["factor"]).await
+}
+
+/// Fails with the usage of the command unless it's given a number first, for commands that would do
+/// nothing without their amount.
+async fn require_amount(ctx: &Context, message: &Message, args: &Args, command: &str) -> CommandResult {
+    let flags = Flags::parse(args, &[])?;
+
+    match flags.positional().first().map_or(false, |raw| raw.parse::<f64>().is_ok()) {
+        true => Ok(()),
+        false => Err(help::usage(ctx, message, command).await.into()),
+    }
+}
+
+#[command]
+#[aliases("bright")]
+#[description("Brightens an image by a percentage, or darkens it when negative.")]
+#[usage("<-100 to 100> [image]")]
+#[example("30 @user")]
+#[min_args(1)]
+async fn brightness(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    require_amount(ctx, message, &args, "brightness").await?;
+    pipeline::run_filter(ctx, message, &args, "adjust", &["brightness"]).await
+}
+
+#[command]
+#[description("Increases the contrast of an image by a percentage, or flattens it when negative.")]
+#[usage("<-100 to 100> [image]")]
+#[example("50 @user")]
+#[min_args(1)]
+async fn contrast(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    require_amount(ctx, message, &args, "contrast").await?;
+    pipeline::run_filter(ctx, message, &args, "adjust", &["contrast"]).await
+}
+
+#[command]
+#[description("Adjusts the brightness and contrast of an image at once.")]
+#[usage("[image] [--brightness <-100-100>] [--contrast <-100-100>]")]
+#[example("@user --brightness -10 --contrast 40")]
+async fn adjust(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "adjust", &[]).await
+}
+
+#[command]
+#[description("Gamma corrects an image. Values from 1 to 5 brighten the midtones, values from 0.1 to 1 darken them.")]
+#[usage("[gamma] [image]")]
+#[example("0.5 @user")]
+async fn gamma(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "gamma", &["gamma"]).await
+}
+
+#[command]
+#[description("Tones an image sepia. The intensity goes from 0 to 1, fully toned by default.")]
+#[usage("[intensity] [image]")]
+#[example("0.6 @user")]
+async fn sepia(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "sepia", &["intensity"]).await
+}
+
+#[command]
+#[description("Inverts the color channels of an image above a threshold from 0 to 255, 128 unless given, like overexposed film.")]
+#[usage("[threshold] [image] [--smooth]")]
+#[example("100 @user --smooth")]
+async fn solarize(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "solarize", &["threshold"]).await
+}
+
+#[command]
+#[aliases("binarize", "bw")]
+#[description("Turns an image black and white, with pixels brighter than a threshold from 1 to 255 going white. Without one, or with `otsu`, the threshold is picked to best split the image into dark and light.")]
+#[usage("[threshold | otsu] [image] [--invert]")]
+#[example("otsu @user --invert")]
+async fn threshold(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    if args.current().map_or(false, |raw| raw.eq_ignore_ascii_case("otsu")) {
+        args.advance();
+        return pipeline::run_filter(ctx, message, &args, "threshold", &[]).await;
+    }
+    pipeline::run_filter(ctx, message, &args, "threshold", &["threshold"]).await
+}
+
+#[command]
+#[description("Recolors an image with two colors, the shadow color for dark parts and the highlight color for bright ones. Colors can be hex codes or names.")]
+#[usage("[shadow] [highlight] [image]")]
+#[example("purple #ff8c28 @user")]
+async fn duotone(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "duotone", &["shadow", "highlight"]).await
+}
+
+#[command]
+#[aliases("gradient", "gmap")]
+#[description("Recolors an image through a gradient, the first color for the darkest parts and the last for the brightest. Takes 2 to 16 colors as hex codes or names.")]
+#[usage("[colors...] [image]")]
+#[example("black red orange yellow @user")]
+async fn gradientmap(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "gradientmap", &["colors"]).await
+}
+
+#[command]
+#[aliases("colorize")]
+#[description("Tints an image with a color, given as a hex code or name, with a strength from 0 to 1 defaulting to 0.5.")]
+#[usage("[color] [strength] [image] [--mode <normal | multiply | screen | overlay | add | difference>]")]
+#[example("blurple 0.8 @user --mode screen")]
+async fn tint(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "tint", &["color", "strength"]).await
+}
+
+#[command]
+#[aliases("swapcolor", "recolor")]
+#[description("Swaps one color for another, given as hex codes or names. Colors that look close to it are swapped too, within a tolerance from 0 to 100 defaulting to 15. Replace a color with `transparent` to cut it out.")]
+#[usage("<from> <to> [tolerance] [image]")]
+#[example("red blurple 25 @user")]
+#[min_args(2)]
+async fn replacecolor(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "replacecolor", &["from", "to", "tolerance"]).await
+}
+
+#[command]
+#[description("Blurs an image. The radius is roughly how many pixels colors spread, between 1 and 50.")]
+#[usage("[radius] [image]")]
+#[example("10 @user")]
+async fn blur(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "blur", &["radius"]).await
+}
+
+#[command]
+#[description("Sharpens an image. The amount defaults to 1, higher is stronger.")]
+#[usage("[amount] [image] [--threshold <0-255>]")]
+#[example("2.5 @user")]
+async fn sharpen(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "sharpen", &["amount"]).await
+}
+
+#[command]
+#[description("Turns an image into a gray relief, lit from the top left unless `--direction` says otherwise.")]
+#[usage("[image] [--direction <degrees>] [--strength <0.1-10>]")]
+#[example("@user --direction 90 --strength 2")]
+async fn emboss(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "emboss", &[]).await
+}
+
+#[command]
+#[aliases("edge", "outline")]
+#[description("Finds the edges in an image, drawing them white on black or, with `--overlay`, over the image.")]
+#[usage("[image] [--method <sobel | canny>] [--threshold <0-255>] [--overlay]")]
+#[example("@user --method canny --overlay")]
+async fn edges(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "edges", &[]).await
+}
+
+#[command]
+#[aliases("neonify", "glowedges")]
+#[description("Traces the edges in an image as glowing neon on a dark background, pink unless another color is given.")]
+#[usage("[color] [image] [--size <2-64>]")]
+#[example("cyan @user --size 12")]
+async fn neon(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "neon", &["color"]).await
+}
+
+#[command]
+#[aliases("pixel", "pixelize")]
+#[description("Pixelates an image. The block size defaults to a 32nd of the shorter side.")]
+#[usage("[block size] [image]")]
+#[example("8 @user")]
+async fn pixelate(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "pixelate", &["size"]).await
+}
+
+#[command]
+#[aliases("dots", "newsprint")]
+#[description("Prints an image in dots of ink on a grid, larger where it's darker like in a newspaper. `--cmyk` prints in color with a screen of dots for every ink. The dot size defaults to a 96th of the shorter side.")]
+#[usage("[dot size] [image] [--angle <degrees>] [--cmyk]")]
+#[example("10 @user --cmyk")]
+async fn halftone(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "halftone", &["size"]).await
+}
+
+#[command]
+#[aliases("oilpaint", "paint")]
+#[description("Makes an image look like an oil painting. Takes the brush radius (1-10) and how many brightness levels to mix the paint from (2-64).")]
+#[usage("[radius] [levels] [image]")]
+#[example("6 10 @user")]
+async fn oil(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "oil", &["radius", "levels"]).await
+}
+
+#[command]
+#[aliases("dropshadow")]
+#[description("Casts a soft shadow behind the opaque parts of an image, best with transparent ones like emoji or stickers. The canvas grows to fit the shadow.")]
+#[usage("[image] [--blur <0-64>] [--offset <pixels>] [--color <color>]")]
+#[example("@user --offset 16 --color #000000cc")]
+async fn shadow(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "shadow", &[]).await
+}
+
+#[command]
+#[aliases("aura", "halo")]
+#[description("Makes the opaque parts of an image glow, best with transparent ones like emoji or stickers. The canvas grows to fit the glow.")]
+#[usage("[image] [--color <color>] [--size <1-128>]")]
+#[example("@user --color cyan --size 24")]
+async fn glow(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "glow", &[]).await
+}
+
+#[command]
+#[description("Darkens an image toward its corners. The strength goes from 0 to 1 and defaults to 0.7, `--color` fades to another color instead.")]
+#[usage("[strength] [image] [--color <color>]")]
+#[example("1 @user --color #ff0066")]
+async fn vignette(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "vignette", &["strength"]).await
+}
+
+#[command]
+#[aliases("grain")]
+#[description("Adds noise to an image, from 0 to 1 and 0.25 unless given. GIFs get different noise on every frame.")]
+#[usage("[amount] [image] [--type <gaussian | salt>]")]
+#[example("0.5 @user --type salt")]
+async fn noise(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "noise", &["amount"]).await
+}
+
+#[command]
+#[aliases("datamosh")]
+#[description("Corrupts an image with glitch artifacts, the intensity going from 0 to 1 and defaulting to 0.5. GIFs glitch differently on every frame.")]
+#[usage("[intensity] [image] [--animate]")]
+#[example("0.8 @user --animate")]
+async fn glitch(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "glitch", &["intensity"]).await
+}
+
+#[command]
+#[aliases("fry", "deepfried")]
+#[description("Deep fries an image. The level goes from 1 to 3, higher being more cursed.")]
+#[usage("[level] [image]")]
+#[example("3 @user")]
+async fn deepfry(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "deepfry", &["level"]).await
+}
+
+#[command]
+#[aliases("jpg", "needsmorejpeg")]
+#[description("Compresses an image into JPEG artifacts. The quality goes from 1 to 20, lower being blockier, and transparency is kept.")]
+#[usage("[quality] [image] [--passes <1-10>]")]
+#[example("2 @user --passes 8")]
+async fn jpeg(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "jpeg", &["quality"]).await
+}
+
+#[command]
+#[aliases("chromatic", "rgbsplit")]
+#[description("Splits the red and blue channels of an image apart by the given number of pixels, 5 by default.")]
+#[usage("[offset] [image] [--radial]")]
+#[example("12 @user --radial")]
+async fn aberration(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "aberration", &["offset"]).await
+}
+
+#[command]
+#[aliases("scanlines", "tv")]
+#[description("Puts an image on an old CRT screen, with scanlines, a curved glass and visible phosphors.")]
+#[usage("[image] [--curvature <0-1>] [--line-gap <0-16>]")]
+#[example("@user --curvature 0.5")]
+async fn crt(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "crt", &[]).await
+}
+
+#[command]
+#[aliases("tape", "vcr")]
+#[description("Plays an image off a worn VHS tape, with smeared colors, tracking noise and the player's display.")]
+#[usage("[image] [--animate <frames>]")]
+#[example("@user --animate 12")]
+async fn vhs(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "vhs", &[]).await
+}
+
+/// Turns `WxH`, `W`, `Wx`, `xH` or `N%` into the parameters of the resize filter.
+fn parse_size(raw: &str) -> Option<Vec<(&'static str, String)>> {
+    if let Some(percent) = raw.strip_suffix('%') {
+        return Some(vec![("scale", percent.to_string())]);
+    }
+
+    let (width, height) = raw.split_once(|c| c == 'x' || c == 'X' || c == '×').unwrap_or((raw, ""));
+    let is_side = |side: &str| side.is_empty() || side.chars().all(|c| c.is_ascii_digit());
+    if !is_side(width) || !is_side(height) || width.len() + height.len() == 0 {
+        return None;
+    }
+
+    Some(
+        [("width", width), ("height", height)]
+            .into_iter()
+            .filter(|(_, side)| !side.is_empty())
+            .map(|(name, side)| (name, side.to_string()))
+            .collect(),
+    )
+}
+
+#[command]
+#[aliases("scale")]
+#[description("Resizes an image to `WxH` pixels, or by a percentage. Leaving out the width or height keeps the aspect ratio. When both are given the image is stretched to them unless `--fit` or `--fill` is given.")]
+#[usage("<WxH | W | xH | N%> [image] [--fit | --fill | --stretch] [--filter <nearest | bilinear | lanczos>]")]
+#[example("512x512 @user --fill")]
+#[min_args(1)]
+async fn resize(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let raw = args.single::<String>()?;
+    let size = parse_size(&raw).ok_or_else(|| tr!("resize-invalid-size", size = &raw))?;
+
+    pipeline::run_filter_with(ctx, message, &args, "resize", &[], size).await
+}
+
+/// Turns an aspect ratio like `16:9` into width over height.
+fn parse_aspect(raw: &str) -> Option<f64> {
+    let (width, height) = raw.split_once(':')?;
+    let (width, height) = (width.parse::<f64>().ok()?, height.parse::<f64>().ok()?);
+
+    (width > 0.0 && height > 0.0).then(|| width / height)
+}
+
+#[command]
+#[description("Crops an image to the area `width` by `height` pixels from `x` and `y`, or to an aspect ratio like `16:9` keeping the part given by `--gravity`.")]
+#[usage("<x> <y> <width> <height> [image] | <W:H> [image] [--gravity <position>]")]
+#[example("1:1 @user --gravity top")]
+#[min_args(1)]
+async fn crop(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    match args.current().and_then(parse_aspect) {
+        Some(aspect) => {
+            args.advance();
+            pipeline::run_filter_with(ctx, message, &args, "crop", &[], vec![("aspect", aspect.to_string())]).await
+        },
+        None => pipeline::run_filter(ctx, message, &args, "crop", &["x", "y", "width", "height"]).await,
+    }
+}
+
+#[command]
+#[aliases("rot")]
+#[description("Rotates an image clockwise by any number of degrees, negative ones going counterclockwise. The corners stay in, on a transparent background.")]
+#[usage("<degrees> [image]")]
+#[example("45 @user")]
+#[min_args(1)]
+async fn rotate(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "rotate", &["degrees"]).await
+}
+
+#[command]
+#[aliases("vflip")]
+#[description("Flips an image upside down.")]
+#[usage("[image]")]
+#[example("@user")]
+async fn flip(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "flip", &[]).await
+}
+
+#[command]
+#[aliases("hflip")]
+#[description("Mirrors an image left to right.")]
+#[usage("[image]")]
+#[example("@user")]
+async fn flop(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "flop", &[]).await
+}
+
+#[command]
+#[description("Reflects the left half of an image onto the right.")]
+#[usage("[image]")]
+#[example("@user")]
+async fn haah(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter_with(ctx, message, &args, "mirror", &[], vec![("side", "left".to_string())]).await
+}
+
+#[command]
+#[description("Reflects the right half of an image onto the left.")]
+#[usage("[image]")]
+#[example("@user")]
+async fn waaw(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter_with(ctx, message, &args, "mirror", &[], vec![("side", "right".to_string())]).await
+}
+
+#[command]
+#[description("Reflects the top half of an image onto the bottom.")]
+#[usage("[image]")]
+#[example("@user")]
+async fn woow(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter_with(ctx, message, &args, "mirror", &[], vec![("side", "top".to_string())]).await
+}
+
+#[command]
+#[description("Reflects the bottom half of an image onto the top.")]
+#[usage("[image]")]
+#[example("@user")]
+async fn hooh(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter_with(ctx, message, &args, "mirror", &[], vec![("side", "bottom".to_string())]).await
+}
+
+#[command]
+#[aliases("kaleido")]
+#[description("Reflects a wedge of an image around its center, repeating it the given number of times (2 to 32, 6 by default).")]
+#[usage("[segments] [image] [--angle <degrees>]")]
+#[example("8 @user --angle 45")]
+async fn kaleidoscope(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "kaleidoscope", &["segments"]).await
+}
+
+#[command]
+#[aliases("frame", "pad")]
+#[description("Puts a border around an image, growing it rather than covering any of it. `--gradient` fades the border between two colors, separated by a comma, instead of a solid one.")]
+#[usage("[width] [color] [image] [--gradient <color>,<color>]")]
+#[example("24 white @user")]
+async fn border(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "border", &["width", "color"]).await
+}
+
+#[command]
+#[aliases("repeat")]
+#[description("Repeats an image in a grid of columns by rows, 3 by 3 by default. Leaving out the rows makes it square.")]
+#[usage("[columns] [rows] [image] [--mirror]")]
+#[example("4 2 @user --mirror")]
+async fn tile(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "tile", &["columns", "rows"]).await
+}
+
+#[command]
+#[aliases("squish")]
+#[description("Scales the width and height of an image by separate percentages, 200% and 50% by default.")]
+#[usage("[width%] [height%] [image]")]
+#[example("50% 300% @user")]
+async fn stretch(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let mut leading = Vec::new();
+
+    for name in ["x", "y"] {
+        let percent = match args.current().map(|raw| raw.trim_end_matches('%')) {
+            Some(percent) if percent.parse::<f64>().is_ok() => percent.to_string(),
+            _ => break,
+        };
+
+        leading.push((name, percent));
+        args.advance();
+    }
+
+    pipeline::run_filter_with(ctx, message, &args, "stretch", &[], leading).await
+}
+
+#[command]
+#[aliases("fisheye", "pinch")]
+#[description("Bulges an image out from its center, with a strength from -1 to 1 (0.5 by default). Negative strengths pinch it in instead.")]
+#[usage("[strength] [image] [--x <0-1>] [--y <0-1>]")]
+#[example("-0.8 @user --y 0.3")]
+async fn bulge(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "bulge", &["strength"]).await
+}
+
+#[command]
+#[description("Sucks an image into its center, like ImageMagick's implode. The amount goes up to 2, 0.6 by default.")]
+#[usage("[amount] [image]")]
+#[example("1.2 @user")]
+async fn implode(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "implode", &["amount"]).await
+}
+
+#[command]
+#[description("Blows an image up from its center, the opposite of `implode`. The amount goes up to 2, 0.8 by default.")]
+#[usage("[amount] [image]")]
+#[example("1.5 @user")]
+async fn explode(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "explode", &["amount"]).await
+}
+
+#[command]
+#[aliases("twist")]
+#[description("Twists an image around its center by the given degrees (270 by default), reaching out to the radius in pixels.")]
+#[usage("[degrees] [radius] [image] [--animate]")]
+#[example("-540 200 @user --animate")]
+async fn swirl(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "swirl", &["degrees", "radius"]).await
+}
+
+#[command]
+#[aliases("ripple")]
+#[description("Ripples an image with a wave, moving pixels by the amplitude (10 by default) over the wavelength (60 by default), both in pixels.")]
+#[usage("[amplitude] [wavelength] [image] [--axis <x | y>] [--animate]")]
+#[example("20 100 @user --axis y --animate")]
+async fn wave(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "wave", &["amplitude", "wavelength"]).await
+}
+
+#[command]
+#[aliases("magic", "cas", "liquid")]
+#[description("Liquid rescales an image: seam carving squashes it down by the intensity (0.5 by default, up to 0.9) and it's stretched back to size. GIFs keep at most 24 frames.")]
+#[usage("[intensity] [image]")]
+#[example("0.7 @user")]
+async fn magik(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "magik", &["intensity"]).await
+}
+
+#[command]
+#[aliases("zoomin")]
+#[description("Makes a GIF zooming into the center of an image, 3 times larger by the end unless `--factor` says otherwise. `--out` zooms back out instead.")]
+#[usage("[image] [--frames <2-60>] [--factor <1-10>] [--out]")]
+#[example("@user --factor 6 --out")]
+async fn zoom(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "zoom", &[]).await
+}
+
+#[command]
+#[aliases("spinny", "rotating")]
+#[description("Makes a GIF of an image spinning around, cropped to a circle so the corners don't get cut off. `--square` spins the whole thing instead.")]
+#[usage("[image] [--speed <0.25-4>] [--square]")]
+#[example("@user --speed 2")]
+async fn spin(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "spin", &[]).await
+}
+
+#[command]
+#[aliases("jitter", "shaking")]
+#[description("Makes a GIF of an image shaking, jumping by up to the intensity in percent of its size (5 by default) every frame.")]
+#[usage("[intensity] [image]")]
+#[example("15 @user")]
+async fn shake(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "shake", &["intensity"]).await
+}
+
+#[command]
+#[aliases("sway")]
+#[description("Makes a GIF of an image smoothly swaying about, by up to the intensity in percent of its size (8 by default).")]
+#[usage("[intensity] [image]")]
+#[example("20 @user")]
+async fn wiggle(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "wiggle", &["intensity"]).await
+}
+
+#[command]
+#[aliases("bouncy", "boing")]
+#[description("Makes a GIF of an image bouncing, squashing a little every time it lands.")]
+#[usage("[image] [--height <5-300>] [--speed <0.25-4>]")]
+#[example("@user --height 120 --speed 1.5")]
+async fn bounce(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "bounce", &[]).await
+}
+
+#[command]
+#[aliases("trigger")]
+#[description("Makes the classic triggered GIF: the image shaking in a red haze above a \"TRIGGERED\" banner.")]
+#[usage("[image] [--intensity <1-20>]")]
+#[example("@user --intensity 8")]
+async fn triggered(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "triggered", &[]).await
+}
+
+#[command]
+#[aliases("pet", "pat", "headpat")]
+#[description("Makes a GIF of a hand patting an image, usually someone's avatar.")]
+#[usage("[image] [--speed <0.25-3>]")]
+#[example("@user --speed 1.5")]
+async fn petpet(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "petpet", &[]).await
+}
+
+#[command]
+#[aliases("fadefrom", "intro")]
+#[description("Makes a GIF of an image fading in from black, or from `--from` which can be `transparent`. Handy for the start of an edit.")]
+#[usage("[image] [--frames <2-60>] [--from <color>]")]
+#[example("@user --from white")]
+async fn fadein(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "fadein", &[]).await
+}
+
+#[command]
+#[aliases("fadetoblack", "outro")]
+#[description("Makes a GIF of an image fading out to black, or to `--to` which can be `transparent`. Handy for the end of an edit.")]
+#[usage("[image] [--frames <2-60>] [--to <color>]")]
+#[example("@user --to transparent --frames 30")]
+async fn fadeout(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "fadeout", &[]).await
+}
+
+#[command]
+#[aliases("rewind", "backwards")]
+#[description("Plays a GIF backwards, every frame keeping its own delay.")]
+#[usage("[gif]")]
+#[example("@user")]
+async fn reverse(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "reverse", &[]).await
+}
+
+#[command]
+#[aliases("pingpong", "bounceback")]
+#[description("Plays a GIF forwards and then backwards, looping back and forth. Long GIFs are cut down to 150 frames first.")]
+#[usage("[gif]")]
+#[example("@user")]
+async fn boomerang(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "boomerang", &[]).await
+}
+
+#[command]
+#[aliases("fast", "speedup")]
+#[description("Plays a GIF faster by the given factor, 2 by default. Factors below 1 slow it down, as in `speed 0.5` for half speed.")]
+#[usage("[factor] [gif]")]
+#[example("3 @user")]
+async fn speed(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "speed", &["factor"]).await
+}
+
+#[command]
+#[aliases("still", "pickframe")]
+#[description("Picks a single frame out of a GIF as a PNG, by its number counting from 1, or `first` or `last`. Negative numbers count back from the end.")]
+#[usage("[n | first | last] [gif]")]
+#[example("last @user")]
+async fn freeze(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let frame = match args.current().map(str::to_lowercase).as_deref() {
+        Some("first") => Some("1"),
+        Some("last") => Some("-1"),
+        _ => None,
+    };
+
+    match frame {
+        Some(frame) => {
+            args.advance();
+            pipeline::run_filter_with(ctx, message, &args, "freeze", &[], vec![("frame", frame.to_string())]).await
+        },
+        None => pipeline::run_filter(ctx, message, &args, "freeze", &["frame"]).await,
+    }
+}
+
+#[command]
+#[aliases("sheet", "contactsheet")]
+#[description("Lays out every frame of a GIF in a grid as one PNG, with that many columns or about as many as rows.")]
+#[usage("[columns] [gif] [--numbered]")]
+#[example("8 @user --numbered")]
+async fn spritesheet(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "spritesheet", &["columns"]).await
+}
+
+#[command]
+#[aliases("togif", "slideshow")]
+#[description("Puts the attached or linked images together into a GIF, in the order they're given. Links to messages work too. Every frame takes the size of the first image.")]
+#[usage("[image...] [--delay <ms>]")]
+#[example("https://example.com/1.png https://example.com/2.png --delay 200")]
+async fn makegif(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, &[])?;
+    let delay = flags.get_in("delay", 20..=10_000, 500)?;
+    let queries = flags.positional().to_vec();
+
+    if message.attachments.len() + queries.len() > MAX_MAKEGIF_IMAGES {
+        return Err(tr!("makegif-too-many", max = MAX_MAKEGIF_IMAGES).into());
+    }
+
+    let resolver = ImageResolver::for_message(ctx, message).await?;
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let results = resolver.resolve_many(ctx, message, queries).await?;
+
+    if results.len() < 2 {
+        return Err(tr!("makegif-not-enough").into());
+    }
+
+    pipeline::process_many(ctx, message, results, "makegif", move |inputs| {
+        let images = inputs.iter().map(|media| media.first_frame().clone()).collect::<Vec<_>>();
+        Ok(filters::makegif(&images, delay))
+    }).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[aliases("fadeto", "transition")]
+#[description("Makes a GIF fading from one image into another and back. Give the two images as attachments, links or links to messages, or just the second to start from your avatar.")]
+#[usage("[image] <image> [--frames <2-30>]")]
+#[example("@user @friend --frames 20")]
+async fn crossfade(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, &[])?;
+    let frames = flags.get_in("frames", 2..=30, 12)?;
+
+    let resolver = ImageResolver::for_message(ctx, message).await?;
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let (from, to) = resolver.resolve_pair(ctx, message, flags.positional().to_vec()).await?;
+
+    pipeline::process_many(ctx, message, vec![from, to], "crossfade", move |inputs| {
+        Ok(filters::crossfade(inputs[0].first_frame(), inputs[1].first_frame(), frames))
+    }).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[aliases("mix", "composite")]
+#[description("Blends a second image over the first, scaled to cover it. `--mode` picks how: `normal`, `multiply` to darken, `screen` to lighten, `overlay` for contrast, `add` or `difference`.")]
+#[usage("[image] <image> [--mode <mode>] [--opacity <0-1>]")]
+#[example("@user https://example.com/texture.png --mode overlay --opacity 0.6")]
+async fn blend(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, BlendMode::NAMES)?;
+    let opacity = flags.get_in("opacity", 0.0..=1.0, 1.0)?;
+    let mode = match flags.raw("mode") {
+        Some(name) => BlendMode::from_name(name).ok_or_else(|| tr!("blend-unknown-mode", mode = name))?,
+        None => BlendMode::NAMES
+            .iter()
+            .find(|name| flags.has(name))
+            .and_then(|name| BlendMode::from_name(name))
+            .unwrap_or(BlendMode::Normal),
+    };
+
+    let resolver = ImageResolver::for_message(ctx, message).await?;
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let (below, top) = resolver.resolve_pair(ctx, message, flags.positional().to_vec()).await?;
+
+    pipeline::process_many(ctx, message, vec![below, top], "blend", move |mut inputs| {
+        let top = inputs.pop().expect("Both images were decoded");
+        Ok(filters::blend(inputs.remove(0), top.first_frame(), mode, opacity))
+    }).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[aliases("paste", "stamp")]
+#[description("Puts a second image on top of the first, by default fit to it and centered. `--anchor` lines it up with an edge or corner instead, which `--x` and `--y` move it away from. Animated images on either side animate the result.")]
+#[usage("[image] <image> [--anchor <position>] [--x <pixels>] [--y <pixels>] [--scale <0.01-4>]")]
+#[example("@user https://example.com/sticker.png --anchor bottom-right --scale 0.3 --x -10 --y -10")]
+async fn overlay(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, &[])?;
+    let max = filters::MAX_RESIZE_DIMENSION;
+    let anchor = match flags.raw("anchor") {
+        Some(name) => filters::Gravity::from_name(name).ok_or_else(|| tr!("overlay-unknown-anchor", anchor = name))?,
+        None => filters::Gravity::Center,
+    };
+    let placement = filters::Placement {
+        anchor,
+        x: flags.get_in("x", -max..=max, 0)?,
+        y: flags.get_in("y", -max..=max, 0)?,
+        scale: flags.get_in("scale", 0.01..=4.0, 1.0)?,
+    };
+
+    let resolver = ImageResolver::for_message(ctx, message).await?;
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let (below, top) = resolver.resolve_pair(ctx, message, flags.positional().to_vec()).await?;
+
+    pipeline::process_many(ctx, message, vec![below, top], "overlay", move |mut inputs| {
+        let top = inputs.pop().expect("Both images were decoded");
+        Ok(filters::overlay(inputs.remove(0), top, placement))
+    }).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+/// How compatible two users are, from 0 to 100. Always the same for the same pair, in either order.
+fn compatibility(first: UserId, second: UserId) -> u8 {
+    if first == second {
+        return 100;
+    }
+
+    // Mixed like SplitMix64, so neighbouring IDs don't get similar numbers.
+    let mut x = first.0.min(second.0).wrapping_mul(0x9E37_79B9_7F4A_7C15) ^ first.0.max(second.0);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    ((x ^ (x >> 31)) % 101) as u8
+}
+
+#[command]
+#[aliases("sidebyside", "beforeafter")]
+#[description("Puts two images side by side at the same height, or stacked at the same width with `--orientation v`. Handy for showing off a filter before and after.")]
+#[usage("[image] <image> [--orientation <h | v>] [--labels <first,second>]")]
+#[example("@user https://example.com/deepfried.png --labels \"before,after\"")]
+async fn compare(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, &[])?;
+    let vertical = match flags.raw("orientation") {
+        Some("h" | "horizontal") | None => false,
+        Some("v" | "vertical") => true,
+        Some(raw) => return Err(tr!("flag-invalid-value", flag = "orientation", value = raw).into()),
+    };
+    let labels = match flags.raw("labels") {
+        Some(raw) => match raw.split_once(',') {
+            Some((first, second)) if !second.contains(',') => Some([first.trim().to_string(), second.trim().to_string()]),
+            _ => return Err(tr!("compare-invalid-labels", labels = raw).into()),
+        },
+        None => None,
+    };
+
+    let resolver = ImageResolver::for_message(ctx, message).await?;
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let (first, second) = resolver.resolve_pair(ctx, message, flags.positional().to_vec()).await?;
+
+    pipeline::process_many(ctx, message, vec![first, second], "compare", move |inputs| {
+        let fonts = [&**fonts::BOLD];
+        let labels = labels.as_ref().map(|[first, second]| [first.as_str(), second.as_str()]);
+        Ok(Media::Static(filters::compare(inputs[0].first_frame(), inputs[1].first_frame(), vertical, labels, &fonts)))
+    }).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[aliases("love", "compatibility")]
+#[description("Ships two users, putting their avatars on either side of a heart with how compatible they are. Giving just one ships them with you.")]
+#[usage("<user> [user]")]
+#[example("@user @friend")]
+#[min_args(1)]
+#[max_args(2)]
+async fn ship(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let mut users = vec![];
+    for raw in args.raw() {
+        let user = User::convert(ctx, message.guild_id, Some(message.channel_id), raw)
+            .await
+            .map_err(|_| tr!("ship-unknown-user", user = raw))?;
+        users.push(user);
+    }
+    if users.len() == 1 {
+        users.insert(0, message.author.clone());
+    }
+
+    let percent = compatibility(users[0].id, users[1].id);
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    resolver.prefer_static_avatars();
+
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let first = resolver.resolve_avatar(ctx, message, &users[0]).await?;
+    let second = resolver.resolve_avatar(ctx, message, &users[1]).await?;
+
+    pipeline::process_many(ctx, message, vec![first, second], "ship", move |inputs| {
+        let fonts = [&**fonts::BOLD];
+        Ok(Media::Static(filters::ship(inputs[0].first_frame(), inputs[1].first_frame(), percent, &fonts)))
+    }).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[aliases("collage", "gallery")]
+#[description("Puts up to 9 images together in a grid of equal squares. Give them as attachments, mentions, links or links to messages.")]
+#[usage("<image...> [--gap <0-64>] [--background <color>]")]
+#[example("@user @friend @someone --gap 0 --background black")]
+async fn grid(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, &[])?;
+    let gap = flags.get_in("gap", 0..=64, 8)?;
+    let background = match flags.raw("background") {
+        Some(raw) => parse_color(raw).ok_or_else(|| tr!("flag-invalid-value", flag = "background", value = raw))?,
+        None => Rgba([255, 255, 255, 255]),
+    };
+    let queries = flags.positional().to_vec();
+
+    if message.attachments.len() + queries.len() > MAX_GRID_IMAGES {
+        return Err(tr!("grid-too-many", max = MAX_GRID_IMAGES).into());
+    }
+
+    let resolver = ImageResolver::for_message(ctx, message).await?;
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let results = resolver.resolve_many(ctx, message, queries).await?;
+
+    if results.len() < 2 {
+        return Err(tr!("grid-not-enough").into());
+    }
+
+    pipeline::process_many(ctx, message, results, "grid", move |inputs| {
+        let images = inputs.iter().map(|media| media.first_frame().clone()).collect::<Vec<_>>();
+        Ok(Media::Static(filters::grid(&images, gap, background)))
+    }).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[aliases("wm", "brand")]
+#[description("Stamps this server's watermark on an image, which admins set with `settings watermark`. Every frame of a GIF gets it.")]
+#[usage("[image] [--corner <position>] [--opacity <0.05-1>]")]
+#[example("@user --corner top-left")]
+#[only_in(guilds)]
+async fn watermark(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, &[])?;
+    let watermark = Database::get(ctx).await
+        .watermark(message.guild_id.unwrap())?
+        .ok_or_else(|| tr!("watermark-not-set"))?;
+
+    let anchor = match flags.raw("corner") {
+        Some(name) => filters::Gravity::from_name(name).ok_or_else(|| tr!("overlay-unknown-anchor", anchor = name))?,
+        None => watermark.anchor,
+    };
+    let opacity = flags.get_in("opacity", 0.05..=1.0, watermark.opacity)?;
+
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    resolver.apply_flags(&flags)?;
+    let query = flags.query();
+
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let result = resolver.resolve(ctx, message, query).await?;
+
+    pipeline::process(ctx, message, result, "watermark", move |media| {
+        let mark = match &watermark.mark {
+            WatermarkMark::Image(data) => Media::decode(data, &Quality::full())?.first_frame().clone(),
+            WatermarkMark::Text(text) => filters::watermark_text(text, &[&**fonts::BOLD]),
+        };
+        Ok(filters::watermark(media, &mark, anchor, opacity))
+    }).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[aliases("extract", "extractframes")]
+#[description("Extracts frames of a GIF as PNGs, all of them or just a range such as `5`, `2-8` or `10-`. Sends a ZIP when there are too many frames for one message or when `--zip` is given.")]
+#[usage("[range] [gif] [--zip]")]
+#[example("10-20 @user")]
+async fn frames(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, &["zip"])?;
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    resolver.policy(SourcePolicy::animated()).apply_flags(&flags)?;
+
+    let mut rest = flags.positional().iter().peekable();
+    let range = match rest.peek().and_then(|raw| parse_frame_range(raw)) {
+        Some(range) => {
+            rest.next();
+            Some(range?)
+        },
+        None => None,
+    };
+    let query = rest.next().cloned();
+
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let mut result = resolver.resolve(ctx, message, query).await?;
+
+    // Frame numbers have to match the original, so no frames are dropped even under load.
+    let quality = Quality { frame_step: 1, ..LoadMonitor::get(ctx).await.quality() };
+    let input = std::mem::take(&mut result.data);
+
+    let files = JobQueue::get(ctx).await.run(move || {
+        let frames = match Media::decode(&input, &quality)? {
+            Media::Animated(frames) => frames,
+            Media::Static(_) => return Err(photon_core::Error::NotAnimated.into()),
+        };
+
+        let count = frames.len();
+        let (start, end) = frame_bounds(range.unwrap_or((1, None)), count)?;
+        if end - start + 1 > MAX_EXTRACTED_FRAMES {
+            return Err(tr!("frames-too-many", count = end - start + 1, max = MAX_EXTRACTED_FRAMES).into());
+        }
+
+        let digits = count.to_string().len();
+        frames[start - 1..end]
+            .iter()
+            .zip(start..)
+            .map(|(frame, number)| {
+                let encoded = encode_image(frame.buffer(), OutputFormat::Png)?;
+                Ok(OutputFile::new(encoded, format!("frame-{:0width$}.png", number, width = digits)))
+            })
+            .collect::<Result<Vec<_>, CommandError>>()
+    }).await?;
+
+    let note = pipeline::degraded_note(ctx, message, &quality).await;
+    if flags.has("zip") || files.len() > MAX_FILES {
+        let archive = output::zip_files(&files, "frames.zip")?;
+        output::send(ctx, message, result.hash, result.nsfw, &[archive], note.as_deref()).await?;
+    } else {
+        output::send(ctx, message, result.hash, result.nsfw, &files, note.as_deref()).await?;
+    }
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[aliases("gifstats", "inspect")]
+#[description("Shows what a GIF is made of: its frames and their delays, how often it loops, its palettes and its size.")]
+#[usage("[gif]")]
+#[example("@user")]
+async fn gifinfo(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    let query = resolver.policy(SourcePolicy::animated()).parse_args(&args)?;
+    let result = resolver.resolve(ctx, message, query).await?;
+
+    let info = gif_info(&result.data)?;
+    let lang = i18n::catalog(ctx, message).await;
+    let (min, max) = info.delay_range();
+
+    let delays = match min == max {
+        true => lang.format(&tr!("gifinfo-delay", delay = min)),
+        false => lang.format(&tr!("gifinfo-delays", min = min, max = max, average = format!("{:.1}", info.average_delay()))),
+    };
+    let duration = lang.format(&tr!("gifinfo-duration-value", seconds = format!("{:.2}", info.duration_ms() as f64 / 1000.0)));
+    let loops = lang.format(&match info.loop_count {
+        None => tr!("gifinfo-loops-once"),
+        Some(0) => tr!("gifinfo-loops-forever"),
+        Some(count) => tr!("gifinfo-loops-count", count = count as u32 + 1),
+    });
+    let palettes = [
+        info.global_palette.map(|colors| lang.format(&tr!("gifinfo-palette-global", colors = colors))),
+        info.largest_local_palette.map(|colors| lang.format(&tr!(
+            "gifinfo-palette-local",
+            frames = info.local_palettes,
+            colors = colors,
+        ))),
+    ];
+    let palettes = match palettes.into_iter().flatten().collect::<Vec<_>>() {
+        palettes if palettes.is_empty() => lang.format(&tr!("gifinfo-palette-none")),
+        palettes => palettes.join("\n"),
+    };
+
+    message.channel_id.send_message(ctx, |m| m.embed(|e| e
+        .title(lang.format(&tr!("gifinfo-title")))
+        .field(lang.format(&tr!("gifinfo-frames")), info.frame_count(), true)
+        .field(lang.format(&tr!("gifinfo-duration")), duration, true)
+        .field(lang.format(&tr!("gifinfo-loops")), loops, true)
+        .field(lang.format(&tr!("gifinfo-delays-title")), delays, true)
+        .field(lang.format(&tr!("gifinfo-dimensions")), format!("{}×{}", info.width, info.height), true)
+        .field(lang.format(&tr!("gifinfo-size")), ImageResolver::humanize_size(info.size as f64), true)
+        .field(lang.format(&tr!("gifinfo-palettes")), palettes, false)
+    )).await?;
+
+    Ok(())
+}
+
+/// Parses how many times a GIF should play, as the loop count stored in it.
+fn parse_loop_count(raw: &str) -> Option<Option<u16>> {
+    match raw.to_lowercase().as_str() {
+        "forever" | "infinite" | "always" => Some(Some(0)),
+        "once" => Some(None),
+        raw => match raw.parse::<u32>().ok()? {
+            0 => None,
+            1 => Some(None),
+            times => Some(Some(u16::try_from(times - 1).ok()?)),
+        },
+    }
+}
+
+#[command("loop")]
+#[aliases("loops", "loopcount")]
+#[description("Changes how many times a GIF plays, from `once` to `forever`. Only the looping info is changed, so the frames stay exactly as they are.")]
+#[usage("<count | once | forever> [gif]")]
+#[example("3 @user")]
+#[min_args(1)]
+async fn loop_count(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let raw = args.single::<String>()?;
+    let count = parse_loop_count(&raw).ok_or_else(|| tr!("loop-invalid-count", count = &raw))?;
+
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    let query = resolver.policy(SourcePolicy::animated()).parse_args(&args)?;
+    let result = resolver.resolve(ctx, message, query).await?;
+
+    let file = OutputFile::new(set_loop_count(&result.data, count)?, "loop.gif");
+    output::send(ctx, message, result.hash, result.nsfw, &[file], None).await?;
+
+    Ok(())
+}
+
+#[command]
+#[aliases("round-crop")]
+#[description("Crops an image to a circle, like an avatar. `--border` draws a ring of that color around it.")]
+#[usage("[image] [--border <color>] [--border-width <pixels>]")]
+#[example("@user --border blurple --border-width 8")]
+async fn circle(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "circle", &[]).await
+}
+
+#[command]
+#[aliases("rounded", "corners")]
+#[description("Rounds the corners of an image, by a radius in pixels or a percentage of the shorter side. Defaults to 10%.")]
+#[usage("[radius | N%] [image]")]
+#[example("25% @user")]
+async fn round(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    match args.current().and_then(|raw| raw.strip_suffix('%')).map(str::to_string) {
+        Some(percent) => {
+            args.advance();
+            pipeline::run_filter_with(ctx, message, &args, "round", &[], vec![("percent", percent)]).await
+        },
+        None => pipeline::run_filter(ctx, message, &args, "round", &["radius"]).await,
+    }
+}
+
+#[command]
+#[aliases("sticker", "stickerize")]
+#[description("Makes a sticker: the subject is cut out of its background and given a thick white outline and a soft shadow. Works best on things in front of a plain background, raise `--tolerance` if some of it is left over.")]
+#[usage("[image] [--tolerance <0-200>]")]
+#[example("@user --tolerance 60")]
+async fn stickerify(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    pipeline::run_filter(ctx, message, &args, "stickerify", &[]).await
+}
+
+#[command]
+#[aliases("bg", "backdrop")]
+#[description("Fills in the transparent parts of an image with a color, or puts it in front of a second image scaled to cover it. Turns stickers and cutouts into images that look right anywhere, even as JPEGs.")]
+#[usage("<color | image> [image]")]
+#[example("white @user")]
+#[min_args(1)]
+async fn background(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, &[])?;
+    if flags.positional().first().map_or(false, |raw| parse_color(raw).is_some()) {
+        return pipeline::run_filter(ctx, message, &args, "background", &["color"]).await;
+    }
+
+    // Otherwise the backdrop comes last like the second image of `blend`, with the image in front of it
+    // found as usual when it's the only one given.
+    let mut queries = flags.positional().to_vec();
+    queries.rotate_left(1);
+
+    let resolver = ImageResolver::for_message(ctx, message).await?;
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let (front, backdrop) = resolver.resolve_pair(ctx, message, queries).await?;
+
+    pipeline::process_many(ctx, message, vec![front, backdrop], "background", move |mut inputs| {
+        let backdrop = inputs.pop().expect("Both images were decoded");
+        Ok(filters::background_image(inputs.remove(0), backdrop.first_frame()))
+    }).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+/// How `removebg` tells the subject apart from the background.
+enum Backend {
+    /// Clears the background connected to the edges of the image.
+    Flood,
+    /// Keys out a color everywhere, the background's when none is given.
+    Chroma(Option<Rgba<u8>>),
+    #[cfg(feature = "removebg")]
+    Model(Arc<SegmentationModel>),
+}
+
+#[cfg(feature = "removebg")]
+async fn model_backend(ctx: &Context) -> Option<Backend> {
+    SegmentationModel::get(ctx).await.map(Backend::Model)
+}
+
+#[cfg(not(feature = "removebg"))]
+async fn model_backend(_ctx: &Context) -> Option<Backend> {
+    None
+}
+
+#[command]
+#[aliases("nobg", "cutout")]
+#[description("Removes the background of an image or GIF, leaving a transparent PNG or GIF. Plain backgrounds are cleared from the edges in, or keyed out everywhere with `--backend chroma`. When the bot has a segmentation model set up, it's used for everything else and by default.")]
+#[usage("[image] [--backend <flood | chroma | model>] [--color <color>] [--tolerance <0-200>]")]
+#[example("@user --backend chroma --color green")]
+async fn removebg(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, &[])?;
+    let color = match flags.raw("color") {
+        Some(raw) => Some(parse_color(raw).ok_or_else(|| tr!("flag-invalid-value", flag = "color", value = raw))?),
+        None => None,
+    };
+    let backend = match flags.raw("backend").map(str::to_lowercase).as_deref() {
+        None if color.is_some() => Backend::Chroma(color),
+        None => model_backend(ctx).await.unwrap_or(Backend::Flood),
+        Some("flood") => Backend::Flood,
+        Some("chroma") => Backend::Chroma(color),
+        Some("model") => model_backend(ctx).await.ok_or_else(|| tr!("removebg-model-unavailable"))?,
+        Some(raw) => return Err(tr!("flag-invalid-value", flag = "backend", value = raw).into()),
+    };
+    let tolerance = flags.get_in("tolerance", 0.0..=200.0, 40.0)?;
+
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    resolver.apply_flags(&flags)?;
+    let query = flags.query();
+
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let result = resolver.resolve(ctx, message, query).await?;
+
+    // Every frame of an animation is cut out, keying out the same color in all of them.
+    pipeline::process(ctx, message, result, "removebg", move |media| {
+        Ok(match backend {
+            Backend::Flood => media.par_map_images(|img| filters::remove_background(img, tolerance)),
+            Backend::Chroma(color) => {
+                let color = color.unwrap_or_else(|| filters::backdrop_color(media.first_frame()));
+                media.par_map_images(|img| filters::chroma_key(img, color, tolerance))
+            },
+            #[cfg(feature = "removebg")]
+            Backend::Model(model) => {
+                let masks = media.images().into_iter().map(|img| model.predict(img)).collect::<Result<Vec<_>, _>>()?;
+                media.map_images_indexed(|index, img| filters::apply_mask(img, &masks[index]))
+            },
+        })
+    }).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[description("Resizes an image to several sizes at once, e.g. for icons. Sends a ZIP when there are too many sizes for one message or when `--zip` is given.")]
+#[usage("<size,size,...> [image] [--zip]")]
+#[example("64,128,256,512 @user")]
+async fn sizes(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let config = Config::get(ctx).await;
+    let max = config.resolver.max_width.min(config.resolver.max_height) as u32;
+    let raw = args.single::<String>()?;
+
+    let mut sizes = raw
+        .split(',')
+        .filter(|s| !s.trim().is_empty())
+        .map(|s| match s.trim().parse::<u32>() {
+            Ok(size) if (1..=max).contains(&size) => Ok(size),
+            _ => Err(tr!("sizes-invalid-size", size = s.trim(), max = max)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    sizes.sort_unstable();
+    sizes.dedup();
+
+    if sizes.is_empty() || sizes.len() > MAX_SIZES {
+        return Err(tr!("sizes-invalid-count", max = MAX_SIZES).into());
+    }
+
+    let flags = Flags::parse(&args, &["zip"])?;
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    resolver.apply_flags(&flags)?;
+    let query = flags.query();
+
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let mut result = resolver.resolve(ctx, message, query).await?;
+
+    let quality = LoadMonitor::get(ctx).await.quality();
+    let format = output::static_format(ctx, message).await?;
+    let input = std::mem::take(&mut result.data);
+
+    let files = JobQueue::get(ctx).await.run(move || {
+        let media = Media::decode(&input, &quality)?;
+
+        sizes
+            .into_iter()
+            .map(|size| {
+                let (encoded, format) = media.clone().resize_to_fit(size, size, FilterType::Lanczos3).encode(format)?;
+                Ok(OutputFile::new(encoded, format!("{}.{}", size, format.extension())))
+            })
+            .collect::<Result<Vec<_>, CommandError>>()
+    }).await?;
+
+    let note = pipeline::degraded_note(ctx, message, &quality).await;
+    if flags.has("zip") || files.len() > MAX_FILES {
+        let archive = output::zip_files(&files, "sizes.zip")?;
+        output::send(ctx, message, result.hash, result.nsfw, &[archive], note.as_deref()).await?;
+    } else {
+        output::send(ctx, message, result.hash, result.nsfw, &files, note.as_deref()).await?;
+    }
+
+    typing.stop();
+    Ok(())
+}
+
+#[command]
+#[description("Color grades an image with a LUT. See `lut list` for the ones available here.")]
+#[usage("<name> [image]")]
+#[example("noir @user")]
+#[sub_commands(lut_list, lut_upload, lut_remove)]
+#[min_args(1)]
+async fn lut(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>()?.to_lowercase();
+    let table = luts::find(ctx, message.guild_id, &name).await?;
+
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    let query = resolver.parse_args(&args)?;
+
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let result = resolver.resolve(ctx, message, query).await?;
+
+    pipeline::process(ctx, message, result, "lut", move |media| Ok(filters::apply_lut(media, &table))).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[command("list")]
+#[description("Lists the bundled LUTs and the ones uploaded to this server.")]
+async fn lut_list(ctx: &Context, message: &Message) -> CommandResult {
+    let lang = i18n::catalog(ctx, message).await;
+    let list = |names: Vec<&str>| names.iter().map(|n| format!("`{}`", n)).collect::<Vec<_>>().join(", ");
+
+    let uploaded = match message.guild_id {
+        Some(guild_id) => Database::get(ctx).await.guild_lut_names(guild_id)?,
+        None => vec![],
+    };
+    let uploaded = match uploaded.is_empty() {
+        true => lang.format(&tr!("lut-list-none")),
+        false => list(uploaded.iter().map(String::as_str).collect()),
+    };
+
+    message.channel_id.send_message(ctx, |m| m.embed(|e| e
+        .title(lang.format(&tr!("lut-list-title")))
+        .field(lang.format(&tr!("lut-list-bundled")), list(luts::BUNDLED.iter().map(|(name, _)| *name).collect()), false)
+        .field(lang.format(&tr!("lut-list-uploaded")), uploaded, false)
+    )).await?;
+
+    Ok(())
+}
+
+#[command("upload")]
+#[aliases("add")]
+#[description("Adds a LUT to this server from an attached `.cube` file, replacing any with the same name.")]
+#[usage("<name>")]
+#[example("sunset")]
+#[only_in(guilds)]
+#[required_permissions(MANAGE_GUILD)]
+#[num_args(1)]
+async fn lut_upload(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>()?.to_lowercase();
+    if !luts::is_valid_name(&name) {
+        return Err(tr!("lut-invalid-name", name = name).into());
+    }
+
+    let attachment = message.attachments
+        .iter()
+        .find(|a| a.filename.to_lowercase().ends_with(".cube"))
+        .ok_or_else(|| tr!("lut-missing-file"))?;
+
+    if attachment.size > MAX_FILE_SIZE {
+        return Err(tr!("lut-file-too-big", max = MAX_FILE_SIZE / 1024 / 1024).into());
+    }
+
+    let guild_id = message.guild_id.unwrap();
+    let db = Database::get(ctx).await;
+    let existing = db.guild_lut_names(guild_id)?;
+
+    if existing.len() >= MAX_GUILD_LUTS && !existing.contains(&name) {
+        return Err(tr!("lut-too-many", max = MAX_GUILD_LUTS).into());
+    }
+
+    let source = String::from_utf8(attachment.download().await?).map_err(|_| tr!("lut-not-text"))?;
+    let table = luts::parse(ctx, source.clone()).await?;
+
+    db.set_guild_lut(guild_id, &name, &source, message.author.id)?;
+
+    let text = tr!("lut-uploaded", name = name, size = table.size());
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await?;
+
+    Ok(())
+}
+
+#[command("remove")]
+#[aliases("delete")]
+#[description("Removes a LUT uploaded to this server.")]
+#[usage("<name>")]
+#[only_in(guilds)]
+#[required_permissions(MANAGE_GUILD)]
+#[num_args(1)]
+async fn lut_remove(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let name = args.single::<String>()?.to_lowercase();
+
+    let text = match Database::get(ctx).await.remove_guild_lut(message.guild_id.unwrap(), &name)? {
+        true => tr!("lut-removed", name = name),
+        false => tr!("lut-unknown", name = name),
+    };
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn range(raw: &str) -> Option<Result<(usize, Option<usize>), ()>> {
+        parse_frame_range(raw).map(|range| range.map_err(|_| ()))
+    }
+
+    #[test]
+    fn parses_frame_ranges() {
+        assert_eq!(range("2-8"), Some(Ok((2, Some(8)))));
+        assert_eq!(range("5-"), Some(Ok((5, None))));
+        assert_eq!(range("-3"), Some(Ok((1, Some(3)))));
+        assert_eq!(range("4"), Some(Ok((4, Some(4)))));
+        assert_eq!(range("3-3"), Some(Ok((3, Some(3)))));
+    }
+
+    #[test]
+    fn rejects_invalid_frame_ranges() {
+        assert_eq!(range("8-2"), Some(Err(())));
+        assert_eq!(range("0"), Some(Err(())));
+        assert_eq!(range("0-4"), Some(Err(())));
+        assert_eq!(range("1-2-3"), Some(Err(())));
+        assert_eq!(range("99999999999999999999999"), Some(Err(())));
+
+        // Anything that isn't shaped like a range is left to be the image query.
+        assert_eq!(range("-"), None);
+        assert_eq!(range("@user"), None);
+        assert_eq!(range("https://example.com/1-2.gif"), None);
+    }
+
+    #[test]
+    fn bounds_frame_ranges() {
+        assert_eq!(frame_bounds((2, Some(8)), 10).unwrap(), (2, 8));
+        assert_eq!(frame_bounds((5, None), 10).unwrap(), (5, 10));
+        assert_eq!(frame_bounds((10, Some(10)), 10).unwrap(), (10, 10));
+        assert!(frame_bounds((2, Some(11)), 10).is_err());
+        assert!(frame_bounds((11, None), 10).is_err());
+    }
+
+    #[test]
+    fn parses_loop_counts() {
+        assert_eq!(parse_loop_count("forever"), Some(Some(0)));
+        assert_eq!(parse_loop_count("Infinite"), Some(Some(0)));
+        assert_eq!(parse_loop_count("once"), Some(None));
+        assert_eq!(parse_loop_count("1"), Some(None));
+
+        // GIFs store how often they repeat after the first play.
+        assert_eq!(parse_loop_count("3"), Some(Some(2)));
+        assert_eq!(parse_loop_count("65536"), Some(Some(u16::MAX)));
+
+        assert_eq!(parse_loop_count("0"), None);
+        assert_eq!(parse_loop_count("65537"), None);
+        assert_eq!(parse_loop_count("-1"), None);
+        assert_eq!(parse_loop_count("twice"), None);
+    }
+}
@@ -0,0 +1,158 @@
+//! The commands made from meme templates. They all share one command function, which looks up the
+//! template by the name it was invoked with.
+
+use photon_core::Media;
+
+use serenity::client::Context;
+use serenity::framework::standard::{
+    Args,
+    Check,
+    Command,
+    CommandGroup,
+    CommandOptions,
+    CommandResult,
+    GroupOptions,
+    macros::command,
+};
+use serenity::model::channel::Message;
+
+use crate::fonts;
+use crate::jobs::JobQueue;
+use crate::nsfw::NSFW_CHECK;
+use crate::output::{self, OutputFile};
+use crate::pipeline;
+use crate::quality::LoadMonitor;
+use crate::resolve_image::ImageResolver;
+use crate::templates::{Input, InputKind, Templates};
+use crate::tr;
+
+/// The checks of templates marked NSFW.
+static NSFW_CHECKS: &[&Check] = &[&NSFW_CHECK];
+
+/// Leaks the value, command metadata has to live for the rest of the program.
+fn leak<T>(value: T) -> &'static T {
+    Box::leak(Box::new(value))
+}
+
+fn leak_str(s: &str) -> &'static str {
+    Box::leak(s.to_string().into_boxed_str())
+}
+
+/// Builds a command for every template, `None` when there are none.
+pub fn group(templates: &Templates) -> Option<&'static CommandGroup> {
+    let commands = templates
+        .iter()
+        .map(|template| {
+            let names = template.names().map(leak_str).collect::<Vec<_>>();
+            let examples = template.example.as_deref().map(leak_str).into_iter().collect::<Vec<_>>();
+
+            &*leak(Command {
+                fun: MEME_COMMAND.fun,
+                options: leak(CommandOptions {
+                    checks: if template.nsfw { NSFW_CHECKS } else { &[] },
+                    names: Box::leak(names.into_boxed_slice()),
+                    desc: Some(leak_str(&template.description)),
+                    usage: Some(leak_str(&template.usage())),
+                    examples: Box::leak(examples.into_boxed_slice()),
+                    help_available: true,
+                    owner_privilege: true,
+                    ..Default::default()
+                }),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if commands.is_empty() {
+        return None;
+    }
+
+    Some(leak(CommandGroup {
+        name: "Memes",
+        options: leak(GroupOptions {
+            commands: Box::leak(commands.into_boxed_slice()),
+            help_available: true,
+            owner_privilege: true,
+            ..Default::default()
+        }),
+    }))
+}
+
+/// The name the command was invoked with, the framework doesn't pass it along.
+async fn invoked_name(ctx: &Context, message: &Message) -> Option<String> {
+    let prefix = crate::prefix(ctx, message).await;
+    let rest = message.content.strip_prefix(prefix.as_str())?;
+
+    rest.split_whitespace().next().map(str::to_lowercase)
+}
+
+/// An argument before the images are decoded.
+enum RawInput {
+    Image(Vec<u8>),
+    Text(String),
+}
+
+#[command]
+async fn meme(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let name = invoked_name(ctx, message).await.unwrap_or_default();
+    let template = Templates::get(ctx).await
+        .find(&name)
+        .ok_or_else(|| tr!("unknown-command", command = &name))?;
+
+    let inputs = template.inputs();
+    let mut segments = args.rest().splitn(inputs.len().max(1), '|').map(str::trim);
+
+    let resolver = ImageResolver::for_message(ctx, message).await?;
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+
+    let mut raw = Vec::with_capacity(inputs.len());
+    let mut texts = Vec::new();
+    let mut hash = None;
+    let mut nsfw = false;
+
+    for (kind, label) in inputs {
+        let segment = segments.next().unwrap_or_default();
+
+        match kind {
+            InputKind::Image => {
+                let query = Some(segment.to_string()).filter(|query| !query.is_empty());
+                let result = resolver.resolve(ctx, message, query).await?;
+
+                hash = hash.or(result.hash);
+                nsfw |= result.nsfw;
+                raw.push(RawInput::Image(result.data));
+            },
+            InputKind::Text { optional } => {
+                if segment.is_empty() && !optional {
+                    return Err(tr!("meme-missing-text", slot = label, usage = template.usage()).into());
+                }
+
+                texts.push(segment.to_string());
+                raw.push(RawInput::Text(segment.to_string()));
+            },
+        }
+    }
+
+    let emojis = fonts::fetch_emojis(&template.text(&texts)).await;
+    let quality = LoadMonitor::get(ctx).await.quality();
+    let format = output::static_format(ctx, message).await?;
+
+    let render = template.clone();
+    let (encoded, format) = JobQueue::get(ctx).await.run(move || {
+        let inputs = raw
+            .into_iter()
+            .map(|input| Ok(match input {
+                RawInput::Image(data) => Input::Image(Media::decode(&data, &quality)?),
+                RawInput::Text(text) => Input::Text(text),
+            }))
+            .collect::<photon_core::Result<Vec<_>>>()?;
+
+        Ok(render.render(inputs, &emojis).encode(format)?)
+    }).await?;
+
+    let file = OutputFile::new(encoded, format!("{}.{}", template.name, format.extension()));
+    let note = pipeline::degraded_note(ctx, message, &quality).await;
+    output::send(ctx, message, hash, nsfw, &[file], note.as_deref()).await?;
+
+    typing.stop();
+    Ok(())
+}
@@ -0,0 +1,23 @@
+pub mod fonts;
+pub mod imaging;
+pub mod memes;
+pub mod moderation;
+pub mod owner;
+pub mod prefs;
+pub mod settings;
+pub mod utility;
+
+use serenity::framework::standard::{Command, CommandGroup};
+
+/// Finds a command by any of its names or aliases, searching the given groups and their sub-groups.
+pub fn find_command(groups: &[&'static CommandGroup], name: &str) -> Option<&'static Command> {
+    let name = name.to_lowercase();
+
+    groups.iter().find_map(|group| {
+        group.options.commands
+            .iter()
+            .copied()
+            .find(|command| command.options.names.contains(&name.as_str()))
+            .or_else(|| find_command(group.options.sub_groups, &name))
+    })
+}
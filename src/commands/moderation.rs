@@ -0,0 +1,86 @@
+use serenity::client::Context;
+use serenity::framework::standard::{
+    CommandError,
+    CommandResult,
+    macros::{command, group},
+};
+use serenity::model::channel::Message;
+
+use crate::db::Database;
+use crate::i18n;
+use crate::resolve_image::{ImageResolver, Source};
+use crate::tr;
+
+#[group]
+#[only_in(guilds)]
+#[required_permissions(MANAGE_MESSAGES)]
+#[commands(block_image, unblock_image)]
+struct Moderation;
+
+/// Finds the hash of the image a moderator is replying to.
+///
+/// If the replied message is one of our outputs, this is the hash of the source it was made from,
+/// otherwise the image on the replied message itself is hashed.
+async fn replied_hash(ctx: &Context, message: &Message) -> Result<u64, CommandError> {
+    let reference = message.referenced_message
+        .as_ref()
+        .ok_or_else(|| tr!("block-image-no-reply"))?;
+
+    if let Some(hash) = Database::get(ctx).await.output_source(reference.id)? {
+        return Ok(hash);
+    }
+
+    let mut resolver = ImageResolver::for_guild(ctx, message.guild_id).await?;
+    resolver.source(Source::Reply);
+
+    resolver
+        .resolve(ctx, message, None)
+        .await?
+        .hash
+        .ok_or_else(|| tr!("replied-image-unreadable").into())
+}
+
+#[command("block-image")]
+#[aliases("blockimage")]
+#[description("Blocks the replied image (and anything that looks like it) from being processed in this server.")]
+async fn block_image(ctx: &Context, message: &Message) -> CommandResult {
+    let hash = replied_hash(ctx, message).await?;
+
+    Database::get(ctx).await.block_hash(message.guild_id.unwrap(), hash, message.author.id)?;
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&tr!("block-image-done"))).await?;
+
+    Ok(())
+}
+
+#[command("unblock-image")]
+#[aliases("unblockimage")]
+#[description("Unblocks a previously blocked image. Reply to my output of it, or to a copy of the image.")]
+async fn unblock_image(ctx: &Context, message: &Message) -> CommandResult {
+    let reference = message.referenced_message
+        .as_ref()
+        .ok_or_else(|| tr!("unblock-image-no-reply"))?;
+
+    let db = Database::get(ctx).await;
+    let hash = match db.output_source(reference.id)? {
+        Some(hash) => hash,
+        // The resolver refuses blocked images, so the replied image has to be hashed directly.
+        None => {
+            let attachment = reference.attachments
+                .first()
+                .ok_or_else(|| tr!("replied-image-missing"))?;
+            let data = attachment.download().await?;
+
+            tokio::task::spawn_blocking(move || crate::phash::dhash_bytes(&data))
+                .await?
+                .ok_or_else(|| tr!("replied-image-unreadable"))?
+        },
+    };
+
+    let text = match db.unblock_hash(message.guild_id.unwrap(), hash)? {
+        0 => tr!("unblock-image-not-blocked"),
+        _ => tr!("unblock-image-done"),
+    };
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+use serenity::client::Context;
+use serenity::framework::standard::{
+    Args,
+    CommandError,
+    CommandResult,
+    macros::{command, group},
+};
+use serenity::model::channel::Message;
+
+use crate::db::{BlacklistKind, Database};
+use crate::i18n;
+use crate::tr;
+
+#[group]
+#[owners_only]
+#[prefixes("blacklist", "bl")]
+#[default_command(list)]
+#[commands(add, remove, list)]
+struct Blacklist;
+
+/// Parses a raw id or a user, channel or role mention.
+fn parse_id(value: &str) -> Result<u64, CommandError> {
+    value
+        .trim_start_matches(|c| c == '<' || c == '@' || c == '!' || c == '#' || c == '&')
+        .trim_end_matches('>')
+        .parse()
+        .map_err(|_| tr!("invalid-id", id = value).into())
+}
+
+#[command]
+#[description("Blacklists a user or guild from using the bot.")]
+#[usage("<user | guild> <id> [reason]")]
+#[min_args(2)]
+async fn add(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let kind = args.single::<BlacklistKind>()?;
+    let id = parse_id(&args.single::<String>()?)?;
+    let reason = Some(args.rest().trim()).filter(|r| !r.is_empty());
+
+    Database::get(ctx).await.add_to_blacklist(kind, id, reason, None)?;
+    let text = tr!("blacklist-added", kind = kind.as_str(), id = id);
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description("Removes a user or guild from the blacklist.")]
+#[usage("<user | guild> <id>")]
+#[num_args(2)]
+async fn remove(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let kind = args.single::<BlacklistKind>()?;
+    let id = parse_id(&args.single::<String>()?)?;
+
+    let text = match Database::get(ctx).await.remove_from_blacklist(kind, id)? {
+        true => tr!("blacklist-removed", kind = kind.as_str(), id = id),
+        false => tr!("blacklist-not-listed", kind = kind.as_str(), id = id),
+    };
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description("Lists every blacklisted user and guild.")]
+async fn list(ctx: &Context, message: &Message) -> CommandResult {
+    let lang = i18n::catalog(ctx, message).await;
+    let entries = Database::get(ctx).await.blacklist()?;
+
+    if entries.is_empty() {
+        message.reply(ctx, lang.format(&tr!("blacklist-empty"))).await?;
+        return Ok(());
+    }
+
+    let lines = entries
+        .iter()
+        .map(|entry| format!(
+            "{} `{}`{}{}",
+            entry.kind.as_str(),
+            entry.id,
+            entry.reason.as_ref().map_or(String::new(), |r| format!(" - {}", r)),
+            entry.expires_at.map_or(String::new(), |t| lang.format(&tr!("blacklist-expires", timestamp = t))),
+        ))
+        .collect::<Vec<_>>();
+
+    // Stay well within the embed description limit.
+    let mut description = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if description.len() + line.len() > 3900 {
+            description.push_str(&lang.format(&tr!("blacklist-more", count = lines.len() - i)));
+            break;
+        }
+
+        description.push_str(line);
+        description.push('\n');
+    }
+
+    message.channel_id.send_message(ctx, |m| m.embed(|e| e
+        .title(lang.format(&tr!("blacklist-title", count = entries.len())))
+        .description(description)
+    )).await?;
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use photon_core::OutputFormat;
+
+use serenity::client::Context;
+use serenity::framework::standard::{
+    Args,
+    CommandError,
+    CommandResult,
+    macros::{command, group},
+};
+use serenity::model::channel::Message;
+
+use crate::db::{Database, UserToggle};
+use crate::i18n::{self, Catalog, Text};
+use crate::tr;
+
+#[group]
+#[prefixes("prefs", "preferences")]
+#[default_command(show)]
+#[commands(show, set_format, static_avatars, spoiler, dm)]
+struct Preferences;
+
+fn parse_toggle(value: &str) -> Result<bool, CommandError> {
+    match value.to_lowercase().as_str() {
+        "on" | "yes" | "true" | "enable" => Ok(true),
+        "off" | "no" | "false" | "disable" => Ok(false),
+        _ => Err(tr!("toggle-invalid", value = value).into()),
+    }
+}
+
+fn describe_toggle(lang: &Catalog, value: bool) -> String {
+    lang.format(&if value { tr!("toggle-on") } else { tr!("toggle-off") })
+}
+
+#[command]
+#[description("Shows your preferences.")]
+async fn show(ctx: &Context, message: &Message) -> CommandResult {
+    let lang = i18n::catalog(ctx, message).await;
+    let prefs = Database::get(ctx).await.user_prefs(message.author.id)?;
+    let format = prefs.format.map_or_else(|| lang.format(&tr!("prefs-server-default")), |f| f.to_string());
+
+    message.channel_id.send_message(ctx, |m| m.embed(|e| e
+        .title(lang.format(&tr!("prefs-title", user = message.author.tag())))
+        .field(lang.format(&tr!("prefs-format")), format, true)
+        .field(lang.format(&tr!("prefs-static-avatars")), describe_toggle(&lang, prefs.static_avatars), true)
+        .field(lang.format(&tr!("prefs-spoiler")), describe_toggle(&lang, prefs.spoiler), true)
+        .field(lang.format(&tr!("prefs-dm-results")), describe_toggle(&lang, prefs.dm_results), true)
+    )).await?;
+
+    Ok(())
+}
+
+#[command("format")]
+#[description("Sets your preferred output format for static images. Use `reset` to follow the server default.")]
+#[usage("<png | jpeg | gif | reset>")]
+#[num_args(1)]
+async fn set_format(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let value = args.single::<String>()?;
+    let format = match value.to_lowercase().as_str() {
+        "reset" | "default" | "none" => None,
+        _ => Some(value.parse::<OutputFormat>()?),
+    };
+
+    Database::get(ctx).await.set_user_format(message.author.id, format)?;
+    let text = match format {
+        Some(format) => tr!("prefs-format-set", format = format),
+        None => tr!("prefs-format-reset"),
+    };
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await?;
+
+    Ok(())
+}
+
+/// `name` is the message key of the preference's name.
+async fn toggle(ctx: &Context, message: &Message, mut args: Args, toggle: UserToggle, name: &'static str) -> CommandResult {
+    let value = parse_toggle(&args.single::<String>()?)?;
+    let lang = i18n::catalog(ctx, message).await;
+
+    Database::get(ctx).await.set_user_toggle(message.author.id, toggle, value)?;
+
+    let text = match value {
+        true => tr!("prefs-toggle-on", name = lang.format(&Text::new(name))),
+        false => tr!("prefs-toggle-off", name = lang.format(&Text::new(name))),
+    };
+    message.reply(ctx, lang.format(&text)).await?;
+
+    Ok(())
+}
+
+#[command("staticavatars")]
+#[description("Whether to use the static version of animated avatars.")]
+#[usage("<on | off>")]
+#[num_args(1)]
+async fn static_avatars(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    toggle(ctx, message, args, UserToggle::StaticAvatars, "prefs-static-avatars").await
+}
+
+#[command]
+#[description("Whether to mark your outputs as spoilers.")]
+#[usage("<on | off>")]
+#[num_args(1)]
+async fn spoiler(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    toggle(ctx, message, args, UserToggle::Spoiler, "prefs-spoiler").await
+}
+
+#[command]
+#[description("Whether to send your outputs to your DMs instead of the channel.")]
+#[usage("<on | off>")]
+#[num_args(1)]
+async fn dm(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    toggle(ctx, message, args, UserToggle::DmResults, "prefs-dm-results").await
+}
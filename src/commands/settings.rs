@@ -0,0 +1,267 @@
+use photon_core::filters::Gravity;
+use photon_core::{Media, OutputFormat, Quality};
+
+use serenity::client::Context;
+use serenity::framework::standard::{
+    Args,
+    CommandResult,
+    macros::{command, group},
+};
+use serenity::model::channel::Message;
+
+use crate::args::Flags;
+use crate::config::Config;
+use crate::db::{Database, Watermark, WatermarkMark};
+use crate::i18n::{self, Locales};
+use crate::jobs::JobQueue;
+use crate::tr;
+
+#[group]
+#[prefixes("settings", "config")]
+#[only_in(guilds)]
+#[required_permissions(MANAGE_GUILD)]
+#[default_command(show)]
+#[commands(show, set_prefix, disable, enable, max_size, set_format, language, set_watermark)]
+struct Settings;
+
+/// Watermark images are kept in the database, so they can't be any larger than this.
+const MAX_WATERMARK_SIZE: u64 = 1024 * 1024;
+/// The most characters a text watermark can have.
+const MAX_WATERMARK_TEXT: usize = 64;
+
+fn is_reset(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "reset" | "default" | "none")
+}
+
+#[command]
+#[description("Shows this server's settings.")]
+async fn show(ctx: &Context, message: &Message) -> CommandResult {
+    let config = Config::get(ctx).await;
+    let lang = i18n::catalog(ctx, message).await;
+    let settings = Database::get(ctx).await.guild_settings(message.guild_id.unwrap())?;
+
+    let disabled = if settings.disabled_commands.is_empty() {
+        lang.format(&tr!("settings-none"))
+    } else {
+        settings.disabled_commands.iter().map(|c| format!("`{}`", c)).collect::<Vec<_>>().join(", ")
+    };
+    let max_size = settings.max_image_size.unwrap_or(config.resolver.max_size) / 1024 / 1024;
+    let watermark = match Database::get(ctx).await.watermark(message.guild_id.unwrap())? {
+        Some(watermark) => {
+            let mark = match watermark.mark {
+                WatermarkMark::Image(_) => lang.format(&tr!("settings-watermark-image")),
+                WatermarkMark::Text(text) => format!("`{}`", text),
+            };
+            let opacity = (watermark.opacity * 100.0).round();
+            lang.format(&tr!("settings-watermark-summary", mark = mark, anchor = watermark.anchor.name(), opacity = opacity))
+        },
+        None => lang.format(&tr!("settings-none")),
+    };
+
+    message.channel_id.send_message(ctx, |m| m.embed(|e| e
+        .title(lang.format(&tr!("settings-title")))
+        .field(lang.format(&tr!("settings-prefix")), format!("`{}`", settings.prefix.as_deref().unwrap_or(&config.prefix)), true)
+        .field(lang.format(&tr!("settings-max-size")), lang.format(&tr!("size-mib", size = max_size)), true)
+        .field(lang.format(&tr!("settings-default-format")), settings.default_format.unwrap_or_default().as_str(), true)
+        .field(lang.format(&tr!("settings-language")), format!("`{}`", lang.language()), true)
+        .field(lang.format(&tr!("settings-watermark")), watermark, false)
+        .field(lang.format(&tr!("settings-disabled-commands")), disabled, false)
+    )).await?;
+
+    Ok(())
+}
+
+#[command("prefix")]
+#[description("Sets this server's prefix. Use `reset` to go back to the default prefix.")]
+#[usage("<prefix | reset>")]
+#[num_args(1)]
+async fn set_prefix(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let prefix = args.single_quoted::<String>()?;
+    let db = Database::get(ctx).await;
+    let lang = i18n::catalog(ctx, message).await;
+
+    if is_reset(&prefix) {
+        db.set_prefix(message.guild_id.unwrap(), None)?;
+        message.reply(ctx, lang.format(&tr!("settings-prefix-reset"))).await?;
+
+        return Ok(());
+    }
+
+    if prefix.chars().count() > 16 || prefix.chars().any(char::is_whitespace) {
+        return Err(tr!("settings-prefix-invalid").into());
+    }
+
+    db.set_prefix(message.guild_id.unwrap(), Some(&prefix))?;
+    message.reply(ctx, lang.format(&tr!("settings-prefix-set", prefix = prefix))).await?;
+
+    Ok(())
+}
+
+async fn toggle_command(ctx: &Context, message: &Message, mut args: Args, disabled: bool) -> CommandResult {
+    let name = args.single::<String>()?;
+    let command = crate::commands::find_command(crate::TOGGLEABLE_GROUPS, &name)
+        .ok_or_else(|| tr!("unknown-command", command = &name))?;
+    let name = command.options.names[0];
+
+    Database::get(ctx).await.set_command_disabled(message.guild_id.unwrap(), name, disabled)?;
+
+    let text = match disabled {
+        true => tr!("settings-command-disabled", command = name),
+        false => tr!("settings-command-enabled", command = name),
+    };
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description("Disables a command in this server.")]
+#[usage("<command>")]
+#[num_args(1)]
+async fn disable(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    toggle_command(ctx, message, args, true).await
+}
+
+#[command]
+#[description("Re-enables a disabled command in this server.")]
+#[usage("<command>")]
+#[num_args(1)]
+async fn enable(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    toggle_command(ctx, message, args, false).await
+}
+
+#[command("maxsize")]
+#[description("Sets the maximum image size in MiB that commands will accept. Cannot exceed the bot-wide limit.")]
+#[usage("<MiB | reset>")]
+#[num_args(1)]
+async fn max_size(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let value = args.single::<String>()?;
+    let db = Database::get(ctx).await;
+    let lang = i18n::catalog(ctx, message).await;
+
+    if is_reset(&value) {
+        db.set_max_image_size(message.guild_id.unwrap(), None)?;
+        message.reply(ctx, lang.format(&tr!("settings-max-size-reset"))).await?;
+
+        return Ok(());
+    }
+
+    let limit = Config::get(ctx).await.resolver.max_size;
+    let size = value
+        .parse::<f64>()
+        .ok()
+        .filter(|mib| *mib > 0.0)
+        .map(|mib| (mib * 1024.0 * 1024.0) as usize)
+        .ok_or_else(|| tr!("settings-max-size-invalid", size = &value))?;
+
+    if size > limit {
+        return Err(tr!("settings-max-size-too-big", limit = limit / 1024 / 1024).into());
+    }
+
+    db.set_max_image_size(message.guild_id.unwrap(), Some(size))?;
+    message.reply(ctx, lang.format(&tr!("settings-max-size-set", size = value))).await?;
+
+    Ok(())
+}
+
+#[command("format")]
+#[description("Sets the default output format for static images.")]
+#[usage("<png | jpeg | gif | reset>")]
+#[num_args(1)]
+async fn set_format(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let value = args.single::<String>()?;
+    let format = if is_reset(&value) { None } else { Some(value.parse::<OutputFormat>()?) };
+
+    Database::get(ctx).await.set_default_format(message.guild_id.unwrap(), format)?;
+
+    let text = tr!("settings-format-set", format = format.unwrap_or_default());
+    message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await?;
+
+    Ok(())
+}
+
+#[command]
+#[description("Sets the language the bot replies in. Leave it out to list the available languages.")]
+#[usage("[language | reset]")]
+#[example("en")]
+#[max_args(1)]
+async fn language(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let locales = Locales::get(ctx).await;
+    let available = locales.languages().iter().map(|l| format!("`{}`", l)).collect::<Vec<_>>().join(", ");
+
+    let value = match args.current() {
+        Some(value) => value.to_lowercase(),
+        None => {
+            let lang = i18n::catalog(ctx, message).await;
+            message.reply(ctx, lang.format(&tr!("settings-language-list", languages = available))).await?;
+
+            return Ok(());
+        },
+    };
+
+    let db = Database::get(ctx).await;
+
+    if is_reset(&value) {
+        db.set_language(message.guild_id.unwrap(), None)?;
+    } else if locales.has(&value) {
+        db.set_language(message.guild_id.unwrap(), Some(&value))?;
+    } else {
+        return Err(tr!("settings-language-unknown", language = value, languages = available).into());
+    }
+
+    // Reply in the newly picked language.
+    let lang = i18n::catalog(ctx, message).await;
+    message.reply(ctx, lang.format(&tr!("settings-language-set", language = lang.language()))).await?;
+
+    Ok(())
+}
+
+#[command("watermark")]
+#[description("Sets the watermark the `watermark` command stamps on images, either an attached image or some text, and where it goes and how opaque it is by default. Use `reset` to remove it.")]
+#[usage("<text | reset> [--corner <position>] [--opacity <0.05-1>]")]
+#[example("made with photon --corner bottom-left --opacity 0.7")]
+async fn set_watermark(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let flags = Flags::parse(&args, &[])?;
+    let text = flags.positional().join(" ");
+    let guild_id = message.guild_id.unwrap();
+    let db = Database::get(ctx).await;
+    let lang = i18n::catalog(ctx, message).await;
+
+    if is_reset(&text) && message.attachments.is_empty() {
+        let text = match db.remove_watermark(guild_id)? {
+            true => tr!("settings-watermark-reset"),
+            false => tr!("settings-watermark-not-set"),
+        };
+        message.reply(ctx, lang.format(&text)).await?;
+
+        return Ok(());
+    }
+
+    let anchor = match flags.raw("corner") {
+        Some(name) => Gravity::from_name(name).ok_or_else(|| tr!("overlay-unknown-anchor", anchor = name))?,
+        None => Gravity::BottomRight,
+    };
+    let opacity = flags.get_in("opacity", 0.05..=1.0, 0.5)?;
+
+    let mark = match message.attachments.first() {
+        Some(attachment) => {
+            if attachment.size > MAX_WATERMARK_SIZE {
+                return Err(tr!("settings-watermark-too-big", max = MAX_WATERMARK_SIZE / 1024).into());
+            }
+
+            let data = attachment.download().await?;
+            let data = JobQueue::get(ctx).await.run(move || Ok(Media::decode(&data, &Quality::full()).map(|_| data)?)).await?;
+            WatermarkMark::Image(data)
+        },
+        None if text.is_empty() => return Err(tr!("settings-watermark-missing").into()),
+        None if text.chars().count() > MAX_WATERMARK_TEXT => {
+            return Err(tr!("settings-watermark-text-too-long", max = MAX_WATERMARK_TEXT).into());
+        },
+        None => WatermarkMark::Text(text),
+    };
+
+    db.set_watermark(guild_id, &Watermark { mark, anchor, opacity }, message.author.id)?;
+    message.reply(ctx, lang.format(&tr!("settings-watermark-set", anchor = anchor.name()))).await?;
+
+    Ok(())
+}
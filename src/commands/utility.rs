@@ -0,0 +1,182 @@
+use image::DynamicImage;
+
+use photon_core::{encode_image, Media, OutputFormat, Quality};
+
+use regex::Regex;
+
+use reqwest::Url;
+
+use serenity::client::Context;
+use serenity::framework::standard::{
+    Args,
+    CommandError,
+    CommandResult,
+    macros::{command, group},
+};
+use serenity::model::channel::Message;
+
+use std::collections::HashMap;
+
+use crate::card::{self, Preview};
+use crate::fonts;
+use crate::jobs::JobQueue;
+use crate::output;
+use crate::quality::LoadMonitor;
+use crate::resolve_image::ImageResolver;
+use crate::tr;
+
+#[group]
+#[commands(preview_card)]
+struct Utility;
+
+/// The most of a page that is read while looking for its metadata.
+const MAX_PAGE_SIZE: usize = 1024 * 1024 * 2;  // 2 MiB
+const MAX_FAVICON_SIZE: usize = 1024 * 256;  // 256 KiB
+
+lazy_static::lazy_static! {
+    static ref META_REGEX: Regex = Regex::new(r"(?i)<meta\s[^>]*>").unwrap();
+    static ref LINK_REGEX: Regex = Regex::new(r"(?i)<link\s[^>]*>").unwrap();
+    static ref ATTR_REGEX: Regex = Regex::new(r#"(?i)([a-z:_-]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap();
+    static ref TITLE_REGEX: Regex = Regex::new(r"(?is)<title[^>]*>(.*?)</title>").unwrap();
+}
+
+/// Decodes a fetched favicon or hero the same way command inputs are, keeping only the first frame.
+fn decode_still(data: Option<Vec<u8>>, quality: &Quality) -> Option<DynamicImage> {
+    let media = Media::decode(&data?, quality).ok()?;
+    Some(DynamicImage::ImageRgba8(media.first_frame().clone()))
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&apos;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+fn attributes(tag: &str) -> HashMap<String, String> {
+    ATTR_REGEX
+        .captures_iter(tag)
+        .map(|c| (
+            c[1].to_lowercase(),
+            c.get(2).or_else(|| c.get(3)).map_or(String::new(), |m| decode_entities(m.as_str())),
+        ))
+        .collect()
+}
+
+/// Collects `og:*`, `twitter:*` and plain named `<meta>` tags by their property or name.
+fn meta_tags(html: &str) -> HashMap<String, String> {
+    let mut tags = HashMap::new();
+
+    for tag in META_REGEX.find_iter(html) {
+        let mut attrs = attributes(tag.as_str());
+
+        if let (Some(key), Some(content)) = (attrs.remove("property").or_else(|| attrs.remove("name")), attrs.remove("content")) {
+            if !content.is_empty() {
+                tags.entry(key.to_lowercase()).or_insert(content);
+            }
+        }
+    }
+
+    tags
+}
+
+fn favicon_url(html: &str, base: &Url) -> Option<Url> {
+    LINK_REGEX
+        .find_iter(html)
+        .map(|tag| attributes(tag.as_str()))
+        .find(|attrs| attrs.get("rel").map_or(false, |rel| rel.to_lowercase().split_whitespace().any(|r| r == "icon")))
+        .and_then(|attrs| attrs.get("href").and_then(|href| base.join(href).ok()))
+        .or_else(|| base.join("/favicon.ico").ok())
+}
+
+async fn fetch_limited(url: Url, limit: usize) -> Result<Vec<u8>, CommandError> {
+    let mut resp = reqwest::get(url).await?;
+
+    if !resp.status().is_success() {
+        return Err(tr!("url-bad-status", status = resp.status()).into());
+    }
+
+    let mut data = vec![];
+    while let Some(chunk) = resp.chunk().await? {
+        data.extend_from_slice(&chunk);
+
+        if data.len() >= limit {
+            data.truncate(limit);
+            break;
+        }
+    }
+
+    Ok(data)
+}
+
+#[command("preview-card")]
+#[aliases("previewcard", "card")]
+#[description("Renders a preview card of a web page from its title, description, icon and image.")]
+#[usage("<url>")]
+#[example("https://github.com/jay3332/PhotonBotRs")]
+#[num_args(1)]
+async fn preview_card(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
+    let raw = args.single::<String>()?;
+    let url = Url::parse(raw.trim_matches(|c| c == '<' || c == '>'))
+        .ok()
+        .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+        .ok_or_else(|| tr!("invalid-url", url = &raw))?;
+
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+
+    let page = fetch_limited(url.clone(), MAX_PAGE_SIZE).await?;
+    let html = String::from_utf8_lossy(&page);
+    let tags = meta_tags(&html);
+
+    let title = tags.get("og:title")
+        .or_else(|| tags.get("twitter:title"))
+        .cloned()
+        .or_else(|| TITLE_REGEX.captures(&html).map(|c| decode_entities(&c[1])))
+        .filter(|title| !title.is_empty())
+        .unwrap_or_else(|| url.to_string());
+
+    let mut preview = Preview {
+        site: tags.get("og:site_name").cloned().unwrap_or_else(|| url.host_str().unwrap_or_default().to_string()),
+        title,
+        description: tags.get("og:description")
+            .or_else(|| tags.get("twitter:description"))
+            .or_else(|| tags.get("description"))
+            .cloned(),
+        ..Preview::default()
+    };
+
+    // Both images are optional, a card without them is still useful.
+    let mut favicon = None;
+    if let Some(favicon_url) = favicon_url(&html, &url) {
+        favicon = fetch_limited(favicon_url, MAX_FAVICON_SIZE).await.ok();
+    }
+
+    let mut hero = None;
+    if let Some(hero_url) = tags.get("og:image").or_else(|| tags.get("twitter:image")).and_then(|h| url.join(h).ok()) {
+        let mut resolver = ImageResolver::for_message(ctx, message).await?;
+        resolver.disallow_gifs();
+
+        hero = resolver.fetch_url(hero_url.to_string()).await.ok();
+    }
+
+    let text = format!("{} {} {}", preview.site, preview.title, preview.description.as_deref().unwrap_or_default());
+    preview.emojis = fonts::fetch_emojis(&text).await;
+
+    let quality = LoadMonitor::get(ctx).await.quality();
+    let encoded = JobQueue::get(ctx).await.run(move || {
+        preview.favicon = decode_still(favicon, &quality);
+        preview.hero = decode_still(hero, &quality);
+
+        Ok(encode_image(&card::render(&preview), OutputFormat::Png)?)
+    }).await?;
+    output::send_file(ctx, message, None, false, &encoded, "card.png").await?;
+
+    typing.stop();
+    Ok(())
+}
@@ -0,0 +1,485 @@
+use serde::Deserialize;
+
+use serenity::client::Context;
+use serenity::client::bridge::gateway::GatewayIntents;
+use serenity::prelude::TypeMapKey;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::resolve_image::{DEFAULT_MAX_HEIGHT, DEFAULT_MAX_SIZE, DEFAULT_MAX_WIDTH};
+
+pub const DEFAULT_CONFIG_PATH: &str = "photon.toml";
+pub const DEFAULT_PREFIX: &str = "pt";
+pub const DEFAULT_APPLICATION_ID: u64 = 914283059501735977;
+pub const DEFAULT_DATABASE_PATH: &str = "photon.db";
+pub const DEFAULT_LOCALES_PATH: &str = "locales";
+pub const DEFAULT_TEMPLATES_PATH: &str = "templates";
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Env(&'static str, String),
+    Invalid(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, why) => write!(f, "could not read `{}`: {}", path.display(), why),
+            Self::Parse(path, why) => write!(f, "could not parse `{}`: {}", path.display(), why),
+            Self::Env(key, value) => write!(f, "environment variable `{}` has an invalid value `{}`", key, value),
+            Self::Invalid(why) => write!(f, "{}", why),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResolverConfig {
+    pub max_width: usize,
+    pub max_height: usize,
+    pub max_size: usize,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            max_width: DEFAULT_MAX_WIDTH,
+            max_height: DEFAULT_MAX_HEIGHT,
+            max_size: DEFAULT_MAX_SIZE,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JobsConfig {
+    /// The amount of image jobs that may run at once, 0 meaning one per CPU.
+    pub workers: usize,
+    /// How long to wait for in-flight commands to finish when shutting down, in seconds.
+    pub drain_timeout: u64,
+    /// How long a single image job may take before the command fails, in seconds. 0 disables the limit.
+    pub timeout: u64,
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            workers: 0,
+            drain_timeout: 30,
+            timeout: 60,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QualityConfig {
+    /// Quality is degraded once this many jobs are waiting for a worker...
+    pub queue_threshold: usize,
+    /// ...or once CPU usage (in percent) reaches this.
+    pub cpu_threshold: f32,
+    pub degraded_max_dimension: u32,
+    pub degraded_frame_step: usize,
+}
+
+impl Default for QualityConfig {
+    fn default() -> Self {
+        Self {
+            queue_threshold: 8,
+            cpu_threshold: 90.0,
+            degraded_max_dimension: 512,
+            degraded_frame_step: 2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Users may run this many commands...
+    pub commands: usize,
+    /// ...every this many seconds.
+    pub per: u64,
+    /// Users tripping the limiter this many times...
+    pub strikes: usize,
+    /// ...within this many seconds are temporarily blacklisted...
+    pub strike_window: u64,
+    /// ...for this many minutes.
+    pub blacklist_minutes: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            commands: 5,
+            per: 10,
+            strikes: 5,
+            strike_window: 600,
+            blacklist_minutes: 30,
+        }
+    }
+}
+
+/// Overrides the default cooldowns for a single command.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandCooldown {
+    pub user: Option<u64>,
+    pub channel: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CooldownConfig {
+    /// Seconds a user has to wait between heavy commands, 0 disabling the cooldown.
+    pub user: u64,
+    /// Seconds between heavy commands in the same channel, regardless of who runs them.
+    pub channel: u64,
+    /// Per-command overrides, keyed by the command's name.
+    pub commands: HashMap<String, CommandCooldown>,
+}
+
+impl Default for CooldownConfig {
+    fn default() -> Self {
+        Self {
+            user: 5,
+            channel: 0,
+            commands: HashMap::new(),
+        }
+    }
+}
+
+impl CooldownConfig {
+    /// The user and channel cooldowns of the command, in seconds.
+    pub fn for_command(&self, name: &str) -> (u64, u64) {
+        let overrides = self.commands.get(name).copied().unwrap_or_default();
+        (overrides.user.unwrap_or(self.user), overrides.channel.unwrap_or(self.channel))
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ApiConfig {
+    /// Only has an effect when built with the `api` feature.
+    pub enabled: bool,
+    pub bind: SocketAddr,
+    /// Bearer tokens accepted by the API.
+    pub tokens: Vec<String>,
+}
+
+impl Default for ApiConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            bind: SocketAddr::from(([127, 0, 0, 1], 8080)),
+            tokens: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RemoveBgConfig {
+    /// An ONNX segmentation model such as u2net, only used when built with the `removebg` feature.
+    pub model: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub prefix: String,
+    pub application_id: u64,
+    pub intents: Vec<String>,
+    pub database: PathBuf,
+    /// The language used where a guild hasn't picked one.
+    pub language: String,
+    /// Directory of additional `<language>.ftl` catalogs.
+    pub locales: PathBuf,
+    /// Directory of meme templates, each becoming a command.
+    pub templates: PathBuf,
+    pub resolver: ResolverConfig,
+    pub jobs: JobsConfig,
+    pub quality: QualityConfig,
+    pub ratelimit: RateLimitConfig,
+    pub cooldown: CooldownConfig,
+    pub api: ApiConfig,
+    pub removebg: RemoveBgConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            prefix: DEFAULT_PREFIX.to_string(),
+            application_id: DEFAULT_APPLICATION_ID,
+            intents: vec!["non_privileged".to_string()],
+            database: PathBuf::from(DEFAULT_DATABASE_PATH),
+            language: crate::i18n::FALLBACK_LANGUAGE.to_string(),
+            locales: PathBuf::from(DEFAULT_LOCALES_PATH),
+            templates: PathBuf::from(DEFAULT_TEMPLATES_PATH),
+            resolver: ResolverConfig::default(),
+            jobs: JobsConfig::default(),
+            quality: QualityConfig::default(),
+            ratelimit: RateLimitConfig::default(),
+            cooldown: CooldownConfig::default(),
+            api: ApiConfig::default(),
+            removebg: RemoveBgConfig::default(),
+        }
+    }
+}
+
+pub struct ConfigKey;
+
+impl TypeMapKey for ConfigKey {
+    type Value = Arc<Config>;
+}
+
+fn env_var<T: std::str::FromStr>(key: &'static str) -> Result<Option<T>, ConfigError> {
+    match std::env::var(key) {
+        Ok(value) => value.trim().parse().map(Some).map_err(|_| ConfigError::Env(key, value)),
+        Err(_) => Ok(None),
+    }
+}
+
+fn intent_from_name(name: &str) -> Option<GatewayIntents> {
+    Some(match name.to_lowercase().as_str() {
+        "all" => GatewayIntents::all(),
+        "non_privileged" => GatewayIntents::non_privileged(),
+        "guilds" => GatewayIntents::GUILDS,
+        "guild_members" => GatewayIntents::GUILD_MEMBERS,
+        "guild_presences" => GatewayIntents::GUILD_PRESENCES,
+        "guild_messages" => GatewayIntents::GUILD_MESSAGES,
+        "guild_message_reactions" => GatewayIntents::GUILD_MESSAGE_REACTIONS,
+        "direct_messages" => GatewayIntents::DIRECT_MESSAGES,
+        _ => return None,
+    })
+}
+
+impl Config {
+    /// Loads the configuration from `PHOTON_CONFIG` (or `photon.toml` when unset),
+    /// then applies `PHOTON_*` environment variable overrides and validates the result.
+    ///
+    /// A missing `photon.toml` is not an error, the defaults are used instead.
+    pub fn load() -> Result<Self, ConfigError> {
+        let mut config = match std::env::var("PHOTON_CONFIG") {
+            Ok(path) => Self::from_file(path)?,
+            Err(_) if Path::new(DEFAULT_CONFIG_PATH).exists() => Self::from_file(DEFAULT_CONFIG_PATH)?,
+            Err(_) => Self::default(),
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| ConfigError::Io(path.to_path_buf(), e))?;
+
+        toml::from_str(&content).map_err(|e| ConfigError::Parse(path.to_path_buf(), e))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        if let Some(prefix) = env_var("PHOTON_PREFIX")? {
+            self.prefix = prefix;
+        }
+
+        if let Some(id) = env_var("PHOTON_APPLICATION_ID")? {
+            self.application_id = id;
+        }
+
+        if let Some(intents) = env_var::<String>("PHOTON_INTENTS")? {
+            self.intents = intents.split(',').map(|s| s.trim().to_string()).collect();
+        }
+
+        if let Some(database) = env_var::<String>("PHOTON_DATABASE")? {
+            self.database = PathBuf::from(database);
+        }
+
+        if let Some(language) = env_var("PHOTON_LANGUAGE")? {
+            self.language = language;
+        }
+
+        if let Some(locales) = env_var::<String>("PHOTON_LOCALES")? {
+            self.locales = PathBuf::from(locales);
+        }
+
+        if let Some(templates) = env_var::<String>("PHOTON_TEMPLATES")? {
+            self.templates = PathBuf::from(templates);
+        }
+
+        if let Some(width) = env_var("PHOTON_MAX_WIDTH")? {
+            self.resolver.max_width = width;
+        }
+
+        if let Some(height) = env_var("PHOTON_MAX_HEIGHT")? {
+            self.resolver.max_height = height;
+        }
+
+        if let Some(size) = env_var("PHOTON_MAX_SIZE")? {
+            self.resolver.max_size = size;
+        }
+
+        if let Some(workers) = env_var("PHOTON_WORKERS")? {
+            self.jobs.workers = workers;
+        }
+
+        if let Some(enabled) = env_var("PHOTON_API")? {
+            self.api.enabled = enabled;
+        }
+
+        if let Some(bind) = env_var("PHOTON_API_BIND")? {
+            self.api.bind = bind;
+        }
+
+        if let Some(tokens) = env_var::<String>("PHOTON_API_TOKENS")? {
+            self.api.tokens = tokens.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect();
+        }
+
+        if let Some(model) = env_var::<String>("PHOTON_REMOVEBG_MODEL")? {
+            self.removebg.model = Some(PathBuf::from(model)).filter(|path| !path.as_os_str().is_empty());
+        }
+
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.prefix.is_empty() || self.prefix.chars().any(char::is_whitespace) {
+            return Err(ConfigError::Invalid(format!(
+                "`prefix` must be non-empty and contain no whitespace, got `{}`", self.prefix,
+            )));
+        }
+
+        if self.application_id == 0 {
+            return Err(ConfigError::Invalid("`application_id` must be set".to_string()));
+        }
+
+        if self.intents.is_empty() {
+            return Err(ConfigError::Invalid("`intents` must contain at least one intent".to_string()));
+        }
+
+        if let Some(name) = self.intents.iter().find(|name| intent_from_name(name).is_none()) {
+            return Err(ConfigError::Invalid(format!("unknown intent `{}`", name)));
+        }
+
+        if self.resolver.max_width == 0 || self.resolver.max_height == 0 || self.resolver.max_size == 0 {
+            return Err(ConfigError::Invalid(
+                "`resolver.max_width`, `resolver.max_height` and `resolver.max_size` must be greater than 0".to_string()
+            ));
+        }
+
+        if self.quality.queue_threshold == 0 || self.quality.degraded_max_dimension == 0 || self.quality.degraded_frame_step == 0 {
+            return Err(ConfigError::Invalid(
+                "`quality.queue_threshold`, `quality.degraded_max_dimension` and `quality.degraded_frame_step` must be greater than 0".to_string()
+            ));
+        }
+
+        if self.ratelimit.commands == 0 || self.ratelimit.per == 0 || self.ratelimit.strikes == 0 {
+            return Err(ConfigError::Invalid(
+                "`ratelimit.commands`, `ratelimit.per` and `ratelimit.strikes` must be greater than 0".to_string()
+            ));
+        }
+
+        if !(0.0..=100.0).contains(&self.quality.cpu_threshold) {
+            return Err(ConfigError::Invalid(format!(
+                "`quality.cpu_threshold` must be a percentage between 0 and 100, got {}", self.quality.cpu_threshold,
+            )));
+        }
+
+        if self.api.enabled && self.api.tokens.iter().all(|token| token.is_empty()) {
+            return Err(ConfigError::Invalid("`api.tokens` must contain at least one token when the API is enabled".to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub fn gateway_intents(&self) -> GatewayIntents {
+        self.intents
+            .iter()
+            .filter_map(|name| intent_from_name(name))
+            .fold(GatewayIntents::empty(), |acc, intent| acc | intent)
+    }
+
+    pub async fn get(ctx: &Context) -> Arc<Config> {
+        ctx.data.read().await.get::<ConfigKey>().expect("Config was not inserted into client data").clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(source)
+    }
+
+    fn invalid(source: &str) -> String {
+        match parse(source).unwrap().validate() {
+            Err(ConfigError::Invalid(why)) => why,
+            other => panic!("expected `{}` to be invalid, got {:?}", source, other),
+        }
+    }
+
+    #[test]
+    fn accepts_the_defaults() {
+        assert!(Config::default().validate().is_ok());
+
+        let config = parse("").unwrap();
+        assert_eq!(config.prefix, DEFAULT_PREFIX);
+        assert_eq!(config.resolver.max_size, DEFAULT_MAX_SIZE);
+    }
+
+    #[test]
+    fn parses_sections() {
+        let config = parse(r#"
+            prefix = "!"
+            intents = ["guilds", "GUILD_MESSAGES"]
+
+            [ratelimit]
+            commands = 3
+
+            [cooldown]
+            user = 10
+
+            [cooldown.commands.resize]
+            channel = 2
+        "#).unwrap();
+
+        assert!(config.validate().is_ok());
+        assert_eq!(config.prefix, "!");
+        assert_eq!(config.gateway_intents(), GatewayIntents::GUILDS | GatewayIntents::GUILD_MESSAGES);
+        assert_eq!(config.ratelimit.commands, 3);
+        assert_eq!(config.ratelimit.per, RateLimitConfig::default().per);
+        assert_eq!(config.cooldown.for_command("resize"), (10, 2));
+        assert_eq!(config.cooldown.for_command("invert"), (10, 0));
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert!(parse("prefx = \"!\"").is_err());
+        assert!(parse("[resolver]\nmax_depth = 2").is_err());
+    }
+
+    #[test]
+    fn rejects_invalid_values() {
+        assert!(invalid("prefix = \"\"").contains("`prefix`"));
+        assert!(invalid("prefix = \"p t\"").contains("`prefix`"));
+        assert!(invalid("application_id = 0").contains("`application_id`"));
+        assert!(invalid("intents = []").contains("`intents`"));
+        assert!(invalid("intents = [\"guilds\", \"everything\"]").contains("`everything`"));
+        assert!(invalid("[resolver]\nmax_width = 0").contains("`resolver.max_width`"));
+        assert!(invalid("[quality]\ndegraded_frame_step = 0").contains("`quality.degraded_frame_step`"));
+        assert!(invalid("[quality]\ncpu_threshold = 150.0").contains("`quality.cpu_threshold`"));
+        assert!(invalid("[ratelimit]\nstrikes = 0").contains("`ratelimit.strikes`"));
+        assert!(invalid("[api]\nenabled = true\ntokens = [\"\"]").contains("`api.tokens`"));
+    }
+}
@@ -0,0 +1,137 @@
+use serenity::client::Context;
+use serenity::framework::standard::CommandGroup;
+use serenity::model::channel::Message;
+use serenity::prelude::TypeMapKey;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::commands;
+use crate::config::Config;
+use crate::i18n;
+use crate::owners;
+use crate::tr;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+enum Scope {
+    User(u64),
+    Channel(u64),
+}
+
+/// When each user and channel last ran each command.
+pub struct Cooldowns {
+    /// Only commands in these groups are put on cooldown.
+    groups: Vec<&'static CommandGroup>,
+    last_used: Mutex<HashMap<(&'static str, Scope), Instant>>,
+}
+
+pub struct CooldownsKey;
+
+impl TypeMapKey for CooldownsKey {
+    type Value = Arc<Cooldowns>;
+}
+
+impl Cooldowns {
+    pub fn new(groups: Vec<&'static CommandGroup>) -> Self {
+        Self { groups, last_used: Mutex::default() }
+    }
+
+    pub async fn get(ctx: &Context) -> Arc<Cooldowns> {
+        ctx.data.read().await.get::<CooldownsKey>().expect("Cooldowns was not inserted into client data").clone()
+    }
+
+    /// Returns how long is left if any of the scopes is still on cooldown, otherwise starts a new one for all of them.
+    fn try_use(&self, command: &'static str, scopes: &[(Scope, Duration)], now: Instant) -> Option<Duration> {
+        let mut last_used = self.last_used.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        // Entries only matter while they're on cooldown, so forget the rest every now and then.
+        if last_used.len() > 10_000 {
+            last_used.retain(|_, used| now.duration_since(*used) < Duration::from_secs(60 * 60));
+        }
+
+        let remaining = scopes
+            .iter()
+            .filter_map(|(scope, cooldown)| {
+                let elapsed = now.duration_since(*last_used.get(&(command, *scope))?);
+                cooldown.checked_sub(elapsed).filter(|left| !left.is_zero())
+            })
+            .max();
+
+        if remaining.is_none() {
+            for (scope, _) in scopes {
+                last_used.insert((command, *scope), now);
+            }
+        }
+
+        remaining
+    }
+}
+
+/// Puts the command on a per-user and per-channel cooldown, as configured under `[cooldown]`. Returns the reply to
+/// refuse it with while it's still on one. Commands outside the cooled down groups are always let through.
+pub async fn check(ctx: &Context, message: &Message, cmd_name: &str) -> Result<(), String> {
+    let cooldowns = Cooldowns::get(ctx).await;
+    let command = match commands::find_command(&cooldowns.groups, cmd_name) {
+        Some(command) => command.options.names[0],
+        None => return Ok(()),
+    };
+
+    if owners::is_owner(ctx, message.author.id).await {
+        return Ok(());
+    }
+
+    let (user, channel) = Config::get(ctx).await.cooldown.for_command(command);
+
+    let scopes = [
+        (Scope::User(message.author.id.0), Duration::from_secs(user)),
+        (Scope::Channel(message.channel_id.0), Duration::from_secs(channel)),
+    ];
+    let scopes = scopes.iter().copied().filter(|(_, cooldown)| !cooldown.is_zero()).collect::<Vec<_>>();
+
+    match cooldowns.try_use(command, &scopes, Instant::now()) {
+        Some(remaining) => {
+            let text = tr!("cooldown", command = command, seconds = remaining.as_secs_f32().ceil() as u64);
+            Err(i18n::catalog(ctx, message).await.format(&text))
+        },
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: (Scope, Duration) = (Scope::User(1), Duration::from_secs(5));
+    const CHANNEL: (Scope, Duration) = (Scope::Channel(2), Duration::from_secs(10));
+
+    #[test]
+    fn scopes_cooldowns() {
+        let cooldowns = Cooldowns::new(Vec::new());
+        let now = Instant::now();
+
+        assert_eq!(cooldowns.try_use("invert", &[USER], now), None);
+        assert_eq!(cooldowns.try_use("invert", &[USER], now), Some(Duration::from_secs(5)));
+
+        // Other commands and other users have cooldowns of their own.
+        assert_eq!(cooldowns.try_use("blur", &[USER], now), None);
+        assert_eq!(cooldowns.try_use("invert", &[(Scope::User(3), USER.1)], now), None);
+
+        // The longest remaining cooldown wins, and a refused use doesn't start the other scopes' cooldowns.
+        assert_eq!(cooldowns.try_use("resize", &[CHANNEL], now), None);
+        assert_eq!(cooldowns.try_use("resize", &[USER, CHANNEL], now + Duration::from_secs(4)), Some(Duration::from_secs(6)));
+        assert_eq!(cooldowns.try_use("resize", &[USER], now + Duration::from_secs(4)), None);
+    }
+
+    #[test]
+    fn expires_cooldowns() {
+        let cooldowns = Cooldowns::new(Vec::new());
+        let now = Instant::now();
+
+        assert_eq!(cooldowns.try_use("invert", &[USER, CHANNEL], now), None);
+        assert_eq!(cooldowns.try_use("invert", &[USER], now + Duration::from_secs(3)), Some(Duration::from_secs(2)));
+        assert_eq!(cooldowns.try_use("invert", &[USER], now + Duration::from_secs(5)), None);
+        assert_eq!(cooldowns.try_use("invert", &[CHANNEL], now + Duration::from_secs(9)), Some(Duration::from_secs(1)));
+        assert_eq!(cooldowns.try_use("invert", &[CHANNEL], now + Duration::from_secs(10)), None);
+    }
+}
@@ -0,0 +1,658 @@
+use photon_core::filters::Gravity;
+use photon_core::OutputFormat;
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use serenity::client::Context;
+use serenity::model::id::{GuildId, MessageId, UserId};
+use serenity::prelude::TypeMapKey;
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::stats::CommandStats;
+use crate::tr;
+
+const MIGRATIONS: &str = "
+CREATE TABLE IF NOT EXISTS guild_settings (
+    guild_id INTEGER PRIMARY KEY,
+    prefix TEXT,
+    max_image_size INTEGER,
+    default_format TEXT
+);
+
+CREATE TABLE IF NOT EXISTS disabled_commands (
+    guild_id INTEGER NOT NULL,
+    command TEXT NOT NULL,
+    PRIMARY KEY (guild_id, command)
+);
+
+CREATE TABLE IF NOT EXISTS user_prefs (
+    user_id INTEGER PRIMARY KEY,
+    format TEXT,
+    static_avatars INTEGER NOT NULL DEFAULT 0,
+    spoiler INTEGER NOT NULL DEFAULT 0,
+    dm_results INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS blacklist (
+    kind TEXT NOT NULL,
+    id INTEGER NOT NULL,
+    reason TEXT,
+    expires_at INTEGER,
+    PRIMARY KEY (kind, id)
+);
+
+CREATE TABLE IF NOT EXISTS command_stats (
+    command TEXT PRIMARY KEY,
+    invocations INTEGER NOT NULL DEFAULT 0,
+    bytes INTEGER NOT NULL DEFAULT 0,
+    total_latency_ms INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS command_users (
+    command TEXT NOT NULL,
+    user_id INTEGER NOT NULL,
+    PRIMARY KEY (command, user_id)
+);
+
+CREATE TABLE IF NOT EXISTS blocked_images (
+    guild_id INTEGER NOT NULL,
+    hash INTEGER NOT NULL,
+    blocked_by INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, hash)
+);
+
+CREATE TABLE IF NOT EXISTS guild_luts (
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    source TEXT NOT NULL,
+    added_by INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS guild_fonts (
+    guild_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    data BLOB NOT NULL,
+    added_by INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS output_sources (
+    message_id INTEGER PRIMARY KEY,
+    guild_id INTEGER NOT NULL,
+    source_hash INTEGER NOT NULL,
+    created_at INTEGER NOT NULL DEFAULT (strftime('%s', 'now'))
+);
+
+CREATE TABLE IF NOT EXISTS guild_watermarks (
+    guild_id INTEGER PRIMARY KEY,
+    image BLOB,
+    text TEXT,
+    anchor TEXT NOT NULL,
+    opacity REAL NOT NULL,
+    set_by INTEGER NOT NULL
+);
+";
+
+/// Columns added to tables after they were first created, as `(table, column, definition)`.
+const ADDED_COLUMNS: &[(&str, &str, &str)] = &[
+    ("guild_settings", "language", "TEXT"),
+];
+
+/// How long the source of an output message is remembered for `block-image`, in seconds.
+const OUTPUT_SOURCE_RETENTION: i64 = 60 * 60 * 24 * 7;
+
+#[derive(Clone, Debug, Default)]
+pub struct GuildSettings {
+    pub prefix: Option<String>,
+    pub disabled_commands: Vec<String>,
+    pub max_image_size: Option<usize>,
+    pub default_format: Option<OutputFormat>,
+    pub language: Option<String>,
+}
+
+impl GuildSettings {
+    pub fn is_disabled(&self, command: &str) -> bool {
+        self.disabled_commands.iter().any(|c| c == command)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct UserPrefs {
+    pub format: Option<OutputFormat>,
+    pub static_avatars: bool,
+    pub spoiler: bool,
+    pub dm_results: bool,
+}
+
+/// A boolean column of `user_prefs` that can be toggled.
+#[derive(Clone, Copy, Debug)]
+pub enum UserToggle {
+    StaticAvatars,
+    Spoiler,
+    DmResults,
+}
+
+impl UserToggle {
+    fn column(&self) -> &'static str {
+        match self {
+            Self::StaticAvatars => "static_avatars",
+            Self::Spoiler => "spoiler",
+            Self::DmResults => "dm_results",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlacklistKind {
+    User,
+    Guild,
+}
+
+impl BlacklistKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Guild => "guild",
+        }
+    }
+}
+
+impl std::str::FromStr for BlacklistKind {
+    type Err = serenity::framework::standard::CommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "user" | "member" => Ok(Self::User),
+            "guild" | "server" => Ok(Self::Guild),
+            _ => Err(tr!("blacklist-unknown-kind", kind = s).into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BlacklistEntry {
+    pub kind: BlacklistKind,
+    pub id: u64,
+    pub reason: Option<String>,
+    /// Unix timestamp, `None` for permanent entries.
+    pub expires_at: Option<i64>,
+}
+
+/// What a guild's watermark is made of.
+#[derive(Clone, Debug)]
+pub enum WatermarkMark {
+    /// An uploaded image, kept encoded.
+    Image(Vec<u8>),
+    Text(String),
+}
+
+/// The watermark `watermark` stamps on images in a guild, set by its admins.
+#[derive(Clone, Debug)]
+pub struct Watermark {
+    pub mark: WatermarkMark,
+    pub anchor: Gravity,
+    pub opacity: f32,
+}
+
+pub struct Database {
+    conn: Mutex<Connection>,
+}
+
+pub struct DatabaseKey;
+
+impl TypeMapKey for DatabaseKey {
+    type Value = Arc<Database>;
+}
+
+impl Database {
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", &"WAL")?;
+        conn.execute_batch(MIGRATIONS)?;
+
+        for (table, column, definition) in ADDED_COLUMNS {
+            let exists = conn.query_row(
+                "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?",
+                params![table, column],
+                |row| row.get::<_, i64>(0),
+            )? > 0;
+
+            if !exists {
+                conn.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {};", table, column, definition))?;
+            }
+        }
+
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    pub async fn get(ctx: &Context) -> Arc<Database> {
+        ctx.data.read().await.get::<DatabaseKey>().expect("Database was not inserted into client data").clone()
+    }
+
+    /// Checkpoints the write-ahead log into the main database file.
+    pub fn flush(&self) -> rusqlite::Result<()> {
+        self.conn().execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn guild_settings(&self, guild_id: GuildId) -> rusqlite::Result<GuildSettings> {
+        let conn = self.conn();
+
+        let mut settings = conn
+            .query_row(
+                "SELECT prefix, max_image_size, default_format, language FROM guild_settings WHERE guild_id = ?",
+                params![guild_id.0 as i64],
+                |row| Ok(GuildSettings {
+                    prefix: row.get(0)?,
+                    disabled_commands: vec![],
+                    max_image_size: row.get::<_, Option<i64>>(1)?.map(|size| size as usize),
+                    default_format: row.get::<_, Option<String>>(2)?.and_then(|f| f.parse().ok()),
+                    language: row.get(3)?,
+                }),
+            )
+            .optional()?
+            .unwrap_or_default();
+
+        let mut stmt = conn.prepare("SELECT command FROM disabled_commands WHERE guild_id = ? ORDER BY command")?;
+        settings.disabled_commands = stmt
+            .query_map(params![guild_id.0 as i64], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+
+        Ok(settings)
+    }
+
+    fn ensure_guild(conn: &Connection, guild_id: GuildId) -> rusqlite::Result<()> {
+        conn.execute("INSERT OR IGNORE INTO guild_settings (guild_id) VALUES (?)", params![guild_id.0 as i64])?;
+        Ok(())
+    }
+
+    pub fn set_prefix(&self, guild_id: GuildId, prefix: Option<&str>) -> rusqlite::Result<()> {
+        let conn = self.conn();
+        Self::ensure_guild(&conn, guild_id)?;
+        conn.execute("UPDATE guild_settings SET prefix = ? WHERE guild_id = ?", params![prefix, guild_id.0 as i64])?;
+
+        Ok(())
+    }
+
+    pub fn set_max_image_size(&self, guild_id: GuildId, size: Option<usize>) -> rusqlite::Result<()> {
+        let conn = self.conn();
+        Self::ensure_guild(&conn, guild_id)?;
+        conn.execute(
+            "UPDATE guild_settings SET max_image_size = ? WHERE guild_id = ?",
+            params![size.map(|s| s as i64), guild_id.0 as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn set_default_format(&self, guild_id: GuildId, format: Option<OutputFormat>) -> rusqlite::Result<()> {
+        let conn = self.conn();
+        Self::ensure_guild(&conn, guild_id)?;
+        conn.execute(
+            "UPDATE guild_settings SET default_format = ? WHERE guild_id = ?",
+            params![format.map(|f| f.as_str()), guild_id.0 as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn set_language(&self, guild_id: GuildId, language: Option<&str>) -> rusqlite::Result<()> {
+        let conn = self.conn();
+        Self::ensure_guild(&conn, guild_id)?;
+        conn.execute("UPDATE guild_settings SET language = ? WHERE guild_id = ?", params![language, guild_id.0 as i64])?;
+
+        Ok(())
+    }
+
+    pub fn set_command_disabled(&self, guild_id: GuildId, command: &str, disabled: bool) -> rusqlite::Result<()> {
+        let conn = self.conn();
+
+        if disabled {
+            conn.execute(
+                "INSERT OR IGNORE INTO disabled_commands (guild_id, command) VALUES (?, ?)",
+                params![guild_id.0 as i64, command],
+            )?;
+        } else {
+            conn.execute(
+                "DELETE FROM disabled_commands WHERE guild_id = ? AND command = ?",
+                params![guild_id.0 as i64, command],
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn user_prefs(&self, user_id: UserId) -> rusqlite::Result<UserPrefs> {
+        self.conn()
+            .query_row(
+                "SELECT format, static_avatars, spoiler, dm_results FROM user_prefs WHERE user_id = ?",
+                params![user_id.0 as i64],
+                |row| Ok(UserPrefs {
+                    format: row.get::<_, Option<String>>(0)?.and_then(|f| f.parse().ok()),
+                    static_avatars: row.get(1)?,
+                    spoiler: row.get(2)?,
+                    dm_results: row.get(3)?,
+                }),
+            )
+            .optional()
+            .map(Option::unwrap_or_default)
+    }
+
+    fn ensure_user(conn: &Connection, user_id: UserId) -> rusqlite::Result<()> {
+        conn.execute("INSERT OR IGNORE INTO user_prefs (user_id) VALUES (?)", params![user_id.0 as i64])?;
+        Ok(())
+    }
+
+    pub fn set_user_format(&self, user_id: UserId, format: Option<OutputFormat>) -> rusqlite::Result<()> {
+        let conn = self.conn();
+        Self::ensure_user(&conn, user_id)?;
+        conn.execute(
+            "UPDATE user_prefs SET format = ? WHERE user_id = ?",
+            params![format.map(|f| f.as_str()), user_id.0 as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn set_user_toggle(&self, user_id: UserId, toggle: UserToggle, value: bool) -> rusqlite::Result<()> {
+        let conn = self.conn();
+        Self::ensure_user(&conn, user_id)?;
+        conn.execute(
+            &format!("UPDATE user_prefs SET {} = ? WHERE user_id = ?", toggle.column()),
+            params![value, user_id.0 as i64],
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the entry for the given id if it is blacklisted and the entry hasn't expired.
+    pub fn blacklist_entry(&self, kind: BlacklistKind, id: u64) -> rusqlite::Result<Option<BlacklistEntry>> {
+        let conn = self.conn();
+        conn.execute("DELETE FROM blacklist WHERE expires_at IS NOT NULL AND expires_at <= strftime('%s', 'now')", [])?;
+
+        conn.query_row(
+            "SELECT reason, expires_at FROM blacklist WHERE kind = ? AND id = ?",
+            params![kind.as_str(), id as i64],
+            |row| Ok(BlacklistEntry { kind, id, reason: row.get(0)?, expires_at: row.get(1)? }),
+        ).optional()
+    }
+
+    /// Blacklists the id, for `duration` seconds or permanently if it is `None`.
+    pub fn add_to_blacklist(&self, kind: BlacklistKind, id: u64, reason: Option<&str>, duration: Option<i64>) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO blacklist (kind, id, reason, expires_at) VALUES (?, ?, ?, strftime('%s', 'now') + ?)",
+            params![kind.as_str(), id as i64, reason, duration],
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns whether the id was blacklisted at all.
+    pub fn remove_from_blacklist(&self, kind: BlacklistKind, id: u64) -> rusqlite::Result<bool> {
+        let removed = self.conn().execute(
+            "DELETE FROM blacklist WHERE kind = ? AND id = ?",
+            params![kind.as_str(), id as i64],
+        )?;
+
+        Ok(removed > 0)
+    }
+
+    pub fn blacklist(&self) -> rusqlite::Result<Vec<BlacklistEntry>> {
+        let conn = self.conn();
+        conn.execute("DELETE FROM blacklist WHERE expires_at IS NOT NULL AND expires_at <= strftime('%s', 'now')", [])?;
+
+        let mut stmt = conn.prepare("SELECT kind, id, reason, expires_at FROM blacklist ORDER BY kind, id")?;
+        let entries = stmt
+            .query_map([], |row| Ok(BlacklistEntry {
+                kind: if row.get::<_, String>(0)? == "guild" { BlacklistKind::Guild } else { BlacklistKind::User },
+                id: row.get::<_, i64>(1)? as u64,
+                reason: row.get(2)?,
+                expires_at: row.get(3)?,
+            }))?
+            .collect();
+
+        entries
+    }
+
+    pub fn add_command_stats(
+        &self,
+        command: &str,
+        invocations: u64,
+        bytes: u64,
+        total_latency_ms: u64,
+        users: &HashSet<UserId>,
+    ) -> rusqlite::Result<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+
+        tx.execute(
+            "INSERT INTO command_stats (command, invocations, bytes, total_latency_ms) VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (command) DO UPDATE SET
+                invocations = invocations + ?2,
+                bytes = bytes + ?3,
+                total_latency_ms = total_latency_ms + ?4",
+            params![command, invocations as i64, bytes as i64, total_latency_ms as i64],
+        )?;
+
+        for user_id in users {
+            tx.execute(
+                "INSERT OR IGNORE INTO command_users (command, user_id) VALUES (?, ?)",
+                params![command, user_id.0 as i64],
+            )?;
+        }
+
+        tx.commit()
+    }
+
+    /// Returns the stored stats of every command, most used first.
+    pub fn command_stats(&self) -> rusqlite::Result<Vec<(String, CommandStats)>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT s.command, s.invocations, s.bytes, s.total_latency_ms,
+                (SELECT COUNT(*) FROM command_users u WHERE u.command = s.command)
+            FROM command_stats s ORDER BY s.invocations DESC",
+        )?;
+        let stats = stmt
+            .query_map([], |row| Ok((row.get(0)?, CommandStats {
+                invocations: row.get::<_, i64>(1)? as u64,
+                bytes: row.get::<_, i64>(2)? as u64,
+                total_latency_ms: row.get::<_, i64>(3)? as u64,
+                unique_users: row.get::<_, i64>(4)? as u64,
+            })))?
+            .collect();
+
+        stats
+    }
+
+    /// The amount of distinct users that have used any command.
+    pub fn total_unique_users(&self) -> rusqlite::Result<u64> {
+        self.conn()
+            .query_row("SELECT COUNT(DISTINCT user_id) FROM command_users", [], |row| row.get::<_, i64>(0))
+            .map(|count| count as u64)
+    }
+
+    pub fn blocked_hashes(&self, guild_id: GuildId) -> rusqlite::Result<Vec<u64>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT hash FROM blocked_images WHERE guild_id = ?")?;
+        let hashes = stmt
+            .query_map(params![guild_id.0 as i64], |row| row.get::<_, i64>(0).map(|h| h as u64))?
+            .collect();
+
+        hashes
+    }
+
+    pub fn block_hash(&self, guild_id: GuildId, hash: u64, blocked_by: UserId) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT OR IGNORE INTO blocked_images (guild_id, hash, blocked_by) VALUES (?, ?, ?)",
+            params![guild_id.0 as i64, hash as i64, blocked_by.0 as i64],
+        )?;
+
+        Ok(())
+    }
+
+    /// Removes every blocked hash matching the given one, returning how many were removed.
+    pub fn unblock_hash(&self, guild_id: GuildId, hash: u64) -> rusqlite::Result<usize> {
+        let matching = self
+            .blocked_hashes(guild_id)?
+            .into_iter()
+            .filter(|blocked| crate::phash::is_match(*blocked, hash))
+            .collect::<Vec<_>>();
+
+        let conn = self.conn();
+        for blocked in &matching {
+            conn.execute(
+                "DELETE FROM blocked_images WHERE guild_id = ? AND hash = ?",
+                params![guild_id.0 as i64, *blocked as i64],
+            )?;
+        }
+
+        Ok(matching.len())
+    }
+
+    /// The `.cube` source of a LUT uploaded to the guild.
+    pub fn guild_lut(&self, guild_id: GuildId, name: &str) -> rusqlite::Result<Option<String>> {
+        self.conn()
+            .query_row(
+                "SELECT source FROM guild_luts WHERE guild_id = ? AND name = ?",
+                params![guild_id.0 as i64, name],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    pub fn guild_lut_names(&self, guild_id: GuildId) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT name FROM guild_luts WHERE guild_id = ? ORDER BY name")?;
+        let names = stmt.query_map(params![guild_id.0 as i64], |row| row.get(0))?.collect();
+
+        names
+    }
+
+    /// Adds a LUT to the guild, replacing any with the same name.
+    pub fn set_guild_lut(&self, guild_id: GuildId, name: &str, source: &str, added_by: UserId) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO guild_luts (guild_id, name, source, added_by) VALUES (?, ?, ?, ?)",
+            params![guild_id.0 as i64, name, source, added_by.0 as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn remove_guild_lut(&self, guild_id: GuildId, name: &str) -> rusqlite::Result<bool> {
+        let removed = self.conn().execute(
+            "DELETE FROM guild_luts WHERE guild_id = ? AND name = ?",
+            params![guild_id.0 as i64, name],
+        )?;
+
+        Ok(removed > 0)
+    }
+
+    pub fn guild_font(&self, guild_id: GuildId, name: &str) -> rusqlite::Result<Option<Vec<u8>>> {
+        self.conn()
+            .query_row(
+                "SELECT data FROM guild_fonts WHERE guild_id = ? AND name = ?",
+                params![guild_id.0 as i64, name],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+
+    pub fn guild_font_names(&self, guild_id: GuildId) -> rusqlite::Result<Vec<String>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT name FROM guild_fonts WHERE guild_id = ? ORDER BY name")?;
+        let names = stmt.query_map(params![guild_id.0 as i64], |row| row.get(0))?.collect();
+
+        names
+    }
+
+    /// Adds a font to the guild, replacing any with the same name.
+    pub fn set_guild_font(&self, guild_id: GuildId, name: &str, data: &[u8], added_by: UserId) -> rusqlite::Result<()> {
+        self.conn().execute(
+            "INSERT OR REPLACE INTO guild_fonts (guild_id, name, data, added_by) VALUES (?, ?, ?, ?)",
+            params![guild_id.0 as i64, name, data, added_by.0 as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn remove_guild_font(&self, guild_id: GuildId, name: &str) -> rusqlite::Result<bool> {
+        let removed = self.conn().execute(
+            "DELETE FROM guild_fonts WHERE guild_id = ? AND name = ?",
+            params![guild_id.0 as i64, name],
+        )?;
+
+        Ok(removed > 0)
+    }
+
+    pub fn watermark(&self, guild_id: GuildId) -> rusqlite::Result<Option<Watermark>> {
+        self.conn()
+            .query_row(
+                "SELECT image, text, anchor, opacity FROM guild_watermarks WHERE guild_id = ?",
+                params![guild_id.0 as i64],
+                |row| {
+                    let mark = match row.get::<_, Option<Vec<u8>>>(0)? {
+                        Some(image) => WatermarkMark::Image(image),
+                        None => WatermarkMark::Text(row.get::<_, Option<String>>(1)?.unwrap_or_default()),
+                    };
+                    Ok(Watermark {
+                        mark,
+                        anchor: Gravity::from_name(&row.get::<_, String>(2)?).unwrap_or(Gravity::BottomRight),
+                        opacity: row.get::<_, f64>(3)? as f32,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    /// Sets the guild's watermark, replacing the one it had.
+    pub fn set_watermark(&self, guild_id: GuildId, watermark: &Watermark, set_by: UserId) -> rusqlite::Result<()> {
+        let (image, text) = match &watermark.mark {
+            WatermarkMark::Image(image) => (Some(image.as_slice()), None),
+            WatermarkMark::Text(text) => (None, Some(text.as_str())),
+        };
+        self.conn().execute(
+            "INSERT OR REPLACE INTO guild_watermarks (guild_id, image, text, anchor, opacity, set_by) VALUES (?, ?, ?, ?, ?, ?)",
+            params![guild_id.0 as i64, image, text, watermark.anchor.name(), watermark.opacity as f64, set_by.0 as i64],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn remove_watermark(&self, guild_id: GuildId) -> rusqlite::Result<bool> {
+        let removed = self.conn().execute("DELETE FROM guild_watermarks WHERE guild_id = ?", params![guild_id.0 as i64])?;
+
+        Ok(removed > 0)
+    }
+
+    pub fn record_output_source(&self, message_id: MessageId, guild_id: GuildId, hash: u64) -> rusqlite::Result<()> {
+        let conn = self.conn();
+        conn.execute(
+            "INSERT OR REPLACE INTO output_sources (message_id, guild_id, source_hash) VALUES (?, ?, ?)",
+            params![message_id.0 as i64, guild_id.0 as i64, hash as i64],
+        )?;
+        conn.execute(
+            "DELETE FROM output_sources WHERE created_at < strftime('%s', 'now') - ?",
+            params![OUTPUT_SOURCE_RETENTION],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn output_source(&self, message_id: MessageId) -> rusqlite::Result<Option<u64>> {
+        self.conn()
+            .query_row(
+                "SELECT source_hash FROM output_sources WHERE message_id = ?",
+                params![message_id.0 as i64],
+                |row| row.get::<_, i64>(0),
+            )
+            .optional()
+            .map(|hash| hash.map(|h| h as u64))
+    }
+}
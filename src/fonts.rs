@@ -0,0 +1,159 @@
+//! Fonts for rendering text: the bundled ones, ones guilds uploaded, and emoji images from Twemoji.
+
+use image::RgbaImage;
+
+use photon_core::text::{self, Emojis, Font};
+
+use serenity::client::Context;
+use serenity::framework::standard::CommandError;
+use serenity::model::id::GuildId;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::db::Database;
+use crate::tr;
+
+lazy_static::lazy_static! {
+    pub static ref REGULAR: Arc<Font<'static>> = Arc::new(
+        Font::try_from_bytes(include_bytes!("../assets/fonts/DejaVuSans.ttf")).expect("Bundled regular font is invalid")
+    );
+    pub static ref BOLD: Arc<Font<'static>> = Arc::new(text::bold_font());
+    static ref BUNDLED: Vec<(&'static str, Arc<Font<'static>>)> = vec![
+        ("sans", REGULAR.clone()),
+        ("sans-bold", BOLD.clone()),
+        ("serif", Arc::new(Font::try_from_bytes(include_bytes!("../assets/fonts/DejaVuSerif.ttf")).expect("Bundled serif font is invalid"))),
+        ("mono", Arc::new(Font::try_from_bytes(include_bytes!("../assets/fonts/DejaVuSansMono.ttf")).expect("Bundled mono font is invalid"))),
+    ];
+
+    /// Parsed guild fonts, so they aren't re-read from the database for every render.
+    static ref GUILD_FONTS: Mutex<HashMap<(GuildId, String), Arc<Font<'static>>>> = Mutex::new(HashMap::new());
+    /// Downloaded emoji, `None` for sequences Twemoji doesn't have.
+    static ref EMOJI: Mutex<HashMap<String, Option<RgbaImage>>> = Mutex::new(HashMap::new());
+}
+
+pub const MAX_GUILD_FONTS: usize = 10;
+pub const MAX_FILE_SIZE: u64 = 8 * 1024 * 1024;
+const MAX_NAME_LENGTH: usize = 32;
+const MAX_CACHED_FONTS: usize = 64;
+const MAX_CACHED_EMOJI: usize = 512;
+
+const TWEMOJI_URL: &str = "https://cdn.jsdelivr.net/gh/twitter/twemoji@14.0.2/assets/72x72";
+
+pub fn bundled_names() -> impl Iterator<Item = &'static str> {
+    BUNDLED.iter().map(|(name, _)| *name)
+}
+
+pub fn bundled(name: &str) -> Option<Arc<Font<'static>>> {
+    BUNDLED.iter().find(|(n, _)| *n == name).map(|(_, font)| font.clone())
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    (1..=MAX_NAME_LENGTH).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        && bundled(name).is_none()
+}
+
+fn parse(data: Vec<u8>) -> Result<Font<'static>, CommandError> {
+    Font::try_from_vec(data).ok_or_else(|| tr!("font-invalid").into())
+}
+
+/// Checks that an uploaded file is a TrueType or OpenType font.
+pub fn validate(data: &[u8]) -> Result<(), CommandError> {
+    match Font::try_from_bytes(data) {
+        Some(_) => Ok(()),
+        None => Err(tr!("font-invalid").into()),
+    }
+}
+
+/// Finds a font by name, bundled ones first and then the ones uploaded to the guild.
+pub async fn find(ctx: &Context, guild_id: Option<GuildId>, name: &str) -> Result<Arc<Font<'static>>, CommandError> {
+    if let Some(font) = bundled(name) {
+        return Ok(font);
+    }
+
+    let guild_id = guild_id.ok_or_else(|| tr!("font-unknown", name = name))?;
+    let key = (guild_id, name.to_string());
+
+    if let Some(font) = GUILD_FONTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(&key) {
+        return Ok(font.clone());
+    }
+
+    let data = Database::get(ctx).await
+        .guild_font(guild_id, name)?
+        .ok_or_else(|| tr!("font-unknown", name = name))?;
+    let font = Arc::new(parse(data)?);
+
+    let mut cache = GUILD_FONTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if cache.len() >= MAX_CACHED_FONTS {
+        cache.clear();
+    }
+    cache.insert(key, font.clone());
+
+    Ok(font)
+}
+
+/// Drops a guild font from the cache after it was replaced or removed.
+pub fn forget(guild_id: GuildId, name: &str) {
+    GUILD_FONTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&(guild_id, name.to_string()));
+}
+
+/// The font along with the bundled fonts to fall back to for glyphs it doesn't have.
+pub fn with_fallbacks(font: Arc<Font<'static>>) -> Vec<Arc<Font<'static>>> {
+    let mut fonts = vec![font];
+
+    for (_, fallback) in BUNDLED.iter() {
+        if !fonts.iter().any(|font| Arc::ptr_eq(font, fallback)) {
+            fonts.push(fallback.clone());
+        }
+    }
+
+    fonts
+}
+
+/// Downloads an emoji, `Ok(None)` meaning Twemoji doesn't have it.
+async fn download_emoji(sequence: &str) -> reqwest::Result<Option<RgbaImage>> {
+    let url = format!("{}/{}.png", TWEMOJI_URL, text::emoji_code(sequence));
+    let resp = reqwest::get(&url).await?;
+
+    if !resp.status().is_success() {
+        return Ok(None);
+    }
+
+    let data = resp.bytes().await?;
+    Ok(image::load_from_memory(&data).ok().map(|img| img.into_rgba8()))
+}
+
+/// Fetches images for the emoji in the text, those that can't be fetched are left to the fonts.
+pub async fn fetch_emojis(text: &str) -> Emojis {
+    let mut emojis = Emojis::new();
+
+    for sequence in text::emojis(text) {
+        let cached = EMOJI.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(sequence).cloned();
+
+        let image = match cached {
+            Some(image) => image,
+            None => {
+                // Not cached, so it is retried next time.
+                let image = match download_emoji(sequence).await {
+                    Ok(image) => image,
+                    Err(_) => continue,
+                };
+
+                let mut cache = EMOJI.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                if cache.len() >= MAX_CACHED_EMOJI {
+                    cache.clear();
+                }
+                cache.insert(sequence.to_string(), image.clone());
+
+                image
+            },
+        };
+
+        if let Some(image) = image {
+            emojis.insert(sequence.to_string(), image);
+        }
+    }
+
+    emojis
+}
@@ -0,0 +1,306 @@
+use image::imageops::FilterType;
+use image::{Rgba, RgbaImage};
+
+use photon_core::{encode_image, filters, Filter, Media, OutputFormat, ParamKind, Quality};
+
+use serenity::client::Context;
+use serenity::framework::standard::{
+    Args,
+    Command,
+    CommandError,
+    CommandGroup,
+    CommandResult,
+    HelpOptions,
+    macros::help,
+};
+use serenity::model::{channel::Message, id::UserId};
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+
+use crate::commands::{self, imaging::{self, COMMON_FLAGS, IMAGING_GROUP}};
+use crate::i18n::{self, Catalog, Text};
+use crate::jobs::JobQueue;
+use crate::tr;
+
+const SAMPLE: &[u8] = include_bytes!("../assets/sample.png");
+const THUMBNAIL_SIZE: u32 = 128;
+const GAP: u32 = 8;
+const PREVIEW_FILENAME: &str = "preview.png";
+
+lazy_static::lazy_static! {
+    /// The sample never changes, so each command's preview only has to be rendered once.
+    static ref PREVIEWS: Mutex<HashMap<&'static str, Vec<u8>>> = Mutex::new(HashMap::new());
+}
+
+/// Renders the sample image and the command's output of it side by side.
+fn render_preview(transform: fn(Media) -> Result<Media, CommandError>) -> Result<Vec<u8>, CommandError> {
+    let before = Media::decode(SAMPLE, &Quality::full())?;
+    let after = transform(before.clone())?;
+
+    let first_frame = |media: Media| match media {
+        Media::Static(img) => img,
+        Media::Animated(frames) => frames.into_iter().next().map(|f| f.into_buffer()).unwrap_or_else(|| RgbaImage::new(1, 1)),
+    };
+    let thumbnail = |img: RgbaImage| {
+        image::imageops::resize(&img, THUMBNAIL_SIZE, THUMBNAIL_SIZE, FilterType::Nearest)
+    };
+
+    let mut canvas = RgbaImage::from_pixel(THUMBNAIL_SIZE * 2 + GAP, THUMBNAIL_SIZE, Rgba([0, 0, 0, 0]));
+    image::imageops::overlay(&mut canvas, &thumbnail(first_frame(before)), 0, 0);
+    image::imageops::overlay(&mut canvas, &thumbnail(first_frame(after)), THUMBNAIL_SIZE + GAP, 0);
+
+    Ok(encode_image(&canvas, OutputFormat::Png)?)
+}
+
+async fn preview(ctx: &Context, command: &'static str) -> Option<Vec<u8>> {
+    if let Some(cached) = PREVIEWS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get(command) {
+        return Some(cached.clone());
+    }
+
+    let transform = imaging::help_extras(command)?.preview?;
+    let rendered = JobQueue::get(ctx).await.run(move || render_preview(transform)).await.ok()?;
+
+    PREVIEWS.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).insert(command, rendered.clone());
+    Some(rendered)
+}
+
+/// How to use a command, to reply with when it's given the wrong arguments.
+pub async fn usage(ctx: &Context, message: &Message, command_name: &str) -> Text {
+    let prefix = crate::prefix(ctx, message).await;
+    let usage = commands::find_command(crate::GROUPS, command_name).and_then(|command| command.options.usage);
+    let invocation = format!("{}{} {}", prefix, command_name, usage.unwrap_or_default());
+
+    tr!("command-usage", usage = invocation.trim_end())
+}
+
+fn is_visible(group: &CommandGroup, is_owner: bool) -> bool {
+    group.options.help_available && (is_owner || !group.options.owners_only)
+}
+
+/// Finds a command by name, also accepting it prefixed by its group, e.g. `settings prefix`.
+fn find(groups: &[&'static CommandGroup], query: &str) -> Option<(&'static CommandGroup, &'static Command)> {
+    let query = query.to_lowercase();
+    let mut words = query.split_whitespace();
+    let first = words.next()?;
+    let rest = words.collect::<Vec<_>>().join(" ");
+
+    for group in groups {
+        let in_group = |name: &str| group.options.commands
+            .iter()
+            .copied()
+            .find(|command| command.options.names.contains(&name));
+
+        if group.options.prefixes.contains(&first) {
+            let found = match rest.as_str() {
+                "" => group.options.default_command,
+                name => in_group(name),
+            };
+
+            if let Some(command) = found {
+                return Some((group, command));
+            }
+        } else if group.options.prefixes.is_empty() && rest.is_empty() {
+            if let Some(command) = in_group(first) {
+                return Some((group, command));
+            }
+        }
+
+        if let Some(found) = find(group.options.sub_groups, &query) {
+            return Some(found);
+        }
+    }
+
+    None
+}
+
+/// The full invocation of a command without the prefix, e.g. `settings prefix`.
+fn full_name(group: &CommandGroup, command: &Command) -> String {
+    match group.options.prefixes.first() {
+        Some(prefix) => format!("{} {}", prefix, command.options.names[0]),
+        None => command.options.names[0].to_string(),
+    }
+}
+
+fn description(lang: &Catalog, command: &Command) -> String {
+    lang.lookup(&format!("cmd-{}-description", command.options.names[0]))
+        .map(str::to_string)
+        .or_else(|| command.options.desc.map(str::to_string))
+        .unwrap_or_else(|| lang.format(&tr!("help-no-description")))
+}
+
+fn hex([r, g, b, a]: [u8; 4]) -> String {
+    format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+}
+
+/// A filter's parameters as the flags they are given with, along with their descriptions. Those are
+/// the filter's own unless the catalog has one under `param-<filter>-<name>`, followed by the default.
+fn param_flags(lang: &Catalog, filter: &Filter) -> Vec<(String, String)> {
+    filter.params
+        .iter()
+        .map(|param| {
+            let (value, default) = match param.kind {
+                ParamKind::Int { min, max, default } => (format!(" <{}-{}>", min, max), Some(default.to_string())),
+                ParamKind::Float { min, max, default } => (format!(" <{}-{}>", min, max), Some(default.to_string())),
+                ParamKind::Bool => (String::new(), None),
+                ParamKind::Choice { options, default } => (format!(" <{}>", options.join(" | ")), Some(default.to_string())),
+                ParamKind::Color { default } => (" <color>".to_string(), Some(hex(default))),
+                ParamKind::Colors { min, max, default } => {
+                    let colors = vec!["<color>"; min].join(",");
+                    let value = match max > min {
+                        true => format!(" {},...", colors),
+                        false => format!(" {}", colors),
+                    };
+                    let default = (!default.is_empty()).then(|| default.iter().copied().map(hex).collect::<Vec<_>>().join(","));
+                    (value, default)
+                },
+            };
+
+            let mut description = lang
+                .lookup(&format!("param-{}-{}", filter.name, param.name))
+                .unwrap_or(param.description)
+                .to_string();
+            if let Some(default) = default {
+                description = format!("{} {}", description, lang.format(&tr!("help-flag-default", value = default)));
+            }
+
+            (format!("--{}{}", param.name, value), description)
+        })
+        .collect()
+}
+
+fn format_flags(flags: &[(String, String)]) -> String {
+    flags
+        .iter()
+        .map(|(flag, description)| format!("`{}` - {}", flag, description))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+async fn send_overview(
+    ctx: &Context,
+    message: &Message,
+    lang: &Catalog,
+    prefix: &str,
+    groups: &[&'static CommandGroup],
+    is_owner: bool,
+) -> CommandResult {
+    let fields = groups
+        .iter()
+        .filter(|group| is_visible(group, is_owner))
+        .map(|group| {
+            let commands = group.options.commands
+                .iter()
+                .filter(|command| command.options.help_available)
+                .map(|command| format!("`{}`", full_name(group, command)))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            (group.name, commands)
+        })
+        .filter(|(_, commands)| !commands.is_empty())
+        .collect::<Vec<_>>();
+
+    message.channel_id.send_message(ctx, |m| m.embed(|e| e
+        .title(lang.format(&tr!("help-title")))
+        .description(lang.format(&tr!("help-overview", prefix = prefix)))
+        .fields(fields.into_iter().map(|(name, commands)| (name, commands, false)))
+    )).await?;
+
+    Ok(())
+}
+
+async fn send_command(
+    ctx: &Context,
+    message: &Message,
+    lang: &Catalog,
+    prefix: &str,
+    group: &'static CommandGroup,
+    command: &'static Command,
+) -> CommandResult {
+    let name = full_name(group, command);
+    let options = command.options;
+
+    let usage = format!("`{}{} {}`", prefix, name, options.usage.unwrap_or_default());
+    let examples = options.examples
+        .iter()
+        .map(|example| format!("`{}{} {}`", prefix, name, example))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let aliases = options.names[1..].iter().map(|a| format!("`{}`", a)).collect::<Vec<_>>().join(", ");
+
+    let is_imaging = std::ptr::eq(group, &IMAGING_GROUP);
+    let extras = imaging::help_extras(options.names[0]);
+
+    // Imaging commands take the parameters of the filter of the same name as flags, if there is one.
+    let mut flags = match is_imaging {
+        true => filters::find(options.names[0]).map_or(vec![], |filter| param_flags(lang, filter)),
+        false => vec![],
+    };
+    let own_flags = extras.as_ref().map_or(&[][..], |extras| extras.flags);
+    let common_flags = match is_imaging {
+        true => COMMON_FLAGS,
+        false => &[],
+    };
+    flags.extend(own_flags.iter().chain(common_flags).map(|(flag, key)| (flag.to_string(), lang.format(&Text::new(key)))));
+
+    let preview = match is_imaging {
+        true => preview(ctx, options.names[0]).await,
+        false => None,
+    };
+
+    message.channel_id.send_message(ctx, |m| {
+        m.embed(|e| {
+            e.title(format!("{}{}", prefix, name));
+            e.description(description(lang, command));
+            e.field(lang.format(&tr!("help-usage")), usage, false);
+
+            if !examples.is_empty() {
+                e.field(lang.format(&tr!("help-examples")), examples, false);
+            }
+            if !flags.is_empty() {
+                e.field(lang.format(&tr!("help-flags")), format_flags(&flags), false);
+            }
+            if !aliases.is_empty() {
+                e.field(lang.format(&tr!("help-aliases")), aliases, false);
+            }
+            if preview.is_some() {
+                e.image(format!("attachment://{}", PREVIEW_FILENAME));
+            }
+
+            e
+        });
+
+        if let Some(preview) = &preview {
+            m.add_file((preview.as_slice(), PREVIEW_FILENAME));
+        }
+
+        m
+    }).await?;
+
+    Ok(())
+}
+
+#[help]
+async fn help_command(
+    ctx: &Context,
+    message: &Message,
+    args: Args,
+    _: &'static HelpOptions,
+    groups: &[&'static CommandGroup],
+    owners: HashSet<UserId>,
+) -> CommandResult {
+    let lang = i18n::catalog(ctx, message).await;
+    let prefix = crate::prefix(ctx, message).await;
+    let is_owner = owners.contains(&message.author.id);
+
+    let query = args.rest().trim();
+    if query.is_empty() {
+        return send_overview(ctx, message, &lang, &prefix, groups, is_owner).await;
+    }
+
+    match find(groups, query).filter(|(group, _)| is_visible(group, is_owner)) {
+        Some((group, command)) => send_command(ctx, message, &lang, &prefix, group, command).await,
+        None => Err(tr!("unknown-command", command = query).into()),
+    }
+}
@@ -0,0 +1,360 @@
+//! User facing strings, looked up by key in per-language catalogs written in a subset of
+//! [Fluent](https://projectfluent.org): `key = value` messages, indented continuation lines,
+//! `# comments`, `{ $variable }` placeables and `{ "literal" }` placeables for escaping braces.
+
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use serenity::prelude::TypeMapKey;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::ConfigError;
+use crate::db::Database;
+
+pub const FALLBACK_LANGUAGE: &str = "en";
+
+lazy_static::lazy_static! {
+    /// Used for keys missing from other catalogs and wherever the language isn't known.
+    static ref FALLBACK: Arc<Catalog> = Arc::new(
+        Catalog::parse(FALLBACK_LANGUAGE, include_str!("../locales/en.ftl")).expect("Bundled en.ftl is invalid")
+    );
+}
+
+/// Builds a [`Text`], e.g. `tr!("prefix-set", prefix = "!")`.
+#[macro_export]
+macro_rules! tr {
+    ($key:literal $(, $name:ident = $value:expr)* $(,)?) => {
+        $crate::i18n::Text::new($key)$(.arg(stringify!($name), $value))*
+    };
+}
+
+/// A message to be rendered in a catalog. It can also be returned as a command error,
+/// in which case it gets rendered in the language of the guild when it is reported.
+#[derive(Clone, Debug)]
+pub struct Text {
+    key: &'static str,
+    args: Vec<(&'static str, String)>,
+}
+
+impl Text {
+    pub fn new(key: &'static str) -> Self {
+        Self { key, args: vec![] }
+    }
+
+    pub fn arg(mut self, name: &'static str, value: impl fmt::Display) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+}
+
+impl fmt::Display for Text {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&FALLBACK.format(self))
+    }
+}
+
+impl std::error::Error for Text {}
+
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+#[derive(Debug)]
+pub struct Catalog {
+    language: String,
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    pub fn parse(language: &str, source: &str) -> Result<Self, ParseError> {
+        let mut messages = HashMap::new();
+        let mut current: Option<(String, String)> = None;
+        // Blank lines only end a message if no continuation line follows them.
+        let mut blank_lines = 0;
+
+        for (i, line) in source.lines().enumerate() {
+            let line = line.trim_end();
+
+            if line.is_empty() {
+                blank_lines += 1;
+                continue;
+            }
+
+            if line.starts_with(char::is_whitespace) {
+                match &mut current {
+                    Some((_, value)) => {
+                        if !value.is_empty() {
+                            value.push_str(&"\n".repeat(blank_lines + 1));
+                        }
+                        value.push_str(line.trim_start());
+                    },
+                    None => return Err(ParseError { line: i + 1, message: "continuation line outside of a message" }),
+                }
+
+                blank_lines = 0;
+                continue;
+            }
+
+            blank_lines = 0;
+            if let Some((key, value)) = current.take() {
+                messages.insert(key, value);
+            }
+
+            if line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line
+                .split_once('=')
+                .ok_or(ParseError { line: i + 1, message: "expected `key = value`" })?;
+            let key = key.trim();
+
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                return Err(ParseError { line: i + 1, message: "invalid message key" });
+            }
+
+            current = Some((key.to_string(), value.trim().to_string()));
+        }
+
+        if let Some((key, value)) = current {
+            messages.insert(key, value);
+        }
+
+        Ok(Self { language: language.to_string(), messages })
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The raw message for a key built at runtime, without falling back to English.
+    pub fn lookup(&self, key: &str) -> Option<&str> {
+        self.messages.get(key).map(String::as_str)
+    }
+
+    /// Renders the text, falling back to English and then to the bare key if it is missing.
+    pub fn format(&self, text: &Text) -> String {
+        match self.messages.get(text.key).or_else(|| FALLBACK.messages.get(text.key)) {
+            Some(pattern) => substitute(pattern, &text.args),
+            None => text.key.to_string(),
+        }
+    }
+}
+
+fn substitute(pattern: &str, args: &[(&'static str, String)]) -> String {
+    let mut result = String::with_capacity(pattern.len());
+    let mut rest = pattern;
+
+    while let Some(start) = rest.find('{') {
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+
+        result.push_str(&rest[..start]);
+        let inner = rest[start + 1..end].trim();
+
+        if let Some(name) = inner.strip_prefix('$') {
+            match args.iter().find(|(n, _)| *n == name) {
+                Some((_, value)) => result.push_str(value),
+                None => result.push_str(&rest[start..=end]),
+            }
+        } else if inner.len() >= 2 && inner.starts_with('"') && inner.ends_with('"') {
+            result.push_str(&inner[1..inner.len() - 1]);
+        } else {
+            result.push_str(&rest[start..=end]);
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+    result
+}
+
+/// Every loaded catalog, by language code.
+pub struct Locales {
+    catalogs: HashMap<String, Arc<Catalog>>,
+    default: String,
+}
+
+pub struct LocalesKey;
+
+impl TypeMapKey for LocalesKey {
+    type Value = Arc<Locales>;
+}
+
+impl Locales {
+    /// Loads every `<language>.ftl` file in `dir` on top of the bundled English catalog.
+    /// A missing directory just means only English is available.
+    pub fn load(dir: &Path, default: &str) -> Result<Self, ConfigError> {
+        let mut catalogs = HashMap::new();
+        catalogs.insert(FALLBACK_LANGUAGE.to_string(), FALLBACK.clone());
+
+        if dir.is_dir() {
+            let entries = std::fs::read_dir(dir).map_err(|e| ConfigError::Io(dir.to_path_buf(), e))?;
+
+            for entry in entries {
+                let path = entry.map_err(|e| ConfigError::Io(dir.to_path_buf(), e))?.path();
+
+                let language = match (path.extension(), path.file_stem()) {
+                    (Some(ext), Some(stem)) if ext == "ftl" => stem.to_string_lossy().to_lowercase(),
+                    _ => continue,
+                };
+
+                let source = std::fs::read_to_string(&path).map_err(|e| ConfigError::Io(path.clone(), e))?;
+                let catalog = Catalog::parse(&language, &source)
+                    .map_err(|e| ConfigError::Invalid(format!("could not parse `{}`: {}", path.display(), e)))?;
+
+                catalogs.insert(language, Arc::new(catalog));
+            }
+        }
+
+        let default = default.to_lowercase();
+        if !catalogs.contains_key(&default) {
+            return Err(ConfigError::Invalid(format!("no catalog found for the default language `{}`", default)));
+        }
+
+        Ok(Self { catalogs, default })
+    }
+
+    pub async fn get(ctx: &Context) -> Arc<Locales> {
+        ctx.data.read().await.get::<LocalesKey>().expect("Locales was not inserted into client data").clone()
+    }
+
+    pub fn has(&self, language: &str) -> bool {
+        self.catalogs.contains_key(&language.to_lowercase())
+    }
+
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages = self.catalogs.keys().map(String::as_str).collect::<Vec<_>>();
+        languages.sort_unstable();
+        languages
+    }
+
+    /// The catalog for the language, or the default one if it isn't loaded.
+    pub fn catalog(&self, language: Option<&str>) -> Arc<Catalog> {
+        language
+            .and_then(|language| self.catalogs.get(&language.to_lowercase()))
+            .unwrap_or_else(|| &self.catalogs[&self.default])
+            .clone()
+    }
+}
+
+/// The catalog for the language of the guild the message was sent in.
+pub async fn catalog(ctx: &Context, message: &Message) -> Arc<Catalog> {
+    let language = match message.guild_id {
+        Some(guild_id) => Database::get(ctx).await.guild_settings(guild_id).ok().and_then(|s| s.language),
+        None => None,
+    };
+
+    Locales::get(ctx).await.catalog(language.as_deref())
+}
+
+/// The message for an imaging error, `None` for ones only the underlying library can describe.
+fn core_error_text(error: &photon_core::Error) -> Option<Text> {
+    use photon_core::Error;
+
+    Some(match error {
+        Error::Image(_) | Error::Io(_) => return None,
+        Error::NoFrames => tr!("gif-no-frames"),
+        Error::NotAnimated => tr!("gif-not-animated"),
+        Error::UnknownFormat(format) => tr!("unknown-format", format = format),
+        Error::UnknownFilter(name) => tr!("filter-unknown", filter = name),
+        Error::UnknownParam(name) => tr!("filter-unknown-param", flag = name),
+        Error::MissingValue(name) => tr!("flag-missing-value", flag = name),
+        Error::InvalidParam { name, value } => tr!("flag-invalid-value", flag = name, value = value),
+        Error::OutOfRange { name, min, max, value } => tr!("flag-out-of-range", flag = name, min = min, max = max, value = value),
+        Error::InvalidLut { line, reason } => tr!("lut-invalid", line = line, reason = reason),
+    })
+}
+
+/// Renders a command error, translating it if it is a [`Text`] or an imaging error.
+pub fn format_error(catalog: &Catalog, error: &(dyn std::error::Error + Send + Sync)) -> String {
+    if let Some(text) = error.downcast_ref::<Text>() {
+        return catalog.format(text);
+    }
+
+    match error.downcast_ref::<photon_core::Error>().and_then(core_error_text) {
+        Some(text) => catalog.format(&text),
+        None => error.to_string(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &str = "\
+# A comment.
+greeting = Hello, { $name }!
+notes = First line
+    second line
+
+    after a blank line
+braces = Use { \"{\" }name} or { $missing }
+
+rate-limited = Nicht so schnell.
+";
+
+    #[test]
+    fn parses_catalogs() {
+        let catalog = Catalog::parse("de", SOURCE).unwrap();
+        assert_eq!(catalog.language(), "de");
+        assert_eq!(catalog.lookup("greeting"), Some("Hello, { $name }!"));
+        assert_eq!(catalog.lookup("notes"), Some("First line\nsecond line\n\nafter a blank line"));
+        assert_eq!(catalog.lookup("# A comment."), None);
+        assert_eq!(catalog.lookup("command-error"), None);
+
+        // The bundled catalog has to parse for anything to be shown at all.
+        assert!(FALLBACK.lookup("command-error").is_some());
+    }
+
+    #[test]
+    fn rejects_malformed_catalogs() {
+        let error = Catalog::parse("de", "  continued").unwrap_err();
+        assert_eq!((error.line, error.message), (1, "continuation line outside of a message"));
+
+        let error = Catalog::parse("de", "greeting = Hi\njust text").unwrap_err();
+        assert_eq!((error.line, error.message), (2, "expected `key = value`"));
+
+        let error = Catalog::parse("de", "some key = Hi").unwrap_err();
+        assert_eq!((error.line, error.message), (1, "invalid message key"));
+    }
+
+    #[test]
+    fn formats_with_fallbacks() {
+        let catalog = Catalog::parse("de", SOURCE).unwrap();
+        assert_eq!(catalog.format(&tr!("greeting", name = "Photon")), "Hello, Photon!");
+        assert_eq!(catalog.format(&tr!("braces")), "Use {name} or { $missing }");
+
+        // Keys missing from the catalog fall back to English, then to the key itself.
+        assert_eq!(catalog.format(&tr!("rate-limited")), "Nicht so schnell.");
+        assert_eq!(catalog.format(&tr!("unknown-command", command = "nope")), "There is no command named `nope`.");
+        assert_eq!(catalog.format(&tr!("no-such-key")), "no-such-key");
+        assert_eq!(tr!("cooldown", command = "blur", seconds = 3).to_string(), "Slow down! You can use `blur` again in 3s.");
+    }
+
+    #[test]
+    fn loads_locales() {
+        let missing = Path::new("locales/does-not-exist");
+
+        let locales = Locales::load(missing, "EN").unwrap();
+        assert_eq!(locales.languages(), ["en"]);
+        assert!(locales.has("En"));
+        assert_eq!(locales.catalog(Some("fr")).language(), "en");
+        assert!(Locales::load(missing, "fr").is_err());
+    }
+}
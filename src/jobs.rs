@@ -0,0 +1,93 @@
+use serenity::client::Context;
+use serenity::framework::standard::CommandError;
+use serenity::prelude::TypeMapKey;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Semaphore;
+
+use crate::tr;
+
+/// Runs CPU-heavy image jobs on the blocking thread pool, at most `workers` at a time.
+pub struct JobQueue {
+    semaphore: Arc<Semaphore>,
+    workers: usize,
+    /// How long to wait on a job before giving up on it, `None` to wait for as long as it takes.
+    timeout: Option<Duration>,
+    queued: AtomicUsize,
+    running: AtomicUsize,
+}
+
+pub struct JobQueueKey;
+
+impl TypeMapKey for JobQueueKey {
+    type Value = Arc<JobQueue>;
+}
+
+impl JobQueue {
+    /// Creates a queue with the given amount of workers, or one per CPU if `workers` is 0. Jobs taking
+    /// longer than `timeout` fail, unless it is zero.
+    pub fn new(workers: usize, timeout: Duration) -> Self {
+        let workers = match workers {
+            0 => std::thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        };
+
+        Self {
+            semaphore: Arc::new(Semaphore::new(workers)),
+            workers,
+            timeout: Some(timeout).filter(|timeout| !timeout.is_zero()),
+            queued: AtomicUsize::new(0),
+            running: AtomicUsize::new(0),
+        }
+    }
+
+    pub async fn get(ctx: &Context) -> Arc<JobQueue> {
+        ctx.data.read().await.get::<JobQueueKey>().expect("JobQueue was not inserted into client data").clone()
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// The amount of jobs waiting for a free worker.
+    pub fn depth(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    pub fn running(&self) -> usize {
+        self.running.load(Ordering::Relaxed)
+    }
+
+    /// Runs the job once a worker is free. A job that times out can't be stopped, so it keeps its worker
+    /// until it finishes and only the caller stops waiting on it.
+    pub async fn run<T, F>(&self, job: F) -> Result<T, CommandError>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> Result<T, CommandError> + Send + 'static,
+    {
+        self.queued.fetch_add(1, Ordering::Relaxed);
+        let permit = self.semaphore.clone().acquire_owned().await;
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+
+        let permit = permit?;
+        self.running.fetch_add(1, Ordering::Relaxed);
+        let handle = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            job()
+        });
+
+        let result = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, handle).await,
+            None => Ok(handle.await),
+        };
+        self.running.fetch_sub(1, Ordering::Relaxed);
+
+        match result {
+            Ok(result) => result?,
+            Err(_) => Err(tr!("job-timed-out", seconds = self.timeout.unwrap_or_default().as_secs()).into()),
+        }
+    }
+}
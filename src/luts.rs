@@ -0,0 +1,69 @@
+//! Named LUTs for the `lut` command, the ones shipped in `assets/luts` and ones guilds uploaded.
+
+use photon_core::Lut;
+
+use serenity::client::Context;
+use serenity::framework::standard::CommandError;
+use serenity::model::id::GuildId;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::db::Database;
+use crate::jobs::JobQueue;
+use crate::tr;
+
+pub const BUNDLED: &[(&str, &str)] = &[
+    ("cool", include_str!("../assets/luts/cool.cube")),
+    ("noir", include_str!("../assets/luts/noir.cube")),
+    ("teal-orange", include_str!("../assets/luts/teal-orange.cube")),
+    ("vintage", include_str!("../assets/luts/vintage.cube")),
+    ("warm", include_str!("../assets/luts/warm.cube")),
+];
+
+pub const MAX_GUILD_LUTS: usize = 25;
+pub const MAX_FILE_SIZE: u64 = 4 * 1024 * 1024;
+const MAX_NAME_LENGTH: usize = 32;
+
+/// Taken by the `lut` subcommands.
+const RESERVED_NAMES: &[&str] = &["list", "upload", "add", "remove", "delete"];
+
+lazy_static::lazy_static! {
+    static ref PARSED: HashMap<&'static str, Arc<Lut>> = BUNDLED
+        .iter()
+        .map(|(name, source)| (*name, Arc::new(Lut::parse(source).expect("Bundled LUT is invalid"))))
+        .collect();
+}
+
+pub fn bundled(name: &str) -> Option<Arc<Lut>> {
+    PARSED.get(name).cloned()
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    (1..=MAX_NAME_LENGTH).contains(&name.len())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_')
+        && !RESERVED_NAMES.contains(&name)
+        && bundled(name).is_none()
+}
+
+/// Parses a `.cube` file on the job queue, large tables take a moment.
+pub async fn parse(ctx: &Context, source: String) -> Result<Arc<Lut>, CommandError> {
+    JobQueue::get(ctx).await.run(move || Ok(Arc::new(Lut::parse(&source)?))).await
+}
+
+/// Finds a LUT by name, bundled ones first and then the ones uploaded to the guild.
+pub async fn find(ctx: &Context, guild_id: Option<GuildId>, name: &str) -> Result<Arc<Lut>, CommandError> {
+    if let Some(lut) = bundled(name) {
+        return Ok(lut);
+    }
+
+    let source = match guild_id {
+        Some(guild_id) => Database::get(ctx).await.guild_lut(guild_id, name)?,
+        None => None,
+    };
+
+    match source {
+        Some(source) => parse(ctx, source).await,
+        None => Err(tr!("lut-unknown", name = name).into()),
+    }
+}
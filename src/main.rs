@@ -1,48 +1,223 @@
 #![feature(async_closure)]
 
+#[cfg(feature = "api")]
+mod api;
+mod args;
+mod card;
+mod commands;
+mod config;
+mod cooldown;
+mod db;
+mod fonts;
+mod help;
+mod i18n;
+mod jobs;
+mod luts;
+mod nsfw;
+mod output;
+mod owners;
+#[cfg(feature = "pdf")]
+mod pdf;
+mod phash;
+mod pipeline;
+mod preflight;
+mod quality;
+mod ratelimit;
+#[cfg(feature = "removebg")]
+mod removebg;
 mod resolve_image;
-use resolve_image::ImageResolver;
+mod shutdown;
+mod stats;
+mod templates;
+use commands::fonts::FONTS_GROUP;
+use commands::imaging::IMAGING_GROUP;
+use commands::moderation::MODERATION_GROUP;
+use commands::owner::BLACKLIST_GROUP;
+use commands::prefs::PREFERENCES_GROUP;
+use commands::settings::SETTINGS_GROUP;
+use commands::utility::UTILITY_GROUP;
+use config::{Config, ConfigKey};
+use cooldown::{Cooldowns, CooldownsKey};
+use db::{BlacklistKind, Database, DatabaseKey};
+use help::HELP_COMMAND;
+use i18n::{Catalog, Locales, LocalesKey};
+use jobs::{JobQueue, JobQueueKey};
+use owners::OwnersKey;
+use quality::{LoadMonitor, LoadMonitorKey};
+use ratelimit::{Outcome, RateLimiter, RateLimiterKey};
+use shutdown::{Shutdown, ShutdownKey};
+use stats::{Stats, StatsKey};
+use templates::{Templates, TemplatesKey};
 
 use dotenv::dotenv;
-use image::codecs::{png::PngEncoder, gif::{GifDecoder, GifEncoder}};
-use image::AnimationDecoder;
 
 use serenity::async_trait;
 use serenity::client::{Client, Context, EventHandler as BaseEventHandler};
+use serenity::client::bridge::gateway::{ShardId, ShardManager};
+use serenity::http::Http;
 use serenity::framework::standard::{
     Args,
     StandardFramework,
     CommandGroup,
     CommandResult,
-    HelpOptions,
-    help_commands,
-    macros::{command, help, hook, group},
+    DispatchError,
+    Reason,
+    macros::{command, hook, group},
 };
-use serenity::model::{channel::Message, gateway::Ready, id::UserId};
+use serenity::model::{channel::Message, gateway::Ready};
+use serenity::prelude::{Mutex, TypeMapKey};
 
-use std::collections::hash_set::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[group]
-#[commands(ping)]
+#[commands(ping, stats)]
 struct Miscellaneous;
 
-#[group]
-#[commands(try_image, invert)]
-struct Imaging;
+/// Every group of built-in commands, in the order `help` lists them.
+pub static GROUPS: &[&CommandGroup] = &[
+    &MISCELLANEOUS_GROUP,
+    &IMAGING_GROUP,
+    &UTILITY_GROUP,
+    &MODERATION_GROUP,
+    &SETTINGS_GROUP,
+    &PREFERENCES_GROUP,
+    &FONTS_GROUP,
+    &BLACKLIST_GROUP,
+];
+
+/// Groups whose commands can be disabled per guild.
+pub static TOGGLEABLE_GROUPS: &[&CommandGroup] = &[&MISCELLANEOUS_GROUP, &IMAGING_GROUP, &UTILITY_GROUP];
+
+pub struct ShardManagerContainer;
+
+impl TypeMapKey for ShardManagerContainer {
+    type Value = Arc<Mutex<ShardManager>>;
+}
 
 struct EventHandler;
 
 #[async_trait]
 impl BaseEventHandler for EventHandler {
     async fn ready(&self, _: Context, data: Ready) {
-        println!("Logged in as {} ({})", data.user.tag(), data.user.id);
+        let shard = data.shard.map_or(String::new(), |[id, total]| format!(" on shard {}/{}", id + 1, total));
+        println!("Logged in as {} ({}){}", data.user.tag(), data.user.id, shard);
+    }
+}
+
+/// The prefix commands have to be invoked with where the message was sent.
+pub async fn prefix(ctx: &Context, message: &Message) -> String {
+    if let Some(guild_id) = message.guild_id {
+        if let Ok(settings) = Database::get(ctx).await.guild_settings(guild_id) {
+            if let Some(prefix) = settings.prefix {
+                return prefix;
+            }
+        }
+    }
+
+    Config::get(ctx).await.prefix.clone()
+}
+
+#[hook]
+async fn dynamic_prefix(ctx: &Context, message: &Message) -> Option<String> {
+    Some(prefix(ctx, message).await)
+}
+
+#[hook]
+async fn before_hook(ctx: &Context, message: &Message, cmd_name: &str) -> bool {
+    let database = Database::get(ctx).await;
+
+    // Blacklisted users and guilds are ignored entirely.
+    let blacklisted = database.blacklist_entry(BlacklistKind::User, message.author.id.0).ok().flatten().is_some()
+        || message.guild_id.map_or(false, |guild_id| {
+            database.blacklist_entry(BlacklistKind::Guild, guild_id.0).ok().flatten().is_some()
+        });
+    if blacklisted {
+        return false;
+    }
+
+    if !owners::is_owner(ctx, message.author.id).await {
+        let limiter = RateLimiter::get(ctx).await;
+
+        if let Outcome::Limited { strikes } = limiter.hit(message.author.id) {
+            let config = limiter.config();
+
+            if strikes >= config.strikes {
+                limiter.reset(message.author.id);
+
+                let _ = database.add_to_blacklist(
+                    BlacklistKind::User,
+                    message.author.id.0,
+                    Some("Automatic: repeatedly tripping the rate limiter"),
+                    Some(config.blacklist_minutes as i64 * 60),
+                );
+                let text = tr!("rate-limit-blacklisted", minutes = config.blacklist_minutes);
+                let _ = message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await;
+            } else if strikes == 1 {
+                let _ = message.reply(ctx, i18n::catalog(ctx, message).await.format(&tr!("rate-limited"))).await;
+            }
+
+            return false;
+        }
+    }
+
+    if let Some(guild_id) = message.guild_id {
+        if let Ok(settings) = database.guild_settings(guild_id) {
+            if settings.is_disabled(cmd_name) {
+                let text = tr!("command-disabled", command = cmd_name);
+                let _ = message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await;
+                return false;
+            }
+        }
+    }
+
+    let missing = preflight::missing_permissions(ctx, message, cmd_name).await;
+    if !missing.is_empty() {
+        preflight::report_missing(ctx, message, &missing).await;
+        return false;
+    }
+
+    // Only once everything else let the command through, so a refused command doesn't use up the cooldown.
+    if let Err(reason) = cooldown::check(ctx, message, cmd_name).await {
+        let _ = message.reply(ctx, reason).await;
+        return false;
+    }
+
+    // This has to stay last, every command that gets past it must reach the after hook.
+    if !Shutdown::get(ctx).await.begin() {
+        let _ = message.reply(ctx, i18n::catalog(ctx, message).await.format(&tr!("restarting"))).await;
+        return false;
     }
+
+    Stats::get(ctx).await.start(message.id);
+    true
 }
 
 #[hook]
 async fn after_hook(ctx: &Context, message: &Message, cmd_name: &str, result: CommandResult) {
+    Shutdown::get(ctx).await.finish();
+    Stats::get(ctx).await.finish(message.id, cmd_name, message.author.id);
+
     if let Err(why) = result {
-        let _ = message.reply(ctx, format!("Error occured in `{}`: {}", cmd_name, why)).await;
+        let lang = i18n::catalog(ctx, message).await;
+        let text = tr!("command-error", command = cmd_name, error = i18n::format_error(&lang, why.as_ref()));
+
+        let _ = message.reply(ctx, lang.format(&text)).await;
+    }
+}
+
+#[hook]
+async fn dispatch_error(ctx: &Context, message: &Message, error: DispatchError, command_name: &str) {
+    match error {
+        // Checks such as `Nsfw` carry their own user facing, already translated refusal.
+        DispatchError::CheckFailed(_, Reason::User(reason)) => {
+            let _ = message.reply(ctx, reason).await;
+        },
+        DispatchError::NotEnoughArguments { .. } | DispatchError::TooManyArguments { .. } => {
+            let text = help::usage(ctx, message, command_name).await;
+            let _ = message.reply(ctx, i18n::catalog(ctx, message).await.format(&text)).await;
+        },
+        _ => {},
     }
 }
 
@@ -53,116 +228,255 @@ async fn main() {
     let token = std::env::var("TOKEN")
         .expect("Missing environment variable 'TOKEN'");
 
-    Client::builder(token)
-        .application_id(914283059501735977_u64)
-        .event_handler(EventHandler)
-        .framework(
-            StandardFramework::new()
-                .configure(|config|
-                    config.prefix("pt").allow_dm(false).with_whitespace(true)
-                )
-                .after(after_hook)
-                .group(&MISCELLANEOUS_GROUP)
-                .group(&IMAGING_GROUP)
-                .help(&HELP_COMMAND)
+    let config = Config::load().unwrap_or_else(|why| {
+        eprintln!("Invalid configuration: {}", why);
+        std::process::exit(1);
+    });
+
+    let database = Database::open(&config.database).unwrap_or_else(|why| {
+        eprintln!("Could not open database `{}`: {}", config.database.display(), why);
+        std::process::exit(1);
+    });
+
+    let locales = Locales::load(&config.locales, &config.language).unwrap_or_else(|why| {
+        eprintln!("Could not load locales: {}", why);
+        std::process::exit(1);
+    });
+
+    // Template names must not shadow built-in commands (or `help`, which isn't in a group).
+    let templates = Templates::load(&config.templates, |name| {
+        name == "help" || commands::find_command(GROUPS, name).is_some()
+    }).unwrap_or_else(|why| {
+        eprintln!("Could not load meme templates: {}", why);
+        std::process::exit(1);
+    });
+
+    #[cfg(feature = "removebg")]
+    let segmentation_model = config.removebg.model.as_ref().map(|path| {
+        removebg::SegmentationModel::load(path).map(Arc::new).unwrap_or_else(|why| {
+            eprintln!("Could not load segmentation model `{}`: {}", path.display(), why);
+            std::process::exit(1);
+        })
+    });
+
+    let owners = owners::fetch_owners(&Http::new_with_token(&token)).await.unwrap_or_else(|why| {
+        eprintln!("Could not fetch application owners: {}", why);
+        std::process::exit(1);
+    });
+
+    let drain_timeout = Duration::from_secs(config.jobs.drain_timeout);
+    let config = Arc::new(config);
+    let locales = Arc::new(locales);
+    let database = Arc::new(database);
+    let shutdown = Arc::new(Shutdown::new());
+
+    let stats = Arc::new(Stats::default());
+    tokio::spawn(stats.clone().run(database.clone()));
+
+    let limiter = Arc::new(RateLimiter::new(config.ratelimit.clone()));
+    let jobs = Arc::new(JobQueue::new(config.jobs.workers, Duration::from_secs(config.jobs.timeout)));
+    let monitor = Arc::new(LoadMonitor::new(config.quality.clone(), jobs.clone()));
+    tokio::spawn(monitor.clone().run());
+
+    #[cfg(feature = "api")]
+    if config.api.enabled {
+        tokio::spawn(api::serve(config.api.bind, api::ApiState {
+            config: config.clone(),
+            jobs: jobs.clone(),
+            monitor: monitor.clone(),
+            catalog: locales.catalog(None),
+        }));
+    }
+
+    let mut framework = StandardFramework::new()
+        .configure(|c| c
+            .prefix("")
+            .dynamic_prefix(dynamic_prefix)
+            .allow_dm(false)
+            .with_whitespace(true)
+            .owners(owners.clone())
         )
-        .intents(serenity::client::bridge::gateway::GatewayIntents::non_privileged())
-        .await
-        .expect("Could not configure client")
-        .start()
+        .before(before_hook)
+        .after(after_hook)
+        .on_dispatch_error(dispatch_error)
+        .help(&HELP_COMMAND);
+
+    let mut cooldown_groups = vec![&IMAGING_GROUP, &UTILITY_GROUP];
+
+    for group in GROUPS {
+        framework = framework.group(group);
+    }
+    if let Some(group) = commands::memes::group(&templates) {
+        framework = framework.group(group);
+        cooldown_groups.push(group);
+    }
+
+    let mut client = Client::builder(token)
+        .application_id(config.application_id)
+        .event_handler(EventHandler)
+        .framework(framework)
+        .intents(config.gateway_intents())
+        .type_map_insert::<ConfigKey>(config.clone())
+        .type_map_insert::<DatabaseKey>(database.clone())
+        .type_map_insert::<ShutdownKey>(shutdown.clone())
+        .type_map_insert::<JobQueueKey>(jobs)
+        .type_map_insert::<LoadMonitorKey>(monitor)
+        .type_map_insert::<RateLimiterKey>(limiter)
+        .type_map_insert::<StatsKey>(stats.clone())
+        .type_map_insert::<CooldownsKey>(Arc::new(Cooldowns::new(cooldown_groups)))
+        .type_map_insert::<OwnersKey>(Arc::new(owners))
+        .type_map_insert::<LocalesKey>(locales)
+        .type_map_insert::<TemplatesKey>(Arc::new(templates))
         .await
-        .expect("Could not start client");
+        .expect("Could not configure client");
+
+    client.data.write().await.insert::<ShardManagerContainer>(client.shard_manager.clone());
+    #[cfg(feature = "removebg")]
+    if let Some(model) = segmentation_model {
+        client.data.write().await.insert::<removebg::SegmentationModelKey>(model);
+    }
+
+    let shard_manager = client.shard_manager.clone();
+    tokio::spawn(async move {
+        shutdown::wait_for_signal().await;
+        println!("Shutting down, waiting for {} running command(s)...", shutdown.in_flight());
+
+        let remaining = shutdown.drain(drain_timeout).await;
+        if remaining > 0 {
+            eprintln!("Gave up waiting on {} command(s) after {:?}", remaining, drain_timeout);
+        }
+
+        if let Err(why) = stats.flush(&database) {
+            eprintln!("Could not flush command stats: {}", why);
+        }
+
+        if let Err(why) = database.flush() {
+            eprintln!("Could not flush database: {}", why);
+        }
+
+        shard_manager.lock().await.shutdown_all().await;
+    });
+
+    client.start_autosharded().await.expect("Could not start client");
 }
 
-#[help]
-async fn help_command(
-   context: &Context,
-   message: &Message,
-   args: Args,
-   help_options: &'static HelpOptions,
-   groups: &[&'static CommandGroup],
-   owners: HashSet<UserId>,
-) -> CommandResult {
-    help_commands::with_embeds(context, message, args, help_options, groups, owners).await;
-    Ok(())
+/// The most shards `ping` lists individually before summarizing.
+const MAX_LISTED_SHARDS: usize = 20;
+
+fn format_latency(lang: &Catalog, latency: Option<std::time::Duration>) -> String {
+    lang.format(&match latency {
+        Some(latency) => tr!("latency-ms", ms = latency.as_millis()),
+        None => tr!("ping-no-heartbeat"),
+    })
 }
 
 #[command]
+#[description("Shows the gateway heartbeat latency of every shard.")]
 async fn ping(ctx: &Context, message: &Message) -> CommandResult {
-    let instant = std::time::Instant::now();
+    let lang = i18n::catalog(ctx, message).await;
+    let manager = ctx.data.read().await
+        .get::<ShardManagerContainer>()
+        .expect("ShardManager was not inserted into client data")
+        .clone();
+    let manager = manager.lock().await;
+    let runners = manager.runners.lock().await;
 
-    let mut msg = message.reply(ctx, "Please wait...").await?;
-    msg.edit(ctx, |m| m.content(format!("Pong! Latency: {} ms", instant.elapsed().as_millis()))).await?;
+    let mut shards = runners.iter().map(|(id, runner)| (id.0, runner.latency)).collect::<Vec<_>>();
+    shards.sort_unstable_by_key(|(id, _)| *id);
 
-    Ok(())
-}
+    let current = runners.get(&ShardId(ctx.shard_id)).and_then(|runner| runner.latency);
+    let mut content = lang.format(&tr!("ping-pong", shard = ctx.shard_id, latency = format_latency(&lang, current)));
 
-#[command]
-async fn try_image(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
-    let resolver = ImageResolver::new();
-    let query = args.single_quoted::<String>().ok();
-    
-    let result = resolver.resolve(ctx, message, query).await?;
-    message.channel_id.send_message(ctx, |m| m.add_file((result.as_slice(), "my_file.gif"))).await?;
+    if shards.len() > 1 {
+        let lines = shards
+            .iter()
+            .take(MAX_LISTED_SHARDS)
+            .map(|(id, latency)| lang.format(&tr!("ping-shard", shard = id, latency = format_latency(&lang, *latency))))
+            .collect::<Vec<_>>()
+            .join("\n");
+        content.push_str(&format!("\n```\n{}\n```", lines));
+
+        let known = shards.iter().filter_map(|(_, latency)| *latency).collect::<Vec<_>>();
+        if !known.is_empty() {
+            let average = known.iter().sum::<std::time::Duration>() / known.len() as u32;
+            content.push_str(&lang.format(&tr!("ping-average", shards = shards.len(), ms = average.as_millis())));
+        }
+    }
+
+    message.reply(ctx, content).await?;
 
     Ok(())
 }
 
-fn is_gif(data: &Vec<u8>) -> bool {
-    &data[0..6] == b"\x47\x49\x46\x38\x39\x61" || &data[0..6] == b"\x47\x49\x46\x38\x37\x61"
+/// How many commands `stats` lists.
+const TOP_COMMANDS: usize = 10;
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.2} GiB", b as f64 / (1u64 << 30) as f64),
+        b if b >= 1 << 20 => format!("{:.2} MiB", b as f64 / (1u64 << 20) as f64),
+        b if b >= 1 << 10 => format!("{:.2} KiB", b as f64 / (1u64 << 10) as f64),
+        b => format!("{} B", b),
+    }
 }
 
 #[command]
-async fn invert(ctx: &Context, message: &Message, mut args: Args) -> CommandResult {
-    let resolver = ImageResolver::new();
-    let query = args.single_quoted::<String>().ok();
-    
-    let typing = message.channel_id.start_typing(&ctx.http)?;
-    let result = resolver.resolve(ctx, message, query).await?;
-    if is_gif(&result) {
-        let data = tokio::task::spawn_blocking(move || -> CommandResult<std::io::Cursor<Vec<u8>>> {
-            let decoder = GifDecoder::new(result.as_slice()).unwrap();
-            let frames = decoder.into_frames().filter(|f| f.is_ok()).map(|f| {
-                let mut frame = f.unwrap().clone();
-                let buffer = frame.buffer_mut();
-                image::imageops::invert(buffer);
-                frame
-            });
-    
-            let mut buffer = std::io::Cursor::new(vec![]);
-            GifEncoder::new(&mut buffer).encode_frames(frames).unwrap();
-
-            Ok(buffer.clone())
-        }).await?.unwrap();
-
-        let encoded = data.into_inner();
-        message.channel_id.send_message(ctx, |m| m.add_file((encoded.as_slice(), "my_file.gif"))).await?;
-
-        typing.stop();
-        return Ok(());
-    }
+#[description("Shows how much the bot's commands are used, or the numbers of a single command.")]
+#[usage("[command]")]
+#[example("invert")]
+#[max_args(1)]
+async fn stats(ctx: &Context, message: &Message, args: Args) -> CommandResult {
+    let lang = i18n::catalog(ctx, message).await;
+    let database = Database::get(ctx).await;
+    Stats::get(ctx).await.flush(&database)?;
+
+    let all = database.command_stats()?;
 
-    let mut img = image::load_from_memory(result.as_slice())?.into_rgba8();
+    if let Some(name) = args.current() {
+        let command = commands::find_command(TOGGLEABLE_GROUPS, name)
+            .ok_or_else(|| tr!("unknown-command", command = name))?;
+        let name = command.options.names[0];
+        let stats = all.into_iter().find(|(c, _)| c == name).map(|(_, s)| s).unwrap_or_default();
 
-    image::imageops::invert(&mut img);
+        message.channel_id.send_message(ctx, |m| m.embed(|e| e
+            .title(lang.format(&tr!("stats-command-title", command = name)))
+            .field(lang.format(&tr!("stats-uses")), stats.invocations, true)
+            .field(lang.format(&tr!("stats-unique-users")), stats.unique_users, true)
+            .field(lang.format(&tr!("stats-processed")), format_bytes(stats.bytes), true)
+            .field(lang.format(&tr!("stats-average-latency")), lang.format(&tr!("latency-ms", ms = stats.average_latency_ms())), true)
+        )).await?;
 
-    let mut buffer = std::io::Cursor::new(vec![]);
-    let encoder = PngEncoder::new(&mut buffer);
+        return Ok(());
+    }
 
-    let (width, height) = img.dimensions();
-    let bytes = img.into_raw();
+    let invocations = all.iter().map(|(_, s)| s.invocations).sum::<u64>();
+    let bytes = all.iter().map(|(_, s)| s.bytes).sum::<u64>();
+    let latency = all.iter().map(|(_, s)| s.total_latency_ms).sum::<u64>().checked_div(invocations).unwrap_or(0);
 
-    let _ = encoder.encode(
-        bytes.as_slice(),
-        width,
-        height,
-        image::ColorType::Rgba8,
-    );
+    let top = all
+        .iter()
+        .take(TOP_COMMANDS)
+        .enumerate()
+        .map(|(i, (name, s))| lang.format(&tr!(
+            "stats-top-entry",
+            rank = i + 1,
+            command = name,
+            uses = s.invocations,
+            ms = s.average_latency_ms(),
+        )))
+        .collect::<Vec<_>>();
 
-    let encoded = buffer.into_inner();
+    let users = database.total_unique_users()?;
+    let top = if top.is_empty() { lang.format(&tr!("stats-nothing-yet")) } else { top.join("\n") };
 
-    message.channel_id.send_message(ctx, |m| m.add_file((encoded.as_slice(), "invert.png"))).await?;
+    message.channel_id.send_message(ctx, |m| m.embed(|e| e
+        .title(lang.format(&tr!("stats-title")))
+        .field(lang.format(&tr!("stats-uses")), invocations, true)
+        .field(lang.format(&tr!("stats-unique-users")), users, true)
+        .field(lang.format(&tr!("stats-processed")), format_bytes(bytes), true)
+        .field(lang.format(&tr!("stats-average-latency")), lang.format(&tr!("latency-ms", ms = latency)), true)
+        .field(lang.format(&tr!("stats-top-commands")), top, false)
+    )).await?;
 
-    typing.stop();
     Ok(())
 }
@@ -0,0 +1,38 @@
+use serenity::client::Context;
+use serenity::framework::standard::{
+    Args,
+    CommandError,
+    CommandOptions,
+    Reason,
+    macros::check,
+};
+use serenity::model::channel::Message;
+
+use crate::i18n;
+use crate::tr;
+
+/// Whether the channel the message was sent in is marked as age-restricted.
+pub async fn is_nsfw_channel(ctx: &Context, message: &Message) -> bool {
+    message.channel(ctx).await.map_or(false, |channel| channel.is_nsfw())
+}
+
+/// For outputs that turn out to be explicit only after processing, call this before sending them.
+pub async fn ensure_nsfw_allowed(ctx: &Context, message: &Message) -> Result<(), CommandError> {
+    if is_nsfw_channel(ctx, message).await {
+        Ok(())
+    } else {
+        Err(tr!("nsfw-only").into())
+    }
+}
+
+/// Restricts a command to age-restricted channels, use it with `#[checks(Nsfw)]`.
+#[check]
+#[name = "Nsfw"]
+#[display_in_help]
+async fn nsfw_check(ctx: &Context, message: &Message, _: &mut Args, _: &CommandOptions) -> Result<(), Reason> {
+    if is_nsfw_channel(ctx, message).await {
+        Ok(())
+    } else {
+        Err(Reason::User(i18n::catalog(ctx, message).await.format(&tr!("nsfw-only"))))
+    }
+}
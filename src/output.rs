@@ -0,0 +1,139 @@
+use photon_core::OutputFormat;
+
+use serenity::builder::CreateMessage;
+use serenity::client::Context;
+use serenity::framework::standard::CommandError;
+use serenity::model::channel::Message;
+
+use zip::write::{FileOptions, ZipWriter};
+
+use std::io::Write;
+
+use crate::db::Database;
+use crate::i18n;
+use crate::nsfw;
+use crate::tr;
+
+/// Discord's upload limit for bots, shared by all files of a message.
+pub const UPLOAD_LIMIT: usize = 1024 * 1024 * 8;  // 8 MiB
+
+/// Discord's limit on the amount of attachments per message.
+pub const MAX_FILES: usize = 10;
+
+pub struct OutputFile {
+    pub data: Vec<u8>,
+    pub filename: String,
+}
+
+impl OutputFile {
+    pub fn new(data: Vec<u8>, filename: impl Into<String>) -> Self {
+        Self { data, filename: filename.into() }
+    }
+}
+
+/// Bundles the files into a single ZIP archive.
+pub fn zip_files(files: &[OutputFile], filename: impl Into<String>) -> Result<OutputFile, CommandError> {
+    let mut zip = ZipWriter::new(std::io::Cursor::new(vec![]));
+
+    for file in files {
+        // The files are already compressed images, deflating them again would just waste time.
+        zip.start_file(file.filename.as_str(), FileOptions::default().compression_method(zip::CompressionMethod::Stored))?;
+        zip.write_all(&file.data)?;
+    }
+
+    Ok(OutputFile::new(zip.finish()?.into_inner(), filename))
+}
+
+/// Picks the format static outputs should be encoded in: the user's preference,
+/// then the guild's default, then PNG.
+pub async fn static_format(ctx: &Context, message: &Message) -> Result<OutputFormat, CommandError> {
+    let db = Database::get(ctx).await;
+
+    if let Some(format) = db.user_prefs(message.author.id)?.format {
+        return Ok(format);
+    }
+
+    Ok(match message.guild_id {
+        Some(guild_id) => db.guild_settings(guild_id)?.default_format.unwrap_or_default(),
+        None => OutputFormat::default(),
+    })
+}
+
+fn build_message<'a, 'b>(
+    m: &'b mut CreateMessage<'a>,
+    files: &'a [OutputFile],
+    filenames: &'a [String],
+    content: Option<&str>,
+) -> &'b mut CreateMessage<'a> {
+    if let Some(content) = content {
+        m.content(content);
+    }
+
+    m.add_files(files.iter().zip(filenames).map(|(file, filename)| (file.data.as_slice(), filename.as_str())))
+}
+
+/// Sends processed files to the invoking user, honoring their spoiler and DM preferences.
+///
+/// The perceptual hash of the files' source (if any) is remembered so moderators can later
+/// `block-image` by replying to the output. Files made from an age-restricted source are only sent
+/// in age-restricted channels.
+pub async fn send(
+    ctx: &Context,
+    message: &Message,
+    source_hash: Option<u64>,
+    nsfw: bool,
+    files: &[OutputFile],
+    content: Option<&str>,
+) -> Result<Message, CommandError> {
+    if nsfw {
+        nsfw::ensure_nsfw_allowed(ctx, message).await?;
+    }
+
+    let total = files.iter().map(|file| file.data.len()).sum::<usize>();
+    if total > UPLOAD_LIMIT {
+        return Err(tr!(
+            "output-too-big",
+            size = format!("{:.2}", total as f64 / 1024.0 / 1024.0),
+            limit = UPLOAD_LIMIT / 1024 / 1024,
+        ).into());
+    }
+
+    let db = Database::get(ctx).await;
+    let prefs = db.user_prefs(message.author.id)?;
+
+    let filenames = files
+        .iter()
+        .map(|file| if prefs.spoiler { format!("SPOILER_{}", file.filename) } else { file.filename.clone() })
+        .collect::<Vec<_>>();
+    let mut sent = None;
+
+    if prefs.dm_results {
+        if let Ok(dm) = message.author.direct_message(ctx, |m| build_message(m, files, &filenames, content)).await {
+            let _ = message.reply(ctx, i18n::catalog(ctx, message).await.format(&tr!("output-sent-to-dms"))).await;
+            sent = Some(dm);
+        }
+    }
+
+    // Either DMs weren't requested or they are closed, so fall back to the channel.
+    let sent = match sent {
+        Some(sent) => sent,
+        None => message.channel_id.send_message(ctx, |m| build_message(m, files, &filenames, content)).await?,
+    };
+
+    if let (Some(guild_id), Some(hash)) = (message.guild_id, source_hash) {
+        db.record_output_source(sent.id, guild_id, hash)?;
+    }
+
+    Ok(sent)
+}
+
+pub async fn send_file(
+    ctx: &Context,
+    message: &Message,
+    source_hash: Option<u64>,
+    nsfw: bool,
+    data: &[u8],
+    filename: &str,
+) -> Result<Message, CommandError> {
+    send(ctx, message, source_hash, nsfw, &[OutputFile::new(data.to_vec(), filename)], None).await
+}
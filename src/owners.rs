@@ -0,0 +1,27 @@
+use serenity::client::Context;
+use serenity::http::Http;
+use serenity::model::id::UserId;
+use serenity::prelude::TypeMapKey;
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+pub struct OwnersKey;
+
+impl TypeMapKey for OwnersKey {
+    type Value = Arc<HashSet<UserId>>;
+}
+
+/// Fetches the owner of the application, or every member of its team if it belongs to one.
+pub async fn fetch_owners(http: &Http) -> serenity::Result<HashSet<UserId>> {
+    let info = http.get_current_application_info().await?;
+
+    Ok(match info.team {
+        Some(team) => team.members.iter().map(|member| member.user.id).collect(),
+        None => std::iter::once(info.owner.id).collect(),
+    })
+}
+
+pub async fn is_owner(ctx: &Context, user_id: UserId) -> bool {
+    ctx.data.read().await.get::<OwnersKey>().map_or(false, |owners| owners.contains(&user_id))
+}
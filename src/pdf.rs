@@ -0,0 +1,53 @@
+use image::RgbaImage;
+
+use pdfium_render::prelude::*;
+
+use photon_core::{encode_image, OutputFormat};
+
+use serenity::framework::standard::CommandError;
+
+use crate::tr;
+
+pub fn is_pdf(data: &[u8]) -> bool {
+    data.starts_with(b"%PDF-")
+}
+
+fn pdfium_error(why: PdfiumError) -> CommandError {
+    tr!("pdf-unreadable", error = format!("{:?}", why)).into()
+}
+
+/// Renders the given (1-indexed) page of a PDF document to fit within the given bounds, returning it as a PNG.
+///
+/// This requires the pdfium library to be installed on the system.
+pub fn rasterize(data: &[u8], page: usize, max_width: u32, max_height: u32) -> Result<Vec<u8>, CommandError> {
+    let pdfium = Pdfium::new(Pdfium::bind_to_system_library().map_err(pdfium_error)?);
+    let document = pdfium.load_pdf_from_bytes(data, None).map_err(pdfium_error)?;
+    let pages = document.pages();
+
+    let count = pages.len() as usize;
+    if page > count {
+        return Err(match count {
+            1 => tr!("pdf-page-missing-single", page = page),
+            _ => tr!("pdf-page-missing", page = page, count = count),
+        }.into());
+    }
+
+    let config = PdfBitmapConfig::new()
+        .set_maximum_width(max_width as u16)
+        .set_maximum_height(max_height as u16);
+
+    let rendered = pages
+        .get((page - 1) as u16)
+        .map_err(pdfium_error)?
+        .get_bitmap_with_config(&config)
+        .map_err(pdfium_error)?
+        .as_image()
+        .to_rgba8();
+
+    // Go through raw bytes so pdfium-render's `image` version doesn't have to match ours.
+    let (width, height) = rendered.dimensions();
+    let img = RgbaImage::from_raw(width, height, rendered.into_raw())
+        .ok_or_else(|| tr!("pdf-render-failed"))?;
+
+    Ok(encode_image(&img, OutputFormat::Png)?)
+}
@@ -0,0 +1,37 @@
+use image::{imageops::FilterType, DynamicImage};
+
+/// The maximum hamming distance between two hashes for the images to be considered a match.
+pub const MATCH_DISTANCE: u32 = 6;
+
+/// Computes the 64-bit difference hash (dHash) of an image.
+///
+/// The image is shrunk to 9x8 grayscale and each bit records whether a pixel is brighter than
+/// its right neighbor, which makes the hash resistant to rescaling, recompression and small edits.
+pub fn dhash(img: &DynamicImage) -> u64 {
+    let small = img.resize_exact(9, 8, FilterType::Triangle).into_luma8();
+    let mut hash = 0_u64;
+
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+
+            hash = (hash << 1) | (left > right) as u64;
+        }
+    }
+
+    hash
+}
+
+/// Decodes the image (only the first frame for animated images) and hashes it.
+pub fn dhash_bytes(data: &[u8]) -> Option<u64> {
+    image::load_from_memory(data).ok().map(|img| dhash(&img))
+}
+
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+pub fn is_match(a: u64, b: u64) -> bool {
+    distance(a, b) <= MATCH_DISTANCE
+}
@@ -0,0 +1,221 @@
+use image::imageops::FilterType;
+
+use photon_core::{filters, parse_color, Media, OutputFormat, ParamKind, ParamSpec, Params, Quality};
+
+use serenity::client::Context;
+use serenity::framework::standard::{Args, CommandError, CommandResult};
+use serenity::model::channel::Message;
+
+use crate::args::Flags;
+use crate::i18n;
+use crate::jobs::JobQueue;
+use crate::output::{self, OutputFile};
+use crate::quality::LoadMonitor;
+use crate::resolve_image::{ImageResolver, Resolved, SourcePolicy};
+use crate::tr;
+
+/// The note to send along with outputs made at the given quality, if any.
+pub async fn degraded_note(ctx: &Context, message: &Message, quality: &Quality) -> Option<String> {
+    match quality.degraded {
+        true => Some(i18n::catalog(ctx, message).await.format(&tr!("degraded-note"))),
+        false => None,
+    }
+}
+
+/// Outputs that are never sent as JPEG, since another round of compression would ruin the effect or
+/// their transparency.
+const LOSSLESS_OUTPUT: &[&str] = &["jpeg", "rotate", "circle", "round", "spritesheet", "freeze", "shadow", "glow", "stickerify", "removebg"];
+
+/// Outputs that keep every frame of the input even under load, since they refer to frames by number.
+const KEEP_ALL_FRAMES: &[&str] = &["spritesheet", "freeze"];
+
+/// Filters that only work on GIFs, which resolve their input with [`SourcePolicy::animated`].
+const ANIMATED_INPUT: &[&str] = &["reverse", "boomerang", "speed", "freeze", "spritesheet"];
+
+/// Outputs that are scaled down until they fit in [`output::UPLOAD_LIMIT`] rather than failing to send,
+/// since their size is what the user asked for, or they have many more frames than the input.
+const SHRINK_TO_UPLOAD_LIMIT: &[&str] = &["resize", "tile", "stretch", "boomerang", "spritesheet", "makegif", "compare", "grid"];
+/// How many times an output is scaled down before giving up.
+const MAX_SHRINK_ATTEMPTS: usize = 4;
+
+/// Encodes the media, scaling it down while the result is larger than `limit` bytes.
+fn encode_within(mut media: Media, format: OutputFormat, limit: usize) -> photon_core::Result<(Vec<u8>, OutputFormat)> {
+    for _ in 0..MAX_SHRINK_ATTEMPTS {
+        let (encoded, used) = media.clone().encode(format)?;
+        if encoded.len() <= limit {
+            return Ok((encoded, used));
+        }
+
+        // The encoded size grows about with the area, with some headroom since it isn't exact.
+        let scale = (limit as f64 / encoded.len() as f64).sqrt() * 0.9;
+        let (width, height) = media.dimensions();
+        media = media.resize(
+            ((width as f64 * scale) as u32).max(1),
+            ((height as f64 * scale) as u32).max(1),
+            FilterType::Lanczos3,
+        );
+    }
+
+    media.encode(format)
+}
+
+/// Decodes the resolved image, transforms it on the job queue and sends the result as `<name>.<ext>`.
+///
+/// The quality to process at is decided by the [`LoadMonitor`] right before queueing, and the reply
+/// mentions it when the result had to be degraded.
+pub async fn process<F>(ctx: &Context, message: &Message, source: Resolved, name: &str, transform: F) -> CommandResult
+where
+    F: FnOnce(Media) -> Result<Media, CommandError> + Send + 'static,
+{
+    process_many(ctx, message, vec![source], name, move |mut inputs| transform(inputs.remove(0))).await
+}
+
+/// Like [`process`], for commands that combine several images. They are decoded in the same order.
+pub async fn process_many<F>(ctx: &Context, message: &Message, mut sources: Vec<Resolved>, name: &str, transform: F) -> CommandResult
+where
+    F: FnOnce(Vec<Media>) -> Result<Media, CommandError> + Send + 'static,
+{
+    let mut quality = LoadMonitor::get(ctx).await.quality();
+    if KEEP_ALL_FRAMES.contains(&name) {
+        quality.frame_step = 1;
+    }
+    let mut format = output::static_format(ctx, message).await?;
+    if LOSSLESS_OUTPUT.contains(&name) {
+        format = format.lossless();
+    }
+    let shrink = SHRINK_TO_UPLOAD_LIMIT.contains(&name);
+    let hash = sources.iter().find_map(|source| source.hash);
+    let nsfw = sources.iter().any(|source| source.nsfw);
+    let inputs = sources.iter_mut().map(|source| std::mem::take(&mut source.data)).collect::<Vec<_>>();
+
+    let (encoded, format) = JobQueue::get(ctx).await.run(move || {
+        let inputs = inputs
+            .iter()
+            .map(|input| Media::decode(input, &quality))
+            .collect::<photon_core::Result<Vec<_>>>()?;
+        let media = transform(inputs)?;
+
+        Ok(match shrink {
+            true => encode_within(media, format, output::UPLOAD_LIMIT)?,
+            false => media.encode(format)?,
+        })
+    }).await?;
+
+    let file = OutputFile::new(encoded, format!("{}.{}", name, format.extension()));
+    let note = degraded_note(ctx, message, &quality).await;
+    output::send(ctx, message, hash, nsfw, &[file], note.as_deref()).await?;
+
+    Ok(())
+}
+
+/// Flags handled by the resolver rather than the filter.
+const RESOLVER_FLAGS: &[&str] = &["source", "page"];
+
+/// Runs one of the core filters on the image the arguments point to, taking its parameters as flags.
+///
+/// The parameters named in `positional` may also be given in that order before the image, as in
+/// `blur 10 @user`, as long as the argument is a valid value for them. The options of choice parameters
+/// work as switches too, as in `tint red --multiply`.
+pub async fn run_filter(ctx: &Context, message: &Message, args: &Args, name: &'static str, positional: &[&str]) -> CommandResult {
+    run_filter_with(ctx, message, args, name, positional, Vec::new()).await
+}
+
+/// Whether a positional argument is the value of `spec` rather than the image. Numbers out of range
+/// are, so they're rejected with the range, except for ones long enough to be the ID of a user.
+fn is_value(spec: &ParamSpec, raw: &str) -> bool {
+    spec.accepts(raw) && !(raw.len() >= 17 && raw.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// [`run_filter`] with some parameters already taken from the arguments by the command, for syntax of
+/// its own such as `resize 50%`.
+pub async fn run_filter_with(
+    ctx: &Context,
+    message: &Message,
+    args: &Args,
+    name: &'static str,
+    positional: &[&str],
+    mut leading: Vec<(&'static str, String)>,
+) -> CommandResult {
+    let filter = filters::find(name).expect("Commands only run registered filters");
+    let choices = filter.params
+        .iter()
+        .filter_map(|spec| match spec.kind {
+            ParamKind::Choice { options, .. } => Some(options.iter().map(move |option| (*option, spec.name))),
+            _ => None,
+        })
+        .flatten()
+        .collect::<Vec<_>>();
+    let switches = filter.params
+        .iter()
+        .filter(|spec| spec.kind == ParamKind::Bool)
+        .map(|spec| spec.name)
+        .chain(choices.iter().map(|(option, _)| *option))
+        .collect::<Vec<_>>();
+
+    let flags = Flags::parse(args, &switches)?;
+    let mut resolver = ImageResolver::for_message(ctx, message).await?;
+    if ANIMATED_INPUT.contains(&name) {
+        resolver.policy(SourcePolicy::animated());
+    }
+    resolver.apply_flags(&flags)?;
+
+    let mut rest = flags.positional().iter().peekable();
+
+    for param in positional {
+        let spec = filter.params.iter().find(|spec| spec.name == *param).expect("Positional parameter is not declared");
+
+        let value = match spec.kind {
+            // Lists of colors take every color in a row, as in `gradientmap red orange yellow @user`.
+            ParamKind::Colors { .. } => {
+                let mut colors = Vec::new();
+                while let Some(raw) = rest.next_if(|raw| parse_color(raw).is_some()) {
+                    colors.push(raw.as_str());
+                }
+                Some(colors.join(",")).filter(|colors| !colors.is_empty())
+            },
+            _ => rest.next_if(|raw| is_value(spec, raw)).cloned(),
+        };
+
+        match value {
+            Some(value) => leading.push((spec.name, value)),
+            None => break,
+        }
+    }
+
+    let mut pairs = leading.iter().map(|(name, value)| (*name, Some(value.as_str()))).collect::<Vec<_>>();
+    pairs.extend(flags.values().filter(|(flag, _)| !RESOLVER_FLAGS.contains(flag)).map(|(flag, value)| (flag, Some(value))));
+    pairs.extend(flags.switches().iter().map(|flag| match choices.iter().find(|(option, _)| *option == flag.as_str()) {
+        Some((option, param)) => (*param, Some(*option)),
+        None => (flag.as_str(), None),
+    }));
+
+    let params = Params::parse(filter.params, pairs)?;
+    let query = rest.next().cloned();
+
+    let typing = message.channel_id.start_typing(&ctx.http)?;
+    let result = resolver.resolve(ctx, message, query).await?;
+
+    process(ctx, message, result, name, move |media| Ok(filter.apply(media, &params)?)).await?;
+
+    typing.stop();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn takes_positional_values_out_of_range() {
+        let grayscale = filters::find("grayscale").unwrap();
+        let levels = &grayscale.params[0];
+
+        // `grayscale 1000 @user` is too many levels, not an image called 1000.
+        assert!(is_value(levels, "8"));
+        assert!(is_value(levels, "1000"));
+        assert!(matches!(Params::parse(grayscale.params, [("levels", Some("1000"))]), Err(photon_core::Error::OutOfRange { .. })));
+
+        assert!(!is_value(levels, "@user"));
+        assert!(!is_value(levels, "80351110224678912"));
+    }
+}
@@ -0,0 +1,72 @@
+use serenity::client::Context;
+use serenity::model::channel::Message;
+use serenity::model::permissions::Permissions;
+use serenity::prelude::Mentionable;
+
+use crate::commands::{self, imaging::IMAGING_GROUP, utility::UTILITY_GROUP};
+use crate::i18n::{self, Text};
+use crate::templates::Templates;
+use crate::tr;
+
+/// Every command replies, and most of them send an embed.
+const REQUIRED: &[(Permissions, &str)] = &[
+    (Permissions::SEND_MESSAGES, "permission-send-messages"),
+    (Permissions::EMBED_LINKS, "permission-embed-links"),
+];
+
+/// Only needed by the commands that upload a file, see [`uploads_files`].
+const UPLOAD: (Permissions, &str) = (Permissions::ATTACH_FILES, "permission-attach-files");
+
+/// Whether the command sends its result as a file. `help` does too, for the previews of imaging commands.
+async fn uploads_files(ctx: &Context, cmd_name: &str) -> bool {
+    cmd_name == "help"
+        || commands::find_command(&[&IMAGING_GROUP, &UTILITY_GROUP], cmd_name).is_some()
+        || Templates::get(ctx).await.iter().any(|template| template.names().any(|name| name.eq_ignore_ascii_case(cmd_name)))
+}
+
+/// Returns the message keys of the names of the permissions the bot is missing in the channel the message was sent
+/// in, for running the given command.
+///
+/// Anything that can't be looked up is assumed to be fine, the request will fail loudly later instead.
+pub async fn missing_permissions(ctx: &Context, message: &Message, cmd_name: &str) -> Vec<&'static str> {
+    let channel = match message.channel_id.to_channel(ctx).await.ok().and_then(|c| c.guild()) {
+        Some(channel) => channel,
+        None => return vec![],
+    };
+
+    let permissions = match channel.permissions_for_user(ctx, ctx.cache.current_user_id().await).await {
+        Ok(permissions) => permissions,
+        Err(_) => return vec![],
+    };
+
+    let upload = match uploads_files(ctx, cmd_name).await {
+        true => Some(&UPLOAD),
+        false => None,
+    };
+
+    REQUIRED
+        .iter()
+        .chain(upload)
+        .filter(|(permission, _)| !permissions.contains(*permission))
+        .map(|(_, name)| *name)
+        .collect()
+}
+
+/// Tells the author which permissions are missing, in a DM if the bot can't even speak in the channel.
+pub async fn report_missing(ctx: &Context, message: &Message, missing: &[&'static str]) {
+    let lang = i18n::catalog(ctx, message).await;
+    let names = missing
+        .iter()
+        .map(|key| format!("**{}**", lang.format(&Text::new(key))))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let content = lang.format(&tr!("missing-permissions", channel = message.channel_id.mention(), permissions = names));
+
+    if missing.contains(&"permission-send-messages") {
+        if let Ok(channel) = message.author.create_dm_channel(ctx).await {
+            let _ = channel.say(ctx, content).await;
+        }
+    } else {
+        let _ = message.reply(ctx, content).await;
+    }
+}
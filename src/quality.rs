@@ -0,0 +1,115 @@
+use photon_core::Quality;
+
+use serenity::client::Context;
+use serenity::prelude::TypeMapKey;
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::QualityConfig;
+use crate::jobs::JobQueue;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Load must fall below this fraction of the thresholds before full quality is restored,
+/// so quality doesn't flap back and forth right at the threshold.
+const RESTORE_RATIO: f32 = 0.75;
+
+/// Watches queue depth and CPU usage, degrading processing quality while the bot is under load.
+pub struct LoadMonitor {
+    config: QualityConfig,
+    jobs: Arc<JobQueue>,
+    /// CPU usage in hundredths of a percent.
+    cpu_usage: AtomicU32,
+    degraded: AtomicBool,
+}
+
+pub struct LoadMonitorKey;
+
+impl TypeMapKey for LoadMonitorKey {
+    type Value = Arc<LoadMonitor>;
+}
+
+/// Reads the aggregate `(idle, total)` CPU jiffies from `/proc/stat`.
+fn read_cpu_times() -> Option<(u64, u64)> {
+    let stat = std::fs::read_to_string("/proc/stat").ok()?;
+    let times = stat
+        .lines()
+        .next()?
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|t| t.parse::<u64>().ok())
+        .collect::<Vec<_>>();
+
+    // idle + iowait
+    let idle = times.get(3)? + times.get(4).unwrap_or(&0);
+    Some((idle, times.iter().sum()))
+}
+
+impl LoadMonitor {
+    pub fn new(config: QualityConfig, jobs: Arc<JobQueue>) -> Self {
+        Self {
+            config,
+            jobs,
+            cpu_usage: AtomicU32::new(0),
+            degraded: AtomicBool::new(false),
+        }
+    }
+
+    pub async fn get(ctx: &Context) -> Arc<LoadMonitor> {
+        ctx.data.read().await.get::<LoadMonitorKey>().expect("LoadMonitor was not inserted into client data").clone()
+    }
+
+    /// Samples CPU usage forever. CPU usage is simply not considered on platforms without `/proc/stat`.
+    pub async fn run(self: Arc<Self>) {
+        let mut last = read_cpu_times();
+
+        loop {
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+
+            let now = read_cpu_times();
+            if let (Some((idle_a, total_a)), Some((idle_b, total_b))) = (last, now) {
+                let total = total_b.saturating_sub(total_a);
+
+                if total > 0 {
+                    let idle = idle_b.saturating_sub(idle_a);
+                    let usage = (1.0 - idle as f64 / total as f64).clamp(0.0, 1.0);
+                    self.cpu_usage.store((usage * 10000.0) as u32, Ordering::Relaxed);
+                }
+            }
+
+            last = now;
+        }
+    }
+
+    pub fn cpu_usage(&self) -> f32 {
+        self.cpu_usage.load(Ordering::Relaxed) as f32 / 100.0
+    }
+
+    /// Determines the current quality, updating whether the bot is degraded.
+    pub fn quality(&self) -> Quality {
+        let depth = self.jobs.depth() as f32;
+        let cpu = self.cpu_usage();
+
+        let queue_threshold = self.config.queue_threshold as f32;
+        let cpu_threshold = self.config.cpu_threshold;
+
+        let degraded = if self.degraded.load(Ordering::Relaxed) {
+            depth >= queue_threshold * RESTORE_RATIO || cpu >= cpu_threshold * RESTORE_RATIO
+        } else {
+            depth >= queue_threshold || cpu >= cpu_threshold
+        };
+        self.degraded.store(degraded, Ordering::Relaxed);
+
+        if degraded {
+            Quality {
+                degraded: true,
+                max_dimension: Some(self.config.degraded_max_dimension),
+                frame_step: self.config.degraded_frame_step.max(1),
+            }
+        } else {
+            Quality::full()
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use serenity::client::Context;
+use serenity::model::id::UserId;
+use serenity::prelude::TypeMapKey;
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::config::RateLimitConfig;
+
+/// Stale users are pruned from memory every this many hits.
+const PRUNE_INTERVAL: usize = 1000;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Allowed,
+    /// The user sent commands too quickly, and has now tripped the limiter this many times recently.
+    Limited { strikes: usize },
+}
+
+#[derive(Default)]
+struct UserState {
+    hits: VecDeque<Instant>,
+    strikes: VecDeque<Instant>,
+}
+
+/// A global per-user sliding window rate limiter over all commands.
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    users: Mutex<HashMap<UserId, UserState>>,
+    hits_since_prune: Mutex<usize>,
+}
+
+pub struct RateLimiterKey;
+
+impl TypeMapKey for RateLimiterKey {
+    type Value = Arc<RateLimiter>;
+}
+
+fn expire(queue: &mut VecDeque<Instant>, now: Instant, window: Duration) {
+    while queue.front().map_or(false, |t| now.duration_since(*t) >= window) {
+        queue.pop_front();
+    }
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            users: Mutex::new(HashMap::new()),
+            hits_since_prune: Mutex::new(0),
+        }
+    }
+
+    pub async fn get(ctx: &Context) -> Arc<RateLimiter> {
+        ctx.data.read().await.get::<RateLimiterKey>().expect("RateLimiter was not inserted into client data").clone()
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    pub fn hit(&self, user_id: UserId) -> Outcome {
+        self.hit_at(user_id, Instant::now())
+    }
+
+    fn hit_at(&self, user_id: UserId, now: Instant) -> Outcome {
+        let window = Duration::from_secs(self.config.per);
+        let strike_window = Duration::from_secs(self.config.strike_window);
+
+        let mut users = self.users.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        self.maybe_prune(&mut users, now, window.max(strike_window));
+
+        let state = users.entry(user_id).or_default();
+        expire(&mut state.hits, now, window);
+        expire(&mut state.strikes, now, strike_window);
+
+        if state.hits.len() >= self.config.commands {
+            state.strikes.push_back(now);
+            return Outcome::Limited { strikes: state.strikes.len() };
+        }
+
+        state.hits.push_back(now);
+        Outcome::Allowed
+    }
+
+    /// Forgets a user's history, e.g. once they have been blacklisted for it.
+    pub fn reset(&self, user_id: UserId) {
+        self.users.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&user_id);
+    }
+
+    fn maybe_prune(&self, users: &mut HashMap<UserId, UserState>, now: Instant, window: Duration) {
+        let mut hits = self.hits_since_prune.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *hits += 1;
+
+        if *hits >= PRUNE_INTERVAL {
+            *hits = 0;
+            users.retain(|_, state| {
+                state.hits.back().map_or(false, |t| now.duration_since(*t) < window)
+                    || state.strikes.back().map_or(false, |t| now.duration_since(*t) < window)
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: UserId = UserId(1);
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(RateLimitConfig { commands: 2, per: 10, strikes: 3, strike_window: 60, blacklist_minutes: 30 })
+    }
+
+    #[test]
+    fn limits_within_the_window() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        assert_eq!(limiter.hit_at(USER, now), Outcome::Allowed);
+        assert_eq!(limiter.hit_at(USER, now + Duration::from_secs(1)), Outcome::Allowed);
+        assert_eq!(limiter.hit_at(USER, now + Duration::from_secs(2)), Outcome::Limited { strikes: 1 });
+        assert_eq!(limiter.hit_at(UserId(2), now + Duration::from_secs(2)), Outcome::Allowed);
+
+        // The window slides, so only the first hit has to expire before the next command is allowed.
+        assert_eq!(limiter.hit_at(USER, now + Duration::from_secs(10)), Outcome::Allowed);
+        assert_eq!(limiter.hit_at(USER, now + Duration::from_secs(10)), Outcome::Limited { strikes: 2 });
+    }
+
+    #[test]
+    fn counts_and_decays_strikes() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        limiter.hit_at(USER, now);
+        limiter.hit_at(USER, now);
+        assert_eq!(limiter.hit_at(USER, now), Outcome::Limited { strikes: 1 });
+        assert_eq!(limiter.hit_at(USER, now + Duration::from_secs(30)), Outcome::Allowed);
+        limiter.hit_at(USER, now + Duration::from_secs(30));
+        assert_eq!(limiter.hit_at(USER, now + Duration::from_secs(30)), Outcome::Limited { strikes: 2 });
+
+        // The first strike is forgotten once the strike window has passed it.
+        limiter.hit_at(USER, now + Duration::from_secs(60));
+        limiter.hit_at(USER, now + Duration::from_secs(60));
+        assert_eq!(limiter.hit_at(USER, now + Duration::from_secs(60)), Outcome::Limited { strikes: 2 });
+
+        limiter.reset(USER);
+        assert_eq!(limiter.hit_at(USER, now + Duration::from_secs(60)), Outcome::Allowed);
+    }
+}
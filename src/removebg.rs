@@ -0,0 +1,79 @@
+use image::imageops::{self, FilterType};
+use image::{GrayImage, Luma, RgbaImage};
+
+use serenity::client::Context;
+use serenity::framework::standard::CommandError;
+use serenity::prelude::TypeMapKey;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use tract_onnx::prelude::*;
+
+use crate::tr;
+
+/// The size u2net (and the models rembg ships, which are trained like it) take their input at.
+const INPUT_SIZE: usize = 320;
+/// The per-channel mean and deviation of the ImageNet photos the models are normalized to.
+const MEAN: [f32; 3] = [0.485, 0.456, 0.406];
+const STD: [f32; 3] = [0.229, 0.224, 0.225];
+
+/// A salient object segmentation model in the ONNX format, such as u2net, telling the subject of a
+/// photo apart from its background.
+pub struct SegmentationModel {
+    plan: TypedRunnableModel<TypedModel>,
+}
+
+pub struct SegmentationModelKey;
+
+impl TypeMapKey for SegmentationModelKey {
+    type Value = Arc<SegmentationModel>;
+}
+
+impl SegmentationModel {
+    /// Loads and optimizes the model, which takes a few seconds, so it is done once on startup.
+    pub fn load(path: &Path) -> TractResult<Self> {
+        let shape = tvec!(1, 3, INPUT_SIZE, INPUT_SIZE);
+        let plan = tract_onnx::onnx()
+            .model_for_path(path)?
+            .with_input_fact(0, InferenceFact::dt_shape(f32::datum_type(), shape))?
+            .into_optimized()?
+            .into_runnable()?;
+
+        Ok(Self { plan })
+    }
+
+    /// The loaded model, `None` when none is configured.
+    pub async fn get(ctx: &Context) -> Option<Arc<SegmentationModel>> {
+        ctx.data.read().await.get::<SegmentationModelKey>().cloned()
+    }
+
+    /// Predicts how much of every pixel belongs to the subject, as a mask at the model's input size.
+    pub fn predict(&self, img: &RgbaImage) -> Result<GrayImage, CommandError> {
+        let input = imageops::resize(img, INPUT_SIZE as u32, INPUT_SIZE as u32, FilterType::Triangle);
+        let tensor: Tensor = tract_ndarray::Array4::from_shape_fn((1, 3, INPUT_SIZE, INPUT_SIZE), |(_, c, y, x)| {
+            let value = input.get_pixel(x as u32, y as u32)[c] as f32 / 255.0;
+            (value - MEAN[c]) / STD[c]
+        }).into();
+
+        let outputs = self.plan.run(tvec!(tensor)).map_err(model_error)?;
+        let predicted = outputs[0].to_array_view::<f32>().map_err(model_error)?;
+
+        // The first output is the finest of the model's predictions. It isn't always spread over the
+        // whole range, so it is stretched to be.
+        let (min, max) = predicted.iter().fold((f32::MAX, f32::MIN), |(min, max), &v| (min.min(v), max.max(v)));
+        let range = (max - min).max(f32::EPSILON);
+        let values = predicted.iter().map(|&v| ((v - min) / range * 255.0).round() as u8).collect::<Vec<_>>();
+
+        match values.len() == INPUT_SIZE * INPUT_SIZE {
+            true => Ok(GrayImage::from_fn(INPUT_SIZE as u32, INPUT_SIZE as u32, |x, y| {
+                Luma([values[y as usize * INPUT_SIZE + x as usize]])
+            })),
+            false => Err(tr!("removebg-model-failed", error = "unexpected output shape").into()),
+        }
+    }
+}
+
+fn model_error(why: TractError) -> CommandError {
+    tr!("removebg-model-failed", error = why.to_string()).into()
+}
@@ -3,11 +3,19 @@
 use regex::Regex;
 
 use serenity::client::Context;
-use serenity::model::{channel::{Attachment, Message}, guild::{Member, Emoji}, id::{GuildId, ChannelId}};
-use serenity::framework::standard::CommandError;
+use serenity::model::{channel::{Attachment, Message}, guild::{Member, Emoji}, id::{GuildId, ChannelId}, user::User};
+use serenity::framework::standard::{Args, CommandError};
 
 use serenity::utils::ArgumentConvert;
 
+use crate::args::Flags;
+use crate::config::{Config, ResolverConfig};
+use crate::db::Database;
+use crate::i18n::Text;
+use crate::tr;
+
+use std::str::FromStr;
+
 pub const DEFAULT_MAX_WIDTH: usize = 2048;
 pub const DEFAULT_MAX_HEIGHT: usize = DEFAULT_MAX_WIDTH;
 pub const DEFAULT_MAX_SIZE: usize = 1024 * 1024 * 6;  // 6 MiB
@@ -17,6 +25,9 @@ lazy_static::lazy_static! {
     pub static ref TENOR_REGEX: Regex = Regex::new(r"https?://(www\.)?tenor\.com/view/\S+/").unwrap();
     pub static ref GIPHY_REGEX: Regex = Regex::new(r"https?://(www\.)?giphy\.com/gifs/[A-Za-z0-9]+/?").unwrap();
     pub static ref EMOJI_REGEX: Regex = Regex::new(r"<(a)?:([a-zA-Z0-9_]{2,32}):([0-9]{17,25})>").unwrap();
+    pub static ref MESSAGE_LINK_REGEX: Regex = Regex::new(
+        r"^<?https?://((ptb|canary)\.)?discord(app)?\.com/channels/([0-9]{17,25})/([0-9]{17,25})/([0-9]{17,25})>?$"
+    ).unwrap();
 }
 
 pub const ALLOWED_CONTENT_TYPES: [&str; 4] = [
@@ -37,6 +48,82 @@ pub enum Query {
     String(String),
     Emoji(Emoji),
     Member(Member),
+    Message(Message),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Query,
+    Attachment,
+    Reply,
+    Avatar,
+}
+
+impl Source {
+    /// The error for when nothing could be resolved from only this source.
+    pub fn not_found(&self) -> Text {
+        match self {
+            Self::Query => tr!("resolve-not-found-query"),
+            Self::Attachment => tr!("resolve-not-found-attachment"),
+            Self::Reply => tr!("resolve-not-found-reply"),
+            Self::Avatar => tr!("resolve-not-found-avatar"),
+        }
+    }
+}
+
+impl FromStr for Source {
+    type Err = CommandError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "query" | "url" => Ok(Self::Query),
+            "attachment" | "file" => Ok(Self::Attachment),
+            "reply" => Ok(Self::Reply),
+            "avatar" => Ok(Self::Avatar),
+            _ => Err(tr!("unknown-source", source = s).into()),
+        }
+    }
+}
+
+/// The order in which image sources are tried. The first source that is present on the message wins.
+#[derive(Clone, Debug)]
+pub struct SourcePolicy {
+    order: Vec<Source>,
+}
+
+impl SourcePolicy {
+    pub fn new(order: impl Into<Vec<Source>>) -> Self {
+        Self { order: order.into() }
+    }
+
+    /// A policy that only ever considers the given source.
+    pub fn only(source: Source) -> Self {
+        Self::new([source])
+    }
+
+    /// The policy of commands that only work on GIFs. Avatars are still more often than not, so the
+    /// author's isn't fallen back to, which would only fail with a less helpful error.
+    pub fn animated() -> Self {
+        Self::new([Source::Query, Source::Attachment, Source::Reply])
+    }
+
+    pub fn order(&self) -> &[Source] {
+        &self.order
+    }
+}
+
+impl Default for SourcePolicy {
+    fn default() -> Self {
+        Self::new([Source::Query, Source::Attachment, Source::Reply, Source::Avatar])
+    }
+}
+
+/// The raw bytes of a resolved image, along with the perceptual hash of its source if it was computed.
+pub struct Resolved {
+    pub data: Vec<u8>,
+    pub hash: Option<u64>,
+    /// Whether it was linked from a message in another, age-restricted channel, so outputs made from it are too.
+    pub nsfw: bool,
 }
 
 pub enum RawResult<'a> {
@@ -49,7 +136,10 @@ pub struct ImageResolver {
     pub allow_gifs: bool,
     pub allow_user_avatars: bool,
     pub fallback_to_user_avatar: bool,
+    pub prefer_static_avatars: bool,
     pub run_conversions: bool,
+    pub policy: SourcePolicy,
+    pub page: usize,
 
     pub max_width: usize,
     pub max_height: usize,
@@ -62,13 +152,51 @@ impl ImageResolver {
             allow_gifs: true,
             allow_user_avatars: true,
             fallback_to_user_avatar: true,
+            prefer_static_avatars: false,
             run_conversions: true,
+            policy: SourcePolicy::default(),
+            page: 1,
             max_width: DEFAULT_MAX_WIDTH,
             max_height: DEFAULT_MAX_HEIGHT,
             max_size: DEFAULT_MAX_SIZE,
         }
     }
 
+    pub fn from_config(config: &ResolverConfig) -> Self {
+        let mut resolver = Self::new();
+        resolver
+            .max_width(config.max_width)
+            .max_height(config.max_height)
+            .max_size(config.max_size);
+
+        resolver
+    }
+
+    /// Creates a resolver with the configured limits, narrowed down by the guild's settings if there are any.
+    pub async fn for_guild(ctx: &Context, guild_id: Option<GuildId>) -> Result<Self, CommandError> {
+        let config = Config::get(ctx).await;
+        let mut resolver = Self::from_config(&config.resolver);
+
+        if let Some(guild_id) = guild_id {
+            if let Some(size) = Database::get(ctx).await.guild_settings(guild_id)?.max_image_size {
+                resolver.max_size(size.min(config.resolver.max_size));
+            }
+        }
+
+        Ok(resolver)
+    }
+
+    /// Like [`Self::for_guild`], additionally applying the invoking user's preferences.
+    pub async fn for_message(ctx: &Context, message: &Message) -> Result<Self, CommandError> {
+        let mut resolver = Self::for_guild(ctx, message.guild_id).await?;
+
+        if Database::get(ctx).await.user_prefs(message.author.id)?.static_avatars {
+            resolver.prefer_static_avatars();
+        }
+
+        Ok(resolver)
+    }
+
     pub fn disallow_gifs(&mut self) -> &mut Self {
         self.allow_gifs = false;
         self
@@ -84,11 +212,59 @@ impl ImageResolver {
         self
     }
 
+    pub fn prefer_static_avatars(&mut self) -> &mut Self {
+        self.prefer_static_avatars = true;
+        self
+    }
+
     pub fn disable_conversions(&mut self) -> &mut Self {
         self.run_conversions = false;
         self
     }
 
+    pub fn policy(&mut self, policy: SourcePolicy) -> &mut Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Restricts resolution to a single source, usually from a user-provided `--source` flag.
+    pub fn source(&mut self, source: Source) -> &mut Self {
+        self.policy = SourcePolicy::only(source);
+        self
+    }
+
+    /// Applies the `--source <name>` and `--page <n>` flags.
+    pub fn apply_flags(&mut self, flags: &Flags) -> Result<&mut Self, CommandError> {
+        // Parsed directly to keep the more helpful error listing the sources.
+        if let Some(source) = flags.raw("source") {
+            self.source(source.parse()?);
+        }
+
+        if let Some(page) = flags.get::<usize>("page")? {
+            if page == 0 {
+                return Err(tr!("resolve-invalid-page").into());
+            }
+
+            self.page(page);
+        }
+
+        Ok(self)
+    }
+
+    /// Parses the remaining arguments as [`Flags`] and applies them, returning the query.
+    pub fn parse_args(&mut self, args: &Args) -> Result<Option<String>, CommandError> {
+        let flags = Flags::parse(args, &[])?;
+        self.apply_flags(&flags)?;
+
+        Ok(flags.query())
+    }
+
+    /// Sets the (1-indexed) page to rasterize when the resolved file is a PDF document.
+    pub fn page(&mut self, page: usize) -> &mut Self {
+        self.page = page;
+        self
+    }
+
     pub fn max_width(&mut self, width: usize) -> &mut Self {
         self.max_width = width;
         self
@@ -113,10 +289,21 @@ impl ImageResolver {
             return Query::Emoji(o);
         }
 
+        // Only links within the same server, so images can't be pulled out of servers the user isn't in.
+        let same_guild = MESSAGE_LINK_REGEX
+            .captures(&query)
+            .map_or(false, |c| guild_id.map(|id| id.to_string()).as_deref() == c.get(4).map(|m| m.as_str()));
+
+        if same_guild {
+            if let Ok(o) = Message::convert(ctx, guild_id, channel_id, query.trim_matches(|c| c == '<' || c == '>')).await {
+                return Query::Message(o);
+            }
+        }
+
         Query::String(query)
     }
 
-    fn _humanize_size(mut size: f64) -> String {
+    pub(crate) fn humanize_size(mut size: f64) -> String {
         let units = ["B", "KB", "MB", "GB", "TB", "PB"];
 
         for unit in units {            
@@ -160,7 +347,7 @@ impl ImageResolver {
                 .unwrap()
                 .replace(r"\u002F", "/"))
         } else {
-            Err(CommandError::from(format!("URL returned status code {}", resp.status())))
+            Err(tr!("url-bad-status", status = resp.status()).into())
         }
     }
 
@@ -178,7 +365,7 @@ impl ImageResolver {
                 .nth(0)
                 .unwrap())
         } else {
-            Err(CommandError::from(format!("URL returned status code {}", resp.status())))
+            Err(tr!("url-bad-status", status = resp.status()).into())
         }
     }
 
@@ -189,27 +376,32 @@ impl ImageResolver {
 
                 if !allowed_suffixes.into_iter().any(|suff| filename.ends_with(suff)) {
                     let suffix = filename.split(".").last().unwrap_or("unknown");
-                    Err(CommandError::from(format!("File extension `{}` is not allowed", suffix)))
+                    Err(tr!("resolve-extension-not-allowed", extension = suffix).into())
                 }
                 
                 else if attachment.size > self.max_size as u64 {
-                    Err(CommandError::from(format!(
-                        "Attachment is too big. (`{}` > `{}`)",
-                        Self::_humanize_size(attachment.size as f64),
-                        Self::_humanize_size(self.max_size as f64),
-                    )))
+                    Err(tr!(
+                        "resolve-attachment-too-big",
+                        size = Self::humanize_size(attachment.size as f64),
+                        max = Self::humanize_size(self.max_size as f64),
+                    ).into())
                 }
                 
+                // Documents have no dimensions, they are rendered to fit the limits instead.
+                else if filename.ends_with(".pdf") {
+                    Ok(attachment.download().await?)
+                }
+
                 else if attachment.width.is_none() || attachment.height.is_none() {
-                    Err(CommandError::from("Invalid attachment. (Could not get a width or height from it.)"))
+                    Err(tr!("resolve-no-dimensions").into())
                 }
                 
                 else if attachment.width.unwrap() > self.max_width as u64 {
-                    Err(CommandError::from(format!("Attachment width of {} surpasses the maximum of {}.", attachment.width.unwrap(), self.max_width)))
+                    Err(tr!("resolve-too-wide", width = attachment.width.unwrap(), max = self.max_width).into())
                 }
                 
                 else if attachment.height.unwrap() > self.max_height as u64 {
-                    Err(CommandError::from(format!("Attachment height of {} surpasses the maximum of {}.", attachment.height.unwrap(), self.max_height)))
+                    Err(tr!("resolve-too-tall", height = attachment.height.unwrap(), max = self.max_height).into())
                 }
                 
                 else {
@@ -218,11 +410,11 @@ impl ImageResolver {
             },
             RawResult::Bytes(data) => {
                 if data.len() > self.max_size {
-                    Err(CommandError::from(format!(
-                        "File is too big. (`{}` > `{}`)",
-                        Self::_humanize_size(data.len() as f64),
-                        Self::_humanize_size(self.max_size as f64),
-                    )))
+                    Err(tr!(
+                        "resolve-file-too-big",
+                        size = Self::humanize_size(data.len() as f64),
+                        max = Self::humanize_size(self.max_size as f64),
+                    ).into())
                 }
                 
                 else {
@@ -243,44 +435,102 @@ impl ImageResolver {
                 let resp = reqwest::get(url).await?;
 
                 if resp.status().is_success() {
-                    let content_type = resp.headers().get("Content-Type").ok_or_else(|| CommandError::from("Invalid Content-Type."))?.to_str().unwrap();
+                    let content_type = resp.headers().get("Content-Type").ok_or_else(|| tr!("resolve-invalid-content-type"))?.to_str().unwrap();
 
                     if !allowed_content_types.contains(&content_type) {
-                        return Err(CommandError::from(format!("Content-Type `{}` is not allowed", content_type)));
+                        return Err(tr!("resolve-content-type-not-allowed", content_type = content_type).into());
                     }
 
                     if let Some(content_length) = resp.headers().get("Content-Length") {
                         let size = u64::from_str_radix(content_length.to_str().unwrap(), 10_u32).unwrap_or(0_u64);
 
                         if size > self.max_size as u64 {
-                            return Err(CommandError::from(
-                                format!("File is too big. (`{}` > `{}`)",
-                                    Self::_humanize_size(size as f64),
-                                    Self::_humanize_size(self.max_size as f64),
-                                )
-                            ))
+                            return Err(tr!(
+                                "resolve-file-too-big",
+                                size = Self::humanize_size(size as f64),
+                                max = Self::humanize_size(self.max_size as f64),
+                            ).into())
                         }
 
                         return Ok(resp.bytes().await?.to_vec());
                     }
                 }
 
-                Err(CommandError::from(format!("URL returned status code {}", resp.status())))
+                Err(tr!("url-bad-status", status = resp.status()).into())
             }
         }
     }
 
-    pub async fn resolve(&self, ctx: &Context, message: &Message, query: Option<String>) -> Result<Vec<u8>, CommandError> {
-        let resolved_query = if query.is_some() && self.run_conversions {
-            Some(
-                Self::_run_conversions(ctx, message.guild_id, Some(message.channel_id), query.unwrap()).await
+    fn _avatar_url(&self, user: &User, avatar: Option<&String>) -> String {
+        if let Some(avatar) = avatar {
+            format!(
+                "https://cdn.discordapp.com/avatars/{}/{}.{}?size=512",
+                user.id,
+                avatar,
+                if self.allow_gifs && !self.prefer_static_avatars && avatar.starts_with("a_") { "gif" } else { "png" }
             )
-        } else if query.is_some() {
-            Some(Query::String(query.unwrap()))
-        } else {
-            None
+        }
+        else {
+            format!("https://cdn.discordapp.com/embed/avatars/{}.png", user.discriminator % 5)
+        }
+    }
+
+    /// The image another message carries: its first attachment, the image of its first embed or the first
+    /// link in its content.
+    fn _from_message(message: &Message) -> Option<RawResult<'_>> {
+        if let Some(a) = message.attachments.first() {
+            Some(RawResult::Attachment(a))
+        }
+        else if let Some(url) = message.embeds.first().and_then(|embed| match embed.kind.as_str() {
+            "image" => embed.thumbnail.as_ref().map(|image| image.url.clone()),
+            "rich" => embed.image
+                .as_ref()
+                .map(|image| image.url.to_string())
+                .or_else(|| embed.thumbnail.as_ref().map(|image| image.url.clone())),
+            _ => None,
+        }) {
+            Some(RawResult::Url(url))
+        }
+        else {
+            Some(RawResult::Url(URL_REGEX.find(&message.content)?.as_str().to_string()))
+        }
+    }
+
+    /// Attempts to resolve an image from a single source.
+    ///
+    /// Returns `None` if the source is not present on the message at all, so that the next source
+    /// in the policy can be tried. A source that is present but fails to resolve is an error.
+    async fn _resolve_source(
+        &self,
+        source: Source,
+        message: &Message,
+        query: &Option<Query>,
+        allowed_content_types: &Vec<&str>,
+        allowed_suffixes: &Vec<&str>,
+    ) -> Option<Result<Vec<u8>, CommandError>> {
+        let raw = match source {
+            Source::Query => match query.as_ref()? {
+                Query::String(query) if query.chars().count() < 8 => RawResult::Url(Self::_url_from_emoji(query.clone())),
+                Query::String(query) => RawResult::Url(query.clone()),
+                Query::Emoji(emoji) => RawResult::Url(emoji.url()),
+                Query::Member(member) if self.allow_user_avatars => {
+                    RawResult::Url(self._avatar_url(&member.user, member.avatar.as_ref().or(member.user.avatar.as_ref())))
+                },
+                Query::Member(_) => return None,
+                Query::Message(linked) => Self::_from_message(linked)?,
+            },
+            Source::Attachment => RawResult::Attachment(message.attachments.first()?),
+            Source::Reply => Self::_from_message(message.referenced_message.as_ref()?)?,
+            Source::Avatar if self.allow_user_avatars && self.fallback_to_user_avatar => {
+                RawResult::Url(self._avatar_url(&message.author, message.author.avatar.as_ref()))
+            },
+            Source::Avatar => return None,
         };
 
+        Some(self._sanitize(raw, allowed_content_types, allowed_suffixes).await)
+    }
+
+    fn _allowed_types(&self) -> (Vec<&'static str>, Vec<&'static str>) {
         let mut allowed_content_types = ALLOWED_CONTENT_TYPES.to_vec();
         let mut allowed_suffixes = ALLOWED_SUFFIXES.to_vec();
 
@@ -289,91 +539,179 @@ impl ImageResolver {
             allowed_suffixes.push(".gif");
         }
 
-        let fallback = async || {
-            if let Some(a) = message.attachments.first() {
-                return self._sanitize(RawResult::Attachment(a), &allowed_content_types, &allowed_suffixes).await
-            }
+        if cfg!(feature = "pdf") {
+            allowed_content_types.push("application/pdf");
+            allowed_suffixes.push(".pdf");
+        }
 
-            if let Some(reference) = &message.referenced_message {
-                if let Some(a) = reference.attachments.first() {
-                    return self._sanitize(RawResult::Attachment(a), &allowed_content_types, &allowed_suffixes).await
-                }
+        (allowed_content_types, allowed_suffixes)
+    }
 
-                if let Some(embed) = reference.embeds.first() {
-                    match embed.kind.as_str() {
-                        "image" => if let Some(image) = &embed.thumbnail {
-                            return self._sanitize(RawResult::Url(image.url.clone()), &allowed_content_types, &allowed_suffixes).await
-                        },
-                        "rich" => {
-                            if let Some(image) = &embed.image {
-                                return self._sanitize(RawResult::Url(image.url.to_string()), &allowed_content_types, &allowed_suffixes).await
-                            }
-
-                            if let Some(image) = &embed.thumbnail {
-                                return self._sanitize(RawResult::Url(image.url.clone()), &allowed_content_types, &allowed_suffixes).await
-                            }
-                        },
-                        _ => (),
-                    }
-                }
+    /// Downloads an image from a URL directly, with the same content type and size checks as resolving.
+    pub async fn fetch_url(&self, url: String) -> Result<Vec<u8>, CommandError> {
+        let (allowed_content_types, allowed_suffixes) = self._allowed_types();
+        self._sanitize(RawResult::Url(url), &allowed_content_types, &allowed_suffixes).await
+    }
 
-                if let Some(c) = URL_REGEX.captures_iter(&reference.content).next() {
-                    if let Some(m) = c.get(1) {
-                        return self._sanitize(RawResult::Url(m.as_str().to_string()), &allowed_content_types, &allowed_suffixes).await
-                    }
-                }
+    /// Rejects the image if it is a near match of an image blocked in the guild, returning its hash otherwise.
+    async fn _check_blocklist(ctx: &Context, guild_id: GuildId, data: &[u8]) -> Result<Option<u64>, CommandError> {
+        let blocked = Database::get(ctx).await.blocked_hashes(guild_id)?;
+        let owned = data.to_vec();
+        let hash = tokio::task::spawn_blocking(move || crate::phash::dhash_bytes(&owned)).await?;
+
+        if let Some(hash) = hash {
+            if blocked.into_iter().any(|b| crate::phash::is_match(b, hash)) {
+                return Err(tr!("resolve-blocked").into());
             }
+        }
 
-            if self.allow_user_avatars && self.fallback_to_user_avatar {
-                if let Some(avatar) = &message.author.avatar {
-                    self._sanitize(RawResult::Url(format!(
-                        "https://cdn.discordapp.com/avatars/{}/{}.{}?size=512",
-                        message.author.id,
-                        avatar,
-                        if self.allow_gifs && avatar.starts_with("a_") { "gif" } else { "png" }
-                    )), &allowed_content_types, &allowed_suffixes).await
-                }
-                else {
-                    self._sanitize(RawResult::Url(
-                        format!("https://cdn.discordapp.com/embed/avatars/{}.png", message.author.discriminator % 5)
-                    ), &allowed_content_types, &allowed_suffixes).await
+        Ok(hash)
+    }
+
+    pub async fn resolve(&self, ctx: &Context, message: &Message, query: Option<String>) -> Result<Resolved, CommandError> {
+        let (data, nsfw) = self._resolve_data(ctx, message, query).await?;
+        self._finish(ctx, message, data, nsfw).await
+    }
+
+    /// Resolves several images for commands that combine them: every attachment on the message, then one
+    /// image for each query. Queries are resolved on their own, so they can be links to other messages too.
+    pub async fn resolve_many(&self, ctx: &Context, message: &Message, queries: Vec<String>) -> Result<Vec<Resolved>, CommandError> {
+        let (allowed_content_types, allowed_suffixes) = self._allowed_types();
+        let mut resolved = Vec::with_capacity(message.attachments.len() + queries.len());
+
+        for attachment in &message.attachments {
+            let data = self._sanitize(RawResult::Attachment(attachment), &allowed_content_types, &allowed_suffixes).await?;
+            resolved.push(self._finish(ctx, message, data, false).await?);
+        }
+
+        for query in queries {
+            let query = Some(match self.run_conversions {
+                true => Self::_run_conversions(ctx, message.guild_id, Some(message.channel_id), query).await,
+                false => Query::String(query),
+            });
+
+            let data = self
+                ._resolve_source(Source::Query, message, &query, &allowed_content_types, &allowed_suffixes)
+                .await
+                .ok_or_else(|| Source::Query.not_found())??;
+            let nsfw = Self::_links_nsfw(ctx, message, &query).await;
+            resolved.push(self._finish(ctx, message, data, nsfw).await?);
+        }
+
+        Ok(resolved)
+    }
+
+    /// Resolves the two images of commands that combine a pair, like [`Self::resolve_many`]. When only one
+    /// is given as a query, it becomes the second image and the first comes from the replied message or
+    /// the author's avatar, as a single image would.
+    pub async fn resolve_pair(&self, ctx: &Context, message: &Message, queries: Vec<String>) -> Result<(Resolved, Resolved), CommandError> {
+        let mut resolved = self.resolve_many(ctx, message, queries).await?;
+
+        if resolved.len() == 1 && message.attachments.is_empty() {
+            let (allowed_content_types, allowed_suffixes) = self._allowed_types();
+
+            for source in self.policy.order().iter().filter(|source| matches!(source, Source::Reply | Source::Avatar)) {
+                if let Some(result) = self._resolve_source(*source, message, &None, &allowed_content_types, &allowed_suffixes).await {
+                    resolved.insert(0, self._finish(ctx, message, result?, false).await?);
+                    break;
                 }
-            } else {
-                Err(CommandError::from("Could not retrieve an image from the message."))
             }
+        }
+
+        match <[Resolved; 2]>::try_from(resolved) {
+            Ok([first, second]) => Ok((first, second)),
+            Err(_) => Err(tr!("resolve-needs-two").into()),
+        }
+    }
+
+    /// Downloads a user's avatar, animated or not following the same preferences as avatars of mentions.
+    pub async fn resolve_avatar(&self, ctx: &Context, message: &Message, user: &User) -> Result<Resolved, CommandError> {
+        let (allowed_content_types, allowed_suffixes) = self._allowed_types();
+        let url = self._avatar_url(user, user.avatar.as_ref());
+        let data = self._sanitize(RawResult::Url(url), &allowed_content_types, &allowed_suffixes).await?;
+
+        self._finish(ctx, message, data, false).await
+    }
+
+    /// Whether the query links to a message in another channel that is age-restricted, unlike this one may be.
+    async fn _links_nsfw(ctx: &Context, message: &Message, query: &Option<Query>) -> bool {
+        match query {
+            Some(Query::Message(linked)) if linked.channel_id != message.channel_id => {
+                linked.channel(ctx).await.map_or(false, |channel| channel.is_nsfw())
+            },
+            _ => false,
+        }
+    }
+
+    /// Counts the downloaded bytes, renders documents and checks the result against the blocklist.
+    async fn _finish(&self, ctx: &Context, message: &Message, data: Vec<u8>, nsfw: bool) -> Result<Resolved, CommandError> {
+        crate::stats::Stats::get(ctx).await.add_bytes(message.id, data.len());
+
+        #[cfg(feature = "pdf")]
+        let data = if crate::pdf::is_pdf(&data) {
+            let (page, max_width, max_height) = (self.page, self.max_width as u32, self.max_height as u32);
+
+            tokio::task::spawn_blocking(move || crate::pdf::rasterize(&data, page, max_width, max_height)).await??
+        } else {
+            data
         };
-        
-        if let Some(q) = resolved_query {
-            match q {
-                Query::String(query) => {
-                    if query.chars().count() < 8 {
-                        self._sanitize(RawResult::Url(Self::_url_from_emoji(query)), &allowed_content_types, &allowed_suffixes).await
-                    }
-                    else {
-                        self._sanitize(RawResult::Url(query), &allowed_content_types, &allowed_suffixes).await
-                    }
-                },
-                Query::Emoji(query) => {
-                    self._sanitize(RawResult::Url(query.url()), &allowed_content_types, &allowed_suffixes).await
-                },
-                Query::Member(query) if self.allow_user_avatars => {
-                    if let Some(avatar) = &query.avatar.or(query.user.avatar) {
-                        self._sanitize(RawResult::Url(format!(
-                            "https://cdn.discordapp.com/avatars/{}/{}.{}?size=512",
-                            query.user.id,
-                            avatar,
-                            if self.allow_gifs && avatar.starts_with("a_") { "gif" } else { "png" }
-                        )), &allowed_content_types, &allowed_suffixes).await
-                    }
-                    else {
-                        fallback().await
-                    }
-                },
-                _ => return fallback().await
+        let hash = match message.guild_id {
+            Some(guild_id) => Self::_check_blocklist(ctx, guild_id, &data).await?,
+            None => None,
+        };
+
+        Ok(Resolved { data, hash, nsfw })
+    }
+
+    /// Resolves the image from the first source present, along with whether it was linked from an age-restricted channel.
+    async fn _resolve_data(&self, ctx: &Context, message: &Message, query: Option<String>) -> Result<(Vec<u8>, bool), CommandError> {
+        let resolved_query = if query.is_some() && self.run_conversions {
+            Some(
+                Self::_run_conversions(ctx, message.guild_id, Some(message.channel_id), query.unwrap()).await
+            )
+        } else if query.is_some() {
+            Some(Query::String(query.unwrap()))
+        } else {
+            None
+        };
+
+        let (allowed_content_types, allowed_suffixes) = self._allowed_types();
+
+        for source in self.policy.order() {
+            if let Some(result) = self._resolve_source(*source, message, &resolved_query, &allowed_content_types, &allowed_suffixes).await {
+                let nsfw = *source == Source::Query && Self::_links_nsfw(ctx, message, &resolved_query).await;
+                return Ok((result?, nsfw));
             }
         }
-        else {
-            fallback().await
+
+        match self.policy.order() {
+            [source] => Err(source.not_found().into()),
+            _ => Err(tr!("resolve-not-found").into()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sources() {
+        assert_eq!("URL".parse::<Source>().unwrap(), Source::Query);
+        assert_eq!("file".parse::<Source>().unwrap(), Source::Attachment);
+        assert_eq!("reply".parse::<Source>().unwrap(), Source::Reply);
+        assert!("clipboard".parse::<Source>().is_err());
+    }
+
+    #[test]
+    fn orders_sources_by_policy() {
+        let default = [Source::Query, Source::Attachment, Source::Reply, Source::Avatar];
+        assert_eq!(SourcePolicy::default().order(), default);
+        assert_eq!(SourcePolicy::only(Source::Reply).order(), [Source::Reply]);
+        assert!(!SourcePolicy::animated().order().contains(&Source::Avatar));
+
+        let mut resolver = ImageResolver::new();
+        resolver.policy(SourcePolicy::animated()).source(Source::Attachment);
+        assert_eq!(resolver.policy.order(), [Source::Attachment]);
+    }
+}
@@ -0,0 +1,95 @@
+use serenity::client::Context;
+use serenity::prelude::TypeMapKey;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::Notify;
+
+/// Tracks in-flight commands so a shutdown can wait for them to finish.
+pub struct Shutdown {
+    accepting: AtomicBool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+pub struct ShutdownKey;
+
+impl TypeMapKey for ShutdownKey {
+    type Value = Arc<Shutdown>;
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            accepting: AtomicBool::new(true),
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+
+    pub async fn get(ctx: &Context) -> Arc<Shutdown> {
+        ctx.data.read().await.get::<ShutdownKey>().expect("Shutdown was not inserted into client data").clone()
+    }
+
+    /// Registers a new command, returning `false` if the bot is shutting down and it should be refused.
+    pub fn begin(&self) -> bool {
+        if !self.accepting.load(Ordering::SeqCst) {
+            return false;
+        }
+
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        true
+    }
+
+    pub fn finish(&self) {
+        if self.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Stops accepting commands and waits for in-flight ones to finish, for at most `timeout`.
+    ///
+    /// Returns the amount of commands that were still running when the timeout was hit.
+    pub async fn drain(&self, timeout: Duration) -> usize {
+        self.accepting.store(false, Ordering::SeqCst);
+
+        let _ = tokio::time::timeout(timeout, async {
+            while self.in_flight() > 0 {
+                let idle = self.idle.notified();
+
+                // Re-check now that we're registered, in case the last command finished in between.
+                if self.in_flight() == 0 {
+                    break;
+                }
+
+                idle.await;
+            }
+        }).await;
+
+        self.in_flight()
+    }
+}
+
+/// Resolves once the process receives SIGINT (Ctrl+C) or, on Unix, SIGTERM.
+pub async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate()).expect("Could not register SIGTERM handler");
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => (),
+            _ = terminate.recv() => (),
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
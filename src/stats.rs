@@ -0,0 +1,111 @@
+use serenity::client::Context;
+use serenity::model::id::{MessageId, UserId};
+use serenity::prelude::TypeMapKey;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::db::Database;
+
+/// How often the collected numbers are written to the database.
+pub const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Usage numbers of a single command.
+#[derive(Clone, Debug, Default)]
+pub struct CommandStats {
+    pub invocations: u64,
+    pub unique_users: u64,
+    pub bytes: u64,
+    pub total_latency_ms: u64,
+}
+
+impl CommandStats {
+    pub fn average_latency_ms(&self) -> u64 {
+        self.total_latency_ms.checked_div(self.invocations).unwrap_or(0)
+    }
+}
+
+/// Numbers collected since the last flush, added onto the stored ones.
+#[derive(Default)]
+struct Pending {
+    invocations: u64,
+    bytes: u64,
+    total_latency_ms: u64,
+    users: HashSet<UserId>,
+}
+
+struct Running {
+    started: Instant,
+    bytes: u64,
+}
+
+#[derive(Default)]
+pub struct Stats {
+    running: Mutex<HashMap<MessageId, Running>>,
+    pending: Mutex<HashMap<String, Pending>>,
+}
+
+pub struct StatsKey;
+
+impl TypeMapKey for StatsKey {
+    type Value = Arc<Stats>;
+}
+
+impl Stats {
+    pub async fn get(ctx: &Context) -> Arc<Stats> {
+        ctx.data.read().await.get::<StatsKey>().expect("Stats was not inserted into client data").clone()
+    }
+
+    /// Starts timing the command invoked by the message.
+    pub fn start(&self, message_id: MessageId) {
+        self.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+            .insert(message_id, Running { started: Instant::now(), bytes: 0 });
+    }
+
+    /// Counts input downloaded for the command invoked by the message.
+    pub fn add_bytes(&self, message_id: MessageId, bytes: usize) {
+        if let Some(running) = self.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).get_mut(&message_id) {
+            running.bytes += bytes as u64;
+        }
+    }
+
+    pub fn finish(&self, message_id: MessageId, command: &str, user_id: UserId) {
+        let running = match self.running.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).remove(&message_id) {
+            Some(running) => running,
+            None => return,
+        };
+
+        let mut pending = self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let entry = pending.entry(command.to_string()).or_default();
+
+        entry.invocations += 1;
+        entry.bytes += running.bytes;
+        entry.total_latency_ms += running.started.elapsed().as_millis() as u64;
+        entry.users.insert(user_id);
+    }
+
+    /// Writes everything collected so far to the database.
+    pub fn flush(&self, database: &Database) -> rusqlite::Result<()> {
+        let pending = std::mem::take(&mut *self.pending.lock().unwrap_or_else(|poisoned| poisoned.into_inner()));
+
+        for (command, stats) in pending {
+            database.add_command_stats(&command, stats.invocations, stats.bytes, stats.total_latency_ms, &stats.users)?;
+        }
+
+        Ok(())
+    }
+
+    /// Periodically flushes into the database, for as long as the bot is running.
+    pub async fn run(self: Arc<Self>, database: Arc<Database>) {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            if let Err(why) = self.flush(&database) {
+                eprintln!("Could not flush command stats: {}", why);
+            }
+        }
+    }
+}
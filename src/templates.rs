@@ -0,0 +1,520 @@
+//! Meme templates: JSON files describing a base image and the slots user input is drawn into.
+//! Each template in the templates directory becomes a command named after it.
+//!
+//! ```json
+//! {
+//!     "name": "rather",
+//!     "description": "Rejects one thing in favor of another.",
+//!     "base": { "width": 800, "height": 800, "color": "white" },
+//!     "slots": [
+//!         { "type": "text", "x": 0, "y": 0, "width": 400, "height": 400, "text": "❌", "size": 200 },
+//!         { "type": "text", "x": 400, "y": 0, "width": 400, "height": 400, "label": "bad thing" },
+//!         { "type": "image", "x": 400, "y": 400, "width": 400, "height": 400, "rotation": -5 }
+//!     ]
+//! }
+//! ```
+//!
+//! `base` is either a path to an image relative to the template, or a blank canvas. Slots are drawn in
+//! order on top of it, except image slots with `"below": true` which go underneath (for bases with
+//! transparent cut-outs). Image slots and text slots without a fixed `text` take the command's
+//! arguments in order, separated by `|`.
+
+use image::imageops::FilterType;
+use image::{Delay, Frame, Rgba, RgbaImage};
+
+use photon_core::geometry;
+use photon_core::text::{Align, Emojis, Font, TextBlock, TextStyle};
+use photon_core::{parse_color, Media};
+
+use serde::{Deserialize, Deserializer};
+
+use serenity::client::Context;
+use serenity::prelude::TypeMapKey;
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::config::ConfigError;
+use crate::fonts;
+
+/// Text is shrunk down to this size before it gets cut off.
+const MIN_TEXT_SIZE: f32 = 10.0;
+const MAX_CANVAS_SIZE: u32 = 2048;
+
+#[derive(Clone, Copy)]
+struct Color(Rgba<u8>);
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        parse_color(&s).map(Self).ok_or_else(|| serde::de::Error::custom(format!("invalid color `{}`", s)))
+    }
+}
+
+fn black() -> Color {
+    Color(Rgba([0, 0, 0, 255]))
+}
+
+fn white() -> Color {
+    Color(Rgba([255, 255, 255, 255]))
+}
+
+fn default_font() -> String {
+    "sans-bold".to_string()
+}
+
+fn default_size() -> f32 {
+    48.0
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFile {
+    name: String,
+    #[serde(default)]
+    aliases: Vec<String>,
+    description: String,
+    example: Option<String>,
+    #[serde(default)]
+    nsfw: bool,
+    base: Base,
+    slots: Vec<Slot>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Base {
+    Image(String),
+    Canvas {
+        width: u32,
+        height: u32,
+        #[serde(default = "white")]
+        color: Color,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Slot {
+    Image(ImageSlot),
+    Text(TextSlot),
+    Rect(RectSlot),
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Fit {
+    /// Fills the slot, cropping what doesn't fit.
+    Cover,
+    /// Fits entirely within the slot.
+    Contain,
+    Stretch,
+}
+
+impl Default for Fit {
+    fn default() -> Self {
+        Self::Cover
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ImageSlot {
+    x: i64,
+    y: i64,
+    width: u32,
+    height: u32,
+    /// Clockwise, in degrees.
+    #[serde(default)]
+    rotation: f32,
+    #[serde(default)]
+    fit: Fit,
+    #[serde(default)]
+    below: bool,
+    label: Option<String>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum HorizontalAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum VerticalAlign {
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct StrokeDef {
+    width: u32,
+    color: Color,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TextSlot {
+    x: i64,
+    y: i64,
+    width: u32,
+    height: u32,
+    /// Fixed text, making the slot not take an argument.
+    text: Option<String>,
+    /// Used when the argument is left out, otherwise it is required.
+    default: Option<String>,
+    label: Option<String>,
+    #[serde(default = "default_font")]
+    font: String,
+    /// The largest size, smaller ones are used when the text wouldn't fit.
+    #[serde(default = "default_size")]
+    size: f32,
+    #[serde(default = "black")]
+    color: Color,
+    #[serde(default = "center")]
+    align: HorizontalAlign,
+    #[serde(default = "middle")]
+    valign: VerticalAlign,
+    stroke: Option<StrokeDef>,
+    #[serde(default)]
+    uppercase: bool,
+    #[serde(default)]
+    rotation: f32,
+    #[serde(skip)]
+    fonts: Vec<Arc<Font<'static>>>,
+}
+
+fn center() -> HorizontalAlign {
+    HorizontalAlign::Center
+}
+
+fn middle() -> VerticalAlign {
+    VerticalAlign::Middle
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RectSlot {
+    x: i64,
+    y: i64,
+    width: u32,
+    height: u32,
+    color: Color,
+}
+
+/// What a slot that takes an argument expects.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InputKind {
+    Image,
+    /// Text, and whether it may be left out.
+    Text { optional: bool },
+}
+
+/// The argument given to a slot.
+pub enum Input {
+    Image(Media),
+    Text(String),
+}
+
+/// Something to draw onto the base: one image, or one per frame for animated inputs.
+struct Layer {
+    x: i64,
+    y: i64,
+    below: bool,
+    frames: Vec<RgbaImage>,
+    delays: Option<Vec<Delay>>,
+}
+
+impl Layer {
+    fn fixed(x: i64, y: i64, image: RgbaImage) -> Self {
+        Self { x, y, below: false, frames: vec![image], delays: None }
+    }
+
+    /// Positions the (possibly rotated) image so it stays centered on the slot.
+    fn centered(slot_x: i64, slot_y: i64, width: u32, height: u32, image: &RgbaImage) -> (i64, i64) {
+        (
+            slot_x + (width as i64 - image.width() as i64) / 2,
+            slot_y + (height as i64 - image.height() as i64) / 2,
+        )
+    }
+}
+
+pub struct Template {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub description: String,
+    pub example: Option<String>,
+    /// Whether the meme may only be made in age-restricted channels.
+    pub nsfw: bool,
+    base: RgbaImage,
+    slots: Vec<Slot>,
+}
+
+impl Template {
+    fn load(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file = serde_json::from_str::<TemplateFile>(&source).map_err(|e| e.to_string())?;
+
+        let fits = |width: u32, height: u32| (1..=MAX_CANVAS_SIZE).contains(&width) && (1..=MAX_CANVAS_SIZE).contains(&height);
+
+        let base = match file.base {
+            Base::Image(image) => {
+                let image_path = path.parent().unwrap_or_else(|| Path::new(".")).join(&image);
+                image::open(&image_path)
+                    .map_err(|e| format!("could not open base image `{}`: {}", image_path.display(), e))?
+                    .into_rgba8()
+            },
+            Base::Canvas { width, height, color } if fits(width, height) => RgbaImage::from_pixel(width, height, color.0),
+            Base::Canvas { .. } => RgbaImage::new(0, 0),
+        };
+
+        if !fits(base.width(), base.height()) {
+            return Err(format!("the base must be between 1x1 and {0}x{0} pixels", MAX_CANVAS_SIZE));
+        }
+
+        let mut slots = file.slots;
+        for (index, slot) in slots.iter_mut().enumerate() {
+            let (slot_width, slot_height) = match slot {
+                Slot::Image(slot) => (slot.width, slot.height),
+                Slot::Rect(slot) => (slot.width, slot.height),
+                Slot::Text(slot) => {
+                    let font = fonts::bundled(&slot.font)
+                        .ok_or_else(|| format!("slot {} uses the unknown font `{}`", index + 1, slot.font))?;
+                    slot.fonts = fonts::with_fallbacks(font);
+
+                    if !(1.0..=512.0).contains(&slot.size) {
+                        return Err(format!("slot {} has a text size outside of 1 to 512", index + 1));
+                    }
+                    (slot.width, slot.height)
+                },
+            };
+
+            if !fits(slot_width, slot_height) {
+                return Err(format!("slot {} must be between 1x1 and {max}x{max} pixels", index + 1, max = MAX_CANVAS_SIZE));
+            }
+        }
+
+        Ok(Self {
+            name: file.name.to_lowercase(),
+            aliases: file.aliases.iter().map(|alias| alias.to_lowercase()).collect(),
+            description: file.description,
+            example: file.example,
+            nsfw: file.nsfw,
+            base,
+            slots,
+        })
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.name.as_str()).chain(self.aliases.iter().map(String::as_str))
+    }
+
+    /// The slots taking arguments, in the order the arguments are given.
+    pub fn inputs(&self) -> Vec<(InputKind, &str)> {
+        self.slots
+            .iter()
+            .filter_map(|slot| match slot {
+                Slot::Image(slot) => Some((InputKind::Image, slot.label.as_deref().unwrap_or("image"))),
+                Slot::Text(slot) if slot.text.is_none() => Some((
+                    InputKind::Text { optional: slot.default.is_some() },
+                    slot.label.as_deref().unwrap_or("text"),
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// E.g. `<top text> | <bottom text> | [image]`, images always being optional.
+    pub fn usage(&self) -> String {
+        self.inputs()
+            .into_iter()
+            .map(|(kind, label)| match kind {
+                InputKind::Image | InputKind::Text { optional: true } => format!("[{}]", label),
+                InputKind::Text { optional: false } => format!("<{}>", label),
+            })
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// Every piece of text the template will draw given the text arguments, so emoji in them can be fetched beforehand.
+    pub fn text(&self, given: &[String]) -> String {
+        let fixed = self.slots.iter().filter_map(|slot| match slot {
+            Slot::Text(slot) => slot.text.as_deref().or(slot.default.as_deref()),
+            _ => None,
+        });
+
+        fixed.chain(given.iter().map(String::as_str)).collect::<Vec<_>>().join(" ")
+    }
+
+    fn text_layer(slot: &TextSlot, text: &str, emojis: &Emojis) -> Layer {
+        let fonts = slot.fonts.iter().map(|font| font.as_ref()).collect::<Vec<_>>();
+        let mut style = TextStyle::new(&fonts, slot.size, slot.color.0).align(match slot.align {
+            HorizontalAlign::Left => Align::Left,
+            HorizontalAlign::Center => Align::Center,
+            HorizontalAlign::Right => Align::Right,
+        });
+        if let Some(stroke) = &slot.stroke {
+            style = style.stroke(stroke.width, stroke.color.0);
+        }
+
+        let text = if slot.uppercase { text.to_uppercase() } else { text.to_string() };
+        let block = TextBlock::fit(style, &text, slot.width as f32, slot.height as f32, MIN_TEXT_SIZE);
+
+        // Leave room for the outline around text that touches the slot's edges.
+        let margin = slot.stroke.as_ref().map_or(0, |stroke| stroke.width);
+        let mut image = RgbaImage::new(slot.width + margin * 2, slot.height + margin * 2);
+        let free = (slot.height as f32 - block.height()).max(0.0);
+        let top = match slot.valign {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Middle => free / 2.0,
+            VerticalAlign::Bottom => free,
+        };
+        block.draw(&mut image, margin as i32, (top.round() as u32 + margin) as i32, emojis);
+
+        let image = geometry::rotate(&image, slot.rotation);
+        let (x, y) = Layer::centered(slot.x - margin as i64, slot.y - margin as i64, slot.width + margin * 2, slot.height + margin * 2, &image);
+        Layer::fixed(x, y, image)
+    }
+
+    fn image_layer(slot: &ImageSlot, media: Media) -> Layer {
+        let fit = |frame: &RgbaImage| {
+            let fitted = match slot.fit {
+                Fit::Cover => geometry::cover(frame, slot.width, slot.height, FilterType::Triangle),
+                Fit::Contain => geometry::contain(frame, slot.width, slot.height, FilterType::Triangle),
+                Fit::Stretch => image::imageops::resize(frame, slot.width, slot.height, FilterType::Triangle),
+            };
+            geometry::rotate(&fitted, slot.rotation)
+        };
+
+        let (frames, delays) = match media {
+            Media::Static(image) => (vec![fit(&image)], None),
+            Media::Animated(frames) => (
+                frames.iter().map(|frame| fit(frame.buffer())).collect(),
+                Some(frames.iter().map(Frame::delay).collect()),
+            ),
+        };
+
+        let (x, y) = Layer::centered(slot.x, slot.y, slot.width, slot.height, &frames[0]);
+        Layer { x, y, below: slot.below, frames, delays }
+    }
+
+    /// Draws the inputs (as given in the order of [`inputs`](Self::inputs)) into the template.
+    ///
+    /// The result is animated when any of the images is, following the first animated image's frames.
+    pub fn render(&self, inputs: Vec<Input>, emojis: &Emojis) -> Media {
+        let mut inputs = inputs.into_iter();
+        let mut layers = Vec::with_capacity(self.slots.len());
+
+        for slot in &self.slots {
+            let layer = match slot {
+                Slot::Rect(slot) => Layer::fixed(slot.x, slot.y, RgbaImage::from_pixel(slot.width, slot.height, slot.color.0)),
+                Slot::Text(slot) => {
+                    let text = match &slot.text {
+                        Some(text) => text.clone(),
+                        None => match inputs.next() {
+                            Some(Input::Text(text)) if !text.is_empty() => text,
+                            _ => slot.default.clone().unwrap_or_default(),
+                        },
+                    };
+                    Self::text_layer(slot, &text, emojis)
+                },
+                Slot::Image(slot) => match inputs.next() {
+                    Some(Input::Image(media)) => Self::image_layer(slot, media),
+                    _ => continue,
+                },
+            };
+
+            layers.push(layer);
+        }
+
+        let compose = |index: usize| {
+            let mut canvas = RgbaImage::new(self.base.width(), self.base.height());
+            let draw = |canvas: &mut RgbaImage, layer: &Layer| {
+                geometry::overlay(canvas, &layer.frames[index % layer.frames.len()], layer.x, layer.y);
+            };
+
+            for layer in layers.iter().filter(|layer| layer.below) {
+                draw(&mut canvas, layer);
+            }
+            geometry::overlay(&mut canvas, &self.base, 0, 0);
+            for layer in layers.iter().filter(|layer| !layer.below) {
+                draw(&mut canvas, layer);
+            }
+
+            canvas
+        };
+
+        match layers.iter().find_map(|layer| layer.delays.as_ref()) {
+            Some(delays) => Media::Animated(
+                delays.iter().enumerate().map(|(index, delay)| Frame::from_parts(compose(index), 0, 0, *delay)).collect()
+            ),
+            None => Media::Static(compose(0)),
+        }
+    }
+}
+
+/// Every template loaded at startup.
+pub struct Templates {
+    templates: Vec<Arc<Template>>,
+}
+
+pub struct TemplatesKey;
+
+impl TypeMapKey for TemplatesKey {
+    type Value = Arc<Templates>;
+}
+
+impl Templates {
+    /// Loads every `*.json` template in `dir`, rejecting names that are taken according to `is_taken`.
+    /// A missing directory just means there are no templates.
+    pub fn load(dir: &Path, is_taken: impl Fn(&str) -> bool) -> Result<Self, ConfigError> {
+        let mut templates = Vec::<Arc<Template>>::new();
+
+        if dir.is_dir() {
+            let mut paths = std::fs::read_dir(dir)
+                .map_err(|e| ConfigError::Io(dir.to_path_buf(), e))?
+                .map(|entry| entry.map(|entry| entry.path()).map_err(|e| ConfigError::Io(dir.to_path_buf(), e)))
+                .collect::<Result<Vec<_>, _>>()?;
+            paths.retain(|path| path.extension().map_or(false, |ext| ext == "json"));
+            paths.sort();
+
+            for path in paths {
+                let invalid = |why: String| ConfigError::Invalid(format!("invalid template `{}`: {}", path.display(), why));
+                let template = Template::load(&path).map_err(invalid)?;
+
+                for name in template.names() {
+                    let valid = !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+                    if !valid {
+                        return Err(invalid(format!("`{}` is not a valid command name", name)));
+                    }
+
+                    if is_taken(name) || templates.iter().any(|other| other.names().any(|n| n == name)) {
+                        return Err(invalid(format!("the name `{}` is already taken", name)));
+                    }
+                }
+
+                templates.push(Arc::new(template));
+            }
+        }
+
+        Ok(Self { templates })
+    }
+
+    pub async fn get(ctx: &Context) -> Arc<Templates> {
+        ctx.data.read().await.get::<TemplatesKey>().expect("Templates was not inserted into client data").clone()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Arc<Template>> {
+        self.templates.iter()
+    }
+
+    pub fn find(&self, name: &str) -> Option<Arc<Template>> {
+        let name = name.to_lowercase();
+        self.templates.iter().find(|template| template.names().any(|n| n == name)).cloned()
+    }
+}